@@ -5,6 +5,7 @@ use std::time::{Duration, Instant};
 use std::path::Path;
 
 use asperger::asp::config::{AspDirConfig, Config, DirConfigCache};
+use asperger::asp::page_cache::PageCache;
 
 fn make_request(path: &str) -> String {
     format!("GET /{} HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n\r\n", path)
@@ -19,6 +20,10 @@ fn make_config() -> Config {
         enable_directory_listing: false,
         default_documents: None,
         log_level: None,
+        disable_page_cache: false,
+        page_cache_size: None,
+        worker_threads: None,
+        request_queue_max: None,
     }
 }
 
@@ -30,6 +35,8 @@ fn make_dir_cache(config: &Config) -> DirConfigCache {
         AspDirConfig {
             default_documents: vec!["index.asp".to_string()],
             directory_listing: false,
+            script_timeout: 90,
+            limits: Default::default(),
         },
         root,
     )
@@ -43,6 +50,7 @@ fn bench_single_request(path: &str) -> String {
     rt.block_on(async {
         let config = make_config();
         let dir_cache = Arc::new(make_dir_cache(&config));
+        let page_cache = Arc::new(PageCache::new(64));
         let server = asperger::asp::server::AspServer::new(config);
         let store = Arc::clone(&server.store);
         let listener = Arc::new(tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap());
@@ -52,7 +60,7 @@ fn bench_single_request(path: &str) -> String {
         let accept_handle = tokio::spawn(async move {
             let (mut stream, _) = listener.accept().await.unwrap();
             let _ = asperger::asp::server::AspServer::handle_connection(
-                &mut stream, "asp_files", &dc, &store,
+                &mut stream, "asp_files", dc, page_cache, &store,
             ).await;
         });
 
@@ -66,6 +74,7 @@ fn bench_single_request(path: &str) -> String {
 async fn spawn_accept_tasks(
     listener: Arc<tokio::net::TcpListener>,
     dir_cache: Arc<DirConfigCache>,
+    page_cache: Arc<PageCache>,
     store: Arc<asperger::vbscript::store::Store>,
     count: usize,
 ) -> Vec<tokio::task::JoinHandle<()>> {
@@ -73,11 +82,12 @@ async fn spawn_accept_tasks(
     for _ in 0..count {
         let l = Arc::clone(&listener);
         let dc = Arc::clone(&dir_cache);
+        let pc = Arc::clone(&page_cache);
         let s = Arc::clone(&store);
         handles.push(tokio::spawn(async move {
             let (mut stream, _) = l.accept().await.unwrap();
             let _ = asperger::asp::server::AspServer::handle_connection(
-                &mut stream, "asp_files", &dc, &s,
+                &mut stream, "asp_files", dc, pc, &s,
             ).await;
         }));
     }
@@ -178,6 +188,7 @@ fn bench_throughput_empty(c: &mut Criterion) {
                     rt.block_on(async {
                         let config = make_config();
                         let dir_cache = Arc::new(make_dir_cache(&config));
+                        let page_cache = Arc::new(PageCache::new(64));
                         let server = asperger::asp::server::AspServer::new(config);
                         let store = Arc::clone(&server.store);
                         let listener =
@@ -188,6 +199,7 @@ fn bench_throughput_empty(c: &mut Criterion) {
                         let accept_tasks = spawn_accept_tasks(
                             Arc::clone(&listener),
                             Arc::clone(&dir_cache),
+                            Arc::clone(&page_cache),
                             Arc::clone(&store),
                             total_reqs,
                         )
@@ -226,6 +238,7 @@ fn run_vu(
     rt.block_on(async {
         let config = make_config();
         let dir_cache = Arc::new(make_dir_cache(&config));
+        let page_cache = Arc::new(PageCache::new(64));
         let server = asperger::asp::server::AspServer::new(config);
         let store = Arc::clone(&server.store);
         let listener = Arc::new(tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap());
@@ -235,6 +248,7 @@ fn run_vu(
         let _accept_tasks = spawn_accept_tasks(
             Arc::clone(&listener),
             Arc::clone(&dir_cache),
+            Arc::clone(&page_cache),
             Arc::clone(&store),
             total,
         )
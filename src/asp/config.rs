@@ -1,9 +1,19 @@
 use std::collections::HashMap;
+use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::RwLock;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, RwLock};
 
 use clap::Parser;
 
+use crate::asp::page_cache::PageCache;
+use crate::asp::worker_pool::{stack_size_for, WorkerPool, DEFAULT_STACK_SIZE};
+use crate::vbscript::execution_context::{ResourceLimits, DEFAULT_SCRIPT_TIMEOUT};
+use crate::vbscript::store::{
+    FileBackend, KvBackend, Store, DEFAULT_APP_LOCK_TIMEOUT, DEFAULT_GLOBAL_ASA_CHECK_INTERVAL, DEFAULT_SESSION_LOCK_TIMEOUT,
+    DEFAULT_SESSION_TIMEOUT, MAX_SESSION_TIMEOUT,
+};
+
 /// ASP server CLI configuration.
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -34,6 +44,22 @@ pub struct Config {
     /// Log level (error, warn, info, debug, trace).
     #[clap(long, env = "ASPERGER_LOG_LEVEL")]
     pub log_level: Option<String>,
+
+    /// Compile pages on every request instead of caching them (development).
+    #[clap(long, env = "ASPERGER_DISABLE_PAGE_CACHE")]
+    pub disable_page_cache: bool,
+
+    /// Maximum number of compiled pages kept in the page cache.
+    #[clap(long, env = "ASPERGER_PAGE_CACHE_SIZE")]
+    pub page_cache_size: Option<usize>,
+
+    /// Most threads that execute pages; they are started as load requires.
+    #[clap(long, env = "ASPERGER_WORKER_THREADS")]
+    pub worker_threads: Option<usize>,
+
+    /// Requests allowed to wait for a free worker before answering 503.
+    #[clap(long, env = "ASPERGER_REQUEST_QUEUE_MAX")]
+    pub request_queue_max: Option<usize>,
 }
 
 /// Per-directory settings for an ASP request.
@@ -43,6 +69,10 @@ pub struct AspDirConfig {
     pub default_documents: Vec<String>,
     /// Whether to show directory listing when no default document is found.
     pub directory_listing: bool,
+    /// Default `Server.ScriptTimeout` of pages in the directory, in seconds.
+    pub script_timeout: u32,
+    /// Resource limits of pages in the directory.
+    pub limits: ResourceLimits,
 }
 
 /// Lazy cache of per-directory `AspDirConfig` resolved from `asp.ini` files.
@@ -111,6 +141,10 @@ impl DirConfigCache {
     /// - `default_documents` — comma-separated list (replaces the whole list)
     /// - `default_document`  — single value (backward compat, replaces the list)
     /// - `enable_directory_listing` — boolean (`true` enables)
+    /// - `script_timeout` — default `Server.ScriptTimeout` in seconds (at least 1)
+    /// - `max_instructions`, `max_call_depth`, `max_array_elements`,
+    ///   `max_string_length`, `max_response_bytes` — resource limits (0 for none;
+    ///   `max_call_depth` never exceeds what the worker stack can hold)
     fn apply_ini_to_dir_config(dir_config: &mut AspDirConfig, content: &str) {
         let mut in_server = false;
         for line in content.lines() {
@@ -133,21 +167,66 @@ impl DirConfigCache {
                             .filter(|s| !s.is_empty())
                             .collect();
                     }
-                    "default_document" => {
-                        if !value.is_empty() {
-                            dir_config.default_documents = vec![value.to_string()];
-                        }
+                    "default_document" if !value.is_empty() => {
+                        dir_config.default_documents = vec![value.to_string()];
                     }
                     "enable_directory_listing" => {
                         dir_config.directory_listing = value.eq_ignore_ascii_case("true");
                     }
-                    _ => {}
+                    "script_timeout" => {
+                        if let Ok(n) = value.parse::<u32>() {
+                            dir_config.script_timeout = n.max(1);
+                        }
+                    }
+                    _ => apply_limit(&mut dir_config.limits, &key, value),
                 }
             }
         }
     }
 }
 
+/// Set the resource limit named by an INI `key`; other keys are ignored.
+fn apply_limit(limits: &mut ResourceLimits, key: &str, value: &str) {
+    let Ok(n) = value.parse::<u64>() else {
+        return;
+    };
+    let n_usize = usize::try_from(n).unwrap_or(usize::MAX);
+    match key {
+        "max_instructions" => limits.max_instructions = n,
+        "max_call_depth" => limits.max_call_depth = n_usize,
+        "max_array_elements" => limits.max_array_elements = n_usize,
+        "max_string_length" => limits.max_string_length = n_usize,
+        "max_response_bytes" => limits.max_response_bytes = n_usize,
+        _ => {}
+    }
+}
+
+/// Where session and application variables are kept (`[state]` in asp.ini).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateConfig {
+    /// `memory` (the default, lost on restart), `file` or `kv`.
+    pub backend: String,
+    /// Directory of the `file` backend.
+    pub path: Option<PathBuf>,
+    /// `host:port` of the key-value server of the `kv` backend, which
+    /// speaks the Redis protocol.
+    pub address: String,
+    /// Prefix of the `kv` backend's keys, so that applications can share
+    /// a server.
+    pub prefix: String,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            backend: "memory".to_string(),
+            path: None,
+            address: "127.0.0.1:6379".to_string(),
+            prefix: "asperger".to_string(),
+        }
+    }
+}
+
 /// Runtime server configuration with all overrides applied in order:
 /// defaults < INI file < programmatic overrides.
 #[derive(Debug, Clone)]
@@ -158,7 +237,34 @@ pub struct AspServerConfig {
     /// Prioritized list of default documents (IIS-like fallback chain).
     pub default_documents: Vec<String>,
     pub directory_listing: bool,
+    /// Default `Server.ScriptTimeout` in seconds (IIS `AspScriptTimeout`).
+    pub script_timeout: u32,
+    /// Per-request resource limits (instructions, call depth, memory, output).
+    pub limits: ResourceLimits,
+    /// Default `Session.Timeout` in minutes (IIS `AspSessionTimeout`).
+    pub session_timeout: u32,
+    /// Seconds a request waits for the running request of its session
+    /// before the server answers 503; 0 waits indefinitely.
+    pub session_lock_timeout: u32,
+    /// Seconds a request waits for `Application.Lock` held by another
+    /// request before failing with a script error; 0 waits indefinitely.
+    pub app_lock_timeout: u32,
+    /// Seconds between checks of Global.asa for changes; 0 checks on
+    /// every request.
+    pub global_asa_check_interval: u32,
+    /// Where session and application variables are kept.
+    pub state: StateConfig,
     pub log_level: String,
+    /// Whether compiled pages are cached between requests.
+    pub page_cache: bool,
+    /// Maximum number of compiled pages kept; the least recently used is evicted.
+    pub page_cache_size: usize,
+    /// Most threads executing pages (IIS `ASPProcessorThreadMax` ×
+    /// processors), started as requests need them.
+    pub worker_threads: usize,
+    /// Requests waiting for a worker beyond which the server answers 503
+    /// (IIS `RequestQueueMax`).
+    pub request_queue_max: usize,
 }
 
 impl Default for AspServerConfig {
@@ -177,7 +283,18 @@ impl Default for AspServerConfig {
                 "iisstart.htm".into(),
             ],
             directory_listing: false,
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            limits: ResourceLimits::default(),
+            session_timeout: DEFAULT_SESSION_TIMEOUT,
+            session_lock_timeout: DEFAULT_SESSION_LOCK_TIMEOUT,
+            app_lock_timeout: DEFAULT_APP_LOCK_TIMEOUT,
+            global_asa_check_interval: DEFAULT_GLOBAL_ASA_CHECK_INTERVAL,
+            state: StateConfig::default(),
             log_level: "info".to_string(),
+            page_cache: true,
+            page_cache_size: 256,
+            worker_threads: 25 * std::thread::available_parallelism().map_or(1, |n| n.get()),
+            request_queue_max: 3000,
         }
     }
 }
@@ -185,27 +302,87 @@ impl Default for AspServerConfig {
 impl AspServerConfig {
     /// Load `asp.ini` from the served folder and apply its values on top of defaults.
     ///
-    /// Reads the `[server]` section of `<folder>/asp.ini` and applies recognized keys.
-    /// This is the per-server-root INI; per-directory INI files are handled by
+    /// Reads the `[server]`, `[session]`, `[application]` and `[state]`
+    /// sections of `<folder>/asp.ini` and applies recognized keys.  This is the
+    /// per-server-root INI; per-directory INI files are handled by
     /// `DirConfigCache` at request time.
+    ///
+    /// `[session]` keys:
+    /// - `timeout` — default `Session.Timeout` in minutes
+    /// - `lock_timeout` — seconds a request waits for another request of
+    ///   its session
+    ///
+    /// `[application]` keys:
+    /// - `lock_timeout` — seconds a request waits for `Application.Lock`
+    /// - `check_interval` — seconds between checks of Global.asa for changes
+    ///
+    /// `[state]` keys:
+    /// - `backend` — `memory`, `file` or `kv`
+    /// - `path` — directory of the `file` backend, relative to the folder
+    /// - `address`, `prefix` — server and key prefix of the `kv` backend
     pub fn from_folder(folder: &str) -> Self {
         let mut cfg = Self { folder: folder.to_string(), ..Self::default() };
 
         let ini_path = Path::new(folder).join("asp.ini");
         if let Ok(content) = std::fs::read_to_string(&ini_path) {
-            let mut in_server = false;
+            let mut section = String::new();
             for line in content.lines() {
                 let line = line.trim();
                 if line.starts_with('[') && line.ends_with(']') {
-                    in_server = line.eq_ignore_ascii_case("[server]");
+                    section = line.to_lowercase();
                     continue;
                 }
-                if !in_server || line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
                     continue;
                 }
                 if let Some((key, value)) = line.split_once('=') {
                     let key = key.trim().to_lowercase();
                     let value = value.trim();
+                    if section == "[session]" {
+                        match key.as_str() {
+                            "timeout" => {
+                                if let Ok(n) = value.parse::<u32>() {
+                                    cfg.session_timeout = n.clamp(1, MAX_SESSION_TIMEOUT);
+                                }
+                            }
+                            "lock_timeout" => {
+                                if let Ok(n) = value.parse::<u32>() {
+                                    cfg.session_lock_timeout = n;
+                                }
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if section == "[application]" {
+                        match key.as_str() {
+                            "lock_timeout" => {
+                                if let Ok(n) = value.parse::<u32>() {
+                                    cfg.app_lock_timeout = n;
+                                }
+                            }
+                            "check_interval" => {
+                                if let Ok(n) = value.parse::<u32>() {
+                                    cfg.global_asa_check_interval = n;
+                                }
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if section == "[state]" {
+                        match key.as_str() {
+                            "backend" => cfg.state.backend = value.to_lowercase(),
+                            "path" if !value.is_empty() => cfg.state.path = Some(Path::new(folder).join(value)),
+                            "address" if !value.is_empty() => cfg.state.address = value.to_string(),
+                            "prefix" => cfg.state.prefix = value.to_string(),
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if section != "[server]" {
+                        continue;
+                    }
                     match key.as_str() {
                         "host" => cfg.host = value.to_string(),
                         "port" => {
@@ -220,17 +397,40 @@ impl AspServerConfig {
                                 .filter(|s| !s.is_empty())
                                 .collect();
                         }
-                        "default_document" => {
-                            if !value.is_empty() {
-                                cfg.default_documents = vec![value.to_string()];
-                            }
+                        "default_document" if !value.is_empty() => {
+                            cfg.default_documents = vec![value.to_string()];
                         }
                         "enable_directory_listing" => {
                             cfg.directory_listing = value.eq_ignore_ascii_case("true");
                         }
-                        "log_level" => {
-                            if !value.is_empty() {
-                                cfg.log_level = value.to_string();
+                        "script_timeout" => {
+                            if let Ok(n) = value.parse::<u32>() {
+                                cfg.script_timeout = n.max(1);
+                            }
+                        }
+                        "max_instructions" | "max_call_depth" | "max_array_elements"
+                        | "max_string_length" | "max_response_bytes" => {
+                            apply_limit(&mut cfg.limits, &key, value);
+                        }
+                        "log_level" if !value.is_empty() => {
+                            cfg.log_level = value.to_string();
+                        }
+                        "page_cache" => {
+                            cfg.page_cache = value.eq_ignore_ascii_case("true");
+                        }
+                        "page_cache_size" => {
+                            if let Ok(n) = value.parse::<usize>() {
+                                cfg.page_cache_size = n;
+                            }
+                        }
+                        "worker_threads" => {
+                            if let Ok(n) = value.parse::<usize>() {
+                                cfg.worker_threads = n;
+                            }
+                        }
+                        "request_queue_max" => {
+                            if let Ok(n) = value.parse::<usize>() {
+                                cfg.request_queue_max = n;
                             }
                         }
                         _ => {}
@@ -251,11 +451,58 @@ impl AspServerConfig {
             AspDirConfig {
                 default_documents: self.default_documents.clone(),
                 directory_listing: self.directory_listing,
+                script_timeout: self.script_timeout,
+                limits: self.limits.clone(),
             },
             root,
         )
     }
 
+    /// Build the shared compiled-page cache; disabled when `page_cache` is
+    /// off or `page_cache_size` is 0.
+    pub fn build_page_cache(&self) -> PageCache {
+        if self.page_cache {
+            PageCache::new(self.page_cache_size)
+        } else {
+            PageCache::disabled()
+        }
+    }
+
+    /// Start the pool of threads that execute pages, with native stacks
+    /// deep enough for `limits.max_call_depth`.
+    pub fn build_worker_pool(&self) -> WorkerPool {
+        let stack_size = stack_size_for(self.limits.max_call_depth).max(DEFAULT_STACK_SIZE);
+        WorkerPool::with_stack_size(self.worker_threads, self.request_queue_max, stack_size)
+    }
+
+    /// Open the store keeping session and application state in the
+    /// backend `[state]` names.
+    pub fn build_store(&self) -> io::Result<Arc<Store>> {
+        let store = match self.state.backend.as_str() {
+            "memory" => return Ok(Store::new()),
+            "file" => {
+                let path = self.state.path.as_ref().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "[state] backend = file needs a path")
+                })?;
+                Store::with_backend(Box::new(FileBackend::open(path)?))
+            }
+            "kv" => Store::with_backend(Box::new(KvBackend::connect(&self.state.address, &self.state.prefix)?)),
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown [state] backend: {}", other),
+                ))
+            }
+        };
+        store.session_timeout_minutes.store(self.session_timeout, Ordering::Relaxed);
+        // Sessions in a shared key-value server are timed out by the
+        // instances serving them; those in files belong to this one.
+        if self.state.backend == "file" {
+            store.adopt_sessions()?;
+        }
+        Ok(store)
+    }
+
     /// Apply overrides from external sources (e.g. DAP launch args or CLI args).
     ///
     /// Override priority (highest wins):
@@ -298,6 +545,26 @@ impl AspServerConfig {
             }
         }
     }
+
+    /// Apply page cache settings from CLI overrides (higher priority than ini).
+    pub fn apply_page_cache(&mut self, enabled: Option<bool>, size: Option<usize>) {
+        if let Some(e) = enabled {
+            self.page_cache = e;
+        }
+        if let Some(n) = size {
+            self.page_cache_size = n;
+        }
+    }
+
+    /// Apply worker pool settings from CLI overrides (higher priority than ini).
+    pub fn apply_worker_pool(&mut self, worker_threads: Option<usize>, request_queue_max: Option<usize>) {
+        if let Some(n) = worker_threads {
+            self.worker_threads = n;
+        }
+        if let Some(n) = request_queue_max {
+            self.request_queue_max = n;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -339,6 +606,127 @@ mod tests {
         let _ = std::fs::remove_dir_all(&dir);
     }
 
+    #[test]
+    fn test_asp_server_config_from_folder_page_cache() {
+        let dir = std::env::temp_dir().join(format!("asp_test_page_cache_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(dir.join("asp.ini"), "[server]\npage_cache = false\npage_cache_size = 16\n").unwrap();
+        let mut cfg = AspServerConfig::from_folder(dir.to_str().unwrap());
+        assert!(!cfg.page_cache);
+        assert_eq!(cfg.page_cache_size, 16);
+        assert!(!cfg.build_page_cache().is_enabled());
+
+        cfg.apply_page_cache(Some(true), None);
+        assert!(cfg.build_page_cache().is_enabled());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_asp_server_config_from_folder_worker_pool() {
+        let dir = std::env::temp_dir().join(format!("asp_test_worker_pool_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(dir.join("asp.ini"), "[server]\nworker_threads = 4\nrequest_queue_max = 10\n").unwrap();
+        let mut cfg = AspServerConfig::from_folder(dir.to_str().unwrap());
+        assert_eq!(cfg.worker_threads, 4);
+        assert_eq!(cfg.request_queue_max, 10);
+
+        cfg.apply_worker_pool(Some(2), None);
+        assert_eq!(cfg.worker_threads, 2);
+        assert_eq!(cfg.request_queue_max, 10);
+        assert_eq!(cfg.build_worker_pool().workers(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_script_timeout_from_ini() {
+        let dir = std::env::temp_dir().join(format!("asp_test_script_timeout_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(dir.join("slow"));
+        std::fs::write(dir.join("asp.ini"), "[server]\nscript_timeout = 30\n").unwrap();
+        std::fs::write(dir.join("slow").join("asp.ini"), "[server]\nscript_timeout = 600\n").unwrap();
+        let cfg = AspServerConfig::from_folder(dir.to_str().unwrap());
+        assert_eq!(cfg.script_timeout, 30);
+
+        let root = dir.canonicalize().unwrap();
+        let cache = cfg.build_dir_cache();
+        assert_eq!(cache.resolve(&root).script_timeout, 30);
+        assert_eq!(cache.resolve(&root.join("slow")).script_timeout, 600);
+
+        std::fs::write(dir.join("slow").join("asp.ini"), "[server]\nscript_timeout = 0\n").unwrap();
+        let cache = AspServerConfig::from_folder(dir.to_str().unwrap()).build_dir_cache();
+        assert_eq!(cache.resolve(&root.join("slow")).script_timeout, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_session_timeout_from_ini() {
+        let dir = std::env::temp_dir().join(format!("asp_test_session_timeout_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        assert_eq!(AspServerConfig::from_folder(dir.to_str().unwrap()).session_timeout, 20);
+        std::fs::write(
+            dir.join("asp.ini"),
+            "[server]\ntimeout = 99\n\n[session]\ntimeout = 45\nlock_timeout = 5\nport = 1234\n\n[application]\nlock_timeout = 7\ncheck_interval = 0\n",
+        )
+        .unwrap();
+        let cfg = AspServerConfig::from_folder(dir.to_str().unwrap());
+        assert_eq!(cfg.session_timeout, 45);
+        assert_eq!(cfg.session_lock_timeout, 5);
+        assert_eq!(cfg.app_lock_timeout, 7);
+        assert_eq!(cfg.global_asa_check_interval, 0);
+        assert_eq!(cfg.port, 9090);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_state_backend_from_ini() {
+        let dir = std::env::temp_dir().join(format!("asp_test_state_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::create_dir_all(&dir);
+        let folder = dir.to_str().unwrap();
+        let cfg = AspServerConfig::from_folder(folder);
+        assert_eq!(cfg.state, StateConfig::default());
+        assert!(!cfg.build_store().unwrap().is_persistent());
+
+        std::fs::write(dir.join("asp.ini"), "[state]\nbackend = File\n").unwrap();
+        assert!(AspServerConfig::from_folder(folder).build_store().is_err());
+        std::fs::write(dir.join("asp.ini"), "[state]\nbackend = file\npath = state\n").unwrap();
+        let cfg = AspServerConfig::from_folder(folder);
+        assert_eq!(cfg.state.path, Some(dir.join("state")));
+        let store = cfg.build_store().unwrap();
+        assert!(store.is_persistent());
+        assert!(dir.join("state").is_dir());
+
+        std::fs::write(dir.join("asp.ini"), "[state]\nbackend = kv\naddress = db:6380\nprefix = shop\n").unwrap();
+        let cfg = AspServerConfig::from_folder(folder);
+        assert_eq!(cfg.state.address, "db:6380");
+        assert_eq!(cfg.state.prefix, "shop");
+        std::fs::write(dir.join("asp.ini"), "[state]\nbackend = sql\n").unwrap();
+        assert!(AspServerConfig::from_folder(folder).build_store().is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resource_limits_from_ini() {
+        let dir = std::env::temp_dir().join(format!("asp_test_limits_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(dir.join("big"));
+        std::fs::write(
+            dir.join("asp.ini"),
+            "[server]\nmax_call_depth = 1000\nmax_string_length = 4096\nmax_instructions = 0\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("big").join("asp.ini"), "[server]\nmax_response_bytes = 1048576\n").unwrap();
+        let cfg = AspServerConfig::from_folder(dir.to_str().unwrap());
+        assert_eq!(cfg.limits.max_call_depth, 1000);
+        assert_eq!(cfg.limits.max_string_length, 4096);
+        assert_eq!(cfg.limits.max_instructions, 0);
+        assert_eq!(cfg.limits.max_array_elements, ResourceLimits::default().max_array_elements);
+
+        let root = dir.canonicalize().unwrap();
+        let resolved = cfg.build_dir_cache().resolve(&root.join("big"));
+        assert_eq!(resolved.limits.max_response_bytes, 1048576);
+        assert_eq!(resolved.limits.max_string_length, 4096);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_asp_server_config_apply_overrides_replaces_list() {
         let mut cfg = AspServerConfig::default();
@@ -361,6 +749,8 @@ mod tests {
         let base = AspDirConfig {
             default_documents: vec!["index.asp".to_string()],
             directory_listing: false,
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            limits: ResourceLimits::default(),
         };
         let root = dir.canonicalize().unwrap();
         let cache = DirConfigCache::new(base.clone(), root.clone());
@@ -384,6 +774,8 @@ mod tests {
         let base = AspDirConfig {
             default_documents: vec!["index.asp".to_string()],
             directory_listing: false,
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            limits: ResourceLimits::default(),
         };
         let root = dir.canonicalize().unwrap();
         let sub = root.join("sub");
@@ -412,6 +804,8 @@ mod tests {
         let base = AspDirConfig {
             default_documents: vec!["index.asp".to_string()],
             directory_listing: false,
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            limits: ResourceLimits::default(),
         };
         let root = dir.canonicalize().unwrap();
         let deep = root.join("sub").join("deep");
@@ -430,6 +824,8 @@ mod tests {
         let base = AspDirConfig {
             default_documents: vec!["a.asp".to_string()],
             directory_listing: false,
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            limits: ResourceLimits::default(),
         };
         let root = dir.canonicalize().unwrap();
         let cache = DirConfigCache::new(base, root.clone());
@@ -448,6 +844,8 @@ mod tests {
         let base = AspDirConfig {
             default_documents: vec!["base.asp".to_string()],
             directory_listing: true,
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            limits: ResourceLimits::default(),
         };
         let root = dir.canonicalize().unwrap();
         let empty = root.join("empty");
@@ -464,6 +862,8 @@ mod tests {
         let a = AspDirConfig {
             default_documents: vec!["x.asp".to_string()],
             directory_listing: true,
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            limits: ResourceLimits::default(),
         };
         let b = a.clone();
         assert_eq!(a.default_documents, b.default_documents);
@@ -0,0 +1,576 @@
+//! Global.asa: the application's event handlers and the objects it declares.
+//!
+//! The file in the application root holds `<script runat="server">` blocks,
+//! compiled once when it is loaded, and `<object runat="server">` tags that
+//! declare application- or session-scoped objects.  Its events run in
+//! contexts of their own, so pages never see Global.asa's variables, only
+//! the objects it declares.
+//!
+//! `Application_OnStart` runs before the first request is served and
+//! `Session_OnStart` before the first page of each new session.
+//! `Session_OnEnd` runs once a session is abandoned or has been idle past
+//! its `Session.Timeout`, and `Application_OnEnd` when the server shuts
+//! down.  Changing Global.asa restarts the application: every session and
+//! then the application itself are ended, all their state is discarded and
+//! the next request starts over with the new file.  The file is checked for
+//! changes at most once every `Store::global_asa_check_secs`.  With a
+//! persistent state backend a restart keeps the sessions and variables, as
+//! stopping the server does, and only runs `Application_OnStart` again.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use ahash::AHashMap;
+use regex::Regex;
+
+use crate::asp::asp_error::ASPError;
+use crate::asp::include_resolver::IncludeResolver;
+use crate::asp::parser::AspBlock;
+use crate::asp::server::AspServer;
+use crate::asp::worker_pool;
+use crate::vbscript::interpreter::CompiledScript;
+use crate::vbscript::store::Store;
+use crate::vbscript::{ExecutionContext, VBScriptInterpreter, VBValue};
+
+/// Spellings of the file name looked for in the application root.
+const FILE_NAMES: [&str; 3] = ["global.asa", "Global.asa", "GLOBAL.ASA"];
+
+pub const APPLICATION_ON_START: &str = "Application_OnStart";
+pub const APPLICATION_ON_END: &str = "Application_OnEnd";
+pub const SESSION_ON_START: &str = "Session_OnStart";
+pub const SESSION_ON_END: &str = "Session_OnEnd";
+
+fn get_script_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?is)<script\b([^>]*)>(.*?)</script\s*>").unwrap())
+}
+
+fn get_object_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)<object\b([^>]*)>").unwrap())
+}
+
+fn get_attribute_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r#"([\w-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>/]+))"#).unwrap()
+    })
+}
+
+/// Attributes of a tag, by lowercased name.
+fn parse_attributes(tag: &str) -> AHashMap<String, String> {
+    get_attribute_regex()
+        .captures_iter(tag)
+        .map(|cap| {
+            let value = cap.get(2).or(cap.get(3)).or(cap.get(4)).map_or("", |m| m.as_str());
+            (cap[1].to_lowercase(), value.to_string())
+        })
+        .collect()
+}
+
+fn runs_at_server(attributes: &AHashMap<String, String>) -> bool {
+    attributes.get("runat").is_some_and(|v| v.eq_ignore_ascii_case("server"))
+}
+
+/// Lifetime of an object declared in Global.asa.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectScope {
+    /// One instance shared by the whole application.
+    Application,
+    /// One instance per session.
+    Session,
+}
+
+/// An `<object runat="server">` declaration.
+#[derive(Debug, Clone)]
+pub struct StaticObject {
+    /// Name the object is known by in scripts.
+    pub id: String,
+    pub scope: ObjectScope,
+    /// ProgID the object is created from.
+    pub prog_id: String,
+}
+
+/// A parsed and compiled Global.asa.
+pub struct GlobalAsa {
+    path: PathBuf,
+    /// The server-side script blocks, or `None` when there are none.
+    script: Option<CompiledScript>,
+    objects: Vec<StaticObject>,
+}
+
+impl GlobalAsa {
+    /// Parse and compile the contents of the Global.asa at `path`.
+    pub fn parse(source: &str, path: &Path) -> Result<Self, String> {
+        let mut blocks = Vec::new();
+        for cap in get_script_regex().captures_iter(source) {
+            let attributes = parse_attributes(&cap[1]);
+            if !runs_at_server(&attributes) {
+                continue;
+            }
+            if let Some(language) = attributes.get("language") {
+                if !language.eq_ignore_ascii_case("vbscript") && !language.eq_ignore_ascii_case("vbs") {
+                    return Err(format!("Unsupported script language '{}' in Global.asa", language));
+                }
+            }
+            let code = cap.get(2).unwrap();
+            let line = source[..code.start()].matches('\n').count() + 1;
+            blocks.push(AspBlock::Code(code.as_str().to_string(), line));
+        }
+
+        let mut objects: Vec<StaticObject> = Vec::new();
+        for cap in get_object_regex().captures_iter(source) {
+            let attributes = parse_attributes(&cap[1]);
+            if !runs_at_server(&attributes) {
+                continue;
+            }
+            let id = attributes
+                .get("id")
+                .filter(|id| !id.is_empty())
+                .ok_or("Missing ID attribute in Global.asa <object> tag")?;
+            let scope = match attributes.get("scope").map(|s| s.to_lowercase()).as_deref() {
+                Some("application") => ObjectScope::Application,
+                Some("session") => ObjectScope::Session,
+                _ => return Err(format!("Invalid SCOPE for object '{}' in Global.asa", id)),
+            };
+            let prog_id = attributes
+                .get("progid")
+                .ok_or_else(|| format!("Missing PROGID for object '{}' in Global.asa", id))?;
+            if objects.iter().any(|o| o.id.eq_ignore_ascii_case(id)) {
+                return Err(format!("Object '{}' declared twice in Global.asa", id));
+            }
+            objects.push(StaticObject {
+                id: id.clone(),
+                scope,
+                prog_id: prog_id.clone(),
+            });
+        }
+
+        let blocks: Vec<&AspBlock> = blocks.iter().collect();
+        let script = VBScriptInterpreter
+            .compile_vm_blocks(&blocks)
+            .map_err(|e| format!("Global.asa: {}", e))?;
+        Ok(GlobalAsa {
+            path: path.to_path_buf(),
+            script,
+            objects,
+        })
+    }
+
+    /// Path of the Global.asa in `root`, if there is one.
+    pub fn locate(root: &Path) -> Option<PathBuf> {
+        FILE_NAMES.iter().map(|name| root.join(name)).find(|path| path.is_file())
+    }
+
+    /// Read and compile the Global.asa at `path`, expanding its includes.
+    pub fn load(path: &Path, root: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read '{}': {}", path.display(), e))?;
+        let expanded = IncludeResolver::expand(&source, root, root)?;
+        Self::parse(&expanded, path)
+    }
+
+    /// Whether Global.asa handles `event`.
+    pub fn handles(&self, event: &str) -> bool {
+        self.script.as_ref().is_some_and(|script| script.defines(event))
+    }
+
+    /// Objects declared with the given scope.
+    pub fn objects(&self, scope: ObjectScope) -> impl Iterator<Item = &StaticObject> {
+        self.objects.iter().filter(move |o| o.scope == scope)
+    }
+
+    /// Create the objects declared with `scope`, by uppercased ID.
+    fn create_objects(&self, scope: ObjectScope) -> AHashMap<String, VBValue> {
+        self.objects(scope)
+            .filter_map(|object| match crate::vbscript::asp_objects::create_object(&object.prog_id) {
+                Ok(value) => Some((object.id.to_uppercase(), value)),
+                Err(e) => {
+                    tracing::error!(id = %object.id, progid = %object.prog_id, error = %e, "Global.asa object could not be created");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// A context for running an event, with the session `session_id`
+    /// available if given.
+    fn event_context(&self, store: &Arc<Store>, session_id: Option<&str>) -> ExecutionContext {
+        let mut context = ExecutionContext::new();
+        context.script_path = self.path.display().to_string();
+        context.app_root = self.path.parent().map(Path::to_path_buf).unwrap_or_default();
+        context.limits.max_call_depth = worker_pool::clamp_call_depth(context.limits.max_call_depth);
+        context.store = Some(Arc::clone(store));
+        context.request_id = store.allocate_request_id();
+        if let Some(id) = session_id {
+            context.session.id = id.to_string();
+            context.session.enabled = true;
+        }
+        context.enforce_script_timeout();
+        context
+    }
+
+    /// Run the handler for `event` in `context`, if Global.asa has one.
+    fn fire(&self, event: &str, context: &mut ExecutionContext) {
+        let Some(script) = self.script.as_ref().filter(|_| self.handles(event)) else {
+            return;
+        };
+        let _span = tracing::info_span!("global_asa", event).entered();
+        let store = context.store.clone();
+        let app_lock = store.as_deref().map(|store| store.app_lock_owner(context.request_id));
+        AspServer::inject_asp_intrinsic_objects(context);
+        let interpreter = VBScriptInterpreter;
+        let mut result = interpreter
+            .run_script(script, context)
+            .and_then(|()| interpreter.execute(&format!("Call {}()", event), context));
+        if let Err(e) = context.terminate_class_instances() {
+            result = result.and(Err(e));
+        }
+        if let Some(ref store) = store {
+            store.save_fetched(context.request_id);
+        }
+        drop(app_lock);
+        if let Err(e) = result {
+            tracing::error!(error = %e, "Global.asa event failed");
+        }
+    }
+
+    /// Create the application's objects and run `Application_OnStart`.
+    fn start_application(&self, store: &Arc<Store>) {
+        *store.lock_static_objects() = self.create_objects(ObjectScope::Application);
+        let mut context = self.event_context(store, None);
+        self.fire(APPLICATION_ON_START, &mut context);
+    }
+
+    /// End every session, run `Application_OnEnd` and discard all state.
+    fn end_application(&self, store: &Arc<Store>) {
+        for id in store.session_ids() {
+            end_session(store, Some(self), &id);
+        }
+        let mut context = self.event_context(store, None);
+        self.fire(APPLICATION_ON_END, &mut context);
+        store.clear();
+    }
+}
+
+/// Where the application stands with respect to its Global.asa, kept in
+/// the [`Store`].
+#[derive(Default)]
+pub struct GlobalAsaState {
+    /// Whether the application has started.
+    started: bool,
+    /// Path and modification time of the Global.asa it started with.
+    file: Option<(PathBuf, Option<SystemTime>)>,
+    /// When the file was last compared with the one on disk.
+    checked_at: Option<Instant>,
+    current: Option<Arc<GlobalAsa>>,
+}
+
+impl GlobalAsaState {
+    /// Whether the running application's Global.asa was checked for
+    /// changes less than `interval` ago.
+    fn checked_within(&self, interval: Duration) -> bool {
+        self.started && self.checked_at.is_some_and(|at| at.elapsed() < interval)
+    }
+}
+
+/// Path and modification time of the Global.asa in `root`, if any.
+fn on_disk(root: &Path) -> Option<(PathBuf, Option<SystemTime>)> {
+    GlobalAsa::locate(root).map(|path| {
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        (path, modified)
+    })
+}
+
+/// End the application that ran with `previous` before a restart.  State
+/// kept by a persistent backend outlives it, as it outlives the server.
+fn end_for_restart(store: &Arc<Store>, previous: Option<Arc<GlobalAsa>>) {
+    if store.is_persistent() {
+        store.clear_static_objects();
+    } else if let Some(global) = previous {
+        global.end_application(store);
+    } else {
+        store.clear();
+    }
+}
+
+/// Start the application rooted at `root` if it is not running yet, and
+/// restart it if its Global.asa has been added, changed or removed since.
+///
+/// Returns the Global.asa the application runs with, if any.
+pub fn start_application(store: &Arc<Store>, root: &str) -> Result<Option<Arc<GlobalAsa>>, ASPError> {
+    let root = Path::new(root);
+    let interval = Duration::from_secs(store.global_asa_check_secs.load(Ordering::Relaxed) as u64);
+    {
+        let mut state = store.lock_global_asa();
+        if state.checked_within(interval) {
+            return Ok(state.current.clone());
+        }
+        if state.started {
+            // Requests arriving while this one looks at the file keep going
+            state.checked_at = Some(Instant::now());
+        }
+    }
+    let file = on_disk(root);
+    {
+        let state = store.lock_global_asa();
+        if state.started && state.file == file {
+            return Ok(state.current.clone());
+        }
+    }
+
+    let _restart = store.lock_application_restart();
+    // Another request may have started the application while this one waited
+    let file = on_disk(root);
+    let previous = {
+        let mut state = store.lock_global_asa();
+        if state.started && state.file == file {
+            return Ok(state.current.clone());
+        }
+        let previous = std::mem::take(&mut *state);
+        previous.started.then_some(previous.current)
+    };
+    if let Some(previous) = previous {
+        tracing::info!("Global.asa changed, restarting application");
+        end_for_restart(store, previous);
+    }
+
+    let current = match file {
+        Some((ref path, _)) => Some(Arc::new(
+            GlobalAsa::load(path, root).map_err(|e| ASPError::new(500, e))?,
+        )),
+        None => None,
+    };
+    if let Some(ref global) = current {
+        global.start_application(store);
+    }
+    *store.lock_global_asa() = GlobalAsaState {
+        started: true,
+        file,
+        checked_at: Some(Instant::now()),
+        current: current.clone(),
+    };
+    Ok(current)
+}
+
+/// End the application, if it has started: every session is ended, then
+/// `Application_OnEnd` runs.
+pub fn end_application(store: &Arc<Store>) {
+    let _restart = store.lock_application_restart();
+    let state = std::mem::take(&mut *store.lock_global_asa());
+    if let Some(global) = state.current {
+        global.end_application(store);
+    }
+}
+
+/// The Global.asa of the running application, if any.
+pub fn current(store: &Store) -> Option<Arc<GlobalAsa>> {
+    store.lock_global_asa().current.clone()
+}
+
+/// Start the new session of the page about to run in `context`: create its
+/// objects and run `Session_OnStart`, which writes to the page's response.
+pub fn start_session(store: &Arc<Store>, global: &GlobalAsa, context: &mut ExecutionContext) {
+    let objects = global.create_objects(ObjectScope::Session);
+    if !objects.is_empty() {
+        store
+            .lock_session_objects()
+            .insert(context.session.id.to_uppercase(), objects);
+    }
+    if !global.handles(SESSION_ON_START) {
+        return;
+    }
+    let mut event = global.event_context(store, Some(&context.session.id));
+    event.request = context.request.clone();
+    event.response = std::mem::take(&mut context.response);
+    global.fire(SESSION_ON_START, &mut event);
+    context.response = std::mem::take(&mut event.response);
+}
+
+/// End the session `session_id`: run `Session_OnEnd` of `global` while its
+/// contents are still there, then remove it.
+pub fn end_session(store: &Arc<Store>, global: Option<&GlobalAsa>, session_id: &str) {
+    if let Some(global) = global {
+        let mut context = global.event_context(store, Some(session_id));
+        global.fire(SESSION_ON_END, &mut context);
+    }
+    store.remove_session(session_id);
+}
+
+/// End every session that has been idle past its timeout at `now`.
+pub fn end_expired_sessions(store: &Arc<Store>, now: Instant) {
+    let expired = store.claim_expired_sessions(now);
+    if expired.is_empty() {
+        return;
+    }
+    tracing::debug!(count = expired.len(), "Ending expired sessions");
+    let global = current(store);
+    for id in expired {
+        end_session(store, global.as_deref(), &id);
+    }
+}
+
+/// End a session abandoned by its page, whose contents `Session.Abandon`
+/// took out of the store.
+pub fn end_abandoned_session(store: &Arc<Store>, session_id: &str, contents: AHashMap<String, VBValue>) {
+    let global = current(store);
+    if global.as_ref().is_some_and(|g| g.handles(SESSION_ON_END)) {
+        store.restore_session(session_id, contents);
+    }
+    end_session(store, global.as_deref(), session_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compiled(source: &str) -> GlobalAsa {
+        GlobalAsa::parse(source, Path::new("global.asa")).unwrap()
+    }
+
+    #[test]
+    fn test_parse_finds_server_script_handlers() {
+        let global = compiled(
+            "<SCRIPT LANGUAGE=VBScript RUNAT=Server>\nSub Application_OnStart\nEnd Sub\n</SCRIPT>\n\
+             <script language=\"VBScript\" runat=\"server\">\nSub session_onstart()\nEnd Sub\n</script>\n\
+             <script language=\"VBScript\">\nSub Session_OnEnd\nEnd Sub\n</script>",
+        );
+        assert!(global.handles(APPLICATION_ON_START));
+        assert!(global.handles(SESSION_ON_START));
+        assert!(!global.handles(SESSION_ON_END));
+        assert!(!global.handles(APPLICATION_ON_END));
+    }
+
+    #[test]
+    fn test_parse_object_declarations() {
+        let global = compiled(
+            "<OBJECT RUNAT=Server SCOPE=Application ID=Cache PROGID=\"Scripting.Dictionary\"></OBJECT>\n\
+             <object runat='server' scope='session' id='Cart' progid='Scripting.Dictionary' />\n\
+             <object id=\"Client\" classid=\"clsid:1234\"></object>",
+        );
+        let app: Vec<_> = global.objects(ObjectScope::Application).collect();
+        assert_eq!(app.len(), 1);
+        assert_eq!(app[0].id, "Cache");
+        assert_eq!(app[0].prog_id, "Scripting.Dictionary");
+        let session: Vec<_> = global.objects(ObjectScope::Session).collect();
+        assert_eq!(session.len(), 1);
+        assert_eq!(session[0].id, "Cart");
+        assert!(global.script.is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_declarations() {
+        let path = Path::new("global.asa");
+        assert!(GlobalAsa::parse("<script language=JScript runat=server></script>", path).is_err());
+        assert!(GlobalAsa::parse("<object runat=server scope=page id=x progid=a.b>", path).is_err());
+        assert!(GlobalAsa::parse("<object runat=server scope=session id=x>", path).is_err());
+        assert!(GlobalAsa::parse("<script runat=server>\nSub X(\n</script>", path).is_err());
+    }
+
+    #[test]
+    fn test_application_lifecycle_events() {
+        let dir = std::env::temp_dir().join(format!("asp_global_asa_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let log = dir.join("events.log");
+        let _ = std::fs::remove_file(&log);
+        std::fs::write(
+            dir.join("global.asa"),
+            format!(
+                "<script language=\"VBScript\" runat=\"server\">\n\
+                 Sub Log(s)\n\
+                 Dim fso, f\n\
+                 Set fso = CreateObject(\"Scripting.FileSystemObject\")\n\
+                 Set f = fso.OpenTextFile(\"{}\", 8, True)\n\
+                 f.WriteLine s\n\
+                 f.Close\n\
+                 End Sub\n\
+                 Sub Application_OnStart\nApplication(\"started\") = True\nLog \"app start\"\nEnd Sub\n\
+                 Sub Session_OnEnd\nLog \"session end \" & Session(\"user\")\nEnd Sub\n\
+                 Sub Application_OnEnd\nLog \"app end\"\nEnd Sub\n\
+                 </script>",
+                log.display()
+            ),
+        )
+        .unwrap();
+        let store = Store::new();
+        let root = dir.to_str().unwrap();
+
+        let global = start_application(&store, root).unwrap().unwrap();
+        assert_eq!(store.get_app_var("started").unwrap(), VBValue::Boolean(true));
+        // A running application is not started again
+        start_application(&store, root).unwrap();
+
+        assert!(store.access_session("S1"));
+        store.set_session_var("S1", "user", VBValue::String("ann".into())).unwrap();
+        end_session(&store, Some(&global), "S1");
+        assert_eq!(store.session_count(), 0);
+
+        end_application(&store);
+        assert!(store.app_vars().unwrap().is_empty());
+        let events = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(events.lines().collect::<Vec<_>>(), ["app start", "session end ann", "app end"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn write_counting_global_asa(dir: &Path, version: &str) {
+        // Ensure each version gets a different modification time
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(
+            dir.join("global.asa"),
+            format!(
+                "<script language=\"VBScript\" runat=\"server\">\n\
+                 Sub Application_OnStart\nApplication(\"version\") = \"{}\"\n\
+                 Application(\"starts\") = Application(\"starts\") + 1\nEnd Sub\n\
+                 Sub Session_OnEnd\nApplication(\"ended\") = Session(\"user\")\nEnd Sub\n\
+                 </script>",
+                version
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_global_asa_changes_are_checked_at_an_interval() {
+        let dir = std::env::temp_dir().join(format!("asp_global_asa_interval_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let store = Store::new();
+        let root = dir.to_str().unwrap();
+        write_counting_global_asa(&dir, "one");
+        start_application(&store, root).unwrap();
+
+        write_counting_global_asa(&dir, "two");
+        start_application(&store, root).unwrap();
+        assert_eq!(store.get_app_var("version").unwrap(), VBValue::String("one".into()));
+
+        store.global_asa_check_secs.store(0, Ordering::Relaxed);
+        start_application(&store, root).unwrap();
+        assert_eq!(store.get_app_var("version").unwrap(), VBValue::String("two".into()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_restart_keeps_persistent_state() {
+        use crate::vbscript::store::FileBackend;
+        let dir = std::env::temp_dir().join(format!("asp_global_asa_persistent_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::create_dir_all(dir.join("state"));
+        let store = Store::with_backend(Box::new(FileBackend::open(dir.join("state")).unwrap()));
+        store.global_asa_check_secs.store(0, Ordering::Relaxed);
+        let root = dir.to_str().unwrap();
+        write_counting_global_asa(&dir, "one");
+        start_application(&store, root).unwrap();
+        assert!(store.access_session("S1"));
+        store.set_session_var("S1", "user", VBValue::String("ann".into())).unwrap();
+
+        write_counting_global_asa(&dir, "two");
+        start_application(&store, root).unwrap();
+        assert_eq!(store.get_app_var("version").unwrap(), VBValue::String("two".into()));
+        assert_eq!(store.get_app_var("starts").unwrap(), VBValue::Integer(2));
+        // No session was ended and none of the state was discarded
+        assert_eq!(store.get_app_var("ended").unwrap(), VBValue::Empty);
+        assert_eq!(store.get_session_var("S1", "user").unwrap(), VBValue::String("ann".into()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
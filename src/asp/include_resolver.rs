@@ -21,8 +21,20 @@ impl IncludeResolver {
     /// Expand all includes in `source`, resolving paths relative to `base_dir`
     /// and `root_dir`. Returns the fully expanded source text or an error.
     pub fn expand(source: &str, base_dir: &Path, root_dir: &Path) -> Result<String, String> {
+        Self::expand_with_files(source, base_dir, root_dir).map(|(expanded, _)| expanded)
+    }
+
+    /// Like `expand`, also returning the canonical path of every file that
+    /// was included, directly or transitively, each listed once.
+    pub fn expand_with_files(
+        source: &str,
+        base_dir: &Path,
+        root_dir: &Path,
+    ) -> Result<(String, Vec<PathBuf>), String> {
         let mut path_stack = Vec::new();
-        Self::expand_recursive(source, base_dir, root_dir, &mut path_stack, 0)
+        let mut files = Vec::new();
+        let expanded = Self::expand_recursive(source, base_dir, root_dir, &mut path_stack, &mut files, 0)?;
+        Ok((expanded, files))
     }
 
     fn expand_recursive(
@@ -30,6 +42,7 @@ impl IncludeResolver {
         base_dir: &Path,
         root_dir: &Path,
         path_stack: &mut Vec<PathBuf>,
+        files: &mut Vec<PathBuf>,
         depth: usize,
     ) -> Result<String, String> {
         if depth > MAX_INCLUDE_DEPTH {
@@ -71,12 +84,16 @@ impl IncludeResolver {
             let included = std::fs::read_to_string(&canonical)
                 .map_err(|e| format!("Could not read include '{}': {}", canonical.display(), e))?;
 
+            if !files.contains(&canonical) {
+                files.push(canonical.clone());
+            }
             path_stack.push(canonical.clone());
             let expanded = Self::expand_recursive(
                 &included,
                 canonical.parent().unwrap_or(base_dir),
                 root_dir,
                 path_stack,
+                files,
                 depth + 1,
             )?;
             path_stack.pop();
@@ -167,4 +184,23 @@ mod tests {
         assert!(result.unwrap_err().contains("not found"));
         fs::remove_dir_all(&dir).ok();
     }
+
+    #[test]
+    fn test_expand_with_files_lists_transitive_includes() {
+        let dir = std::env::temp_dir().join("include_test_files");
+        let _ = fs::create_dir_all(&dir);
+        fs::write(dir.join("a.inc"), "A<!-- #include file=\"c.inc\" -->").unwrap();
+        fs::write(dir.join("b.inc"), "B<!-- #include file=\"c.inc\" -->").unwrap();
+        fs::write(dir.join("c.inc"), "C").unwrap();
+
+        let source = "<!-- #include file=\"a.inc\" --><!-- #include file=\"b.inc\" -->";
+        let (result, files) = IncludeResolver::expand_with_files(source, &dir, &dir).unwrap();
+        assert_eq!(result, "ACBC");
+        let canonical = dir.canonicalize().unwrap();
+        assert_eq!(
+            files,
+            vec![canonical.join("a.inc"), canonical.join("c.inc"), canonical.join("b.inc")]
+        );
+        fs::remove_dir_all(&dir).ok();
+    }
 }
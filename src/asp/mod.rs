@@ -1,9 +1,13 @@
 //! ASP server core: HTTP server, request handling, block parsing,
-//! handler chain, include resolution, and preprocessor directives.
+//! handler chain, include resolution, compiled page cache, preprocessor
+//! directives, Global.asa events, and the worker pool that runs pages.
 
 pub mod asp_error;
 pub mod config;
+pub mod global_asa;
 pub mod include_resolver;
+pub mod page_cache;
 pub mod parser;
 pub mod preprocessor;
 pub mod server;
+pub mod worker_pool;
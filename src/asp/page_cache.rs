@@ -0,0 +1,259 @@
+//! Shared cache of compiled ASP pages, so that a page is read, expanded,
+//! parsed and compiled once rather than on every request.
+//!
+//! Entries are keyed by the page's canonical path and remember the
+//! modification time and size of the page and of every file it includes.
+//! A lookup re-checks those files and drops the entry when any of them
+//! changed or disappeared. Once full, the least recently used page is evicted.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use crate::asp::preprocessor::DirectiveConfig;
+use crate::vbscript::interpreter::CompiledScript;
+use crate::vbscript::vbs_error::VBSError;
+
+/// Everything a request needs from a page before it runs.
+pub struct CompiledPage {
+    /// Settings from the page's `<%@ ... %>` directives.
+    pub directive_config: DirectiveConfig,
+    /// The page's script, `None` for a page without code; a compile error
+    /// is kept so that it is reported again without recompiling.
+    pub script: Result<Option<CompiledScript>, VBSError>,
+}
+
+/// State of a source file when its page was compiled.
+#[derive(Debug, PartialEq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(FileStamp {
+            modified: meta.modified().ok(),
+            len: meta.len(),
+        })
+    }
+}
+
+struct CacheEntry {
+    page: Arc<CompiledPage>,
+    /// The page itself followed by its transitive includes.
+    files: Vec<(PathBuf, FileStamp)>,
+    last_used: AtomicU64,
+}
+
+impl CacheEntry {
+    fn is_fresh(&self) -> bool {
+        self.files
+            .iter()
+            .all(|(path, stamp)| FileStamp::of(path).as_ref() == Some(stamp))
+    }
+}
+
+/// LRU cache of `CompiledPage`s shared by all requests.
+pub struct PageCache {
+    /// Maximum number of pages kept; 0 disables caching.
+    capacity: usize,
+    entries: RwLock<HashMap<PathBuf, CacheEntry>>,
+    clock: AtomicU64,
+}
+
+impl PageCache {
+    /// Create a cache holding at most `capacity` pages.
+    pub fn new(capacity: usize) -> Self {
+        PageCache {
+            capacity,
+            entries: RwLock::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    /// A cache that stores nothing, so every request compiles its page.
+    pub fn disabled() -> Self {
+        Self::new(0)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Number of pages currently cached.
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every cached page.
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+
+    /// Return the compiled page for `path` if none of its files changed
+    /// since it was cached.
+    pub fn get(&self, path: &Path) -> Option<Arc<CompiledPage>> {
+        if !self.is_enabled() {
+            return None;
+        }
+        {
+            let entries = self.entries.read().unwrap();
+            let entry = entries.get(path)?;
+            if entry.is_fresh() {
+                entry.last_used.store(self.tick(), Ordering::Relaxed);
+                return Some(Arc::clone(&entry.page));
+            }
+        }
+        tracing::debug!(path = %path.display(), "Page changed on disk, recompiling");
+        self.entries.write().unwrap().remove(path);
+        None
+    }
+
+    /// Cache `page`, compiled from `path` and the files it `includes`, and
+    /// return it shared.
+    pub fn insert(&self, path: &Path, includes: &[PathBuf], page: CompiledPage) -> Arc<CompiledPage> {
+        let page = Arc::new(page);
+        if !self.is_enabled() {
+            return page;
+        }
+        let mut files = Vec::with_capacity(includes.len() + 1);
+        for file in std::iter::once(path).chain(includes.iter().map(PathBuf::as_path)) {
+            match FileStamp::of(file) {
+                Some(stamp) => files.push((file.to_path_buf(), stamp)),
+                // Gone already; a later request will see the change
+                None => return page,
+            }
+        }
+
+        let mut entries = self.entries.write().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(path) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used.load(Ordering::Relaxed))
+                .map(|(p, _)| p.clone());
+            if let Some(oldest) = oldest {
+                tracing::debug!(path = %oldest.display(), "Evicting compiled page");
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            path.to_path_buf(),
+            CacheEntry {
+                page: Arc::clone(&page),
+                files,
+                last_used: AtomicU64::new(self.tick()),
+            },
+        );
+        page
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn page() -> CompiledPage {
+        CompiledPage {
+            directive_config: DirectiveConfig::default(),
+            script: Ok(None),
+        }
+    }
+
+    fn touch(path: &Path, content: &str) {
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_page_cache_hit_returns_same_page() {
+        let dir = std::env::temp_dir().join(format!("page_cache_hit_{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+        let file = dir.join("a.asp");
+        touch(&file, "a");
+
+        let cache = PageCache::new(4);
+        let inserted = cache.insert(&file, &[], page());
+        let hit = cache.get(&file).unwrap();
+        assert!(Arc::ptr_eq(&inserted, &hit));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_page_cache_invalidated_by_include_change() {
+        let dir = std::env::temp_dir().join(format!("page_cache_inc_{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+        let file = dir.join("a.asp");
+        let inc = dir.join("a.inc");
+        touch(&file, "a");
+        touch(&inc, "inc");
+
+        let cache = PageCache::new(4);
+        cache.insert(&file, std::slice::from_ref(&inc), page());
+        assert!(cache.get(&file).is_some());
+        touch(&inc, "changed include");
+        assert!(cache.get(&file).is_none());
+        assert!(cache.is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_page_cache_invalidated_by_deleted_page() {
+        let dir = std::env::temp_dir().join(format!("page_cache_del_{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+        let file = dir.join("a.asp");
+        touch(&file, "a");
+
+        let cache = PageCache::new(4);
+        cache.insert(&file, &[], page());
+        fs::remove_file(&file).unwrap();
+        assert!(cache.get(&file).is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_page_cache_evicts_least_recently_used() {
+        let dir = std::env::temp_dir().join(format!("page_cache_lru_{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+        let files: Vec<PathBuf> = ["a.asp", "b.asp", "c.asp"].iter().map(|f| dir.join(f)).collect();
+        for f in &files {
+            touch(f, "x");
+        }
+
+        let cache = PageCache::new(2);
+        cache.insert(&files[0], &[], page());
+        cache.insert(&files[1], &[], page());
+        assert!(cache.get(&files[0]).is_some());
+        cache.insert(&files[2], &[], page());
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&files[0]).is_some());
+        assert!(cache.get(&files[1]).is_none());
+        assert!(cache.get(&files[2]).is_some());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_page_cache_disabled_stores_nothing() {
+        let dir = std::env::temp_dir().join(format!("page_cache_off_{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+        let file = dir.join("a.asp");
+        touch(&file, "a");
+
+        let cache = PageCache::disabled();
+        cache.insert(&file, &[], page());
+        assert!(cache.get(&file).is_none());
+        assert!(cache.is_empty());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -59,13 +59,9 @@ impl AspParser {
                 if code.trim().is_empty() || code.trim_start().starts_with('@') {
                     return None;
                 }
-                let m = cap.get(0).unwrap();
-                let line = self.content[..m.start()].matches('\n').count() + 1;
-                if code.starts_with('\n') {
-                    Some(line + 1)
-                } else {
-                    Some(line)
-                }
+                // The block is pushed trimmed, so count up to its first code character
+                let start = cap.get(1).unwrap().start() + (code.len() - code.trim_start().len());
+                Some(self.content[..start].matches('\n').count() + 1)
             })
             .collect();
 
@@ -1,15 +1,23 @@
 use crate::asp::asp_error::ASPError;
 use crate::asp::config::{AspDirConfig, AspServerConfig, Config, DirConfigCache};
+use crate::asp::global_asa;
+use crate::asp::page_cache::{CompiledPage, PageCache};
 use crate::asp::parser::AspBlock;
 use crate::asp::parser::AspParser;
 use crate::asp::preprocessor::DirectiveConfig;
+use crate::asp::worker_pool::{self, WorkerPool};
 use crate::vbscript::debugger::Debugger;
-use crate::vbscript::{store::Store, ExecutionContext, VBScriptInterpreter, VBValue};
+use crate::vbscript::vbobject::ObjectRef;
+use crate::vbscript::store::Store;
+use crate::vbscript::{ExecutionContext, VBScriptInterpreter, VBValue};
 use ahash::AHashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpListener;
+use tokio::time::MissedTickBehavior;
 
 use axum::{
     body::Body,
@@ -20,6 +28,9 @@ use axum::{
     Router,
 };
 
+/// Longest `ASPSESSIONID` accepted from a client; issued IDs are shorter.
+const MAX_SESSION_ID_LEN: usize = 32;
+
 /// Parsed HTTP request received by the server.
 #[derive(Debug, Clone)]
 pub struct HttpRequest {
@@ -50,6 +61,15 @@ pub struct HttpResponse {
     pub extra_headers: Vec<(String, String)>,
 }
 
+/// What came of [`AspServer::try_execute_request`].
+pub enum Attempt {
+    /// The page ran, or its error page is the response.
+    Done(Result<HttpResponse, ASPError>),
+    /// Another request of the session is running; `request` is handed back
+    /// to try again once the session is released.
+    SessionBusy { request: HttpRequest, session_id: String },
+}
+
 /// Main ASP server, owning the shared store and config.
 pub struct AspServer {
     /// Shared session/application data store.
@@ -61,10 +81,12 @@ pub struct AspServer {
 impl AspServer {
     /// Create a new `AspServer` with the given configuration.
     pub fn new(config: Config) -> Self {
-        AspServer {
-            store: Store::new(),
-            config,
-        }
+        Self::with_store(config, Store::new())
+    }
+
+    /// Create a new `AspServer` keeping session and application state in `store`.
+    pub fn with_store(config: Config, store: Arc<Store>) -> Self {
+        AspServer { store, config }
     }
 
     /// Start the HTTP server, listening on the configured host:port.
@@ -82,7 +104,18 @@ impl AspServer {
         } else {
             asp_cfg.folder.trim_end_matches('/').to_string()
         };
-        let dir_cache = Arc::new(asp_cfg.build_dir_cache());
+        let state = Arc::new(ServerState {
+            store: Arc::clone(&self.store),
+            folder: folder.clone(),
+            dir_cache: asp_cfg.build_dir_cache(),
+            page_cache: asp_cfg.build_page_cache(),
+            pool: asp_cfg.build_worker_pool(),
+        });
+        self.store.session_timeout_minutes.store(asp_cfg.session_timeout, Ordering::Relaxed);
+        self.store.session_lock_timeout_secs.store(asp_cfg.session_lock_timeout, Ordering::Relaxed);
+        self.store.app_lock_timeout_secs.store(asp_cfg.app_lock_timeout, Ordering::Relaxed);
+        self.store.global_asa_check_secs.store(asp_cfg.global_asa_check_interval, Ordering::Relaxed);
+        state.spawn_session_reaper();
 
         let bind_addr = format!("{}:{}", host, port);
         let listener = TcpListener::bind(&bind_addr).await?;
@@ -91,19 +124,26 @@ impl AspServer {
             port = %port,
             folder = %folder,
             default_documents = ?asp_cfg.default_documents,
+            page_cache = asp_cfg.page_cache,
+            worker_threads = state.pool.workers(),
+            request_queue_max = asp_cfg.request_queue_max,
+            session_timeout = asp_cfg.session_timeout,
             "Server started"
         );
 
         loop {
             let (mut stream, _) = listener.accept().await?;
-            let store = Arc::clone(&self.store);
-            let folder = folder.clone();
-            let dir_cache = Arc::clone(&dir_cache);
+            let state = Arc::clone(&state);
 
             tokio::spawn(async move {
-                if let Err(e) =
-                    Self::handle_connection(&mut stream, &folder, &dir_cache, &store).await
-                {
+                let result = match Self::read_request(&mut stream).await {
+                    Ok(request) => {
+                        let response = state.dispatch(request).await;
+                        Self::write_response(&mut stream, &response).await
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
                     tracing::error!(error = %e, "Connection handling error");
                 }
             });
@@ -347,23 +387,47 @@ impl AspServer {
     pub fn inject_asp_intrinsic_objects(context: &mut ExecutionContext) {
         use crate::vbscript::asp_objects::*;
         if context.get_variable("REQUEST").is_none() {
-            context.set_variable("Request", VBValue::Object(Box::new(RequestObject)));
+            context.set_variable("Request", VBValue::Object(ObjectRef::new(RequestObject)));
         }
         if context.get_variable("RESPONSE").is_none() {
-            context.set_variable("Response", VBValue::Object(Box::new(ResponseObject)));
+            context.set_variable("Response", VBValue::Object(ObjectRef::new(ResponseObject)));
         }
         if context.get_variable("SESSION").is_none() {
             let session = SessionObject {
                 session_id: context.session.id.clone(),
                 session_enabled: context.session.enabled,
             };
-            context.set_variable("Session", VBValue::Object(Box::new(session)));
+            context.set_variable("Session", VBValue::Object(ObjectRef::new(session)));
         }
         if context.get_variable("SERVER").is_none() {
-            context.set_variable("Server", VBValue::Object(Box::new(ServerObject)));
+            context.set_variable("Server", VBValue::Object(ObjectRef::new(ServerObject)));
         }
         if context.get_variable("APPLICATION").is_none() {
-            context.set_variable("Application", VBValue::Object(Box::new(ApplicationObject)));
+            context.set_variable("Application", VBValue::Object(ObjectRef::new(ApplicationObject)));
+        }
+        Self::inject_static_objects(context);
+    }
+
+    /// Make the objects Global.asa declares for the application and for the
+    /// request's session available under their IDs.
+    fn inject_static_objects(context: &mut ExecutionContext) {
+        let Some(store) = context.store.clone() else {
+            return;
+        };
+        let mut objects: Vec<(String, VBValue)> = store
+            .lock_static_objects()
+            .iter()
+            .map(|(id, object)| (id.clone(), object.clone()))
+            .collect();
+        if context.session.enabled {
+            if let Some(session) = store.lock_session_objects().get(&context.session.id.to_uppercase()) {
+                objects.extend(session.iter().map(|(id, object)| (id.clone(), object.clone())));
+            }
+        }
+        for (id, object) in objects {
+            if context.get_variable(&id).is_none() {
+                context.set_variable(&id, object);
+            }
         }
     }
 
@@ -377,11 +441,56 @@ impl AspServer {
         Ok(())
     }
 
+    /// Read, expand, parse and compile the page at `file_path`, or reuse its
+    /// compiled form from `page_cache` while neither it nor its includes changed.
+    /// `canonical_path` is the page's canonical path, which the cache is keyed by.
+    fn load_page(
+        file_path: &str,
+        canonical_path: &Path,
+        folder: &str,
+        page_cache: &PageCache,
+    ) -> Result<Arc<CompiledPage>, HttpResponse> {
+        if let Some(page) = page_cache.get(canonical_path) {
+            return Ok(page);
+        }
+        let path = Path::new(file_path);
+
+        let content = Self::read_asp_file(file_path)?;
+        let file_dir = path.parent().unwrap_or(Path::new(folder));
+        let (expanded, includes) = crate::asp::include_resolver::IncludeResolver::expand_with_files(&content, file_dir, Path::new(folder))
+            .map_err(|e| HttpResponse {
+                status_line: "500 Internal Server Error".to_string(),
+                content_type: "text/html".to_string(),
+                body: ASPError::new(500, e).render_html().into_bytes(),
+                extra_headers: Vec::new(),
+            })?;
+
+        let parser = AspParser::new(expanded);
+        let blocks = parser.parse();
+        let preprocessor = crate::asp::preprocessor::Preprocessor::new();
+        let (directive_config, filtered_blocks) = preprocessor.process(&blocks);
+        let script = VBScriptInterpreter.compile_vm_blocks(&filtered_blocks);
+
+        Ok(page_cache.insert(canonical_path, &includes, CompiledPage { directive_config, script }))
+    }
+
+    /// Run a compiled page's script in the request context.
+    fn run_page(page: &CompiledPage, context: &mut ExecutionContext) -> Result<(), ASPError> {
+        let script = match &page.script {
+            Ok(Some(script)) => script,
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(ASPError::new(500, e.to_string())),
+        };
+        VBScriptInterpreter.run_script(script, context).map_err(|e| ASPError::new(500, e.to_string()))
+    }
+
+    /// Map the request to the page to run: its path as requested, its
+    /// canonical path and the settings of its directory.
     fn resolve_file_path(
         request: &HttpRequest,
         folder: &str,
         dir_cache: &DirConfigCache,
-    ) -> Result<(String, PathBuf), HttpResponse> {
+    ) -> Result<(String, PathBuf, AspDirConfig), HttpResponse> {
         let raw_path = format!("{}/{}", folder, request.path);
         let canonical_path = Path::new(&raw_path).canonicalize().map_err(|e| {
             let err = ASPError::new(404, format!("File not found: {} (folder={}, path={}, error={})", raw_path, folder, request.path, e));
@@ -418,9 +527,12 @@ impl AspServer {
         };
         let dir_config = dir_cache.resolve(request_dir);
 
-        let file_path = if canonical_path.is_dir() {
+        let (file_path, canonical_path) = if canonical_path.is_dir() {
             match Self::resolve_directory_default(&canonical_path, &dir_config) {
-                Some(fp) => fp,
+                Some(fp) => {
+                    let canonical = PathBuf::from(&fp);
+                    (fp, canonical)
+                }
                 None if dir_config.directory_listing => {
                     return Err(Self::generate_directory_listing(
                         &canonical_path, &canonical_folder, &request.path,
@@ -429,10 +541,10 @@ impl AspServer {
                 None => return Err(Self::not_found_response(&request.path, &dir_config.default_documents)),
             }
         } else {
-            raw_path
+            (raw_path, canonical_path)
         };
 
-        Ok((file_path, canonical_folder))
+        Ok((file_path, canonical_path, dir_config))
     }
 
     fn resolve_directory_default(
@@ -562,23 +674,65 @@ impl AspServer {
         context
     }
 
-    fn setup_session(context: &mut ExecutionContext) {
+    /// Whether `id` has the shape of an ID from `generate_session_id`, so
+    /// that no other cookie value reaches the session store.
+    fn is_valid_session_id(id: &str) -> bool {
+        id.len() <= MAX_SESSION_ID_LEN
+            && id.strip_prefix("ASPERGER").is_some_and(|rest| {
+                !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_hexdigit())
+            })
+    }
+
+    /// Pick the request's session.  A new ID is issued unless the request
+    /// brings a well-formed one of a session the store knows, so clients
+    /// cannot pick their own.
+    fn assign_session_id(context: &mut ExecutionContext, store: &Store) {
         if !context.session.enabled {
             return;
         }
         let existing_session = context.request.cookies.get("ASPSESSIONID")
             .cloned().unwrap_or_default();
-        if existing_session.is_empty() {
+        if Self::is_valid_session_id(&existing_session) && store.session_exists(&existing_session) {
+            context.session.id = existing_session;
+        } else {
             context.session.id = Self::generate_session_id();
             context.response.extra_headers.push((
                 "Set-Cookie".to_string(),
                 format!("ASPSESSIONID={}; path=/", context.session.id),
             ));
-        } else {
-            context.session.id = existing_session;
         }
     }
 
+    /// The 503 page for a request whose session another request kept past
+    /// `Store::session_lock_timeout_secs`.
+    fn session_busy_response() -> HttpResponse {
+        tracing::warn!("Timed out waiting for another request of the session");
+        let err = ASPError::new(503, "Server Too Busy: the session is in use by another request");
+        HttpResponse {
+            status_line: status_line(503),
+            content_type: "text/html".to_string(),
+            body: err.render_html().into_bytes(),
+            extra_headers: Vec::new(),
+        }
+    }
+
+    /// Record the request in its session and return whether it starts a
+    /// new session.
+    fn setup_session(context: &mut ExecutionContext) -> bool {
+        if !context.session.enabled {
+            return false;
+        }
+        let Some(store) = context.store.clone() else {
+            return false;
+        };
+        // A session idle past its timeout that the reaper has not swept yet
+        // is over all the same
+        if store.claim_expired_session(&context.session.id, Instant::now()) {
+            global_asa::end_session(&store, global_asa::current(&store).as_deref(), &context.session.id);
+        }
+        store.access_session(&context.session.id)
+    }
+
     fn parse_post_body(context: &mut ExecutionContext, request: &HttpRequest) {
         if !request.method.eq_ignore_ascii_case("POST") {
             return;
@@ -596,45 +750,76 @@ impl AspServer {
         }
     }
 
-    /// Process a parsed HTTP request through the full ASP pipeline.
-    pub async fn process_request(
-        request: HttpRequest,
+    /// Process a parsed HTTP request through the full ASP pipeline, blocking
+    /// until the page is done, including while another request of its
+    /// session runs.
+    ///
+    /// Call it from a thread of its own, never from an async task; servers
+    /// go through [`ServerState::dispatch`] so that pages run on the worker
+    /// pool instead.
+    pub fn process_request(
+        mut request: HttpRequest,
         folder: &str,
         dir_cache: &DirConfigCache,
+        page_cache: &PageCache,
         store: &Arc<Store>,
         debugger: Option<Arc<Debugger>>,
     ) -> Result<HttpResponse, ASPError> {
+        let deadline = store.session_lock_deadline();
+        loop {
+            match Self::try_execute_request(request, folder, dir_cache, page_cache, store, debugger.clone()) {
+                Attempt::Done(result) => return result,
+                Attempt::SessionBusy { request: busy, session_id } => {
+                    if !store.wait_for_session(&session_id, deadline) {
+                        return Ok(Self::session_busy_response());
+                    }
+                    request = busy;
+                }
+            }
+        }
+    }
+
+    /// Resolve, load and run the page for `request` unless another request
+    /// of its session is running, as IIS runs the requests of a session one
+    /// at a time; pages without session state are not held up.
+    pub fn try_execute_request(
+        request: HttpRequest,
+        folder: &str,
+        dir_cache: &DirConfigCache,
+        page_cache: &PageCache,
+        store: &Arc<Store>,
+        debugger: Option<Arc<Debugger>>,
+    ) -> Attempt {
         let _span = tracing::info_span!("request", method = %request.method, path = %request.path).entered();
-        let request_start = std::time::Instant::now();
+        let request_start = Instant::now();
 
-        let (file_path, _canonical_folder) = match Self::resolve_file_path(&request, folder, dir_cache) {
+        let (file_path, canonical_path, dir_config) = match Self::resolve_file_path(&request, folder, dir_cache) {
             Ok(v) => v,
-            Err(resp) => return Ok(resp),
+            Err(resp) => return Attempt::Done(Ok(resp)),
         };
-        let content = match Self::read_asp_file(&file_path) {
+        let page = match Self::load_page(&file_path, &canonical_path, folder, page_cache) {
             Ok(v) => v,
-            Err(resp) => return Ok(resp),
+            Err(resp) => return Attempt::Done(Ok(resp)),
         };
-        let file_dir = Path::new(&file_path).parent().unwrap_or(Path::new(folder));
-
-        let expanded = match crate::asp::include_resolver::IncludeResolver::expand(&content, file_dir, Path::new(folder)) {
-            Ok(v) => v,
-            Err(e) => return Ok(HttpResponse {
-                status_line: "500 Internal Server Error".to_string(),
-                content_type: "text/html".to_string(),
-                body: ASPError::new(500, e).render_html().into_bytes(),
-                extra_headers: Vec::new(),
-            }),
+        let global_asa = match global_asa::start_application(store, folder) {
+            Ok(global_asa) => global_asa,
+            Err(e) => return Attempt::Done(Err(e)),
         };
 
-        let parser = AspParser::new(expanded);
-        let blocks = parser.parse();
-        let preprocessor = crate::asp::preprocessor::Preprocessor::new();
-        let (directive_config, filtered_blocks) = preprocessor.process(&blocks);
-
-        let mut context = Self::setup_execution_context(&request, &file_path, store, &directive_config);
+        let mut context = Self::setup_execution_context(&request, &file_path, store, &page.directive_config);
+        let app_lock = store.app_lock_owner(context.request_id);
+        context.app_root = PathBuf::from(folder);
         Self::parse_post_body(&mut context, &request);
-        Self::setup_session(&mut context);
+        Self::assign_session_id(&mut context, store);
+        let _session_lock = if context.session.enabled {
+            match store.try_lock_session(&context.session.id) {
+                Some(lock) => Some(lock),
+                None => return Attempt::SessionBusy { request, session_id: std::mem::take(&mut context.session.id) },
+            }
+        } else {
+            None
+        };
+        let new_session = Self::setup_session(&mut context);
 
         let folder_clone = folder.to_string();
         context.execute_file_callback = Some(Arc::new(move |path, ctx| {
@@ -658,14 +843,47 @@ impl AspServer {
             Ok(())
         }));
 
+        // Breakpoints may hold a page for any length of time, so the
+        // timeout only applies when no debugger is attached
+        context.set_script_timeout(dir_config.script_timeout);
+        context.limits = dir_config.limits;
+        context.limits.max_call_depth = worker_pool::clamp_call_depth(context.limits.max_call_depth);
+        if debugger.is_none() {
+            context.enforce_script_timeout();
+        }
         context.debugger = debugger;
+        if let (true, Some(global)) = (new_session, &global_asa) {
+            global_asa::start_session(store, global, &mut context);
+        }
         Self::inject_asp_intrinsic_objects(&mut context);
 
-        let render_start = std::time::Instant::now();
+        let render_start = Instant::now();
         let mut response_content = String::new();
         // Process all blocks at once to preserve variable state across blocks
-        match Self::process_blocks(&filtered_blocks, &mut context) {
+        let mut result = Self::run_page(&page, &mut context);
+        // Terminate the page's objects while Class_Terminate can still write
+        // to this response
+        if let Err(e) = context.terminate_class_instances() {
+            tracing::warn!(error = %e, "Class_Terminate failed");
+            if result.is_ok() {
+                result = Err(ASPError::new(500, e.to_string()));
+            }
+        }
+        if let Some(contents) = context.session.abandoned.take() {
+            global_asa::end_abandoned_session(store, &context.session.id, contents);
+        }
+        store.save_fetched(context.request_id);
+        // A page that failed or ended before Application.Unlock must not
+        // keep every other request waiting
+        drop(app_lock);
+        match result {
             Ok(()) => response_content.push_str(&context.response.buffer),
+            Err(_) if context.script_timed_out() => {
+                tracing::warn!(script_timeout = context.script_timeout(), "Script timed out");
+                context.response.status = "500 Internal Server Error".to_string();
+                response_content.push_str(&context.response.buffer);
+                response_content.push_str(&Self::script_timeout_message(&request.path));
+            }
             Err(e) => {
                 response_content.push_str(&context.response.buffer);
                 response_content.push_str(&format!("\n<!-- Error: {} -->\n", e));
@@ -696,21 +914,41 @@ impl AspServer {
         let total_ms = request_start.elapsed().as_secs_f64() * 1000.0;
         let render_ms = render_start.elapsed().as_secs_f64() * 1000.0;
         tracing::info!(status = %response.status_line, body_bytes = response.body.len(), response_time_ms = total_ms, render_time_ms = render_ms, "Request completed");
-        Ok(response)
+        Attempt::Done(Ok(response))
+    }
+
+    /// The text IIS appends to a page aborted for exceeding its timeout.
+    fn script_timeout_message(path: &str) -> String {
+        format!(
+            "<font face=\"Arial\" size=2>\n<p>Active Server Pages</font> <font face=\"Arial\" size=2>error 'ASP 0113'</font>\n\
+             <p>\n<font face=\"Arial\" size=2>Script timed out</font>\n<p>\n<font face=\"Arial\" size=2>/{}</font>\n\
+             <p>\n<font face=\"Arial\" size=2>The maximum amount of time for a script to execute was exceeded. \
+             You can change this limit by specifying a new value for the property Server.ScriptTimeout \
+             or by changing the value in the IIS administration tools.\n</font>\n",
+            Self::html_escape(path)
+        )
     }
 
-    /// Legacy single-connection handler (used by the DAP debug server).
+    /// Legacy single-connection handler.
     ///
-    /// Reads one HTTP request, runs it through `process_request` (no debugger),
-    /// and writes the response back.  Not used by the Axum production server.
+    /// Reads one HTTP request, runs it through `process_request` (no debugger)
+    /// on a blocking thread, and writes the response back.  Not used by the
+    /// Axum production server.
     pub async fn handle_connection(
         stream: &mut tokio::net::TcpStream,
         folder: &str,
-        dir_cache: &DirConfigCache,
+        dir_cache: Arc<DirConfigCache>,
+        page_cache: Arc<PageCache>,
         store: &Arc<Store>,
     ) -> Result<(), ASPError> {
         let request = Self::read_request(stream).await?;
-        let response = Self::process_request(request, folder, dir_cache, store, None).await?;
+        let folder = folder.to_string();
+        let store = Arc::clone(store);
+        let response = tokio::task::spawn_blocking(move || {
+            Self::process_request(request, &folder, &dir_cache, &page_cache, &store, None)
+        })
+        .await
+        .map_err(|e| ASPError::new(500, format!("Request failed: {}", e)))??;
         Self::write_response(stream, &response).await
     }
 
@@ -724,17 +962,22 @@ impl AspServer {
         } else {
             asp_cfg.folder.trim_end_matches('/').to_string()
         };
-        let dir_cache = asp_cfg.build_dir_cache();
-
-        let state = Arc::new(AxumServerState {
+        let state = Arc::new(ServerState {
             store: Arc::clone(&self.store),
             folder: folder.clone(),
-            dir_cache,
+            dir_cache: asp_cfg.build_dir_cache(),
+            page_cache: asp_cfg.build_page_cache(),
+            pool: asp_cfg.build_worker_pool(),
         });
+        self.store.session_timeout_minutes.store(asp_cfg.session_timeout, Ordering::Relaxed);
+        self.store.session_lock_timeout_secs.store(asp_cfg.session_lock_timeout, Ordering::Relaxed);
+        self.store.app_lock_timeout_secs.store(asp_cfg.app_lock_timeout, Ordering::Relaxed);
+        self.store.global_asa_check_secs.store(asp_cfg.global_asa_check_interval, Ordering::Relaxed);
+        state.spawn_session_reaper();
 
         let app = Router::new()
             .fallback(any(axum_handler))
-            .layer(Extension(state));
+            .layer(Extension(Arc::clone(&state)));
 
         let addr: std::net::SocketAddr = format!("{}:{}", host, port)
             .parse()
@@ -744,16 +987,31 @@ impl AspServer {
             port = %port,
             folder = %folder,
             default_documents = ?asp_cfg.default_documents,
+            page_cache = asp_cfg.page_cache,
+            worker_threads = state.pool.workers(),
+            request_queue_max = asp_cfg.request_queue_max,
+            session_timeout = asp_cfg.session_timeout,
             "Server started"
         );
 
         let listener = tokio::net::TcpListener::bind(addr).await?;
-        axum::serve(listener, app)
+        let served = axum::serve(listener, app)
             .with_graceful_shutdown(async {
                 tokio::signal::ctrl_c().await.ok();
             })
             .await
-            .map_err(std::io::Error::other)
+            .map_err(std::io::Error::other);
+
+        // Persistent state is left for the next run instead of ending the application
+        if state.store.is_persistent() {
+            tracing::info!("Keeping session and application state");
+            return served;
+        }
+        let store = Arc::clone(&state.store);
+        if let Err(e) = state.pool.run(move || global_asa::end_application(&store)).await {
+            tracing::error!(error = %e, "Application_OnEnd failed");
+        }
+        served
     }
 
     /// Generate an HTML directory listing for the given canonical directory path.
@@ -950,21 +1208,109 @@ impl AspServer {
     }
 }
 
-/// Shared state injected into all axum handlers.
-struct AxumServerState {
+/// How often sessions idle past their timeout are looked for.
+const SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Shared state of a running server, handed to every connection.
+struct ServerState {
     store: Arc<Store>,
     folder: String,
     dir_cache: DirConfigCache,
+    page_cache: PageCache,
+    /// Threads the pages run on, keeping them off the async runtime.
+    pool: WorkerPool,
+}
+
+impl ServerState {
+    /// Run `request` on the worker pool; a full pool or a failed page is
+    /// answered with an error page carrying its status.
+    ///
+    /// A request whose session is busy waits here, off the pool, so that a
+    /// burst of requests from one session cannot tie up every worker.
+    async fn dispatch(self: &Arc<Self>, mut request: HttpRequest) -> HttpResponse {
+        let deadline = self.store.session_lock_deadline();
+        let result = loop {
+            let state = Arc::clone(self);
+            let attempt = self
+                .pool
+                .run(move || {
+                    AspServer::try_execute_request(
+                        request,
+                        &state.folder,
+                        &state.dir_cache,
+                        &state.page_cache,
+                        &state.store,
+                        None,
+                    )
+                })
+                .await;
+            match attempt {
+                Ok(Attempt::Done(result)) => break result,
+                Ok(Attempt::SessionBusy { request: busy, session_id }) => {
+                    if !self.store.session_released(&session_id, deadline).await {
+                        break Ok(AspServer::session_busy_response());
+                    }
+                    request = busy;
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        result.unwrap_or_else(|e| {
+            if e.code == 503 {
+                tracing::warn!(pending = self.pool.pending(), "Request queue full, rejecting request");
+            }
+            HttpResponse {
+                status_line: status_line(e.code),
+                content_type: "text/html".to_string(),
+                body: e.render_html().into_bytes(),
+                extra_headers: Vec::new(),
+            }
+        })
+    }
+
+    /// End the sessions idle past their timeout every
+    /// `SESSION_SWEEP_INTERVAL`, for as long as the server runs.
+    fn spawn_session_reaper(self: &Arc<Self>) {
+        let state = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SESSION_SWEEP_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let Some(state) = state.upgrade() else {
+                    return;
+                };
+                // Session_OnEnd runs script, so it waits for a worker like a page
+                let store = Arc::clone(&state.store);
+                let swept = state
+                    .pool
+                    .run(move || global_asa::end_expired_sessions(&store, Instant::now()))
+                    .await;
+                if let Err(e) = swept {
+                    tracing::warn!(error = %e, "Session sweep skipped");
+                }
+            }
+        });
+    }
+}
+
+/// Status line for an error status code, e.g. "503 Service Unavailable".
+fn status_line(code: u16) -> String {
+    let reason = StatusCode::from_u16(code)
+        .ok()
+        .and_then(|c| c.canonical_reason())
+        .unwrap_or("Error");
+    format!("{} {}", code, reason)
 }
 
 /// Axum request handler — bridge between axum's HTTP types and the internal pipeline.
 ///
 /// 1. Decompose the axum `Request` into headers, body, URI parts.
 /// 2. Convert to internal `HttpRequest` (cookie parsing, CGI variable population).
-/// 3. Delegate to `AspServer::process_request`.
+/// 3. Run it through the ASP pipeline on the worker pool (`ServerState::dispatch`).
 /// 4. Map the internal `HttpResponse` status/content-type/body back to an axum `Response`.
 async fn axum_handler(
-    Extension(state): Extension<Arc<AxumServerState>>,
+    Extension(state): Extension<Arc<ServerState>>,
     req: Request,
 ) -> Response<Body> {
     let (parts, body) = req.into_parts();
@@ -999,21 +1345,7 @@ async fn axum_handler(
         body: body_bytes.to_vec(),
     };
 
-    match AspServer::process_request(
-        http_request,
-        &state.folder,
-        &state.dir_cache,
-        &state.store,
-        None,
-    )
-    .await
-    {
-        Ok(http_resp) => convert_response(http_resp),
-        Err(e) => Response::builder()
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(Body::from(format!("Internal error: {}", e)))
-            .unwrap(),
-    }
+    convert_response(state.dispatch(http_request).await)
 }
 
 /// Convert our internal HttpResponse to an axum Response<Body>.
@@ -1168,6 +1500,16 @@ mod tests {
         assert!(id[8..].chars().all(|c| c.is_ascii_hexdigit()));
     }
 
+    #[test]
+    fn test_session_id_validation() {
+        assert!(AspServer::is_valid_session_id(&AspServer::generate_session_id()));
+        assert!(!AspServer::is_valid_session_id(""));
+        assert!(!AspServer::is_valid_session_id("ASPERGER"));
+        assert!(!AspServer::is_valid_session_id("ASPERGER../../etc"));
+        assert!(!AspServer::is_valid_session_id("CHOSENBYCLIENT"));
+        assert!(!AspServer::is_valid_session_id(&format!("ASPERGER{}", "a".repeat(40))));
+    }
+
     #[test]
     fn test_html_escape_ampersand() {
         let result = AspServer::html_escape("a&b");
@@ -1206,4 +1548,342 @@ mod tests {
         let result = AspServer::parse_multipart_form_data(b"", "boundary");
         assert!(result.is_empty());
     }
+
+    fn test_state(dir: &Path, pool: WorkerPool) -> Arc<ServerState> {
+        let cfg = AspServerConfig::from_folder(dir.to_str().unwrap());
+        Arc::new(ServerState {
+            store: Store::new(),
+            folder: cfg.folder.clone(),
+            dir_cache: cfg.build_dir_cache(),
+            page_cache: PageCache::disabled(),
+            pool,
+        })
+    }
+
+    fn get(path: &str) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query_string: String::new(),
+            headers: AHashMap::new(),
+            body: Vec::new(),
+            cookies: AHashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_runs_page_on_worker_pool() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(dir.join("page.asp"), "<% Response.Write 6 * 7 %>").unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 0));
+
+        let response = state.dispatch(get("page.asp")).await;
+        assert_eq!(response.status_line, "200 OK");
+        assert_eq!(response.body, b"42");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_answers_503_when_queue_full() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_busy_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(dir.join("page.asp"), "<% Response.Write 1 %>").unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 0));
+
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let busy = {
+            let state = Arc::clone(&state);
+            tokio::spawn(async move { state.pool.run(move || release_rx.recv().unwrap()).await })
+        };
+        while state.pool.pending() < 1 {
+            tokio::task::yield_now().await;
+        }
+
+        let response = state.dispatch(get("page.asp")).await;
+        assert_eq!(response.status_line, "503 Service Unavailable");
+        assert_eq!(parse_status_code(&response.status_line), StatusCode::SERVICE_UNAVAILABLE);
+
+        release_tx.send(()).unwrap();
+        busy.await.unwrap().unwrap();
+        assert_eq!(state.dispatch(get("page.asp")).await.status_line, "200 OK");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_aborts_page_past_script_timeout() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_timeout_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(dir.join("asp.ini"), "[server]\nscript_timeout = 1\n").unwrap();
+        std::fs::write(dir.join("spin.asp"), "<% Response.Write \"before\"\nDo\nLoop %>").unwrap();
+        std::fs::write(
+            dir.join("raised.asp"),
+            "<% Server.ScriptTimeout = 60\nDim i, n\nFor i = 1 To 5000\nn = n + i\nNext\nResponse.Write n %>",
+        )
+        .unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 0));
+
+        let response = state.dispatch(get("spin.asp")).await;
+        assert_eq!(response.status_line, "500 Internal Server Error");
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.starts_with("before"), "got: {}", body);
+        assert!(body.contains("error 'ASP 0113'"), "got: {}", body);
+        assert!(body.contains("Script timed out"), "got: {}", body);
+
+        let response = state.dispatch(get("raised.asp")).await;
+        assert_eq!(response.status_line, "200 OK");
+        assert_eq!(response.body, b"12502500");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_caps_call_depth_at_worker_stack() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_depth_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(dir.join("deep"));
+        std::fs::write(dir.join("deep").join("asp.ini"), "[server]\nmax_call_depth = 100000\n").unwrap();
+        std::fs::write(
+            dir.join("deep").join("recurse.asp"),
+            "<% Dim depth\nSub R(n)\ndepth = n\nIf n > 0 Then R n - 1\nEnd Sub\nR 50000 %>",
+        )
+        .unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 0));
+
+        let response = state.dispatch(get("deep/recurse.asp")).await;
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("Out of stack space"), "got: {}", body);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn with_session(mut request: HttpRequest, response: &HttpResponse) -> HttpRequest {
+        let cookie = response
+            .extra_headers
+            .iter()
+            .find(|(name, _)| name == "Set-Cookie")
+            .map(|(_, value)| value.split(';').next().unwrap().to_string())
+            .unwrap();
+        request.cookies = AspServer::parse_cookies(&cookie);
+        request
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_fires_global_asa_events() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_global_asa_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(
+            dir.join("global.asa"),
+            "<object runat=\"server\" scope=\"application\" id=\"Cache\" progid=\"Scripting.Dictionary\"></object>\n\
+             <script language=\"VBScript\" runat=\"server\">\n\
+             Sub Application_OnStart\nApplication(\"sessions\") = 0\nCache(\"site\") = \"demo\"\nEnd Sub\n\
+             Sub Session_OnStart\nApplication(\"sessions\") = Application(\"sessions\") + 1\n\
+             Session(\"user\") = \"guest\"\nResponse.Write \"welcome \"\nEnd Sub\n\
+             Sub Session_OnEnd\nApplication(\"ended\") = Session(\"user\")\nEnd Sub\n\
+             </script>",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("page.asp"),
+            "<%= Cache(\"site\") & \" \" & Session(\"user\") & \" \" & Application(\"sessions\") %>",
+        )
+        .unwrap();
+        std::fs::write(dir.join("ended.asp"), "<%= Application(\"ended\") %>").unwrap();
+        std::fs::write(dir.join("bye.asp"), "<% Session.Abandon %>").unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 0));
+
+        let first = state.dispatch(get("page.asp")).await;
+        assert_eq!(String::from_utf8_lossy(&first.body), "welcome demo guest 1");
+        let again = state.dispatch(with_session(get("page.asp"), &first)).await;
+        assert_eq!(String::from_utf8_lossy(&again.body), "demo guest 1");
+
+        state.dispatch(with_session(get("bye.asp"), &first)).await;
+        // The abandoned session has ended, so its ID gets a new session
+        let ended = state.dispatch(with_session(get("ended.asp"), &first)).await;
+        assert_eq!(String::from_utf8_lossy(&ended.body), "welcome guest");
+        assert_ne!(ended.extra_headers, first.extra_headers);
+        let restarted = state.dispatch(with_session(get("page.asp"), &ended)).await;
+        assert_eq!(String::from_utf8_lossy(&restarted.body), "demo guest 2");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_restarts_application_when_global_asa_changes() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_global_restart_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        let script = |value: &str| {
+            format!(
+                "<script language=\"VBScript\" runat=\"server\">\n\
+                 Sub Application_OnStart\nApplication(\"version\") = \"{}\"\nEnd Sub\n</script>",
+                value
+            )
+        };
+        std::fs::write(dir.join("global.asa"), script("one")).unwrap();
+        std::fs::write(
+            dir.join("page.asp"),
+            "<% Application(\"hits\") = Application(\"hits\") + 1 %><%= Application(\"version\") & Application(\"hits\") %>",
+        )
+        .unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 0));
+        state.store.global_asa_check_secs.store(0, Ordering::Relaxed);
+
+        assert_eq!(state.dispatch(get("page.asp")).await.body, b"one1");
+        assert_eq!(state.dispatch(get("page.asp")).await.body, b"one2");
+
+        // Ensure the new file gets a different modification time
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(dir.join("global.asa"), script("two")).unwrap();
+        assert_eq!(state.dispatch(get("page.asp")).await.body, b"two1");
+        assert_eq!(state.store.session_count(), 1);
+
+        std::fs::write(dir.join("global.asa"), "<script runat=server>\nSub Broken(\n</script>").unwrap();
+        let broken = state.dispatch(get("page.asp")).await;
+        assert_eq!(broken.status_line, "500 Internal Server Error");
+
+        std::fs::remove_file(dir.join("global.asa")).unwrap();
+        assert_eq!(state.dispatch(get("page.asp")).await.body, b"1");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_idle_sessions_expire_with_session_on_end() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_session_expiry_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(
+            dir.join("global.asa"),
+            "<script language=\"VBScript\" runat=\"server\">\n\
+             Sub Session_OnStart\nSession(\"user\") = \"guest\"\nEnd Sub\n\
+             Sub Session_OnEnd\nApplication(\"ended\") = Application(\"ended\") & Session(\"user\")\nEnd Sub\n\
+             </script>",
+        )
+        .unwrap();
+        std::fs::write(dir.join("short.asp"), "<% Session.Timeout = 1 %><%= Session.Timeout %>").unwrap();
+        std::fs::write(dir.join("page.asp"), "<%= Session.Timeout %>").unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 0));
+
+        let short = state.dispatch(get("short.asp")).await;
+        assert_eq!(short.body, b"1");
+        let long = state.dispatch(get("page.asp")).await;
+        assert_eq!(long.body, b"20");
+        assert_eq!(state.store.session_count(), 2);
+
+        global_asa::end_expired_sessions(&state.store, Instant::now() + Duration::from_secs(61));
+        assert_eq!(state.store.session_count(), 1);
+        assert_eq!(state.store.get_app_var("ended").unwrap(), VBValue::String("guest".into()));
+        // Its ID now gets a new session with the default timeout
+        let again = state.dispatch(with_session(get("page.asp"), &short)).await;
+        assert_eq!(again.body, b"20");
+        assert!(again.extra_headers.iter().any(|(name, _)| name == "Set-Cookie"));
+        assert_eq!(state.store.session_count(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_session_id_gets_a_new_session() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_session_id_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(dir.join("page.asp"), "<%= Session.SessionID <> \"\" %>").unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 0));
+
+        for chosen in ["ASPERGER1234abcd", "../../etc/passwd"] {
+            let mut request = get("page.asp");
+            request.cookies = AspServer::parse_cookies(&format!("ASPSESSIONID={}", chosen));
+            let response = state.dispatch(request).await;
+            let cookie = &response.extra_headers.iter().find(|(name, _)| name == "Set-Cookie").unwrap().1;
+            assert!(!cookie.contains(chosen), "got: {}", cookie);
+            assert!(!state.store.session_exists(chosen));
+        }
+        assert_eq!(state.store.session_count(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_requests_of_a_session_run_one_at_a_time() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_session_lock_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(
+            dir.join("add.asp"),
+            "<% n = Session(\"n\")\nFor i = 1 To 20000 : Next\nSession(\"n\") = n + 1 %><%= Session(\"n\") %>",
+        )
+        .unwrap();
+        std::fs::write(dir.join("stateless.asp"), "<%@ ENABLESESSIONSTATE=False %>free").unwrap();
+        let state = test_state(&dir, WorkerPool::new(4, 8));
+
+        let first = state.dispatch(get("add.asp")).await;
+        assert_eq!(first.body, b"1");
+        let requests = (0..4).map(|_| {
+            let state = Arc::clone(&state);
+            let request = with_session(get("add.asp"), &first);
+            tokio::spawn(async move { state.dispatch(request).await })
+        });
+        for request in requests.collect::<Vec<_>>() {
+            assert_eq!(request.await.unwrap().status_line, "200 OK");
+        }
+        let last = state.dispatch(with_session(get("add.asp"), &first)).await;
+        assert_eq!(last.body, b"6");
+
+        // A request that keeps its session too long makes the next one wait
+        // in vain, but pages without session state go ahead
+        state.store.session_lock_timeout_secs.store(1, Ordering::Relaxed);
+        let session_id = first.extra_headers[0].1.split(['=', ';']).nth(1).unwrap().to_string();
+        let held = state.store.lock_session(&session_id).unwrap();
+        let stateless = state.dispatch(with_session(get("stateless.asp"), &first)).await;
+        assert_eq!(stateless.body, b"free");
+        let waiting = state.dispatch(with_session(get("add.asp"), &first)).await;
+        assert_eq!(waiting.status_line, "503 Service Unavailable");
+        drop(held);
+        assert_eq!(state.dispatch(with_session(get("add.asp"), &first)).await.body, b"7");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_for_a_session_leaves_workers_free() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_session_wait_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(dir.join("count.asp"), "<% Session(\"n\") = Session(\"n\") + 1 %><%= Session(\"n\") %>").unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 4));
+
+        let first = state.dispatch(get("count.asp")).await;
+        let session_id = first.extra_headers[0].1.split(['=', ';']).nth(1).unwrap().to_string();
+        let held = state.store.lock_session(&session_id).unwrap();
+        let waiting: Vec<_> = (0..3)
+            .map(|_| {
+                let state = Arc::clone(&state);
+                let request = with_session(get("count.asp"), &first);
+                tokio::spawn(async move { state.dispatch(request).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The only worker is still free for other sessions
+        assert_eq!(state.pool.pending(), 0);
+        assert_eq!(state.dispatch(get("count.asp")).await.body, b"1");
+        drop(held);
+        for request in waiting {
+            assert_eq!(request.await.unwrap().status_line, "200 OK");
+        }
+        assert_eq!(state.dispatch(with_session(get("count.asp"), &first)).await.body, b"5");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_application_lock_is_released_when_the_page_ends() {
+        let dir = std::env::temp_dir().join(format!("asp_dispatch_app_lock_{}", std::process::id()));
+        let _ = std::fs::create_dir_all(&dir);
+        std::fs::write(dir.join("end.asp"), "<% Application.Lock\nApplication(\"n\") = 1\nResponse.End %>").unwrap();
+        std::fs::write(dir.join("fail.asp"), "<% Application.Lock\nApplication(\"n\") = 2\nx = 1 / 0 %>").unwrap();
+        std::fs::write(dir.join("read.asp"), "<%= Application(\"n\") %>").unwrap();
+        let state = test_state(&dir, WorkerPool::new(1, 0));
+        state.store.app_lock_timeout_secs.store(1, Ordering::Relaxed);
+
+        state.dispatch(get("end.asp")).await;
+        assert_eq!(state.dispatch(get("read.asp")).await.body, b"1");
+        state.dispatch(get("fail.asp")).await;
+        assert_eq!(state.dispatch(get("read.asp")).await.body, b"2");
+
+        // A lock that is never released fails the waiting page
+        let holder = state.store.allocate_request_id();
+        state.store.lock_app_blocking(holder, "/hold.asp").unwrap();
+        let blocked = state.dispatch(get("read.asp")).await;
+        assert!(String::from_utf8_lossy(&blocked.body).contains("held by /hold.asp"));
+        state.store.unlock_app(holder);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
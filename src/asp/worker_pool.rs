@@ -0,0 +1,338 @@
+//! Bounded pool of blocking worker threads that run ASP pages.
+//!
+//! The interpreter is synchronous: a page may loop for a long time, wait on
+//! `Application.Lock` or block on `FetchURL`.  Running it inside an async task
+//! would stall one of the runtime's worker threads for that whole time, so
+//! pages are handed to a fixed set of dedicated threads instead and the async
+//! side only awaits the result.
+//!
+//! Like IIS, the pool admits at most `workers + queue_max` requests at once:
+//! one running on each worker and up to `queue_max` waiting for a free one
+//! (`RequestQueueMax`).  Anything beyond that is turned away with a 503 so a
+//! burst of slow pages cannot queue up unbounded work.  Workers are started
+//! as requests need them, so a large `workers` only costs threads under a
+//! load that uses them.
+
+use std::cell::Cell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+
+use crate::asp::asp_error::ASPError;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Native stack of a worker unless more is asked for.
+pub const DEFAULT_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Native stack a level of script procedure calls may take; generous enough
+/// for unoptimized builds.
+pub const STACK_PER_CALL: usize = 64 * 1024;
+
+/// Native stack kept for the request itself below its script calls.
+pub const STACK_RESERVE: usize = 1024 * 1024;
+
+thread_local! {
+    /// Levels of script calls the stack of this worker can hold; `None` on
+    /// threads outside a pool.
+    static CALL_DEPTH_CAPACITY: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Native stack a worker needs to run `max_call_depth` levels of calls.
+pub fn stack_size_for(max_call_depth: usize) -> usize {
+    max_call_depth.saturating_mul(STACK_PER_CALL).saturating_add(STACK_RESERVE)
+}
+
+/// `max_call_depth` lowered to what the current worker's stack can hold.
+///
+/// A limit of 0 (no limit) becomes that capacity too, so a per-directory
+/// asp.ini cannot ask for more recursion than the thread survives.  Off the
+/// pool the limit is returned unchanged.
+pub fn clamp_call_depth(max_call_depth: usize) -> usize {
+    match CALL_DEPTH_CAPACITY.with(Cell::get) {
+        Some(capacity) if max_call_depth == 0 || max_call_depth > capacity => capacity,
+        _ => max_call_depth,
+    }
+}
+
+/// Bounded pool of threads for synchronous page execution.
+pub struct WorkerPool {
+    sender: Option<Sender<Job>>,
+    receiver: Arc<Mutex<Receiver<Job>>>,
+    /// Runtime the workers enter, if the pool was created inside one.
+    runtime: Option<tokio::runtime::Handle>,
+    stack_size: usize,
+    /// Most workers the pool starts.
+    max_workers: usize,
+    /// Workers started so far.
+    workers: Mutex<Vec<JoinHandle<()>>>,
+    /// Requests accepted and not yet finished (running or queued).
+    pending: Arc<AtomicUsize>,
+    capacity: usize,
+}
+
+/// Releases a request's slot in the pool once its job is done, even if the
+/// job panicked or was dropped without running.
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl WorkerPool {
+    /// Create a pool of up to `workers` threads (at least one) that accepts
+    /// up to `queue_max` further requests while they are all busy.  One
+    /// worker starts right away and the others once requests need them.
+    ///
+    /// When called inside a Tokio runtime the workers enter it, so that code
+    /// run on them can still reach the runtime through `Handle::current()`.
+    pub fn new(workers: usize, queue_max: usize) -> Self {
+        Self::with_stack_size(workers, queue_max, DEFAULT_STACK_SIZE)
+    }
+
+    /// Like [`new`](Self::new), giving each worker a native stack of
+    /// `stack_size` bytes.
+    pub fn with_stack_size(workers: usize, queue_max: usize, stack_size: usize) -> Self {
+        let max_workers = workers.max(1);
+        let (sender, receiver) = channel::<Job>();
+        let pool = WorkerPool {
+            sender: Some(sender),
+            receiver: Arc::new(Mutex::new(receiver)),
+            runtime: tokio::runtime::Handle::try_current().ok(),
+            stack_size,
+            max_workers,
+            workers: Mutex::new(Vec::new()),
+            pending: Arc::new(AtomicUsize::new(0)),
+            capacity: max_workers + queue_max,
+        };
+        let first = pool.spawn_worker(0).expect("failed to spawn ASP worker thread");
+        pool.lock_workers().push(first);
+        pool
+    }
+
+    fn lock_workers(&self) -> MutexGuard<'_, Vec<JoinHandle<()>>> {
+        self.workers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn spawn_worker(&self, index: usize) -> std::io::Result<JoinHandle<()>> {
+        let receiver = Arc::clone(&self.receiver);
+        let runtime = self.runtime.clone();
+        let stack_size = self.stack_size;
+        std::thread::Builder::new()
+            .name(format!("asp-worker-{}", index))
+            .stack_size(stack_size)
+            .spawn(move || {
+                let capacity = stack_size.saturating_sub(STACK_RESERVE) / STACK_PER_CALL;
+                CALL_DEPTH_CAPACITY.with(|c| c.set(Some(capacity)));
+                let _guard = runtime.as_ref().map(|rt| rt.enter());
+                Self::work(&receiver);
+            })
+    }
+
+    /// Start workers until there is one for each of `pending` requests, up
+    /// to the pool's maximum.  The workers already started keep serving the
+    /// queue if another cannot be started.
+    fn grow(&self, pending: usize) {
+        let mut workers = self.lock_workers();
+        while workers.len() < pending.min(self.max_workers) {
+            match self.spawn_worker(workers.len()) {
+                Ok(worker) => workers.push(worker),
+                Err(e) => {
+                    tracing::error!(error = %e, started = workers.len(), "ASP worker thread could not be started");
+                    break;
+                }
+            }
+        }
+    }
+
+    fn work(receiver: &Mutex<Receiver<Job>>) {
+        loop {
+            let job = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            };
+            job();
+        }
+    }
+
+    /// Most worker threads the pool runs.
+    pub fn workers(&self) -> usize {
+        self.max_workers
+    }
+
+    /// Worker threads started so far.
+    pub fn started(&self) -> usize {
+        self.lock_workers().len()
+    }
+
+    /// Requests currently running or waiting for a worker.
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    /// Run `f` on a worker thread and await its result.
+    ///
+    /// Fails with a 503 when the pool is already at capacity, and with a 500
+    /// when `f` panics.
+    pub async fn run<F, R>(&self, f: F) -> Result<R, ASPError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let admitted = self
+            .pending
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < self.capacity).then_some(n + 1));
+        let Ok(before) = admitted else {
+            return Err(ASPError::new(503, "Server Too Busy"));
+        };
+        let slot = Slot(Arc::clone(&self.pending));
+        self.grow(before + 1);
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let job: Job = Box::new(move || {
+            let result = catch_unwind(AssertUnwindSafe(f));
+            drop(slot);
+            if let Ok(result) = result {
+                let _ = tx.send(result);
+            }
+        });
+        let sent = self.sender.as_ref().map(|sender| sender.send(job));
+        if !matches!(sent, Some(Ok(()))) {
+            return Err(ASPError::new(503, "Server is shutting down"));
+        }
+
+        rx.await
+            .map_err(|_| ASPError::new(500, "Request failed: worker thread panicked"))
+    }
+}
+
+impl Drop for WorkerPool {
+    /// Let the workers finish the requests already queued, then join them.
+    fn drop(&mut self) {
+        self.sender.take();
+        for worker in self.lock_workers().drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_worker_pool_runs_jobs_off_the_runtime() {
+        let pool = WorkerPool::new(2, 0);
+        assert_eq!(pool.workers(), 2);
+        let runtime_thread = std::thread::current().id();
+        let worker_thread = pool.run(|| std::thread::current().id()).await.unwrap();
+        assert_ne!(worker_thread, runtime_thread);
+        assert_eq!(pool.pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_clamps_call_depth_to_stack() {
+        assert_eq!(clamp_call_depth(0), 0);
+        assert_eq!(clamp_call_depth(100_000), 100_000);
+
+        let pool = WorkerPool::with_stack_size(1, 0, stack_size_for(300));
+        let clamped = pool.run(|| (clamp_call_depth(0), clamp_call_depth(100_000), clamp_call_depth(50))).await;
+        assert_eq!(clamped.unwrap(), (300, 300, 50));
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_blocking_job_does_not_stall_runtime() {
+        // On a single-threaded runtime, a job that waits for a message sent
+        // from an async task only finishes if the runtime keeps running.
+        let pool = Arc::new(WorkerPool::new(1, 0));
+        let (tx, rx) = mpsc::channel::<u32>();
+        let job = {
+            let pool = Arc::clone(&pool);
+            tokio::spawn(async move { pool.run(move || rx.recv().unwrap() * 2).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        tx.send(21).unwrap();
+        assert_eq!(job.await.unwrap().unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_rejects_beyond_queue_max() {
+        let pool = Arc::new(WorkerPool::new(1, 1));
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+
+        let mut jobs = Vec::new();
+        for _ in 0..2 {
+            let pool = Arc::clone(&pool);
+            let release_rx = Arc::clone(&release_rx);
+            jobs.push(tokio::spawn(async move {
+                pool.run(move || release_rx.lock().unwrap().recv().unwrap()).await
+            }));
+        }
+        while pool.pending() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let rejected = pool.run(|| ()).await.unwrap_err();
+        assert_eq!(rejected.code, 503);
+
+        release_tx.send(()).unwrap();
+        release_tx.send(()).unwrap();
+        for job in jobs {
+            job.await.unwrap().unwrap();
+        }
+        assert_eq!(pool.pending(), 0);
+        pool.run(|| ()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_starts_workers_as_needed() {
+        let pool = Arc::new(WorkerPool::new(4, 0));
+        assert_eq!(pool.started(), 1);
+        pool.run(|| ()).await.unwrap();
+        pool.run(|| ()).await.unwrap();
+        assert_eq!(pool.started(), 1);
+
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+        let (started_tx, mut started_rx) = tokio::sync::mpsc::unbounded_channel::<()>();
+        let mut jobs = Vec::new();
+        for _ in 0..2 {
+            let pool = Arc::clone(&pool);
+            let release_rx = Arc::clone(&release_rx);
+            let started_tx = started_tx.clone();
+            jobs.push(tokio::spawn(async move {
+                pool.run(move || {
+                    started_tx.send(()).unwrap();
+                    release_rx.lock().unwrap().recv().unwrap()
+                })
+                .await
+            }));
+        }
+        // Both jobs run at once, so a second worker was started for them
+        for _ in 0..2 {
+            started_rx.recv().await.unwrap();
+        }
+        assert_eq!(pool.started(), 2);
+        release_tx.send(()).unwrap();
+        release_tx.send(()).unwrap();
+        for job in jobs {
+            job.await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_worker_pool_survives_panicking_job() {
+        let pool = WorkerPool::new(1, 0);
+        let err = pool.run(|| -> () { panic!("boom") }).await.unwrap_err();
+        assert_eq!(err.code, 500);
+        assert_eq!(pool.pending(), 0);
+        assert_eq!(pool.run(|| 7).await.unwrap(), 7);
+    }
+}
@@ -434,12 +434,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                                 value: v.to_string(),
                                                 type_field: Some(match v {
                                                     asperger::vbscript::VBValue::Number(_) => "Double".to_string(),
+                                                    asperger::vbscript::VBValue::Integer(_) => "Integer".to_string(),
+                                                    asperger::vbscript::VBValue::Long(_) => "Long".to_string(),
+                                                    asperger::vbscript::VBValue::Byte(_) => "Byte".to_string(),
+                                                    asperger::vbscript::VBValue::Single(_) => "Single".to_string(),
+                                                    asperger::vbscript::VBValue::Currency(_) => "Currency".to_string(),
+                                                    asperger::vbscript::VBValue::Date(_) => "Date".to_string(),
+                                                    asperger::vbscript::VBValue::Decimal(_) => "Decimal".to_string(),
                                                     asperger::vbscript::VBValue::String(_) => "String".to_string(),
                                                     asperger::vbscript::VBValue::Boolean(_) => "Boolean".to_string(),
                                                     asperger::vbscript::VBValue::Null => "Null".to_string(),
                                                     asperger::vbscript::VBValue::Empty => "Empty".to_string(),
                                                     asperger::vbscript::VBValue::Array(..) => "Array".to_string(),
                                                     asperger::vbscript::VBValue::Object(_) => "Object".to_string(),
+                                                    asperger::vbscript::VBValue::Nothing => "Nothing".to_string(),
                                                 }),
                                                 variables_reference: 0,
                                                 named_variables: None,
@@ -457,12 +465,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                                 value: v.to_string(),
                                                 type_field: Some(match v {
                                                     asperger::vbscript::VBValue::Number(_) => "Double".to_string(),
+                                                    asperger::vbscript::VBValue::Integer(_) => "Integer".to_string(),
+                                                    asperger::vbscript::VBValue::Long(_) => "Long".to_string(),
+                                                    asperger::vbscript::VBValue::Byte(_) => "Byte".to_string(),
+                                                    asperger::vbscript::VBValue::Single(_) => "Single".to_string(),
+                                                    asperger::vbscript::VBValue::Currency(_) => "Currency".to_string(),
+                                                    asperger::vbscript::VBValue::Date(_) => "Date".to_string(),
+                                                    asperger::vbscript::VBValue::Decimal(_) => "Decimal".to_string(),
                                                     asperger::vbscript::VBValue::String(_) => "String".to_string(),
                                                     asperger::vbscript::VBValue::Boolean(_) => "Boolean".to_string(),
                                                     asperger::vbscript::VBValue::Null => "Null".to_string(),
                                                     asperger::vbscript::VBValue::Empty => "Empty".to_string(),
                                                     asperger::vbscript::VBValue::Array(..) => "Array".to_string(),
                                                     asperger::vbscript::VBValue::Object(_) => "Object".to_string(),
+                                                    asperger::vbscript::VBValue::Nothing => "Nothing".to_string(),
                                                 }),
                                                 variables_reference: 0,
                                                 named_variables: None,
@@ -499,7 +515,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                             ("Array".to_string(), ref_id)
                                         }
                                         asperger::vbscript::VBValue::Object(_) => ("Object".to_string(), 0),
+                                        asperger::vbscript::VBValue::Nothing => ("Nothing".to_string(), 0),
                                         asperger::vbscript::VBValue::Number(_) => ("Double".to_string(), 0),
+                                        asperger::vbscript::VBValue::Integer(_) => ("Integer".to_string(), 0),
+                                        asperger::vbscript::VBValue::Long(_) => ("Long".to_string(), 0),
+                                        asperger::vbscript::VBValue::Byte(_) => ("Byte".to_string(), 0),
+                                        asperger::vbscript::VBValue::Single(_) => ("Single".to_string(), 0),
+                                        asperger::vbscript::VBValue::Currency(_) => ("Currency".to_string(), 0),
+                                        asperger::vbscript::VBValue::Date(_) => ("Date".to_string(), 0),
+                                        asperger::vbscript::VBValue::Decimal(_) => ("Decimal".to_string(), 0),
                                         asperger::vbscript::VBValue::String(_) => ("String".to_string(), 0),
                                         asperger::vbscript::VBValue::Boolean(_) => ("Boolean".to_string(), 0),
                                         asperger::vbscript::VBValue::Null => ("Null".to_string(), 0),
@@ -558,7 +582,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     let (type_str, var_ref) = match &val {
                                         asperger::vbscript::VBValue::Array(..) => ("Array".to_string(), 0),
                                         asperger::vbscript::VBValue::Object(_) => ("Object".to_string(), 0),
+                                        asperger::vbscript::VBValue::Nothing => ("Nothing".to_string(), 0),
                                         asperger::vbscript::VBValue::Number(_) => ("Double".to_string(), 0),
+                                        asperger::vbscript::VBValue::Integer(_) => ("Integer".to_string(), 0),
+                                        asperger::vbscript::VBValue::Long(_) => ("Long".to_string(), 0),
+                                        asperger::vbscript::VBValue::Byte(_) => ("Byte".to_string(), 0),
+                                        asperger::vbscript::VBValue::Single(_) => ("Single".to_string(), 0),
+                                        asperger::vbscript::VBValue::Currency(_) => ("Currency".to_string(), 0),
+                                        asperger::vbscript::VBValue::Date(_) => ("Date".to_string(), 0),
+                                        asperger::vbscript::VBValue::Decimal(_) => ("Decimal".to_string(), 0),
                                         asperger::vbscript::VBValue::String(_) => ("String".to_string(), 0),
                                         asperger::vbscript::VBValue::Boolean(_) => ("Boolean".to_string(), 0),
                                         asperger::vbscript::VBValue::Null => ("Null".to_string(), 0),
@@ -644,8 +676,15 @@ fn start_debug_http_server<W: Write + Send + 'static>(
         enable_directory_listing: config.directory_listing,
         default_documents: None,
         log_level: None,
+        disable_page_cache: true,
+        page_cache_size: None,
+        worker_threads: None,
+        request_queue_max: None,
     };
     let server = AspServer::new(asp_cfg);
+    let dir_cache = Arc::new(dir_cache);
+    // Pages are edited while debugging, so always compile them afresh
+    let page_cache = Arc::new(asperger::asp::page_cache::PageCache::disabled());
 
     // Use a single-thread Tokio runtime for all async I/O
     let rt = tokio::runtime::Builder::new_current_thread()
@@ -656,6 +695,9 @@ fn start_debug_http_server<W: Write + Send + 'static>(
 
     let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
         rt.block_on(async {
+            // Pages run on workers of their own, so a page stopped at a
+            // breakpoint leaves this thread free to time the request out
+            let pool = config.build_worker_pool();
             let listener = match tokio::net::TcpListener::bind(&bind_addr).await {
                 Ok(l) => l,
                 Err(e) => {
@@ -726,17 +768,17 @@ fn start_debug_http_server<W: Write + Send + 'static>(
                     }));
                 }
 
-                let response = match tokio::time::timeout(
-                    std::time::Duration::from_secs(10),
-                    AspServer::process_request(
-                        request,
-                        &folder,
-                        &dir_cache,
-                        &server.store,
-                        Some(Arc::clone(&debugger)),
-                    ),
-                )
-                .await
+                let page = {
+                    let folder = folder.clone();
+                    let dir_cache = Arc::clone(&dir_cache);
+                    let page_cache = Arc::clone(&page_cache);
+                    let store = Arc::clone(&server.store);
+                    let debugger = Arc::clone(&debugger);
+                    move || AspServer::process_request(request, &folder, &dir_cache, &page_cache, &store, Some(debugger))
+                };
+                let response = match tokio::time::timeout(std::time::Duration::from_secs(10), pool.run(page))
+                    .await
+                    .map(|result| result.and_then(|response| response))
                 {
                     Ok(Ok(r)) => r,
                     Ok(Err(e)) => {
@@ -36,13 +36,22 @@ async fn main() {
         Some(cli.enable_directory_listing),
     );
     cfg.apply_log_level(cli.log_level.as_deref());
+    cfg.apply_page_cache(cli.disable_page_cache.then_some(false), cli.page_cache_size);
+    cfg.apply_worker_pool(cli.worker_threads, cli.request_queue_max);
 
     // Initialize structured logging.
     // Priority: RUST_LOG env > CLI --log-level > asp.ini log_level > "info"
     init_logging(cli.log_level.as_deref(), &cfg.log_level);
 
     // Start the server with the specified configuration.
-    let server = AspServer::new(cli);
+    let store = match cfg.build_store() {
+        Ok(store) => store,
+        Err(e) => {
+            tracing::error!("Session state error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let server = AspServer::with_store(cli, store);
     if let Err(e) = server.start_axum(&cfg).await {
         tracing::error!("Server error: {}", e);
     }
@@ -0,0 +1,566 @@
+//! ADODB COM objects: `Connection`, `Recordset`, `Fields` and `Field`.
+//!
+//! A connection reaches its database through a [`Provider`], picked by the
+//! `Provider=` or `Driver=` key of the connection string.  The embedded
+//! SQLite provider is built in with the `sqlite` feature; others can be
+//! added with [`register_provider`].  A connection string that names no
+//! provider (e.g. a bare `DSN=`) opens without a database behind it, and
+//! `Execute` then returns an empty recordset.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::execution_context::ExecutionContext;
+use super::symbol::{sym, Symbol};
+use super::value::VBValue;
+use super::value_utils;
+use super::vbobject::{ObjectRef, VBScriptObject};
+use super::vbs_error::{codes, ErrDetails, VBSError, VBSErrorType};
+use crate::{impl_vbscript_object, dispatch_by_symbol, prop_not_found, method_not_found, cannot_set_property};
+
+pub use self::provider::{
+    find_provider, register_provider, ConnectionProperties, Provider, ProviderConnection, ProviderError, ResultSet,
+};
+
+mod provider;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+/// ADO error `0x800A0BCD` (3021), reported as a negative `Err.Number`.
+const ADO_NO_CURRENT_RECORD: i32 = 0x800A0BCDu32 as i32;
+/// ADO error `0x800A0CC1` (3265).
+const ADO_ITEM_NOT_FOUND: i32 = 0x800A0CC1u32 as i32;
+/// ADO error `0x800A0E78` (3704).
+const ADO_OBJECT_CLOSED: i32 = 0x800A0E78u32 as i32;
+/// ADO error `0x800A0E7A` (3706).
+const ADO_PROVIDER_NOT_FOUND: i32 = 0x800A0E7Au32 as i32;
+/// ADO error `0x800A0E7D` (3709).
+const ADO_CONNECTION_NOT_USABLE: i32 = 0x800A0E7Du32 as i32;
+/// `E_FAIL`, the number ADO reports for errors raised by a provider.
+const PROVIDER_FAILURE: i32 = 0x80004005u32 as i32;
+
+/// The `Options` of `Connection.Execute` that are supported: how to read
+/// the command text, and whether to skip building a recordset.
+const AD_OPTION_UNSPECIFIED: i32 = -1;
+const AD_CMD_TEXT: i32 = 1;
+const AD_CMD_TABLE: i32 = 2;
+const AD_CMD_UNKNOWN: i32 = 8;
+const AD_EXECUTE_NO_RECORDS: i32 = 0x80;
+
+fn ado_error(number: i32, message: &str, source: &str) -> VBSError {
+    VBSError::new(number, message.to_string(), VBSErrorType::RuntimeError).with_details(ErrDetails {
+        source: Some(source.to_string()),
+        ..ErrDetails::default()
+    })
+}
+
+fn no_current_record() -> VBSError {
+    ado_error(
+        ADO_NO_CURRENT_RECORD,
+        "Either BOF or EOF is True, or the current record has been deleted. \
+         Requested operation requires a current record.",
+        "ADODB.Recordset",
+    )
+}
+
+fn item_not_found() -> VBSError {
+    ado_error(
+        ADO_ITEM_NOT_FOUND,
+        "Item cannot be found in the collection corresponding to the requested name or ordinal.",
+        "ADODB.Fields",
+    )
+}
+
+fn object_closed() -> VBSError {
+    ado_error(
+        ADO_OBJECT_CLOSED,
+        "Operation is not allowed when the object is closed.",
+        "ADODB.Recordset",
+    )
+}
+
+fn provider_error(provider: &str, e: ProviderError) -> VBSError {
+    ado_error(PROVIDER_FAILURE, &e.to_string(), provider)
+}
+
+// ---- Connection ----
+
+/// `ADODB.Connection` — a connection to a database through a [`Provider`].
+///
+/// `Open` connects using `ConnectionString` (or its argument), `Execute`
+/// runs SQL and returns a `Recordset`, and `Close` drops the connection.
+pub struct Connection {
+    connection_string: Mutex<String>,
+    state: Mutex<i32>,
+    /// The open provider connection, with the provider name it was
+    /// opened with for error sources.
+    link: Mutex<Option<(String, Box<dyn ProviderConnection>)>>,
+}
+
+impl std::fmt::Debug for Connection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connection")
+            .field("connection_string", &self.connection_string)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Connection {
+    pub fn new() -> Self {
+        Connection {
+            connection_string: Mutex::new(String::new()),
+            state: Mutex::new(0),
+            link: Mutex::new(None),
+        }
+    }
+
+    fn connection_string(&self) -> MutexGuard<'_, String> {
+        self.connection_string.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn link(&self) -> MutexGuard<'_, Option<(String, Box<dyn ProviderConnection>)>> {
+        self.link.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn set_state(&self, state: i32) {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) = state;
+    }
+
+    /// Connect, resolving relative file paths in the connection string
+    /// against `root`.
+    fn open(&self, root: &Path) -> Result<(), VBSError> {
+        let properties = ConnectionProperties::parse(&self.connection_string()).with_base_dir(root);
+        let link = match properties.provider_name() {
+            Some(name) => {
+                let provider = find_provider(name).ok_or_else(|| {
+                    ado_error(
+                        ADO_PROVIDER_NOT_FOUND,
+                        "Provider cannot be found. It may not be properly installed.",
+                        "ADODB.Connection",
+                    )
+                })?;
+                let connection = provider.connect(&properties).map_err(|e| provider_error(name, e))?;
+                Some((name.to_string(), connection))
+            }
+            None => None,
+        };
+        *self.link() = link;
+        self.set_state(1);
+        Ok(())
+    }
+
+    /// Run `command` as `options` says; `None` when the connection has
+    /// no database behind it.
+    fn execute(&self, command: &str, options: i32) -> Result<Option<ResultSet>, VBSError> {
+        let no_records = options != AD_OPTION_UNSPECIFIED && options & AD_EXECUTE_NO_RECORDS != 0;
+        let sql = match options {
+            AD_OPTION_UNSPECIFIED => command.to_string(),
+            _ => match options & !AD_EXECUTE_NO_RECORDS {
+                0 | AD_CMD_TEXT | AD_CMD_UNKNOWN => command.to_string(),
+                AD_CMD_TABLE => format!("SELECT * FROM {}", command),
+                _ => return Err(VBSError::runtime_with(codes::INVALID_PROCEDURE_CALL, "Execute")),
+            },
+        };
+        let mut link = self.link();
+        let Some((name, connection)) = link.as_mut() else {
+            let properties = ConnectionProperties::parse(&self.connection_string());
+            if properties.provider_name().is_some() {
+                return Err(ado_error(
+                    ADO_CONNECTION_NOT_USABLE,
+                    "The connection cannot be used to perform this operation. \
+                     It is either closed or invalid in this context.",
+                    "ADODB.Connection",
+                ));
+            }
+            return Ok(None);
+        };
+        let mut result = connection.execute(&sql).map_err(|e| provider_error(name, e))?;
+        if no_records {
+            result.columns.clear();
+            result.rows.clear();
+        }
+        Ok(Some(result))
+    }
+}
+
+impl VBScriptObject for Connection {
+    impl_vbscript_object!(Connection, "Connection");
+    dispatch_by_symbol!(get, set, call);
+
+    fn get_property_sym(
+        &self,
+        member: Symbol,
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        match member {
+            sym::CONNECTIONSTRING => Ok(VBValue::String(self.connection_string().clone().into())),
+            sym::STATE => Ok(VBValue::Number(
+                *self.state.lock().unwrap_or_else(|e| e.into_inner()) as f64,
+            )),
+            _ => prop_not_found!("Connection", member),
+        }
+    }
+
+    fn set_property_sym(
+        &self,
+        member: Symbol,
+        value: VBValue,
+        _context: &mut ExecutionContext,
+    ) -> Result<(), VBSError> {
+        match member {
+            sym::CONNECTIONSTRING => {
+                *self.connection_string() = value_utils::to_arg_string(&value);
+                Ok(())
+            }
+            _ => cannot_set_property!("Connection", member),
+        }
+    }
+
+    fn call_method_sym(
+        &self,
+        member: Symbol,
+        args: &[VBValue],
+        context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        match member {
+            sym::OPEN => {
+                if !args.is_empty() {
+                    *self.connection_string() = value_utils::to_arg_string(&args[0]);
+                }
+                self.open(&context.app_root)?;
+                Ok(VBValue::Empty)
+            }
+            sym::CLOSE => {
+                *self.link() = None;
+                self.set_state(0);
+                Ok(VBValue::Empty)
+            }
+            // Execute CommandText, [RecordsAffected], [Options]
+            sym::EXECUTE => {
+                if args.is_empty() || args.len() > 3 {
+                    return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Execute"));
+                }
+                let command = value_utils::to_arg_string(&args[0]);
+                let options = match args.get(2) {
+                    None | Some(VBValue::Empty) => AD_OPTION_UNSPECIFIED,
+                    Some(value) => value_utils::to_arg_f64(value) as i32,
+                };
+                let result = self.execute(&command, options)?;
+                if args.len() > 1 {
+                    // Statements that return rows report -1, as ADO does
+                    let affected = match &result {
+                        Some(result) if result.columns.is_empty() => result.records_affected as i32,
+                        Some(_) => -1,
+                        None => 0,
+                    };
+                    context.byref_results = vec![None, Some(VBValue::Long(affected))];
+                }
+                if options != AD_OPTION_UNSPECIFIED && options & AD_EXECUTE_NO_RECORDS != 0 {
+                    return Ok(VBValue::Nothing);
+                }
+                let recordset = result.map_or_else(Recordset::empty, Recordset::from_result);
+                Ok(VBValue::Object(ObjectRef::new(recordset)))
+            }
+            _ => method_not_found!("Connection", member),
+        }
+    }
+}
+
+// ---- Recordset ----
+
+/// Position of a recordset.  The current record is `rows[index]` unless
+/// `bof` is set or `index` is past the last row (EOF).
+#[derive(Debug)]
+struct Cursor {
+    index: usize,
+    bof: bool,
+    closed: bool,
+}
+
+/// Rows and position shared by a `Recordset` and its `Field`s, which
+/// always read the current record.
+#[derive(Debug)]
+struct Rows {
+    columns: Vec<String>,
+    rows: Vec<Vec<VBValue>>,
+    cursor: Mutex<Cursor>,
+}
+
+impl Rows {
+    fn cursor(&self) -> MutexGuard<'_, Cursor> {
+        self.cursor.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The cursor of an open recordset.
+    fn open_cursor(&self) -> Result<MutexGuard<'_, Cursor>, VBSError> {
+        let cursor = self.cursor();
+        if cursor.closed {
+            return Err(object_closed());
+        }
+        Ok(cursor)
+    }
+
+    fn ensure_open(&self) -> Result<(), VBSError> {
+        self.open_cursor().map(drop)
+    }
+
+    fn eof(&self, cursor: &Cursor) -> bool {
+        cursor.index >= self.rows.len()
+    }
+
+    fn value(&self, column: usize) -> Result<VBValue, VBSError> {
+        let cursor = self.open_cursor()?;
+        if cursor.bof {
+            return Err(no_current_record());
+        }
+        self.rows
+            .get(cursor.index)
+            .map(|row| row[column].clone())
+            .ok_or_else(no_current_record)
+    }
+
+    /// The column `index` names, by name or by ordinal.
+    fn column(&self, index: &VBValue) -> Result<usize, VBSError> {
+        let found = match index {
+            VBValue::String(name) => self.columns.iter().position(|c| c.eq_ignore_ascii_case(name)),
+            _ => {
+                let n = value_utils::to_arg_f64(index);
+                (n >= 0.0 && (n as usize) < self.columns.len()).then_some(n as usize)
+            }
+        };
+        found.ok_or_else(item_not_found)
+    }
+}
+
+/// `ADODB.Recordset` — the rows returned by `Connection.Execute`.
+///
+/// `rs("name")` and `rs(0)` give the value of a column in the current
+/// record; `rs.Fields("name")` gives the `Field` itself.
+#[derive(Debug)]
+pub struct Recordset(Arc<Rows>);
+
+impl Recordset {
+    pub fn empty() -> Self {
+        Self::new(Vec::new(), Vec::new(), false)
+    }
+
+    /// A recordset over `result`; closed, as ADO returns it, when the
+    /// statement produced no columns.
+    fn from_result(result: ResultSet) -> Self {
+        let closed = result.columns.is_empty();
+        Self::new(result.columns, result.rows, closed)
+    }
+
+    fn new(columns: Vec<String>, rows: Vec<Vec<VBValue>>, closed: bool) -> Self {
+        let bof = rows.is_empty();
+        Recordset(Arc::new(Rows {
+            columns,
+            rows,
+            cursor: Mutex::new(Cursor { index: 0, bof, closed }),
+        }))
+    }
+
+    fn field(&self, index: &VBValue) -> Result<VBValue, VBSError> {
+        let column = self.0.column(index)?;
+        Ok(VBValue::Object(ObjectRef::new(Field { rows: self.0.clone(), column })))
+    }
+}
+
+impl VBScriptObject for Recordset {
+    impl_vbscript_object!(Recordset, "Recordset");
+    dispatch_by_symbol!(get, call);
+
+    fn get_property_sym(
+        &self,
+        member: Symbol,
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        match member {
+            sym::STATE => Ok(VBValue::Number(if self.0.cursor().closed { 0.0 } else { 1.0 })),
+            sym::EOF => {
+                let cursor = self.0.open_cursor()?;
+                Ok(VBValue::Boolean(self.0.eof(&cursor)))
+            }
+            sym::BOF => Ok(VBValue::Boolean(self.0.open_cursor()?.bof)),
+            sym::RECORDCOUNT => {
+                self.0.ensure_open()?;
+                Ok(VBValue::Number(self.0.rows.len() as f64))
+            }
+            sym::FIELDS => {
+                self.0.ensure_open()?;
+                Ok(VBValue::Object(ObjectRef::new(Fields(self.0.clone()))))
+            }
+            _ => prop_not_found!("Recordset", member),
+        }
+    }
+
+    fn call_method_sym(
+        &self,
+        member: Symbol,
+        args: &[VBValue],
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        match member {
+            sym::MOVENEXT => {
+                let mut cursor = self.0.open_cursor()?;
+                if self.0.eof(&cursor) {
+                    return Err(no_current_record());
+                }
+                if cursor.bof {
+                    cursor.bof = false;
+                } else {
+                    cursor.index += 1;
+                }
+                Ok(VBValue::Empty)
+            }
+            sym::MOVEPREVIOUS => {
+                let mut cursor = self.0.open_cursor()?;
+                if cursor.bof {
+                    return Err(no_current_record());
+                }
+                if cursor.index == 0 {
+                    cursor.bof = true;
+                } else {
+                    cursor.index -= 1;
+                }
+                Ok(VBValue::Empty)
+            }
+            sym::MOVEFIRST => {
+                let mut cursor = self.0.open_cursor()?;
+                cursor.index = 0;
+                cursor.bof = self.0.rows.is_empty();
+                Ok(VBValue::Empty)
+            }
+            sym::MOVELAST => {
+                let mut cursor = self.0.open_cursor()?;
+                if self.0.rows.is_empty() {
+                    return Err(no_current_record());
+                }
+                cursor.index = self.0.rows.len() - 1;
+                cursor.bof = false;
+                Ok(VBValue::Empty)
+            }
+            sym::FIELDS if args.len() == 1 => {
+                self.0.ensure_open()?;
+                self.field(&args[0])
+            }
+            sym::CLOSE => {
+                self.0.open_cursor()?.closed = true;
+                Ok(VBValue::Empty)
+            }
+            _ => method_not_found!("Recordset", member),
+        }
+    }
+
+    fn indexed_get(
+        &self,
+        index: &VBValue,
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        let column = self.0.column(index)?;
+        self.0.value(column)
+    }
+}
+
+// ---- Fields ----
+
+/// `Recordset.Fields` — the columns of a recordset, by name or ordinal.
+#[derive(Debug)]
+pub struct Fields(Arc<Rows>);
+
+impl Fields {
+    fn field(&self, index: &VBValue) -> Result<VBValue, VBSError> {
+        Recordset(self.0.clone()).field(index)
+    }
+}
+
+impl VBScriptObject for Fields {
+    impl_vbscript_object!(Fields, "Fields");
+    dispatch_by_symbol!(get, call);
+
+    fn get_property_sym(
+        &self,
+        member: Symbol,
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        match member {
+            sym::COUNT => Ok(VBValue::Number(self.0.columns.len() as f64)),
+            _ => prop_not_found!("Fields", member),
+        }
+    }
+
+    fn call_method_sym(
+        &self,
+        member: Symbol,
+        args: &[VBValue],
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        match member {
+            sym::ITEM if args.len() == 1 => self.field(&args[0]),
+            _ => method_not_found!("Fields", member),
+        }
+    }
+
+    fn indexed_get(
+        &self,
+        index: &VBValue,
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        self.field(index)
+    }
+
+    /// `For Each` visits the `Field`s.
+    fn for_each_items(&self, _context: &mut ExecutionContext) -> Result<Option<Vec<VBValue>>, VBSError> {
+        let fields = (0..self.0.columns.len())
+            .map(|column| VBValue::Object(ObjectRef::new(Field { rows: self.0.clone(), column })))
+            .collect();
+        Ok(Some(fields))
+    }
+}
+
+// ---- Field ----
+
+/// A column of a recordset; `Value` reads it from the current record.
+#[derive(Debug)]
+pub struct Field {
+    rows: Arc<Rows>,
+    column: usize,
+}
+
+impl VBScriptObject for Field {
+    impl_vbscript_object!(Field, "Field");
+    dispatch_by_symbol!(get, call);
+
+    fn get_property_sym(
+        &self,
+        member: Symbol,
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        match member {
+            sym::NAME => Ok(VBValue::String(self.rows.columns[self.column].clone().into())),
+            sym::VALUE => self.rows.value(self.column),
+            _ => prop_not_found!("Field", member),
+        }
+    }
+
+    fn call_method_sym(
+        &self,
+        member: Symbol,
+        _args: &[VBValue],
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        method_not_found!("Field", member)
+    }
+
+    /// `Value` is the default member.
+    fn default_value(&self, _context: &mut ExecutionContext) -> Result<Option<VBValue>, VBSError> {
+        self.rows.value(self.column).map(Some)
+    }
+}
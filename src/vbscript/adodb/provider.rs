@@ -0,0 +1,184 @@
+//! The [`Provider`] trait, the registry of providers, and connection
+//! string parsing.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use super::super::value::VBValue;
+
+/// A database engine that `ADODB.Connection` can open.
+///
+/// A connection string picks its provider by the `Provider=` key, or by
+/// `Driver=` for ODBC-style strings; the value is matched case-insensitively
+/// against [`names`](Self::names).
+pub trait Provider: Send + Sync {
+    /// Names the provider answers to, e.g. `SQLite` or `SQLite3 ODBC Driver`.
+    fn names(&self) -> &[&str];
+
+    /// Open a connection described by `properties`.
+    fn connect(&self, properties: &ConnectionProperties) -> Result<Box<dyn ProviderConnection>, ProviderError>;
+}
+
+/// An open connection of a [`Provider`].
+pub trait ProviderConnection: Send {
+    /// Run one SQL statement.  Statements that return no rows yield a
+    /// [`ResultSet`] without columns.
+    fn execute(&mut self, sql: &str) -> Result<ResultSet, ProviderError>;
+}
+
+/// Rows produced by a statement, with values already converted to the
+/// variant types scripts see.
+#[derive(Debug, Default)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<VBValue>>,
+    /// Rows inserted, updated or deleted by the statement.
+    pub records_affected: u64,
+}
+
+/// An error reported by a provider, shown to scripts as `Err.Description`.
+#[derive(Debug)]
+pub struct ProviderError(pub String);
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The `key=value` pairs of a connection string.
+///
+/// Keys are matched case-insensitively and with surrounding blanks
+/// removed; a value may be enclosed in braces or quotes to contain `;`.
+#[derive(Debug, Default)]
+pub struct ConnectionProperties {
+    pairs: Vec<(String, String)>,
+    /// Directory relative file paths resolve against: the application
+    /// root for connections opened by scripts.
+    base_dir: PathBuf,
+}
+
+impl ConnectionProperties {
+    pub fn parse(connection_string: &str) -> Self {
+        let mut pairs = Vec::new();
+        let mut rest = connection_string;
+        loop {
+            rest = rest.trim_start_matches(|c: char| c == ';' || c.is_whitespace());
+            if rest.is_empty() {
+                break;
+            }
+            let key_end = rest.find(['=', ';']).unwrap_or(rest.len());
+            let key = rest[..key_end].trim().to_string();
+            let (value, after_value) = match rest[key_end..].strip_prefix('=') {
+                Some(after_key) => split_value(after_key.trim_start()),
+                None => ("", &rest[key_end..]),
+            };
+            pairs.push((key, value.trim().to_string()));
+            rest = after_value;
+        }
+        ConnectionProperties { pairs, base_dir: PathBuf::new() }
+    }
+
+    pub fn with_base_dir(mut self, dir: &Path) -> Self {
+        self.base_dir = dir.to_path_buf();
+        self
+    }
+
+    /// `path` from the connection string, resolved against the base
+    /// directory if it is relative.
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        self.base_dir.join(path)
+    }
+
+    /// The value of the first of `keys` present.
+    pub fn get(&self, keys: &[&str]) -> Option<&str> {
+        keys.iter().find_map(|key| {
+            self.pairs
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v.as_str())
+        })
+    }
+
+    /// The provider named by `Provider=` or `Driver=`, if any.
+    pub fn provider_name(&self) -> Option<&str> {
+        self.get(&["Provider", "Driver"]).filter(|name| !name.is_empty())
+    }
+}
+
+/// A value and what follows its terminating `;`.
+fn split_value(s: &str) -> (&str, &str) {
+    let close = match s.chars().next() {
+        Some('{') => '}',
+        Some(quote @ ('"' | '\'')) => quote,
+        _ => return s.split_once(';').unwrap_or((s, "")),
+    };
+    let body = &s[1..];
+    match body.find(close) {
+        Some(end) => (&body[..end], body[end + 1..].split_once(';').map_or("", |(_, tail)| tail)),
+        None => (body, ""),
+    }
+}
+
+fn registry() -> &'static RwLock<Vec<Arc<dyn Provider>>> {
+    static REGISTRY: OnceLock<RwLock<Vec<Arc<dyn Provider>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let providers: Vec<Arc<dyn Provider>> = vec![
+            #[cfg(feature = "sqlite")]
+            Arc::new(super::sqlite::SqliteProvider),
+        ];
+        RwLock::new(providers)
+    })
+}
+
+/// Make `provider` available to connection strings naming it.  A provider
+/// registered later takes precedence over one answering to the same name.
+pub fn register_provider(provider: Arc<dyn Provider>) {
+    registry().write().unwrap_or_else(|e| e.into_inner()).insert(0, provider);
+}
+
+/// The registered provider answering to `name`.
+pub fn find_provider(name: &str) -> Option<Arc<dyn Provider>> {
+    let name = name.trim();
+    registry()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|provider| provider.names().iter().any(|n| n.eq_ignore_ascii_case(name)))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_connection_string() {
+        let props = ConnectionProperties::parse(
+            "Driver={SQLite3 ODBC Driver; v2}; Database = C:\\data\\app.db ;Mode=\"a;b\";;flag",
+        );
+        assert_eq!(props.provider_name(), Some("SQLite3 ODBC Driver; v2"));
+        assert_eq!(props.get(&["Data Source", "DATABASE"]), Some("C:\\data\\app.db"));
+        assert_eq!(props.get(&["mode"]), Some("a;b"));
+        assert_eq!(props.get(&["flag"]), Some(""));
+        assert_eq!(ConnectionProperties::parse("dsn=mydb").provider_name(), None);
+    }
+
+    #[test]
+    fn test_registered_provider_is_found_by_name() {
+        struct Fake;
+        impl Provider for Fake {
+            fn names(&self) -> &[&str] {
+                &["Fake.Provider"]
+            }
+            fn connect(&self, _: &ConnectionProperties) -> Result<Box<dyn ProviderConnection>, ProviderError> {
+                Err(ProviderError("unreachable".to_string()))
+            }
+        }
+        assert!(find_provider("fake.provider").is_none());
+        register_provider(Arc::new(Fake));
+        assert!(find_provider(" FAKE.PROVIDER ").is_some());
+        assert!(find_provider("Other").is_none());
+    }
+}
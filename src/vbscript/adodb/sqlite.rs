@@ -0,0 +1,114 @@
+//! An embedded SQLite provider, so that pages can use a database file
+//! without a database server.
+//!
+//! `Provider=SQLite;Data Source=path` opens (creating if needed) the file
+//! at `path`, relative to the application root;
+//! `Driver={SQLite3 ODBC Driver};Database=path` is accepted as well, and
+//! `:memory:` opens a private in-memory database.
+
+use std::sync::Arc;
+
+use rusqlite::types::ValueRef;
+
+use super::super::value::VBValue;
+use super::provider::{ConnectionProperties, Provider, ProviderConnection, ProviderError, ResultSet};
+
+pub struct SqliteProvider;
+
+impl Provider for SqliteProvider {
+    fn names(&self) -> &[&str] {
+        &["SQLite", "SQLite3", "SQLite ODBC Driver", "SQLite3 ODBC Driver"]
+    }
+
+    fn connect(&self, properties: &ConnectionProperties) -> Result<Box<dyn ProviderConnection>, ProviderError> {
+        let path = properties
+            .get(&["Data Source", "Database", "DBQ"])
+            .filter(|path| !path.is_empty())
+            .ok_or_else(|| ProviderError("SQLite: the connection string names no Data Source".to_string()))?;
+        let connection = if path == ":memory:" {
+            rusqlite::Connection::open_in_memory()
+        } else {
+            rusqlite::Connection::open(properties.resolve_path(path))
+        }
+        .map_err(sqlite_error)?;
+        Ok(Box::new(SqliteConnection(connection)))
+    }
+}
+
+struct SqliteConnection(rusqlite::Connection);
+
+impl ProviderConnection for SqliteConnection {
+    fn execute(&mut self, sql: &str) -> Result<ResultSet, ProviderError> {
+        let mut statement = self.0.prepare(sql).map_err(sqlite_error)?;
+        let columns: Vec<String> = statement.column_names().iter().map(|name| name.to_string()).collect();
+        if columns.is_empty() {
+            let records_affected = statement.execute([]).map_err(sqlite_error)?;
+            return Ok(ResultSet { records_affected: records_affected as u64, ..ResultSet::default() });
+        }
+        let mut rows = Vec::new();
+        let mut cursor = statement.query([]).map_err(sqlite_error)?;
+        while let Some(row) = cursor.next().map_err(sqlite_error)? {
+            let values = (0..columns.len())
+                .map(|i| row.get_ref(i).map(to_value))
+                .collect::<Result<Vec<VBValue>, _>>()
+                .map_err(sqlite_error)?;
+            rows.push(values);
+        }
+        Ok(ResultSet { columns, rows, records_affected: 0 })
+    }
+}
+
+/// Integers that fit become `Long`, larger ones `Double`; blobs become
+/// arrays of `Byte`.
+fn to_value(value: ValueRef<'_>) -> VBValue {
+    match value {
+        ValueRef::Null => VBValue::Null,
+        ValueRef::Integer(n) => i32::try_from(n).map_or(VBValue::Number(n as f64), VBValue::Long),
+        ValueRef::Real(n) => VBValue::Number(n),
+        ValueRef::Text(text) => VBValue::String(String::from_utf8_lossy(text).into_owned().into()),
+        ValueRef::Blob(bytes) => {
+            VBValue::Array(Arc::new(bytes.iter().map(|&b| VBValue::Byte(b)).collect()), vec![])
+        }
+    }
+}
+
+fn sqlite_error(e: rusqlite::Error) -> ProviderError {
+    ProviderError(format!("SQLite: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqlite_types_become_variants() {
+        let props = ConnectionProperties::parse("Provider=SQLite;Data Source=:memory:");
+        let mut conn = SqliteProvider.connect(&props).unwrap();
+        let created = conn.execute("CREATE TABLE t (i INTEGER, big INTEGER, r REAL, s TEXT, b BLOB, n TEXT)").unwrap();
+        assert!(created.columns.is_empty());
+        let inserted = conn
+            .execute("INSERT INTO t VALUES (7, 5000000000, 1.5, 'héllo', x'0102', NULL)")
+            .unwrap();
+        assert_eq!(inserted.records_affected, 1);
+        let result = conn.execute("SELECT * FROM t").unwrap();
+        assert_eq!(result.columns, ["i", "big", "r", "s", "b", "n"]);
+        assert_eq!(
+            result.rows,
+            [vec![
+                VBValue::Long(7),
+                VBValue::Number(5_000_000_000.0),
+                VBValue::Number(1.5),
+                VBValue::String("héllo".into()),
+                VBValue::Array(Arc::new(vec![VBValue::Byte(1), VBValue::Byte(2)]), vec![]),
+                VBValue::Null,
+            ]]
+        );
+        assert!(conn.execute("SELECT * FROM missing").is_err());
+    }
+
+    #[test]
+    fn test_sqlite_requires_a_data_source() {
+        let props = ConnectionProperties::parse("Provider=SQLite");
+        assert!(SqliteProvider.connect(&props).is_err());
+    }
+}
@@ -1,48 +1,50 @@
 use super::super::execution_context::ExecutionContext;
+use super::super::symbol::{sym, Symbol};
 use super::super::value::VBValue;
 use super::super::value_utils;
-use super::super::vbobject::VBScriptObject;
-use super::super::vbs_error::VBSError;
-use crate::{impl_vbscript_object, prop_not_found, method_not_found};
+use super::super::vbobject::{ObjectRef, VBScriptObject};
+use super::super::vbs_error::{codes, VBSError};
+use crate::{impl_vbscript_object, dispatch_by_symbol, prop_not_found, method_not_found};
 
 #[derive(Debug, Clone)]
 pub struct ApplicationObject;
 
 impl VBScriptObject for ApplicationObject {
     impl_vbscript_object!(ApplicationObject, "Application");
+    dispatch_by_symbol!(get, call);
 
-    fn get_property(
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "CONTENTS" => Ok(VBValue::Object(Box::new(ApplicationContents))),
-            "STATICOBJECTS" => Ok(VBValue::Empty),
-            _ => prop_not_found!("Application", name),
+        match member {
+            sym::CONTENTS => Ok(VBValue::Object(ObjectRef::new(ApplicationContents))),
+            sym::STATICOBJECTS => Ok(VBValue::Object(ObjectRef::new(StaticObjects::new(None)))),
+            _ => prop_not_found!("Application", member),
         }
     }
 
-    fn call_method(
-        &mut self,
-        name: &str,
+    fn call_method_sym(
+        &self,
+        member: Symbol,
         _args: &[VBValue],
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "LOCK" => {
+        match member {
+            sym::LOCK => {
                 if let Some(ref store) = context.store {
-                    store.lock_app_blocking(context.request_id);
+                    store.lock_app_blocking(context.request_id, &context.script_path)?;
                 }
                 Ok(VBValue::Empty)
             }
-            "UNLOCK" => {
+            sym::UNLOCK => {
                 if let Some(ref store) = context.store {
                     store.unlock_app(context.request_id);
                 }
                 Ok(VBValue::Empty)
             }
-            _ => method_not_found!("Application", name),
+            _ => method_not_found!("Application", member),
         }
     }
 
@@ -53,27 +55,22 @@ impl VBScriptObject for ApplicationObject {
     ) -> Result<VBValue, VBSError> {
         let key = value_utils::to_arg_string(index);
         if let Some(ref store) = context.store {
-            store.wait_for_app_unlock(context.request_id);
-            let apps = store.lock_apps();
-            return Ok(apps
-                .get(&key.to_uppercase())
-                .cloned()
-                .unwrap_or(VBValue::Empty));
+            store.wait_for_app_unlock(context.request_id)?;
+            return store.fetch_app_var(context.request_id, &key);
         }
         Ok(VBValue::Empty)
     }
 
     fn indexed_set(
-        &mut self,
+        &self,
         index: &VBValue,
         value: VBValue,
         context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
         let key = value_utils::to_arg_string(index);
         if let Some(ref store) = context.store {
-            store.wait_for_app_unlock(context.request_id);
-            let mut apps = store.lock_apps();
-            apps.insert(key.to_uppercase(), value);
+            store.wait_for_app_unlock(context.request_id)?;
+            return store.set_app_var(&key, value);
         }
         Ok(())
     }
@@ -90,21 +87,17 @@ impl VBScriptObject for ApplicationContents {
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         if let Some(ref store) = context.store {
-            store.wait_for_app_unlock(context.request_id);
-            let apps = store.lock_apps();
+            store.wait_for_app_unlock(context.request_id)?;
             match name.to_uppercase().as_str() {
-                "COUNT" => Ok(VBValue::Number(apps.len() as f64)),
-                _ => Ok(apps
-                    .get(&name.to_uppercase())
-                    .cloned()
-                    .unwrap_or(VBValue::Empty)),
+                "COUNT" => Ok(VBValue::Number(store.app_vars()?.len() as f64)),
+                _ => store.fetch_app_var(context.request_id, name),
             }
         } else {
             Ok(VBValue::Number(0.0))
         }
     }
     fn call_method(
-        &mut self,
+        &self,
         _name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -118,27 +111,122 @@ impl VBScriptObject for ApplicationContents {
     ) -> Result<VBValue, VBSError> {
         let key = value_utils::to_arg_string(index);
         if let Some(ref store) = context.store {
-            store.wait_for_app_unlock(context.request_id);
-            let apps = store.lock_apps();
-            return Ok(apps
-                .get(&key.to_uppercase())
-                .cloned()
-                .unwrap_or(VBValue::Empty));
+            store.wait_for_app_unlock(context.request_id)?;
+            return store.fetch_app_var(context.request_id, &key);
         }
         Ok(VBValue::Empty)
     }
     fn indexed_set(
-        &mut self,
+        &self,
         index: &VBValue,
         value: VBValue,
         context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
         let key = value_utils::to_arg_string(index);
         if let Some(ref store) = context.store {
-            store.wait_for_app_unlock(context.request_id);
-            let mut apps = store.lock_apps();
-            apps.insert(key.to_uppercase(), value);
+            store.wait_for_app_unlock(context.request_id)?;
+            return store.set_app_var(&key, value);
         }
         Ok(())
     }
 }
+
+/// `Application.StaticObjects` and `Session.StaticObjects`: the objects
+/// Global.asa declares with `<object runat="server">` for the application,
+/// or for the session with the given ID.
+#[derive(Debug, Clone)]
+pub(crate) struct StaticObjects {
+    session_id: Option<String>,
+}
+
+impl StaticObjects {
+    pub fn new(session_id: Option<String>) -> Self {
+        StaticObjects { session_id }
+    }
+
+    fn objects(&self, context: &ExecutionContext) -> Vec<(String, VBValue)> {
+        let Some(ref store) = context.store else {
+            return Vec::new();
+        };
+        let mut objects: Vec<(String, VBValue)> = match self.session_id {
+            Some(ref id) => store
+                .lock_session_objects()
+                .get(&id.to_uppercase())
+                .map(|objects| objects.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                .unwrap_or_default(),
+            None => store
+                .lock_static_objects()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        };
+        objects.sort_by(|a, b| a.0.cmp(&b.0));
+        objects
+    }
+
+    fn lookup(&self, key: &VBValue, context: &ExecutionContext) -> Result<VBValue, VBSError> {
+        let mut objects = self.objects(context);
+        if let VBValue::String(_) = key {
+            let name = value_utils::to_arg_string(key).to_uppercase();
+            return Ok(objects
+                .into_iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v)
+                .unwrap_or(VBValue::Empty));
+        }
+        let index = value_utils::to_arg_f64(key) as usize;
+        if index < 1 || index > objects.len() {
+            return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
+        }
+        Ok(objects.swap_remove(index - 1).1)
+    }
+}
+
+impl VBScriptObject for StaticObjects {
+    impl_vbscript_object!(StaticObjects, "StaticObjects");
+    fn get_property(
+        &self,
+        name: &str,
+        context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Ok(VBValue::Number(self.objects(context).len() as f64)),
+            _ => self.lookup(&VBValue::String(name.to_string().into()), context),
+        }
+    }
+    fn call_method(
+        &self,
+        name: &str,
+        args: &[VBValue],
+        context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        match name.to_uppercase().as_str() {
+            "ITEM" => {
+                let key = args
+                    .first()
+                    .ok_or_else(|| VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Item"))?;
+                self.lookup(key, context)
+            }
+            "KEY" => {
+                let index = args
+                    .first()
+                    .map(value_utils::to_arg_f64)
+                    .ok_or_else(|| VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Key"))?
+                    as usize;
+                let mut objects = self.objects(context);
+                if index < 1 || index > objects.len() {
+                    return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
+                }
+                Ok(VBValue::String(objects.swap_remove(index - 1).0.into()))
+            }
+            _ => method_not_found!("StaticObjects", name),
+        }
+    }
+    fn indexed_get(
+        &self,
+        index: &VBValue,
+        context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        self.lookup(index, context)
+    }
+}
@@ -1,50 +1,52 @@
 use ahash::AHashMap;
 
 use super::super::execution_context::ExecutionContext;
+use super::super::symbol::{sym, Symbol};
 use super::super::value::VBValue;
 use super::super::value_utils;
-use super::super::vbobject::VBScriptObject;
+use super::super::vbobject::{ObjectRef, VBScriptObject};
 use super::super::vbs_error::VBSError;
-use crate::{impl_vbscript_object, prop_not_found, method_not_found};
+use crate::{impl_vbscript_object, dispatch_by_symbol, prop_not_found, method_not_found};
 
 #[derive(Debug, Clone)]
 pub struct RequestObject;
 
 impl VBScriptObject for RequestObject {
     impl_vbscript_object!(RequestObject, "Request");
+    dispatch_by_symbol!(get, call);
 
-    fn get_property(
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "QUERYSTRING" => Ok(VBValue::Object(Box::new(RequestQueryString(
+        match member {
+            sym::QUERYSTRING => Ok(VBValue::Object(ObjectRef::new(RequestQueryString(
                 context.request.params.clone(),
             )))),
-            "FORM" => Ok(VBValue::Object(Box::new(RequestForm(
+            sym::FORM => Ok(VBValue::Object(ObjectRef::new(RequestForm(
                 context.request.form.clone(),
             )))),
-            "SERVERVARIABLES" => Ok(VBValue::Object(Box::new(RequestServerVariables(
+            sym::SERVERVARIABLES => Ok(VBValue::Object(ObjectRef::new(RequestServerVariables(
                 context.request.headers.clone(),
             )))),
-            "COOKIES" => Ok(VBValue::Object(Box::new(RequestCookies(
+            sym::COOKIES => Ok(VBValue::Object(ObjectRef::new(RequestCookies(
                 context.request.cookies.clone(),
             )))),
-            "TOTALBYTES" => Ok(VBValue::Number(context.request.total_bytes as f64)),
-            _ => prop_not_found!("Request", name),
+            sym::TOTALBYTES => Ok(VBValue::Number(context.request.total_bytes as f64)),
+            _ => prop_not_found!("Request", member),
         }
     }
 
-    fn call_method(
-        &mut self,
-        name: &str,
+    fn call_method_sym(
+        &self,
+        member: Symbol,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "BINARYREAD" => Ok(VBValue::Empty),
-            _ => method_not_found!("Request", name),
+        match member {
+            sym::BINARYREAD => Ok(VBValue::Empty),
+            _ => method_not_found!("Request", member),
         }
     }
 }
@@ -54,14 +56,15 @@ pub(crate) struct RequestQueryString(pub AHashMap<String, String>);
 
 impl VBScriptObject for RequestQueryString {
     impl_vbscript_object!(RequestQueryString, "RequestQueryString");
-    fn get_property(
+    dispatch_by_symbol!(get);
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "COUNT" => Ok(VBValue::Number(self.0.len() as f64)),
-            _ => prop_not_found!("RequestQueryString", name),
+        match member {
+            sym::COUNT => Ok(VBValue::Number(self.0.len() as f64)),
+            _ => prop_not_found!("RequestQueryString", member),
         }
     }
     fn indexed_get(
@@ -74,7 +77,7 @@ impl VBScriptObject for RequestQueryString {
         Ok(VBValue::String(val.into()))
     }
     fn call_method(
-        &mut self,
+        &self,
         _name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -88,14 +91,15 @@ pub(crate) struct RequestForm(pub AHashMap<String, String>);
 
 impl VBScriptObject for RequestForm {
     impl_vbscript_object!(RequestForm, "RequestForm");
-    fn get_property(
+    dispatch_by_symbol!(get);
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "COUNT" => Ok(VBValue::Number(self.0.len() as f64)),
-            _ => prop_not_found!("RequestForm", name),
+        match member {
+            sym::COUNT => Ok(VBValue::Number(self.0.len() as f64)),
+            _ => prop_not_found!("RequestForm", member),
         }
     }
     fn indexed_get(
@@ -108,7 +112,7 @@ impl VBScriptObject for RequestForm {
         Ok(VBValue::String(val.into()))
     }
     fn call_method(
-        &mut self,
+        &self,
         _name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -122,17 +126,18 @@ pub(crate) struct RequestServerVariables(pub AHashMap<String, String>);
 
 impl VBScriptObject for RequestServerVariables {
     impl_vbscript_object!(RequestServerVariables, "RequestServerVariables");
-    fn get_property(
+    dispatch_by_symbol!(get);
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "COUNT" => Ok(VBValue::Number(self.0.len() as f64)),
+        match member {
+            sym::COUNT => Ok(VBValue::Number(self.0.len() as f64)),
             _ => {
                 let val = self
                     .0
-                    .get(&name.to_lowercase())
+                    .get(member.as_str())
                     .cloned()
                     .unwrap_or_default();
                 Ok(VBValue::String(val.into()))
@@ -149,7 +154,7 @@ impl VBScriptObject for RequestServerVariables {
         Ok(VBValue::String(val.into()))
     }
     fn call_method(
-        &mut self,
+        &self,
         _name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -163,15 +168,16 @@ pub(crate) struct RequestCookies(pub AHashMap<String, String>);
 
 impl VBScriptObject for RequestCookies {
     impl_vbscript_object!(RequestCookies, "RequestCookies");
-    fn get_property(
+    dispatch_by_symbol!(get);
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "COUNT" => Ok(VBValue::Number(self.0.len() as f64)),
+        match member {
+            sym::COUNT => Ok(VBValue::Number(self.0.len() as f64)),
             _ => {
-                let val = self.0.get(name).cloned().unwrap_or_default();
+                let val = self.0.get(member.as_str()).cloned().unwrap_or_default();
                 Ok(VBValue::String(val.into()))
             }
         }
@@ -186,7 +192,7 @@ impl VBScriptObject for RequestCookies {
         Ok(VBValue::String(val.into()))
     }
     fn call_method(
-        &mut self,
+        &self,
         _name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -1,67 +1,69 @@
 use super::super::execution_context::{CookieEntry, ExecutionContext};
+use super::super::symbol::{sym, Symbol};
 use super::super::value::VBValue;
 use super::super::value_utils;
-use super::super::vbobject::VBScriptObject;
+use super::super::vbobject::{ObjectRef, VBScriptObject};
 use super::super::vbs_error::VBSError;
-use crate::{impl_vbscript_object, prop_not_found, method_not_found, cannot_set_property};
+use crate::{impl_vbscript_object, dispatch_by_symbol, prop_not_found, method_not_found, cannot_set_property};
 
 #[derive(Debug, Clone)]
 pub struct ResponseObject;
 
 impl VBScriptObject for ResponseObject {
     impl_vbscript_object!(ResponseObject, "Response");
+    dispatch_by_symbol!(get, set, call);
 
-    fn get_property(
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "BUFFER" => Ok(VBValue::Boolean(true)),
-            "CONTENTTYPE" => Ok(VBValue::String("text/html".into())),
-            "STATUS" => Ok(VBValue::String(context.response.status.clone().into())),
-            "EXPIRES" => Ok(VBValue::Number(0.0)),
-            "COOKIES" => Ok(VBValue::Object(Box::new(ResponseCookies::new()))),
-            _ => prop_not_found!("Response", name),
+        match member {
+            sym::BUFFER => Ok(VBValue::Boolean(true)),
+            sym::CONTENTTYPE => Ok(VBValue::String("text/html".into())),
+            sym::STATUS => Ok(VBValue::String(context.response.status.clone().into())),
+            sym::EXPIRES => Ok(VBValue::Number(0.0)),
+            sym::COOKIES => Ok(VBValue::Object(ObjectRef::new(ResponseCookies::new()))),
+            _ => prop_not_found!("Response", member),
         }
     }
 
-    fn set_property(
-        &mut self,
-        name: &str,
+    fn set_property_sym(
+        &self,
+        member: Symbol,
         value: VBValue,
         context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
-        match name.to_uppercase().as_str() {
-            "CONTENTTYPE" => {
+        match member {
+            sym::CONTENTTYPE => {
                 context.response.extra_headers.push((
                     "Content-Type".to_string(),
                     value_utils::to_arg_string(&value),
                 ));
                 Ok(())
             }
-            "STATUS" => {
+            sym::STATUS => {
                 context.response.status = value_utils::to_arg_string(&value);
                 Ok(())
             }
-            "BUFFER" => {
+            sym::BUFFER => {
                 context.response.buffer = value_utils::to_arg_string(&value);
                 Ok(())
             }
-            "EXPIRES" => Ok(()),
-            _ => cannot_set_property!("Response", name),
+            sym::EXPIRES => Ok(()),
+            _ => cannot_set_property!("Response", member),
         }
     }
 
-    fn call_method(
-        &mut self,
-        name: &str,
+    fn call_method_sym(
+        &self,
+        member: Symbol,
         args: &[VBValue],
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "WRITE" => Ok(VBValue::Empty),
-            "REDIRECT" => {
+        match member {
+            sym::WRITE => Ok(VBValue::Empty),
+            sym::REDIRECT => {
                 if !args.is_empty() {
                     let url = value_utils::to_arg_string(&args[0]);
                     context.response.status = "302 Found".to_string();
@@ -74,20 +76,20 @@ impl VBScriptObject for ResponseObject {
                 }
                 Ok(VBValue::Empty)
             }
-            "END" => {
+            sym::END => {
                 context.response.ended = true;
                 Ok(VBValue::Empty)
             }
-            "CLEAR" => {
+            sym::CLEAR => {
                 context.response.buffer.clear();
                 Ok(VBValue::Empty)
             }
-            "FLUSH" => {
+            sym::FLUSH => {
                 context.response.flushed.push_str(&context.response.buffer);
                 context.response.buffer.clear();
                 Ok(VBValue::Empty)
             }
-            "ADDHEADER" => {
+            sym::ADDHEADER => {
                 if args.len() >= 2 {
                     let name = value_utils::to_arg_string(&args[0]);
                     let value = value_utils::to_arg_string(&args[1]);
@@ -95,12 +97,12 @@ impl VBScriptObject for ResponseObject {
                 }
                 Ok(VBValue::Empty)
             }
-            "BINARYWRITE" => {
+            sym::BINARYWRITE => {
                 if let Some(arg) = args.first() {
                     let bytes = match arg {
                         VBValue::Array(items, _dims) => {
                             items.iter().map(|v| match v {
-                                VBValue::Number(n) => *n as u8,
+                                n if n.is_number() => value_utils::to_arg_f64(n) as u8,
                                 VBValue::Boolean(b) => *b as u8,
                                 other => other.to_string().as_bytes().first().copied().unwrap_or(0),
                             }).collect()
@@ -108,11 +110,11 @@ impl VBScriptObject for ResponseObject {
                         VBValue::Null | VBValue::Empty => Vec::new(),
                         other => other.to_string().into_bytes(),
                     };
-                    context.response.write_binary(&bytes);
+                    context.write_binary(&bytes)?;
                 }
                 Ok(VBValue::Empty)
             }
-            _ => method_not_found!("Response", name),
+            _ => method_not_found!("Response", member),
         }
     }
 }
@@ -181,7 +183,7 @@ impl VBScriptObject for CookieObject {
         }
     }
     fn set_property(
-        &mut self,
+        &self,
         name: &str,
         value: VBValue,
         context: &mut ExecutionContext,
@@ -213,7 +215,7 @@ impl VBScriptObject for CookieObject {
         }
     }
     fn indexed_set(
-        &mut self,
+        &self,
         index: &VBValue,
         value: VBValue,
         context: &mut ExecutionContext,
@@ -225,7 +227,7 @@ impl VBScriptObject for CookieObject {
         Ok(())
     }
     fn call_method(
-        &mut self,
+        &self,
         _name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -255,10 +257,10 @@ impl VBScriptObject for ResponseCookies {
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         let name = value_utils::to_arg_string(index);
-        Ok(VBValue::Object(Box::new(CookieObject::new(name))))
+        Ok(VBValue::Object(ObjectRef::new(CookieObject::new(name))))
     }
     fn indexed_set(
-        &mut self,
+        &self,
         index: &VBValue,
         value: VBValue,
         context: &mut ExecutionContext,
@@ -270,7 +272,7 @@ impl VBScriptObject for ResponseCookies {
         Ok(())
     }
     fn call_method(
-        &mut self,
+        &self,
         _name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -1,48 +1,57 @@
 use super::super::execution_context::ExecutionContext;
+use super::super::symbol::{sym, Symbol};
 use super::super::value::VBValue;
 use super::super::value_utils;
-use super::super::vbobject::VBScriptObject;
-use super::super::vbs_error::{VBSError, VBSErrorType};
-use crate::{impl_vbscript_object, prop_not_found, method_not_found, cannot_set_property};
+use super::super::vbobject::{ObjectRef, VBScriptObject};
+use super::super::vbs_error::{codes, VBSError, VBSErrorType};
+use crate::{impl_vbscript_object, dispatch_by_symbol, prop_not_found, method_not_found, cannot_set_property};
 
 #[derive(Debug, Clone)]
 pub struct ServerObject;
 
 impl VBScriptObject for ServerObject {
     impl_vbscript_object!(ServerObject, "Server");
+    dispatch_by_symbol!(get, set, call);
 
-    fn get_property(
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "SCRIPTPATH" => Ok(VBValue::String(context.script_path.clone().into())),
-            "SCRIPTTIMEOUT" => Ok(VBValue::Number(90.0)),
-            _ => prop_not_found!("Server", name),
+        match member {
+            sym::SCRIPTPATH => Ok(VBValue::String(context.script_path.clone().into())),
+            sym::SCRIPTTIMEOUT => Ok(VBValue::Number(context.script_timeout().into())),
+            _ => prop_not_found!("Server", member),
         }
     }
 
-    fn set_property(
-        &mut self,
-        name: &str,
-        _value: VBValue,
-        _context: &mut ExecutionContext,
+    fn set_property_sym(
+        &self,
+        member: Symbol,
+        value: VBValue,
+        context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
-        match name.to_uppercase().as_str() {
-            "SCRIPTTIMEOUT" => Ok(()),
-            _ => cannot_set_property!("Server", name),
+        match member {
+            sym::SCRIPTTIMEOUT => {
+                let seconds = value_utils::to_arg_f64(&value).round();
+                if !(0.0..=u32::MAX as f64).contains(&seconds) {
+                    return Err(VBSError::runtime_with(codes::INVALID_PROCEDURE_CALL, "ScriptTimeout"));
+                }
+                context.set_script_timeout(seconds as u32);
+                Ok(())
+            }
+            _ => cannot_set_property!("Server", member),
         }
     }
 
-    fn call_method(
-        &mut self,
-        name: &str,
+    fn call_method_sym(
+        &self,
+        member: Symbol,
         args: &[VBValue],
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "EXECUTE" | "TRANSFER" => {
+        match member {
+            sym::EXECUTE | sym::TRANSFER => {
                 if !args.is_empty() {
                     let path = value_utils::to_arg_string(&args[0]);
                     let callback = context.execute_file_callback.take();
@@ -53,38 +62,19 @@ impl VBScriptObject for ServerObject {
                         })?;
                         context.execute_file_callback = Some(cb);
                     }
-                    if name.to_uppercase().as_str() == "TRANSFER" {
+                    if member == sym::TRANSFER {
                         context.response.ended = true;
                     }
                 }
                 Ok(VBValue::Empty)
             }
-            "CREATEOBJECT" => {
+            sym::CREATEOBJECT => {
                 if args.is_empty() {
-                    return Err(VBSErrorType::ValueError
-                        .into_error("Server.CreateObject requires 1 argument".to_string()));
-                }
-                let prog_id = value_utils::to_arg_string(&args[0]);
-                match prog_id.to_uppercase().as_str() {
-                    "SCRIPTING.DICTIONARY" => Ok(VBValue::Object(Box::new(
-                        super::super::vbobject::Dictionary::new(),
-                    ))),
-                    "SCRIPTING.FILESYSTEMOBJECT" => Ok(VBValue::Object(Box::new(
-                        super::super::fso::FileSystemObject::new(),
-                    ))),
-                    "VBSCRIPT.REGEXP" => Ok(VBValue::Object(Box::new(
-                        super::super::regexp::RegExpObject::new(),
-                    ))),
-                    "ADODB.CONNECTION" => {
-                        Ok(VBValue::Object(Box::new(super::super::adodb::Connection::new())))
-                    }
-                    _ => Err(VBSErrorType::NotImplementedError.into_error(format!(
-                        "Server.CreateObject('{}') is not implemented",
-                        prog_id
-                    ))),
+                    return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "CreateObject"));
                 }
+                create_object(&value_utils::to_arg_string(&args[0]))
             }
-            "MAPPATH" => {
+            sym::MAPPATH => {
                 let path = value_utils::to_arg_string(&args[0]);
                 let cwd = std::env::current_dir().unwrap_or_default();
                 let full_path = cwd.join(path.trim_start_matches('/').trim_start_matches('\\'));
@@ -92,7 +82,7 @@ impl VBScriptObject for ServerObject {
                     full_path.to_str().unwrap_or(&path).to_string().into(),
                 ))
             }
-            "HTMLENCODE" => {
+            sym::HTMLENCODE => {
                 let s = value_utils::to_arg_string(&args[0]);
                 let encoded = s
                     .replace("&", "&amp;")
@@ -102,7 +92,7 @@ impl VBScriptObject for ServerObject {
                     .replace("'", "&#39;");
                 Ok(VBValue::String(encoded.into()))
             }
-            "URLENCODE" => {
+            sym::URLENCODE => {
                 let s = value_utils::to_arg_string(&args[0]);
                 let encoded: String = s
                     .bytes()
@@ -116,7 +106,7 @@ impl VBScriptObject for ServerObject {
                     .collect();
                 Ok(VBValue::String(encoded.into()))
             }
-            "URLPATHENCODE" => {
+            sym::URLPATHENCODE => {
                 let s = value_utils::to_arg_string(&args[0]);
                 let encoded: String = s
                     .bytes()
@@ -131,7 +121,26 @@ impl VBScriptObject for ServerObject {
                     .collect();
                 Ok(VBValue::String(encoded.into()))
             }
-            _ => method_not_found!("Server", name),
+            _ => method_not_found!("Server", member),
+        }
+    }
+}
+
+/// Create the object registered under `prog_id`, as `Server.CreateObject` does.
+pub(crate) fn create_object(prog_id: &str) -> Result<VBValue, VBSError> {
+    match prog_id.to_uppercase().as_str() {
+        "SCRIPTING.DICTIONARY" => Ok(VBValue::Object(ObjectRef::new(
+            super::super::vbobject::Dictionary::new(),
+        ))),
+        "SCRIPTING.FILESYSTEMOBJECT" => Ok(VBValue::Object(ObjectRef::new(
+            super::super::fso::FileSystemObject::new(),
+        ))),
+        "VBSCRIPT.REGEXP" => Ok(VBValue::Object(ObjectRef::new(
+            super::super::regexp::RegExpObject::new(),
+        ))),
+        "ADODB.CONNECTION" => {
+            Ok(VBValue::Object(ObjectRef::new(super::super::adodb::Connection::new())))
         }
+        _ => Err(VBSError::runtime_with(codes::CANNOT_CREATE_OBJECT, prog_id)),
     }
 }
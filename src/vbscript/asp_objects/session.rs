@@ -1,9 +1,12 @@
 use super::super::execution_context::ExecutionContext;
+use super::application::StaticObjects;
+use super::super::store::{Store, DEFAULT_SESSION_TIMEOUT, MAX_SESSION_TIMEOUT};
+use super::super::symbol::{sym, Symbol};
 use super::super::value::VBValue;
 use super::super::value_utils;
-use super::super::vbobject::VBScriptObject;
-use super::super::vbs_error::{VBSError, VBSErrorType};
-use crate::{impl_vbscript_object, method_not_found, prop_not_found};
+use super::super::vbobject::{ObjectRef, VBScriptObject};
+use super::super::vbs_error::{codes, VBSError};
+use crate::{impl_vbscript_object, dispatch_by_symbol, method_not_found, prop_not_found};
 
 #[derive(Debug, Clone)]
 pub struct SessionObject {
@@ -13,77 +16,90 @@ pub struct SessionObject {
 
 impl VBScriptObject for SessionObject {
     impl_vbscript_object!(SessionObject, "Session");
+    dispatch_by_symbol!(get, set, call);
 
-    fn get_property(
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         if !self.session_enabled {
             return Ok(VBValue::Empty);
         }
-        match name.to_uppercase().as_str() {
-            "SESSIONID" => Ok(VBValue::String(context.session.id.clone().into())),
-            "TIMEOUT" => Ok(VBValue::Number(20.0)),
-            "CONTENTS" => Ok(VBValue::Object(Box::new(SessionContents::new(
+        match member {
+            sym::SESSIONID => Ok(VBValue::String(context.session.id.clone().into())),
+            sym::TIMEOUT => {
+                let minutes = match context.store {
+                    Some(ref store) => store.session_timeout(&context.session.id),
+                    None => DEFAULT_SESSION_TIMEOUT,
+                };
+                Ok(VBValue::Number(minutes.into()))
+            }
+            sym::LCID => Ok(VBValue::Long(context.request.lcid as i32)),
+            sym::STATICOBJECTS => Ok(VBValue::Object(ObjectRef::new(StaticObjects::new(Some(
+                context.session.id.clone(),
+            ))))),
+            sym::CONTENTS => Ok(VBValue::Object(ObjectRef::new(SessionContents::new(
                 context.session.id.clone(),
             )))),
-            _ => {
-                if let Some(ref store) = context.store {
-                    let sessions = store.lock_sessions();
-                    if let Some(data) = sessions.get(&context.session.id.to_uppercase()) {
-                        if let Some(val) = data.get(&name.to_uppercase()) {
-                            return Ok(val.clone());
-                        }
-                    }
-                }
-                Ok(VBValue::Empty)
-            }
+            _ => match context.store {
+                Some(ref store) => store.fetch_session_var(context.request_id, &context.session.id, member.as_str()),
+                None => Ok(VBValue::Empty),
+            },
         }
     }
 
-    fn set_property(
-        &mut self,
-        name: &str,
+    fn set_property_sym(
+        &self,
+        member: Symbol,
         value: VBValue,
         context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
         if !self.session_enabled {
             return Ok(());
         }
-        match name.to_uppercase().as_str() {
-            "TIMEOUT" => Ok(()),
-            _ => {
+        match member {
+            sym::TIMEOUT => {
+                let minutes = value_utils::to_arg_f64(&value).round();
+                if !(1.0..=MAX_SESSION_TIMEOUT as f64).contains(&minutes) {
+                    return Err(VBSError::runtime_with(codes::INVALID_PROCEDURE_CALL, "Timeout"));
+                }
                 if let Some(ref store) = context.store {
-                    let mut sessions = store.lock_sessions();
-                    sessions
-                        .entry(context.session.id.to_uppercase())
-                        .or_default()
-                        .insert(name.to_uppercase(), value);
+                    store.set_session_timeout(&context.session.id, minutes as u32);
                 }
                 Ok(())
             }
+            sym::LCID => {
+                let lcid = value_utils::to_arg_f64(&value) as u32;
+                context.request.lcid = lcid;
+                crate::vbscript::builtins::set_locale(lcid);
+                Ok(())
+            }
+            _ => match context.store {
+                Some(ref store) => store.set_session_var(&context.session.id, member.as_str(), value),
+                None => Ok(()),
+            },
         }
     }
 
-    fn call_method(
-        &mut self,
-        name: &str,
+    fn call_method_sym(
+        &self,
+        member: Symbol,
         _args: &[VBValue],
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         if !self.session_enabled {
             return Ok(VBValue::Empty);
         }
-        match name.to_uppercase().as_str() {
-            "ABANDON" => {
+        match member {
+            sym::ABANDON => {
                 if let Some(ref store) = context.store {
-                    let mut sessions = store.lock_sessions();
-                    sessions.remove(&self.session_id.to_uppercase());
+                    let contents = store.take_session(&self.session_id)?.unwrap_or_default();
+                    context.session.abandoned.get_or_insert(contents);
                 }
                 Ok(VBValue::Empty)
             }
-            _ => method_not_found!("Session", name),
+            _ => method_not_found!("Session", member),
         }
     }
 
@@ -96,20 +112,14 @@ impl VBScriptObject for SessionObject {
             return Ok(VBValue::Empty);
         }
         let key = value_utils::to_arg_string(index);
-        if let Some(ref store) = context.store {
-            let sessions = store.lock_sessions();
-            if let Some(data) = sessions.get(&self.session_id.to_uppercase()) {
-                return Ok(data
-                    .get(&key.to_uppercase())
-                    .cloned()
-                    .unwrap_or(VBValue::Empty));
-            }
+        match context.store {
+            Some(ref store) => store.fetch_session_var(context.request_id, &self.session_id, &key),
+            None => Ok(VBValue::Empty),
         }
-        Ok(VBValue::Empty)
     }
 
     fn indexed_set(
-        &mut self,
+        &self,
         index: &VBValue,
         value: VBValue,
         context: &mut ExecutionContext,
@@ -118,14 +128,10 @@ impl VBScriptObject for SessionObject {
             return Ok(());
         }
         let key = value_utils::to_arg_string(index);
-        if let Some(ref store) = context.store {
-            let mut sessions = store.lock_sessions();
-            sessions
-                .entry(self.session_id.to_uppercase())
-                .or_default()
-                .insert(key.to_uppercase(), value);
+        match context.store {
+            Some(ref store) => store.set_session_var(&self.session_id, &key, value),
+            None => Ok(()),
         }
-        Ok(())
     }
 }
 
@@ -138,6 +144,14 @@ impl SessionContents {
     pub fn new(session_id: String) -> Self {
         SessionContents { session_id }
     }
+
+    /// The session's variables, sorted by name so that `Key(i)` and
+    /// `Item(i)` agree.
+    fn contents(&self, store: &Store) -> Result<Vec<(String, VBValue)>, VBSError> {
+        let mut contents: Vec<(String, VBValue)> = store.session_vars(&self.session_id)?.into_iter().collect();
+        contents.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(contents)
+    }
 }
 
 impl VBScriptObject for SessionContents {
@@ -148,28 +162,15 @@ impl VBScriptObject for SessionContents {
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         match name.to_uppercase().as_str() {
-            "COUNT" => {
-                if let Some(ref store) = context.store {
-                    let sessions = store.lock_sessions();
-                    if let Some(data) = sessions.get(&self.session_id.to_uppercase()) {
-                        return Ok(VBValue::Number(data.len() as f64));
-                    }
-                }
-                Ok(VBValue::Number(0.0))
-            }
+            "COUNT" => match context.store {
+                Some(ref store) => Ok(VBValue::Number(store.session_vars(&self.session_id)?.len() as f64)),
+                None => Ok(VBValue::Number(0.0)),
+            },
             "KEY" | "ITEM" | "REMOVE" | "REMOVEALL" => prop_not_found!("SessionContents", name),
-            _ => {
-                if let Some(ref store) = context.store {
-                    let sessions = store.lock_sessions();
-                    if let Some(data) = sessions.get(&self.session_id.to_uppercase()) {
-                        return Ok(data
-                            .get(&name.to_uppercase())
-                            .cloned()
-                            .unwrap_or(VBValue::Empty));
-                    }
-                }
-                Ok(VBValue::Empty)
-            }
+            _ => match context.store {
+                Some(ref store) => store.fetch_session_var(context.request_id, &self.session_id, name),
+                None => Ok(VBValue::Empty),
+            },
         }
     }
     fn indexed_get(
@@ -178,35 +179,25 @@ impl VBScriptObject for SessionContents {
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         let key = value_utils::to_arg_string(index);
-        if let Some(ref store) = context.store {
-            let sessions = store.lock_sessions();
-            if let Some(data) = sessions.get(&self.session_id.to_uppercase()) {
-                return Ok(data
-                    .get(&key.to_uppercase())
-                    .cloned()
-                    .unwrap_or(VBValue::Empty));
-            }
+        match context.store {
+            Some(ref store) => store.fetch_session_var(context.request_id, &self.session_id, &key),
+            None => Ok(VBValue::Empty),
         }
-        Ok(VBValue::Empty)
     }
     fn indexed_set(
-        &mut self,
+        &self,
         index: &VBValue,
         value: VBValue,
         context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
         let key = value_utils::to_arg_string(index);
-        if let Some(ref store) = context.store {
-            let mut sessions = store.lock_sessions();
-            sessions
-                .entry(self.session_id.to_uppercase())
-                .or_default()
-                .insert(key.to_uppercase(), value);
+        match context.store {
+            Some(ref store) => store.set_session_var(&self.session_id, &key, value),
+            None => Ok(()),
         }
-        Ok(())
     }
     fn call_method(
-        &mut self,
+        &self,
         name: &str,
         args: &[VBValue],
         context: &mut ExecutionContext,
@@ -214,65 +205,37 @@ impl VBScriptObject for SessionContents {
         if let Some(ref store) = context.store {
             match name.to_uppercase().as_str() {
                 "KEY" => {
-                    let sessions = store.lock_sessions();
-                    if let Some(data) = sessions.get(&self.session_id.to_uppercase()) {
-                        if args.is_empty() {
-                            return Err(VBSErrorType::RuntimeError.into_error(
-                                "Session.Contents.Key requires 1 argument (index)".to_string(),
-                            ));
-                        }
-                        let index = value_utils::to_arg_f64(&args[0]) as usize;
-                        let keys: Vec<&String> = data.keys().collect();
-                        if index < 1 || index > keys.len() {
-                            return Err(VBSErrorType::RuntimeError.into_error(format!(
-                                "Key index out of range: {} (valid: 1-{})",
-                                index,
-                                keys.len()
-                            )));
-                        }
-                        Ok(VBValue::String(keys[index - 1].clone().into()))
-                    } else {
-                        Ok(VBValue::Empty)
+                    if args.is_empty() {
+                        return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Key"));
+                    }
+                    let index = value_utils::to_arg_f64(&args[0]) as usize;
+                    let mut contents = self.contents(store)?;
+                    if index < 1 || index > contents.len() {
+                        return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
                     }
+                    Ok(VBValue::String(contents.swap_remove(index - 1).0.into()))
                 }
                 "ITEM" => {
-                    let sessions = store.lock_sessions();
-                    if let Some(data) = sessions.get(&self.session_id.to_uppercase()) {
-                        if args.is_empty() {
-                            return Err(VBSErrorType::RuntimeError.into_error(
-                                "Session.Contents.Item requires 1 argument (index)".to_string(),
-                            ));
-                        }
-                        let index = value_utils::to_arg_f64(&args[0]) as usize;
-                        let values: Vec<VBValue> = data.values().cloned().collect();
-                        if index < 1 || index > values.len() {
-                            return Err(VBSErrorType::RuntimeError.into_error(format!(
-                                "Item index out of range: {} (valid: 1-{})",
-                                index,
-                                values.len()
-                            )));
-                        }
-                        Ok(values[index - 1].clone())
-                    } else {
-                        Ok(VBValue::Empty)
+                    if args.is_empty() {
+                        return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Item"));
                     }
+                    let index = value_utils::to_arg_f64(&args[0]) as usize;
+                    let mut contents = self.contents(store)?;
+                    if index < 1 || index > contents.len() {
+                        return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
+                    }
+                    Ok(contents.swap_remove(index - 1).1)
                 }
                 "REMOVE" => {
                     if args.is_empty() {
-                        return Err(VBSErrorType::RuntimeError.into_error(
-                            "Session.Contents.Remove requires 1 argument (key)".to_string(),
-                        ));
+                        return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Remove"));
                     }
                     let key = value_utils::to_arg_string(&args[0]);
-                    let mut sessions = store.lock_sessions();
-                    if let Some(data) = sessions.get_mut(&self.session_id.to_uppercase()) {
-                        data.remove(&key.to_uppercase());
-                    }
+                    store.remove_session_var(&self.session_id, &key)?;
                     Ok(VBValue::Empty)
                 }
                 "REMOVEALL" => {
-                    let mut sessions = store.lock_sessions();
-                    sessions.remove(&self.session_id.to_uppercase());
+                    store.clear_session_vars(&self.session_id)?;
                     Ok(VBValue::Empty)
                 }
                 _ => Ok(VBValue::Empty),
@@ -1,6 +1,6 @@
-use super::block_types::{BlockStatement, CaseClause, ElseIfBlock};
+use super::block_types::{BlockStatement, CaseClause, ElseIfBlock, Param};
 use crate::vbscript::compiler::Compiler;
-use crate::vbscript::expr::{parse_expression, BinOp, Expr};
+use crate::vbscript::expr::{parse_argument_expression, parse_expression, BinOp, Expr};
 use crate::vbscript::instruction::Instruction;
 use crate::vbscript::syntax::{
     ArrayAssignment, Assignment, Const, Dim, Erase, MethodCall, OnErrorGoto0, OnErrorResumeNext,
@@ -15,6 +15,46 @@ pub(crate) fn first_non_ws(tokens: &[Token]) -> Option<&Token> {
         .find(|t| t.token_type != TokenType::WhiteSpace)
 }
 
+/// Parse a parenthesised parameter list such as `(ByVal a, ByRef b, c())`.
+///
+/// `tokens` are the non-whitespace tokens following the procedure name; an
+/// empty list is returned when they do not start with `(`.  Parameters are
+/// `ByRef` unless marked `ByVal`.
+pub(crate) fn parse_param_list(tokens: &[&Token]) -> Vec<Param> {
+    let mut params = Vec::new();
+    if tokens.first().map(|t| t.token_type) != Some(TokenType::LeftParen) {
+        return params;
+    }
+    let mut by_val = false;
+    let mut name: Option<String> = None;
+    let mut depth = 0;
+    for tok in &tokens[1..] {
+        match tok.token_type {
+            TokenType::LeftParen => depth += 1,
+            TokenType::RightParen if depth == 0 => break,
+            TokenType::RightParen => depth -= 1,
+            TokenType::Comma if depth == 0 => {
+                if let Some(n) = name.take() {
+                    params.push(Param { name: n, by_val });
+                }
+                by_val = false;
+            }
+            TokenType::Identifier if name.is_none() => {
+                if tok.value.eq_ignore_ascii_case("byval") {
+                    by_val = true;
+                } else if !tok.value.eq_ignore_ascii_case("byref") {
+                    name = Some(tok.value.to_lowercase());
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(n) = name {
+        params.push(Param { name: n, by_val });
+    }
+    params
+}
+
 fn find_token(tokens: &[Token], target: TokenType) -> Option<usize> {
     tokens.iter().position(|t| t.token_type == target)
 }
@@ -289,7 +329,7 @@ fn parse_comma_args(tokens: &[Token]) -> Result<Vec<Expr>, VBSError> {
         return Ok(Vec::new());
     }
     if !tokens.iter().any(|t| t.token_type == TokenType::Comma) {
-        return Ok(vec![parse_expression(tokens)?]);
+        return Ok(vec![parse_argument_expression(tokens)?]);
     }
     let mut args = Vec::new();
     let mut start = 0;
@@ -297,14 +337,14 @@ fn parse_comma_args(tokens: &[Token]) -> Result<Vec<Expr>, VBSError> {
         if tok.token_type == TokenType::Comma {
             if i > start {
                 let arg_tokens: Vec<Token> = tokens[start..i].to_vec();
-                args.push(parse_expression(&arg_tokens)?);
+                args.push(parse_argument_expression(&arg_tokens)?);
             }
             start = i + 1;
         }
     }
     if start < tokens.len() {
         let arg_tokens: Vec<Token> = tokens[start..].to_vec();
-        args.push(parse_expression(&arg_tokens)?);
+        args.push(parse_argument_expression(&arg_tokens)?);
     }
     Ok(args)
 }
@@ -568,6 +608,7 @@ fn parse_expression_or_assignment(tokens: &[Token]) -> Result<Box<dyn VBSyntax>,
     if non_ws.len() >= 4
         && non_ws[0].token_type == TokenType::Identifier
         && non_ws[1].token_type == TokenType::LeftParen
+        && non_ws.iter().any(|t| t.token_type == TokenType::Assign)
     {
         let var_name = non_ws[0].value.to_string();
         let mut i = 0;
@@ -754,7 +795,7 @@ fn parse_line_into_syntax(tokens: &[Token]) -> Result<Box<dyn VBSyntax>, VBSErro
 
 fn parse_function_def(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatement, VBSError> {
     let line = &lines[*pos];
-    let line_num = *pos + 1;
+    let line_num = line_number(lines, *pos);
     *pos += 1;
 
     let no_ws: Vec<&Token> = line
@@ -769,16 +810,7 @@ fn parse_function_def(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStat
     let is_function = no_ws[0].token_type == TokenType::Function;
     let name = no_ws[1].value.to_lowercase();
 
-    let mut params = Vec::new();
-    if no_ws.len() > 2 && no_ws[2].token_type == TokenType::LeftParen {
-        let mut i = 3;
-        while i < no_ws.len() && no_ws[i].token_type != TokenType::RightParen {
-            if no_ws[i].token_type == TokenType::Identifier {
-                params.push(no_ws[i].value.to_lowercase());
-            }
-            i += 1;
-        }
-    }
+    let params = parse_param_list(&no_ws[2..]);
 
     let body_start = *pos;
 
@@ -876,7 +908,7 @@ fn parse_select_case_block(
     pos: &mut usize,
 ) -> Result<BlockStatement, VBSError> {
     let line = &lines[*pos];
-    let line_num = *pos + 1;
+    let line_num = line_number(lines, *pos);
     *pos += 1;
 
     let case_idx = find_keyword_or_type(line, "case", TokenType::Case)
@@ -984,7 +1016,7 @@ fn parse_select_case_block(
 
 fn parse_class_def(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatement, VBSError> {
     let line = &lines[*pos];
-    let line_num = *pos + 1;
+    let line_num = line_number(lines, *pos);
     *pos += 1;
 
     let name_idx = line
@@ -1057,7 +1089,9 @@ fn parse_class_def(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStateme
                     .iter()
                     .skip_while(|t| t.token_type == TokenType::WhiteSpace)
                     .skip(1)
-                    .find(|t| t.token_type != TokenType::WhiteSpace);
+                    .find(|t| {
+                        t.token_type != TokenType::WhiteSpace && !t.value.eq_ignore_ascii_case("default")
+                    });
                 if let Some(s) = second {
                     if s.token_type == TokenType::Property || s.token_type == TokenType::Class {
                         depth += 1;
@@ -1085,6 +1119,11 @@ pub fn parse_blocks(lines: &[Vec<Token>]) -> Result<Vec<BlockStatement>, VBSErro
     parse_blocks_inner(lines, &mut pos)
 }
 
+/// Source line of the statement at `pos`: the line its first token is on.
+fn line_number(lines: &[Vec<Token>], pos: usize) -> usize {
+    lines.get(pos).and_then(|line| line.first()).map_or(pos + 1, |t| t.line as usize)
+}
+
 fn parse_blocks_inner(
     lines: &[Vec<Token>],
     pos: &mut usize,
@@ -1094,7 +1133,7 @@ fn parse_blocks_inner(
     while *pos < lines.len() {
         let line = &lines[*pos];
         let first = first_non_ws(line);
-        let line_num = *pos + 1;
+        let line_num = line_number(lines, *pos);
 
         match first {
             Some(t) if t.token_type == TokenType::If => {
@@ -1141,6 +1180,10 @@ fn parse_blocks_inner(
             {
                 blocks.push(parse_exit_statement(lines, pos)?);
             }
+            Some(_) if is_option_explicit(line) => {
+                blocks.push(BlockStatement::OptionExplicit(line_num));
+                *pos += 1;
+            }
             _ => {
                 if line.iter().any(|t| {
                     t.token_type == TokenType::Comment
@@ -1172,7 +1215,7 @@ fn parse_expr_from_slice(tokens: &[Token], start: usize) -> Result<Expr, VBSErro
 
 fn parse_if_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatement, VBSError> {
     let line = &lines[*pos];
-    let line_num = *pos + 1;
+    let line_num = line_number(lines, *pos);
     *pos += 1;
 
     let then_idx = find_keyword_or_type(line, "then", TokenType::Then)
@@ -1247,7 +1290,7 @@ fn parse_if_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatemen
                     }
                 }
                 let line_text = tokens_to_string(next_line);
-                let line = *pos + 1;
+                let line = line_number(lines, *pos);
                 let syntax = parse_line_into_syntax(next_line)
                     .unwrap_or_else(|_| Box::new(create_error_syntax(line_text.clone())));
                 match &section {
@@ -1286,7 +1329,7 @@ fn parse_if_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatemen
                         Ok(s) => s,
                         Err(_) => Box::new(create_error_syntax(line_text)),
                     };
-                    let inline_body = vec![BlockStatement::Syntax(syntax, *pos)];
+                    let inline_body = vec![BlockStatement::Syntax(syntax, line_number(lines, *pos - 1))];
                     else_if_blocks.push(ElseIfBlock {
                         condition: elseif_cond,
                         body: inline_body,
@@ -1332,7 +1375,7 @@ fn parse_if_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatemen
 
 fn parse_for_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatement, VBSError> {
     let line = &lines[*pos];
-    let line_num = *pos + 1;
+    let line_num = line_number(lines, *pos);
     *pos += 1;
 
     let for_line_no_ws: Vec<&Token> = line
@@ -1452,7 +1495,7 @@ fn parse_for_each_block(
 
 fn parse_while_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatement, VBSError> {
     let line = &lines[*pos];
-    let line_num = *pos + 1;
+    let line_num = line_number(lines, *pos);
     *pos += 1;
 
     let condition = parse_expr_from_slice(line, 1)?;
@@ -1494,7 +1537,7 @@ fn parse_while_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockState
 
 fn parse_do_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatement, VBSError> {
     let line = &lines[*pos];
-    let line_num = *pos + 1;
+    let line_num = line_number(lines, *pos);
     *pos += 1;
 
     let do_line_no_ws: Vec<&Token> = line
@@ -1598,7 +1641,7 @@ fn parse_do_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatemen
 
 fn parse_with_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatement, VBSError> {
     let line = &lines[*pos];
-    let line_num = *pos + 1;
+    let line_num = line_number(lines, *pos);
     *pos += 1;
 
     let with_idx = line
@@ -1647,7 +1690,7 @@ fn parse_with_block(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatem
 
 fn parse_exit_statement(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockStatement, VBSError> {
     let line = &lines[*pos];
-    let line_num = *pos + 1;
+    let line_num = line_number(lines, *pos);
     *pos += 1;
 
     let no_ws: Vec<&Token> = line
@@ -1672,6 +1715,18 @@ fn parse_exit_statement(lines: &[Vec<Token>], pos: &mut usize) -> Result<BlockSt
     }
 }
 
+/// Whether `line` is an `Option Explicit` statement.
+fn is_option_explicit(line: &[Token]) -> bool {
+    let mut words = line
+        .iter()
+        .filter(|t| t.token_type != TokenType::WhiteSpace && t.token_type != TokenType::Comment);
+    matches!(
+        (words.next(), words.next(), words.next()),
+        (Some(a), Some(b), None)
+            if a.value.eq_ignore_ascii_case("option") && b.value.eq_ignore_ascii_case("explicit")
+    )
+}
+
 #[derive(Clone)]
 struct ErrorSyntax {
     message: String,
@@ -1798,11 +1853,10 @@ impl VBSyntax for CallStatement {
     }
 
     fn compile(&self, compiler: &mut Compiler) -> Result<(), VBSError> {
-        for arg in &self.args {
-            compiler.compile_expr(arg);
-        }
+        let call = compiler.compile_call_args(&self.args);
         let name_idx = compiler.add_constant(VBValue::String(self.name.to_lowercase().into()));
         compiler.emit(Instruction::Call(name_idx, self.args.len() as u8));
+        compiler.emit_byref_writebacks(call);
         Ok(())
     }
 
@@ -47,13 +47,13 @@ pub enum BlockStatement {
     FunctionDef {
         line: usize,
         name: String,
-        params: Vec<String>,
+        params: Vec<Param>,
         body_lines: Vec<Vec<Token>>,
     },
     SubDef {
         line: usize,
         name: String,
-        params: Vec<String>,
+        params: Vec<Param>,
         body_lines: Vec<Vec<Token>>,
     },
     SelectCase {
@@ -76,6 +76,8 @@ pub enum BlockStatement {
     ExitDo(usize),
     ExitFunction(usize),
     ExitSub(usize),
+    /// `Option Explicit`: every variable must be declared before use.
+    OptionExplicit(usize),
 }
 
 impl Clone for BlockStatement {
@@ -201,6 +203,7 @@ impl Clone for BlockStatement {
             BlockStatement::ExitDo(l) => BlockStatement::ExitDo(*l),
             BlockStatement::ExitFunction(l) => BlockStatement::ExitFunction(*l),
             BlockStatement::ExitSub(l) => BlockStatement::ExitSub(*l),
+            BlockStatement::OptionExplicit(l) => BlockStatement::OptionExplicit(*l),
         }
     }
 }
@@ -239,19 +242,32 @@ impl BlockStatement {
             BlockStatement::ExitDo(l) => *l,
             BlockStatement::ExitFunction(l) => *l,
             BlockStatement::ExitSub(l) => *l,
+            BlockStatement::OptionExplicit(l) => *l,
         }
     }
 }
 
+/// A declared parameter of a `Sub`, `Function` or class method.
+///
+/// VBScript passes arguments `ByRef` unless the parameter is marked `ByVal`;
+/// on return the final value of a `ByRef` parameter is written back to the
+/// caller's variable, array element or object property.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    /// Lowercased parameter name.
+    pub name: String,
+    pub by_val: bool,
+}
+
 /// A user-defined `Sub` or `Function` parsed from source.
 ///
 /// Function bodies are stored as raw token lines so they can be re-parsed
 /// into `BlockStatement`s on each call (VBScript allows redefinition).
-/// The cached parsed bodies are stored separately in `ExecutionContext::function_bodies`.
+/// The compiled code is cached separately in `ExecutionContext::function_code`.
 #[derive(Clone)]
 pub struct UserDefinedFunction {
     pub name: String,
-    pub params: Vec<String>,
+    pub params: Vec<Param>,
     pub body_lines: Vec<Vec<Token>>,
     pub is_function: bool,
 }
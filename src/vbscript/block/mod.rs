@@ -3,7 +3,8 @@
 mod block_types;
 mod block_parse;
 
-pub use block_types::{BlockStatement, CaseClause, ElseIfBlock, UserDefinedFunction};
+pub use block_types::{BlockStatement, CaseClause, ElseIfBlock, Param, UserDefinedFunction};
+pub(crate) use block_parse::parse_param_list;
 pub(crate) use block_parse::first_non_ws;
 pub use block_parse::parse_blocks;
 
@@ -1,25 +1,25 @@
 use super::expect_arg_count;
 use super::expect_min_args;
 use crate::vbscript::fso::FileSystemObject;
+use crate::vbscript::numeric::{self, NumKind};
 use crate::vbscript::value::VBValue;
 use crate::vbscript::value_utils;
-use crate::vbscript::vbobject::Dictionary;
-use crate::vbscript::vbs_error::{VBSError, VBSErrorType};
+use crate::vbscript::vbobject::{Dictionary, ObjectRef};
+use crate::vbscript::vbs_error::{codes, VBSError, VBSErrorType};
 
 pub(super) fn builtin_createobject(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "CreateObject")?;
     let prog_id = value_utils::to_arg_string(&args[0]);
     match prog_id.to_uppercase().as_str() {
-        "ADODB.CONNECTION" => Ok(VBValue::Object(Box::new(
+        "ADODB.CONNECTION" => Ok(VBValue::Object(ObjectRef::new(
             crate::vbscript::adodb::Connection::new(),
         ))),
-        "SCRIPTING.DICTIONARY" => Ok(VBValue::Object(Box::new(Dictionary::new()))),
-        "SCRIPTING.FILESYSTEMOBJECT" => Ok(VBValue::Object(Box::new(FileSystemObject::new()))),
-        "VBSCRIPT.REGEXP" => Ok(VBValue::Object(Box::new(
+        "SCRIPTING.DICTIONARY" => Ok(VBValue::Object(ObjectRef::new(Dictionary::new()))),
+        "SCRIPTING.FILESYSTEMOBJECT" => Ok(VBValue::Object(ObjectRef::new(FileSystemObject::new()))),
+        "VBSCRIPT.REGEXP" => Ok(VBValue::Object(ObjectRef::new(
             crate::vbscript::regexp::RegExpObject::new(),
         ))),
-        _ => Err(VBSErrorType::NotImplementedError
-            .into_error(format!("CreateObject('{}') is not implemented", prog_id))),
+        _ => Err(VBSError::runtime_with(codes::CANNOT_CREATE_OBJECT, prog_id)),
     }
 }
 
@@ -39,28 +39,29 @@ pub(super) fn builtin_fetchurl(args: &[VBValue]) -> Result<VBValue, VBSError> {
             .build()
             .map_err(|e| VBSErrorType::RuntimeError
                 .into_error(format!("FetchURL: failed to create client: {e}")))?;
-        let rt_handle = tokio::runtime::Handle::current();
-        let result = tokio::task::block_in_place(move || {
-            rt_handle.block_on(async {
-                match client.get(&url).send().await {
-                    Ok(resp) => match resp.text().await {
-                        Ok(body) => Ok(VBValue::String(body.into())),
-                        Err(e) => Err(VBSErrorType::RuntimeError
-                            .into_error(format!("FetchURL: failed to read response body: {e}"))),
-                    },
+        // Pages run outside the runtime's own threads (on the server's worker
+        // pool or a blocking thread that entered the runtime), so blocking
+        // on the request here only holds up this page.
+        let rt_handle = tokio::runtime::Handle::try_current().map_err(|_| {
+            VBSErrorType::RuntimeError.into_error("FetchURL: no async runtime to run the request on".to_string())
+        })?;
+        rt_handle.block_on(async {
+            match client.get(&url).send().await {
+                Ok(resp) => match resp.text().await {
+                    Ok(body) => Ok(VBValue::String(body.into())),
                     Err(e) => Err(VBSErrorType::RuntimeError
-                        .into_error(format!("FetchURL: request failed: {e}"))),
-                }
-            })
-        });
-        result
+                        .into_error(format!("FetchURL: failed to read response body: {e}"))),
+                },
+                Err(e) => Err(VBSErrorType::RuntimeError
+                    .into_error(format!("FetchURL: request failed: {e}"))),
+            }
+        })
     }
 }
 
 pub(super) fn builtin_cint(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "CInt")?;
-    let n = value_utils::to_arg_f64(&args[0]);
-    Ok(VBValue::Number(n.round()))
+    numeric::to_integer_kind(NumKind::Integer, numeric::to_number(&args[0])?)
 }
 
 pub(super) fn builtin_cstr(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -81,11 +82,9 @@ pub(super) fn builtin_isempty(args: &[VBValue]) -> Result<VBValue, VBSError> {
 pub(super) fn builtin_isnumeric(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "IsNumeric")?;
     let result = match &args[0] {
-        VBValue::Number(_) => true,
+        v if v.is_number() => true,
         VBValue::String(s) => s.parse::<f64>().is_ok() && !s.is_empty(),
-        VBValue::Boolean(_) => false,
-        VBValue::Null | VBValue::Empty => false,
-        VBValue::Array(..) | VBValue::Object(_) => false,
+        _ => false,
     };
     Ok(VBValue::Boolean(result))
 }
@@ -100,30 +99,26 @@ pub(super) fn builtin_ubound(args: &[VBValue]) -> Result<VBValue, VBSError> {
     let (items, dims) = match &args[0] {
         VBValue::Array(a, d) => (a, d),
         _ => {
-            return Err(VBSErrorType::ValueError
-                .into_error("UBound requires an array".to_string()))
+            return Err(VBSError::runtime_with(codes::TYPE_MISMATCH, "UBound"))
         }
     };
     if items.is_empty() {
-        return Err(
-            VBSErrorType::RuntimeError.into_error("Subscript out of range".to_string())
-        );
+        return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
     }
     if args.len() >= 2 {
         let dim = value_utils::to_arg_f64(&args[1]) as usize;
         if dim < 1 || dim > dims.len() {
-            return Err(VBSErrorType::RuntimeError
-                .into_error("Subscript out of range".to_string()));
+            return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
         }
         if dims.is_empty() {
-            return Ok(VBValue::Number((items.len() - 1) as f64));
+            return Ok(VBValue::Long((items.len() - 1) as i32));
         }
-        return Ok(VBValue::Number(dims[dim - 1] as f64));
+        return Ok(VBValue::Long(dims[dim - 1] as i32));
     }
     if dims.is_empty() {
-        return Ok(VBValue::Number((items.len() - 1) as f64));
+        return Ok(VBValue::Long((items.len() - 1) as i32));
     }
-    Ok(VBValue::Number(dims[0] as f64))
+    Ok(VBValue::Long(dims[0] as i32))
 }
 
 pub(super) fn builtin_lbound(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -131,23 +126,19 @@ pub(super) fn builtin_lbound(args: &[VBValue]) -> Result<VBValue, VBSError> {
     let (items, dims) = match &args[0] {
         VBValue::Array(a, d) => (a, d),
         _ => {
-            return Err(VBSErrorType::ValueError
-                .into_error("LBound requires an array".to_string()))
+            return Err(VBSError::runtime_with(codes::TYPE_MISMATCH, "LBound"))
         }
     };
     if items.is_empty() {
-        return Err(
-            VBSErrorType::RuntimeError.into_error("Subscript out of range".to_string())
-        );
+        return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
     }
     if args.len() >= 2 {
         let dim = value_utils::to_arg_f64(&args[1]) as usize;
         if dim < 1 || dim > dims.len() {
-            return Err(VBSErrorType::RuntimeError
-                .into_error("Subscript out of range".to_string()));
+            return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
         }
     }
-    Ok(VBValue::Number(0.0))
+    Ok(VBValue::Long(0))
 }
 
 pub(super) fn builtin_filter(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -155,8 +146,7 @@ pub(super) fn builtin_filter(args: &[VBValue]) -> Result<VBValue, VBSError> {
     let arr = match &args[0] {
         VBValue::Array(a, _) => a,
         _ => {
-            return Err(VBSErrorType::ValueError
-                .into_error("Filter requires an array".to_string()))
+            return Err(VBSError::runtime_with(codes::TYPE_MISMATCH, "Filter"))
         }
     };
     let match_str = value_utils::to_arg_string(&args[1]);
@@ -197,79 +187,82 @@ pub(super) fn builtin_cbool(args: &[VBValue]) -> Result<VBValue, VBSError> {
 
 pub(super) fn builtin_cbyte(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "CByte")?;
-    let n = value_utils::to_arg_f64(&args[0]).round();
-    if !(0.0..=255.0).contains(&n) {
-        return Err(VBSErrorType::RuntimeError.into_error("Overflow".to_string()));
-    }
-    Ok(VBValue::Number(n))
+    numeric::to_integer_kind(NumKind::Byte, numeric::to_number(&args[0])?)
 }
 
 pub(super) fn builtin_cdate(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "CDate")?;
     match &args[0] {
-        VBValue::Number(n) => Ok(VBValue::Number(*n)),
+        VBValue::Date(d) => Ok(VBValue::Date(*d)),
+        v if v.is_number() => Ok(VBValue::Date(value_utils::to_arg_f64(v))),
         VBValue::String(s) => {
             let dt = super::datetime::try_parse_date(s).ok_or_else(|| {
-                VBSErrorType::RuntimeError.into_error("Invalid date string".to_string())
+                VBSError::runtime_with(codes::TYPE_MISMATCH, "CDate")
             })?;
-            Ok(VBValue::Number(super::datetime::datetime_to_ole_auto(dt)))
+            Ok(VBValue::Date(super::datetime::datetime_to_ole_auto(dt)))
         }
         v => {
             let s = value_utils::to_arg_string(v);
             let dt = super::datetime::try_parse_date(&s).ok_or_else(|| {
-                VBSErrorType::RuntimeError.into_error("Invalid date string".to_string())
+                VBSError::runtime_with(codes::TYPE_MISMATCH, "CDate")
             })?;
-            Ok(VBValue::Number(super::datetime::datetime_to_ole_auto(dt)))
+            Ok(VBValue::Date(super::datetime::datetime_to_ole_auto(dt)))
         }
     }
 }
 
 pub(super) fn builtin_cdbl(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "CDbl")?;
-    Ok(VBValue::Number(value_utils::to_arg_f64(&args[0])))
+    Ok(VBValue::Number(numeric::to_number(&args[0])?))
 }
 
 pub(super) fn builtin_clng(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "CLng")?;
-    let n = value_utils::to_arg_f64(&args[0]);
-    Ok(VBValue::Number(n.trunc()))
+    numeric::to_integer_kind(NumKind::Long, numeric::to_number(&args[0])?)
 }
 
 pub(super) fn builtin_csng(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "CSng")?;
-    Ok(VBValue::Number(value_utils::to_arg_f64(&args[0])))
+    let n = numeric::to_number(&args[0])?;
+    if n.is_finite() && n.abs() > f32::MAX as f64 {
+        return Err(VBSError::runtime(codes::OVERFLOW));
+    }
+    Ok(VBValue::Single(n as f32))
 }
 
 pub(super) fn builtin_ccur(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "CCur")?;
-    let n = value_utils::to_arg_f64(&args[0]);
-    Ok(VBValue::Number((n * 10000.0).round() / 10000.0))
+    let c = match &args[0] {
+        VBValue::Currency(c) => *c,
+        v => numeric::f64_to_currency(numeric::to_number(v)?)?,
+    };
+    Ok(VBValue::Currency(c))
 }
 
 pub(super) fn builtin_hex(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Hex")?;
-    let n = value_utils::to_arg_f64(&args[0]);
-    if n == 0.0 {
-        return Ok(VBValue::String("0".into()));
-    }
-    let n = n as i64;
-    Ok(VBValue::String(format!("{:X}", n).into()))
+    Ok(VBValue::String(format!("{:X}", twos_complement(&args[0])).into()))
 }
 
 pub(super) fn builtin_oct(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Oct")?;
-    let n = value_utils::to_arg_f64(&args[0]);
-    if n == 0.0 {
-        return Ok(VBValue::String("0".into()));
+    Ok(VBValue::String(format!("{:o}", twos_complement(&args[0])).into()))
+}
+
+/// The bit pattern `Hex` and `Oct` print: negative Integers use 16 bits,
+/// anything else 32.
+fn twos_complement(v: &VBValue) -> u32 {
+    match v {
+        VBValue::Integer(i) => *i as u16 as u32,
+        VBValue::Byte(b) => *b as u32,
+        v => numeric::round_half_even(value_utils::to_arg_f64(v)) as i64 as u32,
     }
-    let n = n as i64;
-    Ok(VBValue::String(format!("{:o}", n).into()))
 }
 
 pub(super) fn builtin_isdate(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "IsDate")?;
     match &args[0] {
-        VBValue::Number(_) => Ok(VBValue::Boolean(true)),
+        VBValue::Date(_) => Ok(VBValue::Boolean(true)),
         VBValue::String(s) => Ok(VBValue::Boolean(super::datetime::try_parse_date(s).is_some())),
         _ => Ok(VBValue::Boolean(false)),
     }
@@ -277,7 +270,7 @@ pub(super) fn builtin_isdate(args: &[VBValue]) -> Result<VBValue, VBSError> {
 
 pub(super) fn builtin_isobject(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "IsObject")?;
-    Ok(VBValue::Boolean(matches!(args[0], VBValue::Object(_))))
+    Ok(VBValue::Boolean(args[0].is_object()))
 }
 
 pub(super) fn builtin_typename(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -289,20 +282,15 @@ pub(super) fn builtin_typename(args: &[VBValue]) -> Result<VBValue, VBSError> {
         VBValue::Empty => "Empty",
         VBValue::Array(..) => "Array",
         VBValue::Object(obj) => obj.type_name(),
-        VBValue::Number(n) => {
-            if n.fract() == 0.0 {
-                let n = *n as i64;
-                if (-32768..=32767).contains(&n) {
-                    "Integer"
-                } else if (-2147483648..=2147483647).contains(&n) {
-                    "Long"
-                } else {
-                    "Double"
-                }
-            } else {
-                "Double"
-            }
-        }
+        VBValue::Nothing => "Nothing",
+        VBValue::Number(_) => "Double",
+        VBValue::Integer(_) => "Integer",
+        VBValue::Long(_) => "Long",
+        VBValue::Byte(_) => "Byte",
+        VBValue::Single(_) => "Single",
+        VBValue::Currency(_) => "Currency",
+        VBValue::Date(_) => "Date",
+        VBValue::Decimal(_) => "Decimal",
     };
     Ok(VBValue::String(name.to_string().into()))
 }
@@ -312,24 +300,18 @@ pub(super) fn builtin_vartype(args: &[VBValue]) -> Result<VBValue, VBSError> {
     let vt = match &args[0] {
         VBValue::Empty => 0,
         VBValue::Null => 1,
-        VBValue::Number(n) => {
-            if n.fract() == 0.0 {
-                let n = *n as i64;
-                if (-32768..=32767).contains(&n) {
-                    2
-                } else if (-2147483648..=2147483647).contains(&n) {
-                    3
-                } else {
-                    5
-                }
-            } else {
-                5
-            }
-        }
+        VBValue::Integer(_) => 2,
+        VBValue::Long(_) => 3,
+        VBValue::Single(_) => 4,
+        VBValue::Number(_) => 5,
+        VBValue::Currency(_) => 6,
+        VBValue::Date(_) => 7,
+        VBValue::Decimal(_) => 14,
+        VBValue::Byte(_) => 17,
         VBValue::String(_) => 8,
         VBValue::Boolean(_) => 11,
-        VBValue::Object(_) => 9,
+        VBValue::Object(_) | VBValue::Nothing => 9,
         VBValue::Array(..) => 8204,
     };
-    Ok(VBValue::Number(vt as f64))
+    Ok(VBValue::Integer(vt))
 }
@@ -2,8 +2,53 @@ use super::expect_arg_count;
 use super::expect_min_args;
 use crate::vbscript::value::VBValue;
 use crate::vbscript::value_utils;
-use crate::vbscript::vbs_error::{VBSError, VBSErrorType};
+use crate::vbscript::vbs_error::{codes, VBSError};
 use chrono::{Datelike, Timelike};
+use std::cell::Cell;
+
+thread_local! {
+    /// LCID used to display and parse dates on this thread, set from the
+    /// page's `@LCID` directive or `Session.LCID` before a script runs.
+    static LOCALE: Cell<u32> = const { Cell::new(1033) };
+}
+
+/// Set the LCID used for date display and parsing, returning the previous one.
+pub(crate) fn set_locale(lcid: u32) -> u32 {
+    LOCALE.with(|l| l.replace(lcid))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DateOrder {
+    MonthDayYear,
+    DayMonthYear,
+    YearMonthDay,
+}
+
+/// Short date and long time patterns of a Windows locale.
+struct DateLocale {
+    order: DateOrder,
+    separator: char,
+    /// Two-digit day and month (`dd/MM/yyyy` rather than `d/M/yyyy`).
+    padded: bool,
+    /// `h:mm:ss AM` rather than `HH:mm:ss`.
+    hour12: bool,
+}
+
+fn date_locale() -> DateLocale {
+    let (order, separator, padded, hour12) = match LOCALE.with(Cell::get) {
+        // en-GB, fr-FR, it-IT, es-ES, pt-BR
+        2057 | 1036 | 1040 | 1034 | 3082 | 1046 => (DateOrder::DayMonthYear, '/', true, false),
+        // de-DE, de-CH, ru-RU
+        1031 | 2055 | 1049 => (DateOrder::DayMonthYear, '.', true, false),
+        // nl-NL
+        1043 => (DateOrder::DayMonthYear, '-', false, false),
+        // ja-JP, zh-CN
+        1041 | 2052 => (DateOrder::YearMonthDay, '/', true, false),
+        // en-US and anything not listed
+        _ => (DateOrder::MonthDayYear, '/', false, true),
+    };
+    DateLocale { order, separator, padded, hour12 }
+}
 
 pub(crate) fn ole_auto_to_datetime(serial: f64) -> Option<chrono::NaiveDateTime> {
     if serial.is_nan() || serial.is_infinite() {
@@ -29,6 +74,40 @@ pub(crate) fn datetime_to_ole_auto(dt: chrono::NaiveDateTime) -> f64 {
     days + seconds / 86400.0
 }
 
+/// Display form of a `Date` value in the current locale's short date and
+/// long time format, with the time omitted at midnight and the date omitted
+/// for a pure time value.
+pub(crate) fn format_date(serial: f64) -> String {
+    let Some(dt) = ole_auto_to_datetime(serial) else {
+        return serial.to_string();
+    };
+    let locale = date_locale();
+    let (month, day) = if locale.padded {
+        (format!("{:02}", dt.month()), format!("{:02}", dt.day()))
+    } else {
+        (dt.month().to_string(), dt.day().to_string())
+    };
+    let sep = locale.separator;
+    let date = match locale.order {
+        DateOrder::MonthDayYear => format!("{}{sep}{}{sep}{}", month, day, dt.year()),
+        DateOrder::DayMonthYear => format!("{}{sep}{}{sep}{}", day, month, dt.year()),
+        DateOrder::YearMonthDay => format!("{}{sep}{}{sep}{}", dt.year(), month, day),
+    };
+    let time = if locale.hour12 {
+        let ampm = if dt.hour() < 12 { "AM" } else { "PM" };
+        format!("{}:{:02}:{:02} {}", dt.hour12().1, dt.minute(), dt.second(), ampm)
+    } else {
+        format!("{:02}:{:02}:{:02}", dt.hour(), dt.minute(), dt.second())
+    };
+    if serial.trunc() == 0.0 {
+        time
+    } else if dt.num_seconds_from_midnight() == 0 {
+        date
+    } else {
+        format!("{} {}", date, time)
+    }
+}
+
 fn days_in_month(year: i32, month: u32) -> u32 {
     match month {
         1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
@@ -59,7 +138,8 @@ fn add_months(dt: chrono::NaiveDateTime, months: i32) -> chrono::NaiveDateTime {
 
 pub(crate) fn value_to_datetime(val: &VBValue) -> Option<chrono::NaiveDateTime> {
     match val {
-        VBValue::Number(n) => ole_auto_to_datetime(*n),
+        VBValue::Date(n) => ole_auto_to_datetime(*n),
+        n if n.is_number() => ole_auto_to_datetime(value_utils::to_arg_f64(n)),
         VBValue::String(s) => try_parse_date(s),
         _ => {
             let s = value_utils::to_arg_string(val);
@@ -68,14 +148,48 @@ pub(crate) fn value_to_datetime(val: &VBValue) -> Option<chrono::NaiveDateTime>
     }
 }
 
+/// Operands of a comparison between a `Date` and a string holding a date,
+/// both as OLE serials.  `None` unless exactly that pair is compared.
+pub(crate) fn date_comparison_operands(l: &VBValue, r: &VBValue) -> Option<(f64, f64)> {
+    match (l, r) {
+        (VBValue::Date(d), VBValue::String(s)) => Some((*d, datetime_to_ole_auto(try_parse_date(s)?))),
+        (VBValue::String(s), VBValue::Date(d)) => Some((datetime_to_ole_auto(try_parse_date(s)?), *d)),
+        _ => None,
+    }
+}
+
+/// Parse a date string in the current locale, so `4/7/2024` is the 7th of
+/// April for en-US and the 4th of July for it-IT.
 pub(crate) fn try_parse_date(s: &str) -> Option<chrono::NaiveDateTime> {
+    let locale = date_locale();
+    let day_first = locale.order == DateOrder::DayMonthYear;
+    let normalized;
+    let s = if locale.separator != '/' && locale.separator != '-' {
+        normalized = s.replace(locale.separator, "/");
+        &normalized
+    } else {
+        s
+    };
+    parse_date(s, day_first)
+}
+
+/// Parse the contents of a `#...#` date literal, which is always read in
+/// US month/day order whatever the locale.
+pub(crate) fn parse_date_literal(s: &str) -> Option<chrono::NaiveDateTime> {
+    parse_date(s, false)
+}
+
+fn parse_date(s: &str, day_first: bool) -> Option<chrono::NaiveDateTime> {
     let s = s.trim();
+    let (first, second) = if day_first { ("%d/%m/%Y", "%m/%d/%Y") } else { ("%m/%d/%Y", "%d/%m/%Y") };
     let datetime_formats = [
         "%Y-%m-%d %H:%M:%S",
         "%Y/%m/%d %H:%M:%S",
-        "%m/%d/%Y %H:%M:%S",
+        &format!("{} %H:%M:%S", first),
         "%Y-%m-%d %I:%M:%S %p",
-        "%m/%d/%Y %I:%M:%S %p",
+        &format!("{} %I:%M:%S %p", first),
+        &format!("{} %H:%M", first),
+        &format!("{} %I:%M %p", first),
     ];
     for fmt in &datetime_formats {
         if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(s, fmt) {
@@ -85,10 +199,10 @@ pub(crate) fn try_parse_date(s: &str) -> Option<chrono::NaiveDateTime> {
     let date_formats = [
         "%Y-%m-%d",
         "%Y/%m/%d",
-        "%m/%d/%Y",
+        first,
         "%m-%d-%Y",
         "%d-%m-%Y",
-        "%d/%m/%Y",
+        second,
         "%B %d, %Y",
         "%b %d, %Y",
         "%d %B %Y",
@@ -117,65 +231,65 @@ pub(crate) fn try_parse_date(s: &str) -> Option<chrono::NaiveDateTime> {
 
 pub(super) fn builtin_now(_args: &[VBValue]) -> Result<VBValue, VBSError> {
     let now = chrono::Local::now().naive_local();
-    Ok(VBValue::Number(datetime_to_ole_auto(now)))
+    Ok(VBValue::Date(datetime_to_ole_auto(now)))
 }
 
 pub(super) fn builtin_date(_args: &[VBValue]) -> Result<VBValue, VBSError> {
     let now = chrono::Local::now().naive_local();
-    Ok(VBValue::String(now.format("%m/%d/%Y").to_string().into()))
+    Ok(VBValue::Date(datetime_to_ole_auto(now).trunc()))
 }
 
 pub(super) fn builtin_time(_args: &[VBValue]) -> Result<VBValue, VBSError> {
     let now = chrono::Local::now().naive_local();
-    Ok(VBValue::String(now.format("%H:%M:%S").to_string().into()))
+    Ok(VBValue::Date(datetime_to_ole_auto(now).fract()))
 }
 
 pub(super) fn builtin_year(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Year")?;
     let dt = value_to_datetime(&args[0])
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
-    Ok(VBValue::Number(dt.year() as f64))
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
+    Ok(VBValue::Integer(dt.year() as i16))
 }
 
 pub(super) fn builtin_month(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Month")?;
     let dt = value_to_datetime(&args[0])
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
-    Ok(VBValue::Number(dt.month() as f64))
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
+    Ok(VBValue::Integer(dt.month() as i16))
 }
 
 pub(super) fn builtin_day(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Day")?;
     let dt = value_to_datetime(&args[0])
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
-    Ok(VBValue::Number(dt.day() as f64))
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
+    Ok(VBValue::Integer(dt.day() as i16))
 }
 
 pub(super) fn builtin_hour(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Hour")?;
     let dt = value_to_datetime(&args[0])
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid time".to_string()))?;
-    Ok(VBValue::Number(dt.hour() as f64))
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
+    Ok(VBValue::Integer(dt.hour() as i16))
 }
 
 pub(super) fn builtin_minute(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Minute")?;
     let dt = value_to_datetime(&args[0])
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid time".to_string()))?;
-    Ok(VBValue::Number(dt.minute() as f64))
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
+    Ok(VBValue::Integer(dt.minute() as i16))
 }
 
 pub(super) fn builtin_second(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Second")?;
     let dt = value_to_datetime(&args[0])
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid time".to_string()))?;
-    Ok(VBValue::Number(dt.second() as f64))
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
+    Ok(VBValue::Integer(dt.second() as i16))
 }
 
 pub(super) fn builtin_weekday(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_min_args(args, 1, "Weekday")?;
     let dt = value_to_datetime(&args[0])
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
     let firstday = if args.len() >= 2 {
         value_utils::to_arg_f64(&args[1]) as u32
     } else {
@@ -183,7 +297,7 @@ pub(super) fn builtin_weekday(args: &[VBValue]) -> Result<VBValue, VBSError> {
     };
     let vbs_weekday = dt.weekday().num_days_from_sunday() + 1;
     let result = ((vbs_weekday + 7 - firstday) % 7) + 1;
-    Ok(VBValue::Number(result as f64))
+    Ok(VBValue::Integer(result as i16))
 }
 
 pub(super) fn builtin_weekdayname(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -200,7 +314,7 @@ pub(super) fn builtin_weekdayname(args: &[VBValue]) -> Result<VBValue, VBSError>
         1
     };
     if !(1..=7).contains(&weekday) {
-        return Err(VBSErrorType::ValueError.into_error("Invalid weekday".to_string()));
+        return Err(VBSError::runtime(codes::INVALID_PROCEDURE_CALL));
     }
     let idx = ((weekday - 1) + (firstday - 1)) % 7;
     let names = [
@@ -229,7 +343,7 @@ pub(super) fn builtin_monthname(args: &[VBValue]) -> Result<VBValue, VBSError> {
         false
     };
     if !(1..=12).contains(&month) {
-        return Err(VBSErrorType::ValueError.into_error("Invalid month".to_string()));
+        return Err(VBSError::runtime(codes::INVALID_PROCEDURE_CALL));
     }
     let names = [
         "January",
@@ -259,9 +373,8 @@ pub(super) fn builtin_dateadd(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 3, "DateAdd")?;
     let interval = value_utils::to_arg_string(&args[0]).to_lowercase();
     let number = value_utils::to_arg_f64(&args[1]);
-    let date = value_utils::to_arg_f64(&args[2]);
-    let dt = ole_auto_to_datetime(date)
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
+    let dt = value_to_datetime(&args[2])
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
     let result = match interval.as_str() {
         "yyyy" => add_months(dt, (number as i32) * 12),
         "q" => add_months(dt, (number as i32) * 3),
@@ -269,46 +382,44 @@ pub(super) fn builtin_dateadd(args: &[VBValue]) -> Result<VBValue, VBSError> {
         "y" | "d" | "w" => {
             let days = number as i64;
             dt.checked_add_signed(chrono::Duration::days(days))
-                .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Date overflow".to_string()))?
+                .ok_or_else(|| VBSError::runtime(codes::INVALID_PROCEDURE_CALL))?
         }
         "ww" => {
             let days = (number * 7.0) as i64;
             dt.checked_add_signed(chrono::Duration::days(days))
-                .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Date overflow".to_string()))?
+                .ok_or_else(|| VBSError::runtime(codes::INVALID_PROCEDURE_CALL))?
         }
         "h" => {
             let hours = number as i64;
             dt.checked_add_signed(chrono::Duration::hours(hours))
-                .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Date overflow".to_string()))?
+                .ok_or_else(|| VBSError::runtime(codes::INVALID_PROCEDURE_CALL))?
         }
         "n" => {
             let mins = number as i64;
             dt.checked_add_signed(chrono::Duration::minutes(mins))
-                .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Date overflow".to_string()))?
+                .ok_or_else(|| VBSError::runtime(codes::INVALID_PROCEDURE_CALL))?
         }
         "s" => {
             let secs = number as i64;
             dt.checked_add_signed(chrono::Duration::seconds(secs))
-                .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Date overflow".to_string()))?
+                .ok_or_else(|| VBSError::runtime(codes::INVALID_PROCEDURE_CALL))?
         }
         _ => {
             return Err(
-                VBSErrorType::RuntimeError.into_error(format!("Invalid interval '{}'", interval))
+                VBSError::runtime(codes::INVALID_PROCEDURE_CALL)
             )
         }
     };
-    Ok(VBValue::Number(datetime_to_ole_auto(result)))
+    Ok(VBValue::Date(datetime_to_ole_auto(result)))
 }
 
 pub(super) fn builtin_datediff(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_min_args(args, 3, "DateDiff")?;
     let interval = value_utils::to_arg_string(&args[0]).to_lowercase();
-    let date1 = value_utils::to_arg_f64(&args[1]);
-    let date2 = value_utils::to_arg_f64(&args[2]);
-    let dt1 = ole_auto_to_datetime(date1)
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
-    let dt2 = ole_auto_to_datetime(date2)
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
+    let dt1 = value_to_datetime(&args[1])
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
+    let dt2 = value_to_datetime(&args[2])
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
     let result = match interval.as_str() {
         "yyyy" => (dt2.year() - dt1.year()) as f64,
         "q" => {
@@ -351,7 +462,7 @@ pub(super) fn builtin_datediff(args: &[VBValue]) -> Result<VBValue, VBSError> {
         "s" => dt2.signed_duration_since(dt1).num_seconds() as f64,
         _ => {
             return Err(
-                VBSErrorType::RuntimeError.into_error(format!("Invalid interval '{}'", interval))
+                VBSError::runtime(codes::INVALID_PROCEDURE_CALL)
             )
         }
     };
@@ -362,7 +473,7 @@ pub(super) fn builtin_datepart(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_min_args(args, 2, "DatePart")?;
     let interval = value_utils::to_arg_string(&args[0]).to_lowercase();
     let dt = value_to_datetime(&args[1])
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
     let result = match interval.as_str() {
         "yyyy" => dt.year() as f64,
         "q" => ((dt.month() - 1) / 3 + 1) as f64,
@@ -387,7 +498,7 @@ pub(super) fn builtin_datepart(args: &[VBValue]) -> Result<VBValue, VBSError> {
         "s" => dt.second() as f64,
         _ => {
             return Err(
-                VBSErrorType::RuntimeError.into_error(format!("Invalid interval '{}'", interval))
+                VBSError::runtime(codes::INVALID_PROCEDURE_CALL)
             )
         }
     };
@@ -403,28 +514,27 @@ pub(super) fn builtin_dateserial(args: &[VBValue]) -> Result<VBValue, VBSError>
     let y = year as i64 + m.div_euclid(12) as i64;
     let mo = m.rem_euclid(12) + 1;
     let date = chrono::NaiveDate::from_ymd_opt(y as i32, mo as u32, 1)
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
     let date = date
         .checked_add_signed(chrono::Duration::days((day - 1) as i64))
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Date overflow".to_string()))?;
+        .ok_or_else(|| VBSError::runtime(codes::INVALID_PROCEDURE_CALL))?;
     let dt = chrono::NaiveDateTime::new(
         date,
         chrono::NaiveTime::from_num_seconds_from_midnight_opt(0, 0).unwrap(),
     );
-    Ok(VBValue::Number(datetime_to_ole_auto(dt)))
+    Ok(VBValue::Date(datetime_to_ole_auto(dt)))
 }
 
 pub(super) fn builtin_datevalue(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "DateValue")?;
-    let s = value_utils::to_arg_string(&args[0]);
-    let dt = try_parse_date(&s)
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date string".to_string()))?;
+    let dt = value_to_datetime(&args[0])
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
     let date = chrono::NaiveDate::from_ymd_opt(dt.year(), dt.month(), dt.day()).unwrap();
     let result = datetime_to_ole_auto(chrono::NaiveDateTime::new(
         date,
         chrono::NaiveTime::from_num_seconds_from_midnight_opt(0, 0).unwrap(),
     ));
-    Ok(VBValue::Number(result))
+    Ok(VBValue::Date(result))
 }
 
 pub(super) fn builtin_timeserial(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -437,7 +547,7 @@ pub(super) fn builtin_timeserial(args: &[VBValue]) -> Result<VBValue, VBSError>
     let time = chrono::NaiveTime::from_num_seconds_from_midnight_opt(s as u32, 0).unwrap();
     let dt =
         chrono::NaiveDateTime::new(chrono::NaiveDate::from_ymd_opt(1899, 12, 30).unwrap(), time);
-    Ok(VBValue::Number(datetime_to_ole_auto(dt)))
+    Ok(VBValue::Date(datetime_to_ole_auto(dt)))
 }
 
 pub(super) fn builtin_timevalue(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -458,10 +568,10 @@ pub(super) fn builtin_timevalue(args: &[VBValue]) -> Result<VBValue, VBSError> {
                 })
                 .ok_or(std::io::Error::other(""))
         })
-        .map_err(|_| VBSErrorType::RuntimeError.into_error("Invalid time string".to_string()))?;
+        .map_err(|_| VBSError::runtime(codes::TYPE_MISMATCH))?;
     let dt =
         chrono::NaiveDateTime::new(chrono::NaiveDate::from_ymd_opt(1899, 12, 30).unwrap(), time);
-    Ok(VBValue::Number(datetime_to_ole_auto(dt)))
+    Ok(VBValue::Date(datetime_to_ole_auto(dt)))
 }
 
 pub(super) fn builtin_timer(_args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -473,14 +583,13 @@ pub(super) fn builtin_timer(_args: &[VBValue]) -> Result<VBValue, VBSError> {
 
 pub(super) fn builtin_formatdatetime(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_min_args(args, 1, "FormatDateTime")?;
-    let n = value_utils::to_arg_f64(&args[0]);
     let namedformat = if args.len() >= 2 {
         value_utils::to_arg_f64(&args[1]) as i32
     } else {
         0
     };
-    let dt = ole_auto_to_datetime(n)
-        .ok_or_else(|| VBSErrorType::RuntimeError.into_error("Invalid date".to_string()))?;
+    let dt = value_to_datetime(&args[0])
+        .ok_or_else(|| VBSError::runtime(codes::TYPE_MISMATCH))?;
     let s = match namedformat {
         0 => format!("{} {}", dt.format("%m/%d/%Y"), dt.format("%H:%M:%S")),
         1 => dt.format("%A, %B %-d, %Y").to_string(),
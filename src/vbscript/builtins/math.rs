@@ -1,8 +1,9 @@
 use super::expect_arg_count;
 use super::expect_min_args;
+use crate::vbscript::numeric::{self, NumKind};
 use crate::vbscript::value::VBValue;
 use crate::vbscript::value_utils;
-use crate::vbscript::vbs_error::{VBSError, VBSErrorType};
+use crate::vbscript::vbs_error::{codes, VBSError};
 use std::sync::Mutex;
 
 static RNG_STATE: Mutex<u32> = Mutex::new(0u32);
@@ -15,8 +16,11 @@ fn rnd_next(state: &mut u32) -> f64 {
 
 pub(super) fn builtin_abs(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Abs")?;
-    let n = value_utils::to_arg_f64(&args[0]);
-    Ok(VBValue::Number(n.abs()))
+    if value_utils::to_arg_f64(&args[0]) < 0.0 {
+        numeric::negate(&args[0])
+    } else {
+        Ok(whole_or_number(&args[0], value_utils::to_arg_f64(&args[0])))
+    }
 }
 
 pub(super) fn builtin_rnd(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -58,13 +62,27 @@ pub(super) fn builtin_randomize(args: &[VBValue]) -> Result<VBValue, VBSError> {
 pub(super) fn builtin_int(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Int")?;
     let n = value_utils::to_arg_f64(&args[0]);
-    Ok(VBValue::Number(n.floor()))
+    Ok(whole_or_number(&args[0], n.floor()))
 }
 
 pub(super) fn builtin_fix(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Fix")?;
     let n = value_utils::to_arg_f64(&args[0]);
-    Ok(VBValue::Number(n.trunc()))
+    Ok(whole_or_number(&args[0], n.trunc()))
+}
+
+/// `n` in the subtype of `arg`, for functions such as `Int` and `Abs` that
+/// keep the numeric subtype of their argument.
+fn whole_or_number(arg: &VBValue, n: f64) -> VBValue {
+    match arg {
+        VBValue::Byte(_) => numeric::from_f64(NumKind::Byte, n),
+        VBValue::Integer(_) => numeric::from_f64(NumKind::Integer, n),
+        VBValue::Long(_) => numeric::from_f64(NumKind::Long, n),
+        VBValue::Single(_) => VBValue::Single(n as f32),
+        VBValue::Currency(_) => numeric::from_f64(NumKind::Currency, n),
+        VBValue::Decimal(_) => numeric::from_f64(NumKind::Decimal, n),
+        _ => VBValue::Number(n),
+    }
 }
 
 pub(super) fn builtin_round(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -83,11 +101,11 @@ pub(super) fn builtin_sgn(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Sgn")?;
     let n = value_utils::to_arg_f64(&args[0]);
     if n > 0.0 {
-        Ok(VBValue::Number(1.0))
+        Ok(VBValue::Integer(1))
     } else if n < 0.0 {
-        Ok(VBValue::Number(-1.0))
+        Ok(VBValue::Integer(-1))
     } else {
-        Ok(VBValue::Number(0.0))
+        Ok(VBValue::Integer(0))
     }
 }
 
@@ -95,8 +113,7 @@ pub(super) fn builtin_sqr(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Sqr")?;
     let n = value_utils::to_arg_f64(&args[0]);
     if n < 0.0 {
-        return Err(VBSErrorType::RuntimeError
-            .into_error("Cannot calculate square root of a negative number".to_string()));
+        return Err(VBSError::runtime_with(codes::INVALID_PROCEDURE_CALL, "Sqr"));
     }
     Ok(VBValue::Number(n.sqrt()))
 }
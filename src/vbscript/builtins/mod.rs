@@ -7,18 +7,29 @@ mod math;
 mod conv_misc;
 
 #[cfg_attr(not(test), allow(unused_imports))]
-pub(crate) use datetime::{datetime_to_ole_auto, ole_auto_to_datetime, try_parse_date};
+pub(crate) use datetime::{
+    date_comparison_operands, datetime_to_ole_auto, format_date, ole_auto_to_datetime, parse_date_literal, set_locale,
+};
+pub(crate) use string::{check_string_length, limit_string_length};
 
 macro_rules! builtins {
     ($name:ident, $args:ident, $($entry:literal => $func:ident),* $(,)?) => {
         match $name.to_uppercase().as_str() {
             $($entry => $func(&$args),)*
-            _ => Err(crate::vbscript::vbs_error::VBSErrorType::NotImplementedError
-                .into_error(format!("Function '{}' is not implemented", $name))),
+            _ => Err(crate::vbscript::vbs_error::VBSError::runtime_with(
+                crate::vbscript::vbs_error::codes::TYPE_MISMATCH,
+                $name,
+            )),
         }
     };
 }
 
+/// Whether `name` is a built-in that scripts may use without parentheses,
+/// as in `Response.Write Now`.
+pub(crate) fn is_bare_builtin(name: &str) -> bool {
+    matches!(name.to_uppercase().as_str(), "NOW" | "DATE" | "TIME" | "TIMER" | "RND")
+}
+
 /// Dispatch a built-in VBScript function call by name.
 pub fn call_builtin(name: &str, args: Vec<VBValue>) -> Result<VBValue, VBSError> {
     use self::conv_misc::*;
@@ -114,24 +125,18 @@ pub fn call_builtin(name: &str, args: Vec<VBValue>) -> Result<VBValue, VBSError>
 }
 
 use crate::vbscript::value::VBValue;
-use crate::vbscript::vbs_error::{VBSError, VBSErrorType};
+use crate::vbscript::vbs_error::{codes, VBSError};
 
 pub(crate) fn expect_arg_count(args: &[VBValue], expected: usize, name: &str) -> Result<(), VBSError> {
     if args.len() != expected {
-        return Err(VBSErrorType::ValueError.into_error(format!(
-            "{} requires {} argument(s), got {}",
-            name, expected, args.len()
-        )));
+        return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, name));
     }
     Ok(())
 }
 
 pub(crate) fn expect_min_args(args: &[VBValue], min: usize, name: &str) -> Result<(), VBSError> {
     if args.len() < min {
-        return Err(VBSErrorType::ValueError.into_error(format!(
-            "{} requires at least {} argument(s), got {}",
-            name, min, args.len()
-        )));
+        return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, name));
     }
     Ok(())
 }
@@ -1,18 +1,55 @@
 use super::expect_arg_count;
 use super::expect_min_args;
+use crate::vbscript::numeric::{self, NumKind};
 use crate::vbscript::value::VBValue;
 use crate::vbscript::value_utils;
-use crate::vbscript::vbs_error::{VBSError, VBSErrorType};
+use crate::vbscript::vbs_error::{codes, VBSError};
+use std::cell::Cell;
+
+thread_local! {
+    /// Longest string, in bytes, that the built-ins and concatenation may
+    /// build on this thread (0 for no limit), set from the request's
+    /// `ResourceLimits` before a script runs.
+    static MAX_STRING_LENGTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Limit the strings scripts may build on this thread to `max` bytes
+/// until the returned guard is dropped, which restores the previous limit
+/// so that it does not carry over to the next script the thread runs.
+pub(crate) fn limit_string_length(max: usize) -> StringLimit {
+    StringLimit { previous: MAX_STRING_LENGTH.with(|m| m.replace(max)) }
+}
+
+/// Restores the string limit that was in force before
+/// [`limit_string_length`].
+pub(crate) struct StringLimit {
+    previous: usize,
+}
+
+impl Drop for StringLimit {
+    fn drop(&mut self) {
+        MAX_STRING_LENGTH.with(|m| m.set(self.previous));
+    }
+}
+
+/// Fail with "Out of memory" for a string of `len` bytes past the limit.
+pub(crate) fn check_string_length(len: usize) -> Result<(), VBSError> {
+    let max = MAX_STRING_LENGTH.with(Cell::get);
+    if max != 0 && len > max {
+        return Err(VBSError::runtime(codes::OUT_OF_MEMORY));
+    }
+    Ok(())
+}
 
 pub(super) fn builtin_len(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Len")?;
     let s = match &args[0] {
-        VBValue::String(s) => s.len() as f64,
+        VBValue::String(s) => s.len(),
         VBValue::Null => return Ok(VBValue::Null),
-        VBValue::Empty => return Ok(VBValue::Number(0.0)),
-        v => v.to_string().len() as f64,
+        VBValue::Empty => return Ok(VBValue::Long(0)),
+        v => v.to_string().len(),
     };
-    Ok(VBValue::Number(s))
+    Ok(VBValue::Long(s as i32))
 }
 
 pub(super) fn builtin_ucase(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -96,19 +133,16 @@ pub(super) fn builtin_instr(args: &[VBValue]) -> Result<VBValue, VBSError> {
             value_utils::to_arg_string(&args[1]),
         )
     } else {
-        return Err(VBSErrorType::ValueError.into_error(format!(
-            "InStr requires 2 or 3 arguments, got {}",
-            args.len()
-        )));
+        return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "InStr"));
     };
 
     if start < 1 || start > s1.len() {
-        return Ok(VBValue::Number(0.0));
+        return Ok(VBValue::Long(0));
     }
     let search_from = start - 1;
     match s1[search_from..].find(&s2) {
-        Some(pos) => Ok(VBValue::Number((search_from + pos + 1) as f64)),
-        None => Ok(VBValue::Number(0.0)),
+        Some(pos) => Ok(VBValue::Long((search_from + pos + 1) as i32)),
+        None => Ok(VBValue::Long(0)),
     }
 }
 
@@ -166,8 +200,7 @@ pub(super) fn builtin_join(args: &[VBValue]) -> Result<VBValue, VBSError> {
     let arr = match &args[0] {
         VBValue::Array(a, _) => a,
         _ => {
-            return Err(VBSErrorType::ValueError
-                .into_error("Join requires an array as first argument".to_string()))
+            return Err(VBSError::runtime_with(codes::TYPE_MISMATCH, "Join"))
         }
     };
     let strings: Vec<String> = arr.iter().map(value_utils::to_arg_string).collect();
@@ -228,12 +261,10 @@ pub(super) fn builtin_asc(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Asc")?;
     let s = value_utils::to_arg_string(&args[0]);
     if s.is_empty() {
-        return Err(
-            VBSErrorType::ValueError.into_error("Asc requires a non-empty string".to_string())
-        );
+        return Err(VBSError::runtime_with(codes::INVALID_PROCEDURE_CALL, "Asc"));
     }
     let code = s.chars().next().unwrap() as u32;
-    Ok(VBValue::Number(code as f64))
+    Ok(numeric::int_value(NumKind::Integer, code as i64))
 }
 
 pub(super) fn builtin_chr(args: &[VBValue]) -> Result<VBValue, VBSError> {
@@ -242,7 +273,7 @@ pub(super) fn builtin_chr(args: &[VBValue]) -> Result<VBValue, VBSError> {
     match char::from_u32(code) {
         Some(c) => Ok(VBValue::String(c.to_string().into())),
         None => {
-            Err(VBSErrorType::ValueError.into_error(format!("Invalid character code: {}", code)))
+            Err(VBSError::runtime_with(codes::INVALID_PROCEDURE_CALL, "Chr"))
         }
     }
 }
@@ -262,6 +293,7 @@ pub(super) fn builtin_rtrim(args: &[VBValue]) -> Result<VBValue, VBSError> {
 pub(super) fn builtin_space(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 1, "Space")?;
     let count = value_utils::to_arg_f64(&args[0]) as usize;
+    check_string_length(count)?;
     Ok(VBValue::String(" ".repeat(count).into()))
 }
 
@@ -269,13 +301,14 @@ pub(super) fn builtin_string(args: &[VBValue]) -> Result<VBValue, VBSError> {
     expect_arg_count(args, 2, "String")?;
     let count = value_utils::to_arg_f64(&args[0]) as usize;
     let ch = match &args[1] {
-        VBValue::Number(n) => {
-            let code = *n as u32;
+        n if n.is_number() => {
+            let code = value_utils::to_arg_f64(n) as u32;
             char::from_u32(code).unwrap_or(' ')
         }
         VBValue::String(s) => s.chars().next().unwrap_or(' '),
         _ => ' ',
     };
+    check_string_length(count.saturating_mul(ch.len_utf8()))?;
     Ok(VBValue::String(ch.to_string().repeat(count).into()))
 }
 
@@ -296,13 +329,13 @@ pub(super) fn builtin_instrrev(args: &[VBValue]) -> Result<VBValue, VBSError> {
     };
 
     if s2.is_empty() {
-        return Ok(VBValue::Number(start as f64));
+        return Ok(VBValue::Long(start as i32));
     }
     let end = start.min(s1.len());
     let search_in = &s1[..end];
     match search_in.rfind(&s2) {
-        Some(pos) => Ok(VBValue::Number((pos + 1) as f64)),
-        None => Ok(VBValue::Number(0.0)),
+        Some(pos) => Ok(VBValue::Long((pos + 1) as i32)),
+        None => Ok(VBValue::Long(0)),
     }
 }
 
@@ -320,7 +353,7 @@ pub(super) fn builtin_strcomp(args: &[VBValue]) -> Result<VBValue, VBSError> {
     } else {
         s1.cmp(&s2) as i32
     };
-    Ok(VBValue::Number(result as f64))
+    Ok(VBValue::Integer(result as i16))
 }
 
 fn format_number_internal(num: f64, numdigits: usize) -> String {
@@ -349,8 +382,7 @@ fn format_number_internal(num: f64, numdigits: usize) -> String {
 
 pub(super) fn builtin_formatnumber(args: &[VBValue]) -> Result<VBValue, VBSError> {
     if args.is_empty() {
-        return Err(VBSErrorType::ValueError
-            .into_error("FormatNumber requires at least 1 argument".to_string()));
+        return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "FormatNumber"));
     }
     let num = value_utils::to_arg_f64(&args[0]);
     let numdigits = if args.len() >= 2 {
@@ -379,8 +411,7 @@ pub(super) fn builtin_formatnumber(args: &[VBValue]) -> Result<VBValue, VBSError
 
 pub(super) fn builtin_formatcurrency(args: &[VBValue]) -> Result<VBValue, VBSError> {
     if args.is_empty() {
-        return Err(VBSErrorType::ValueError
-            .into_error("FormatCurrency requires at least 1 argument".to_string()));
+        return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "FormatCurrency"));
     }
     let num = value_utils::to_arg_f64(&args[0]);
     let numdigits = if args.len() >= 2 {
@@ -415,8 +446,7 @@ pub(super) fn builtin_formatcurrency(args: &[VBValue]) -> Result<VBValue, VBSErr
 
 pub(super) fn builtin_formatpercent(args: &[VBValue]) -> Result<VBValue, VBSError> {
     if args.is_empty() {
-        return Err(VBSErrorType::ValueError
-            .into_error("FormatPercent requires at least 1 argument".to_string()));
+        return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "FormatPercent"));
     }
     let num = value_utils::to_arg_f64(&args[0]) * 100.0;
     let numdigits = if args.len() >= 2 {
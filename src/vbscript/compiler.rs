@@ -1,12 +1,16 @@
-use crate::vbscript::block::first_non_ws;
+use crate::vbscript::block::{first_non_ws, parse_param_list};
 use crate::vbscript::block::BlockStatement;
 use crate::vbscript::block::UserDefinedFunction;
-use crate::vbscript::execution_context::{ClassDefinition, MethodDef, PropertyDef};
+use crate::vbscript::block::Param;
+use crate::vbscript::execution_context::{ClassDefinition, ClassMember, ClassProperty, MethodDef, PropertyDef};
 use crate::vbscript::expr::Expr;
-use crate::vbscript::instruction::Instruction;
+use crate::vbscript::instruction::{ByRefGuard, Instruction, VarSlot};
+use crate::vbscript::optimizer;
+use crate::vbscript::symbol::Symbol;
 use crate::vbscript::vbs_error::{VBSError, VBSErrorType};
 use crate::vbscript::{ExecutionContext, Token, TokenType, VBValue};
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct CompiledCode {
@@ -16,6 +20,45 @@ pub struct CompiledCode {
     pub local_names: Vec<String>,
     pub function_defs: Vec<UserDefinedFunction>,
     pub compiled_functions: Vec<(String, CompiledCode)>,
+    /// Classes defined by this code, registered before it runs.
+    pub classes: Vec<Arc<ClassDefinition>>,
+    /// Global variables referenced by this code and its procedures.
+    pub globals: Arc<GlobalNames>,
+}
+
+/// Global variables referenced by a compiled script, shared by its page
+/// code and every procedure compiled with it.  Instructions refer to them
+/// by index; the VM binds each name to a slot of the running context.
+#[derive(Debug, Default)]
+pub struct GlobalNames {
+    /// Lowercased variable names, by index.
+    pub names: Vec<String>,
+    /// Indices declared at page level by `Dim` or `ReDim`, which exist
+    /// from the start of the run.
+    pub declared: Vec<u32>,
+}
+
+/// Bytecode of a procedure body, shared by every call instead of being
+/// copied into each frame.
+#[derive(Clone)]
+pub struct ProcedureCode {
+    pub instructions: Arc<Vec<Instruction>>,
+    pub constants: Arc<Vec<VBValue>>,
+    pub local_count: usize,
+    pub local_names: Arc<Vec<String>>,
+    pub globals: Arc<GlobalNames>,
+}
+
+impl From<CompiledCode> for ProcedureCode {
+    fn from(code: CompiledCode) -> Self {
+        ProcedureCode {
+            instructions: Arc::new(code.instructions),
+            constants: Arc::new(code.constants),
+            local_count: code.local_count,
+            local_names: Arc::new(code.local_names),
+            globals: code.globals,
+        }
+    }
 }
 
 pub struct Compiler<'a> {
@@ -27,6 +70,24 @@ pub struct Compiler<'a> {
     function_defs: Vec<UserDefinedFunction>,
     loop_stack: Vec<LoopInfo>,
     compiled_functions: ahash::AHashMap<String, CompiledCode>,
+    classes: Vec<ClassDefinition>,
+    /// Global variables referenced so far, shared by all procedures.
+    globals: GlobalNames,
+    global_index: AHashMap<String, u32>,
+    /// Globals declared at page level, as in `GlobalNames::declared`.
+    page_variables: AHashSet<u32>,
+    /// Whether a procedure body is being compiled; page-level variables
+    /// are globals.
+    in_procedure: bool,
+    /// Whether `Option Explicit` is in effect.
+    option_explicit: bool,
+    /// Whether to run the optimizer on the compiled code.
+    optimize: bool,
+    /// Names declared in each enclosing scope (page, class, procedure);
+    /// only tracked under `Option Explicit`.
+    scopes: Vec<AHashSet<String>>,
+    /// Script line of the statement being compiled.
+    line: usize,
 }
 
 #[allow(dead_code)]
@@ -38,6 +99,25 @@ struct LoopInfo {
 
 struct Patch(usize);
 
+/// Arguments compiled by [`Compiler::compile_call_args`], as they are
+/// written back after the call.
+pub(crate) struct CallArgs {
+    args: Vec<Expr>,
+    /// Locals holding evaluated parts of `args`, released after the
+    /// write-back.
+    temps: Vec<String>,
+}
+
+/// Whether a `ByRef` parameter can bind to `expr`: a variable, an array
+/// element or a property.
+fn is_reference(expr: &Expr) -> bool {
+    match expr {
+        Expr::Variable(_) | Expr::PropertyAccess { .. } | Expr::WithObject => true,
+        Expr::FunctionCall { args, .. } => !args.is_empty(),
+        _ => false,
+    }
+}
+
 impl<'a> Compiler<'a> {
     pub fn new(context: &'a mut ExecutionContext) -> Self {
         Compiler {
@@ -45,28 +125,72 @@ impl<'a> Compiler<'a> {
             constants: Vec::new(),
             locals: ahash::AHashMap::new(),
             local_count: 0,
-            context,
             loop_stack: Vec::new(),
             compiled_functions: ahash::AHashMap::new(),
             function_defs: Vec::new(),
+            classes: Vec::new(),
+            globals: GlobalNames::default(),
+            global_index: AHashMap::new(),
+            page_variables: AHashSet::new(),
+            in_procedure: false,
+            option_explicit: context.option_explicit,
+            optimize: context.optimize,
+            scopes: Vec::new(),
+            line: 0,
+            context,
         }
     }
 
     pub fn compile(&mut self, blocks: &[BlockStatement]) -> Result<CompiledCode, VBSError> {
+        if blocks.iter().any(|b| matches!(b, BlockStatement::OptionExplicit(_))) {
+            self.option_explicit = true;
+            self.context.option_explicit = true;
+        }
+        if self.option_explicit {
+            let mut names = AHashSet::new();
+            collect_declarations(blocks, &mut names);
+            self.scopes.push(names);
+        }
         self.compile_blocks(blocks)?;
+        if self.optimize {
+            optimizer::optimize(&mut self.code, &mut self.constants, &self.globals.names);
+        }
         let mut local_names = vec![String::new(); self.local_count];
         for (name, slot) in &self.locals {
             if *slot < local_names.len() {
                 local_names[*slot] = name.clone();
             }
         }
+
+        // Procedures share the finished table of the code they belong to
+        let globals = Arc::new(std::mem::take(&mut self.globals));
+        let compiled_functions = self
+            .compiled_functions
+            .drain()
+            .map(|(name, mut code)| {
+                code.globals = Arc::clone(&globals);
+                (name, code)
+            })
+            .collect();
+        let classes = self
+            .classes
+            .drain(..)
+            .map(|mut class| {
+                for member in class.members_mut() {
+                    member.code.globals = Arc::clone(&globals);
+                }
+                Arc::new(class)
+            })
+            .collect();
         Ok(CompiledCode {
             instructions: std::mem::take(&mut self.code),
             constants: std::mem::take(&mut self.constants),
             local_count: self.local_count,
             local_names,
             function_defs: std::mem::take(&mut self.function_defs),
-            compiled_functions: self.compiled_functions.drain().collect(),
+            compiled_functions,
+            classes,
+            globals,
         })
     }
 
@@ -105,6 +229,9 @@ impl<'a> Compiler<'a> {
             Instruction::ForPrep(_, o) | Instruction::ForEachPrep(_, o) => {
                 *o = offset;
             }
+            Instruction::ByRefSkip(_, _, o) => {
+                *o = offset;
+            }
             _ => unreachable!(),
         }
     }
@@ -130,6 +257,80 @@ impl<'a> Compiler<'a> {
         self.locals.get(&name.to_lowercase()).copied()
     }
 
+    fn is_declared(&self, name: &str) -> bool {
+        self.locals.contains_key(name) || self.scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    /// Index of the global variable `name` (lowercased) in this script's
+    /// [`GlobalNames`].
+    pub(crate) fn global_slot(&mut self, name: &str) -> u32 {
+        if let Some(&slot) = self.global_index.get(name) {
+            return slot;
+        }
+        let slot = self.globals.names.len() as u32;
+        self.globals.names.push(name.to_string());
+        self.global_index.insert(name.to_string(), slot);
+        slot
+    }
+
+    /// Resolve a reference to the global variable `name` (lowercased).
+    ///
+    /// Under `Option Explicit`, a name not declared in any enclosing scope
+    /// gets a runtime `CheckDeclared` instead of a compile error: intrinsic
+    /// objects, class fields and `ExecuteGlobal` code may still define it.
+    pub(crate) fn global_variable(&mut self, name: String) -> u32 {
+        if self.option_explicit && !self.is_declared(&name) {
+            let idx = self.add_constant(VBValue::String(name.clone().into()));
+            self.emit(Instruction::CheckDeclared(idx, self.line as u32));
+        }
+        self.global_slot(&name)
+    }
+
+    /// Declare the variable `name`: a local inside a procedure, a global
+    /// that exists from the start of the run at page level.
+    pub(crate) fn declare_variable(&mut self, name: &str) -> VarSlot {
+        if self.in_procedure {
+            return VarSlot::Local(self.allocate_local(name));
+        }
+        let slot = self.global_slot(&name.to_lowercase());
+        if self.page_variables.insert(slot) {
+            self.globals.declared.push(slot);
+        }
+        VarSlot::Global(slot)
+    }
+
+    /// Whether `name` (lowercased) is a variable declared at page level.
+    pub(crate) fn is_page_variable(&self, name: &str) -> bool {
+        !self.in_procedure
+            && self
+                .global_index
+                .get(name)
+                .is_some_and(|slot| self.page_variables.contains(slot))
+    }
+
+    /// Emit a store of the top of the stack into `var`.
+    pub(crate) fn emit_store(&mut self, var: VarSlot) {
+        match var {
+            VarSlot::Local(slot) => self.emit(Instruction::StoreLocal(slot)),
+            VarSlot::Global(slot) => self.emit(Instruction::StoreGlobal(slot)),
+        }
+    }
+
+    /// Resolve the variable of a `For` or `For Each` loop: a local inside
+    /// a procedure, a global at page level.  Under `Option Explicit`, it
+    /// must be declared.
+    fn loop_variable(&mut self, name: &str) -> VarSlot {
+        let name = name.to_lowercase();
+        if self.in_procedure {
+            if self.option_explicit && !self.is_declared(&name) {
+                self.global_variable(name.clone());
+            }
+            VarSlot::Local(self.allocate_local(&name))
+        } else {
+            VarSlot::Global(self.global_variable(name))
+        }
+    }
+
     fn compile_blocks(&mut self, blocks: &[BlockStatement]) -> Result<(), VBSError> {
         for block in blocks {
             self.compile_block(block)?;
@@ -138,6 +339,17 @@ impl<'a> Compiler<'a> {
     }
 
     fn compile_block(&mut self, block: &BlockStatement) -> Result<(), VBSError> {
+        self.line = block.line();
+        // Track the script line of every executable statement for `Err.Line`
+        if !matches!(
+            block,
+            BlockStatement::FunctionDef { .. }
+                | BlockStatement::SubDef { .. }
+                | BlockStatement::ClassDef { .. }
+                | BlockStatement::OptionExplicit(_)
+        ) {
+            self.emit(Instruction::DebugLine(self.line as u32));
+        }
         match block {
             BlockStatement::Syntax(syntax, _line) => {
                 syntax.compile(self)?;
@@ -195,12 +407,12 @@ impl<'a> Compiler<'a> {
                 body,
                 ..
             } => {
-                let slot = self.allocate_local(counter);
+                let slot = self.loop_variable(counter);
                 self.compile_expr(start);
-                self.emit(Instruction::StoreLocal(slot));
+                self.emit_store(slot);
 
                 self.compile_expr(end);
-                self.compile_expr(step.as_ref().unwrap_or(&Expr::Literal(VBValue::Number(1.0))));
+                self.compile_expr(step.as_ref().unwrap_or(&Expr::Literal(VBValue::Integer(1))));
 
                 let prep_offset = self.current_offset();
                 let break_target = prep_offset;
@@ -234,7 +446,7 @@ impl<'a> Compiler<'a> {
                 body,
                 ..
             } => {
-                let slot = self.allocate_local(element);
+                let slot = self.loop_variable(element);
                 self.compile_expr(group);
 
                 let prep_offset = self.current_offset();
@@ -455,45 +667,22 @@ impl<'a> Compiler<'a> {
             } => {
                 if let Ok(properties) = extract_properties_from_class_body(body_lines) {
                     let methods = extract_methods_from_class_body(body_lines);
-                    let class_def = ClassDefinition {
-                        name: name.clone(),
-                        properties,
-                        methods,
-                    };
-                    self.context.define_class(class_def);
-                    if let Some(class_def) = self.context.get_class(name) {
-                        let prop_bodies: Vec<_> = class_def.properties.values().map(|pd| {
-                            (pd.name.clone(), pd.get_body.clone(), pd.let_body.clone(), pd.let_param.clone())
-                        }).collect();
-                        let method_bodies: Vec<_> = class_def.methods.values().map(|md| {
-                            (md.name.clone(), md.params.clone(), md.body_lines.clone())
-                        }).collect();
-                        for (prop_name, get_body, let_body, let_param) in &prop_bodies {
-                            if let Some(body) = get_body {
-                                let lines = self.token_lines_to_blocks(body);
-                                let func_name = format!("__cls_{}_get_{}", name, prop_name);
-                                let compiled = self.compile_function(&lines, &[])?;
-                                self.compiled_functions
-                                    .insert(func_name.to_lowercase(), compiled);
-                            }
-                            if let Some(body) = let_body {
-                                let lines = self.token_lines_to_blocks(body);
-                                let func_name = format!("__cls_{}_let_{}", name, prop_name);
-                                let param = let_param.as_deref().unwrap_or("__value__");
-                                let compiled = self.compile_function(&lines, &[param])?;
-                                self.compiled_functions
-                                    .insert(func_name.to_lowercase(), compiled);
-                            }
-                        }
-                        for (method_name, params, body_lines) in &method_bodies {
-                            let lines = self.token_lines_to_blocks(body_lines);
-                            let func_name = format!("__cls_{}_{}", name, method_name);
-                            let params: Vec<&str> = params.iter().map(|s| s.as_str()).collect();
-                            let compiled = self.compile_function(&lines, &params)?;
-                            self.compiled_functions
-                                .insert(func_name.to_lowercase(), compiled);
-                        }
+                    let fields = extract_fields_from_class_body(body_lines);
+                    if self.option_explicit {
+                        let members = fields
+                            .iter()
+                            .cloned()
+                            .chain(properties.keys().cloned())
+                            .chain(methods.keys().cloned())
+                            .chain(["me".to_string()])
+                            .collect();
+                        self.scopes.push(members);
                     }
+                    let class_def = self.compile_class(name, fields, &properties, &methods, body_lines);
+                    if self.option_explicit {
+                        self.scopes.pop();
+                    }
+                    self.classes.push(class_def?);
                 }
             }
             BlockStatement::With { object, body, .. } => {
@@ -517,7 +706,7 @@ impl<'a> Compiler<'a> {
                     is_function: true,
                 });
                 let lines = self.token_lines_to_blocks(body_lines);
-                let param_strs: Vec<&str> = params.iter().map(|s| s.as_str()).collect();
+                let param_strs: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
                 let compiled = self.compile_function(&lines, &param_strs)?;
                 let key = name.to_lowercase();
                 self.compiled_functions.insert(key, compiled);
@@ -535,7 +724,7 @@ impl<'a> Compiler<'a> {
                     is_function: false,
                 });
                 let lines = self.token_lines_to_blocks(body_lines);
-                let param_strs: Vec<&str> = params.iter().map(|s| s.as_str()).collect();
+                let param_strs: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
                 let compiled = self.compile_function(&lines, &param_strs)?;
                 let key = name.to_lowercase();
                 self.compiled_functions.insert(key, compiled);
@@ -549,6 +738,7 @@ impl<'a> Compiler<'a> {
             BlockStatement::ExitSub(_) => {
                 self.emit(Instruction::ExitSub);
             }
+            BlockStatement::OptionExplicit(_) => {}
             BlockStatement::Unrecognized(e, _msg, _line) => {
                 return Err(e.clone());
             }
@@ -560,6 +750,19 @@ impl<'a> Compiler<'a> {
         &mut self,
         blocks: &[BlockStatement],
         params: &[&str],
+    ) -> Result<CompiledCode, VBSError> {
+        self.compile_procedure(blocks, &[], params)
+    }
+
+    /// Compile a procedure body whose frame starts with one slot per entry
+    /// of `fields`, followed by one slot per parameter.  A field that the
+    /// body redeclares (as a parameter, `Dim` or `Const`) keeps its slot but
+    /// is shadowed by the new local.
+    fn compile_procedure(
+        &mut self,
+        blocks: &[BlockStatement],
+        fields: &[String],
+        params: &[&str],
     ) -> Result<CompiledCode, VBSError> {
         let saved_locals = self.locals.clone();
         let saved_local_count = self.local_count;
@@ -567,14 +770,33 @@ impl<'a> Compiler<'a> {
         let saved_constants = std::mem::take(&mut self.constants);
         let saved_loop_stack = std::mem::take(&mut self.loop_stack);
         let saved_compiled_functions = std::mem::take(&mut self.compiled_functions);
+        let saved_in_procedure = std::mem::replace(&mut self.in_procedure, true);
 
         self.locals.clear();
         self.local_count = 0;
+        let mut declared = AHashSet::new();
+        collect_declarations(blocks, &mut declared);
+        for field in fields {
+            if declared.contains(field) || params.iter().any(|p| p.eq_ignore_ascii_case(field)) {
+                self.local_count += 1;
+            } else {
+                self.allocate_local(field);
+            }
+        }
         for p in params {
             self.allocate_local(p);
         }
+        if self.option_explicit {
+            self.scopes.push(declared);
+        }
 
-        self.compile_blocks(blocks)?;
+        let result = self.compile_blocks(blocks);
+        if self.option_explicit {
+            self.scopes.pop();
+        }
+        if result.is_ok() && self.optimize {
+            optimizer::optimize(&mut self.code, &mut self.constants, &self.globals.names);
+        }
 
         let mut local_names = vec![String::new(); self.local_count];
         for (name, slot) in &self.locals {
@@ -589,6 +811,9 @@ impl<'a> Compiler<'a> {
             local_names,
             function_defs: Vec::new(),
             compiled_functions: Vec::new(),
+            classes: Vec::new(),
+            // Filled in once the whole script is compiled
+            globals: Arc::default(),
         };
 
         self.code = saved_code;
@@ -597,14 +822,196 @@ impl<'a> Compiler<'a> {
         self.local_count = saved_local_count;
         self.loop_stack = saved_loop_stack;
         self.compiled_functions = saved_compiled_functions;
+        self.in_procedure = saved_in_procedure;
 
-        Ok(compiled)
+        result.map(|()| compiled)
+    }
+
+    /// Compile every method and property accessor of a class.
+    fn compile_class(
+        &mut self,
+        name: &str,
+        fields: Vec<String>,
+        properties: &AHashMap<String, PropertyDef>,
+        methods: &AHashMap<String, MethodDef>,
+        body_lines: &[Vec<Token>],
+    ) -> Result<ClassDefinition, VBSError> {
+        let mut compiled_properties = AHashMap::new();
+        for (key, pd) in properties {
+            let mut property = ClassProperty::default();
+            if let Some(body) = &pd.get_body {
+                property.get = Some(self.compile_class_member(&fields, &pd.name, body, &pd.get_params, true)?);
+            }
+            if let Some(body) = &pd.let_body {
+                property.let_ = Some(self.compile_class_member(&fields, &pd.name, body, &pd.let_params, false)?);
+            }
+            if let Some(body) = &pd.set_body {
+                property.set = Some(self.compile_class_member(&fields, &pd.name, body, &pd.set_params, false)?);
+            }
+            compiled_properties.insert(key.clone(), property);
+        }
+        let mut compiled_methods = AHashMap::new();
+        for (key, md) in methods {
+            let member = self.compile_class_member(&fields, &md.name, &md.body_lines, &md.params, md.is_function)?;
+            compiled_methods.insert(key.clone(), member);
+        }
+        Ok(ClassDefinition {
+            name: name.to_string(),
+            fields,
+            properties: compiled_properties,
+            methods: compiled_methods,
+            default_member: find_default_member(body_lines),
+        })
+    }
+
+    /// Compile one class member.  A member that returns a value (`Function`,
+    /// `Property Get`) gets a local slot named after it for the result.
+    fn compile_class_member(
+        &mut self,
+        fields: &[String],
+        name: &str,
+        body: &[Vec<Token>],
+        params: &[Param],
+        returns_value: bool,
+    ) -> Result<ClassMember, VBSError> {
+        let blocks = self.token_lines_to_blocks(body);
+        let mut slots: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
+        if returns_value {
+            slots.push(name);
+        }
+        let compiled = self.compile_procedure(&blocks, fields, &slots)?;
+        let result_slot = if returns_value {
+            compiled.local_names.iter().position(|n| n == name)
+        } else {
+            None
+        };
+        let assigned_fields = assigned_slots(&compiled, fields.len());
+        Ok(ClassMember {
+            name: name.to_string(),
+            params: params.to_vec(),
+            code: compiled.into(),
+            result_slot,
+            assigned_fields,
+        })
     }
 
     fn token_lines_to_blocks(&self, body_lines: &[Vec<crate::vbscript::Token>]) -> Vec<BlockStatement> {
         crate::vbscript::block::parse_blocks(body_lines).unwrap_or_default()
     }
 
+    /// Compile the arguments of a call.
+    ///
+    /// The object or indices of an argument that is written back by `ByRef`
+    /// are evaluated once, into temporaries the write-back then goes
+    /// through, so that their side effects do not run twice.  For a name
+    /// that may be a function rather than an array, indices that could
+    /// themselves bind a `ByRef` parameter are left as they are.
+    pub(crate) fn compile_call_args(&mut self, args: &[Expr]) -> CallArgs {
+        let mut call = CallArgs { args: Vec::with_capacity(args.len()), temps: Vec::new() };
+        for arg in args {
+            let arg = match arg {
+                Expr::FunctionCall { name, args: indices } if !indices.is_empty() => {
+                    let name_lower = name.to_lowercase();
+                    let is_array = self.local_slot(&name_lower).is_some() || self.is_page_variable(&name_lower);
+                    let mut captured = Vec::with_capacity(indices.len());
+                    for index in indices {
+                        let keep = matches!(index, Expr::Literal(_)) || (!is_array && is_reference(index));
+                        captured.push(if keep { index.clone() } else { self.capture(index, &mut call.temps) });
+                    }
+                    Expr::FunctionCall { name: name.clone(), args: captured }
+                }
+                Expr::PropertyAccess { object, property } if !matches!(**object, Expr::Variable(_) | Expr::WithObject) => {
+                    let object = self.capture(object, &mut call.temps);
+                    Expr::PropertyAccess { object: Box::new(object), property: property.clone() }
+                }
+                _ => arg.clone(),
+            };
+            self.compile_expr(&arg);
+            call.args.push(arg);
+        }
+        call
+    }
+
+    /// Evaluate `expr` into a new temporary, returning the expression that
+    /// reads it back.
+    fn capture(&mut self, expr: &Expr, temps: &mut Vec<String>) -> Expr {
+        // No script name starts with '#'
+        let name = format!("#temp{}", self.local_count);
+        let slot = self.allocate_local(&name);
+        self.compile_expr(expr);
+        self.emit(Instruction::StoreLocal(slot));
+        temps.push(name.clone());
+        Expr::Variable(name)
+    }
+
+    /// Emit the write-back of `ByRef` arguments after a call instruction.
+    ///
+    /// Each argument that names a variable, array element or object property
+    /// gets a guarded store of the value the callee left in the matching
+    /// parameter; other arguments are temporaries and are not written back.
+    pub(crate) fn emit_byref_writebacks(&mut self, call: CallArgs) {
+        for (k, arg) in call.args.iter().enumerate() {
+            let k = k as u8;
+            match arg {
+                Expr::Variable(name) => {
+                    let name_lower = name.to_lowercase();
+                    if let Some(slot) = self.local_slot(&name_lower) {
+                        let skip = self.emit_byref_skip(k, ByRefGuard::None);
+                        self.emit(Instruction::LoadByRef(k));
+                        self.emit(Instruction::StoreLocal(slot));
+                        self.patch_jump(skip, self.current_offset());
+                    } else {
+                        let slot = self.global_slot(&name_lower);
+                        let skip = self.emit_byref_skip(k, ByRefGuard::Global(slot));
+                        self.emit(Instruction::LoadByRef(k));
+                        self.emit(Instruction::StoreGlobal(slot));
+                        self.patch_jump(skip, self.current_offset());
+                    }
+                }
+                Expr::FunctionCall { name, args: indices } if !indices.is_empty() => {
+                    let name_lower = name.to_lowercase();
+                    let n = indices.len() as u8;
+                    if let Some(slot) = self.local_slot(&name_lower) {
+                        let skip = self.emit_byref_skip(k, ByRefGuard::LocalArray(slot));
+                        for index in indices {
+                            self.compile_expr(index);
+                        }
+                        self.emit(Instruction::LoadByRef(k));
+                        self.emit(Instruction::IndexStoreLocalMulti(slot, n));
+                        self.patch_jump(skip, self.current_offset());
+                    } else {
+                        let slot = self.global_slot(&name_lower);
+                        let skip = self.emit_byref_skip(k, ByRefGuard::GlobalArray(slot));
+                        for index in indices {
+                            self.compile_expr(index);
+                        }
+                        self.emit(Instruction::LoadByRef(k));
+                        self.emit(Instruction::IndexStoreGlobalMulti(slot, n));
+                        self.patch_jump(skip, self.current_offset());
+                    }
+                }
+                Expr::PropertyAccess { object, property } => {
+                    let skip = self.emit_byref_skip(k, ByRefGuard::None);
+                    self.compile_expr(object);
+                    self.emit(Instruction::LoadByRef(k));
+                    self.emit(Instruction::SetProp(Symbol::intern(property)));
+                    self.patch_jump(skip, self.current_offset());
+                }
+                _ => {}
+            }
+        }
+        // The slots stay reserved; unnamed, they are not exposed as locals
+        for temp in call.temps {
+            self.locals.remove(&temp);
+        }
+    }
+
+    fn emit_byref_skip(&mut self, arg: u8, guard: ByRefGuard) -> Patch {
+        let pos = self.code.len();
+        self.code.push(Instruction::ByRefSkip(arg, guard, 0));
+        Patch(pos)
+    }
+
     pub(crate) fn emit_exit(&mut self) {
         let pos = self.code.len();
         self.emit(Instruction::Jump(0));
@@ -625,8 +1032,8 @@ impl<'a> Compiler<'a> {
                     let slot = self.locals[&name_lower];
                     self.emit(Instruction::LoadLocal(slot));
                 } else {
-                    let idx = self.add_constant(VBValue::String(name_lower.into()));
-                    self.emit(Instruction::LoadGlobal(idx));
+                    let slot = self.global_variable(name_lower);
+                    self.emit(Instruction::LoadGlobal(slot));
                 }
             }
             Expr::BinaryOp { left, op, right } => {
@@ -682,18 +1089,21 @@ impl<'a> Compiler<'a> {
                         }
                         self.emit(Instruction::IndexGet);
                     }
+                } else if args.len() == 1 && self.is_page_variable(&name_lower) {
+                    let slot = self.global_slot(&name_lower);
+                    self.emit(Instruction::LoadGlobal(slot));
+                    self.compile_expr(&args[0]);
+                    self.emit(Instruction::IndexGet);
                 } else {
-                    for arg in args {
-                        self.compile_expr(arg);
-                    }
+                    let call = self.compile_call_args(args);
                     let name_idx = self.add_constant(VBValue::String(name_lower.into()));
                     self.emit(Instruction::Call(name_idx, args.len() as u8));
+                    self.emit_byref_writebacks(call);
                 }
             }
             Expr::PropertyAccess { object, property } => {
                 self.compile_expr(object);
-                let prop_idx = self.add_constant(VBValue::String(property.to_lowercase().into()));
-                self.emit(Instruction::GetProp(prop_idx));
+                self.emit(Instruction::GetProp(Symbol::intern(property)));
             }
             Expr::MethodCall {
                 object,
@@ -701,19 +1111,18 @@ impl<'a> Compiler<'a> {
                 args,
             } => {
                 self.compile_expr(object);
-                for arg in args {
-                    self.compile_expr(arg);
-                }
-                let method_idx = self.add_constant(VBValue::String(method.to_lowercase().into()));
-                self.emit(Instruction::CallMethod(method_idx, args.len() as u8));
+                let call = self.compile_call_args(args);
+                self.emit(Instruction::CallMethod(Symbol::intern(method), args.len() as u8));
+                self.emit_byref_writebacks(call);
             }
             Expr::NewObject(name) => {
                 let name_idx = self.add_constant(VBValue::String(name.to_lowercase().into()));
                 self.emit(Instruction::NewObject(name_idx));
             }
+            Expr::Parenthesized(inner) => self.compile_expr(inner),
             Expr::WithObject => {
-                let name_idx = self.add_constant(VBValue::String("__with_obj__".into()));
-                self.emit(Instruction::LoadGlobal(name_idx));
+                let slot = self.global_slot("__with_obj__");
+                self.emit(Instruction::LoadGlobal(slot));
             }
             Expr::CaseComparison { op, rhs } => {
                 self.emit(Instruction::LoadSelectValue);
@@ -742,6 +1151,85 @@ impl<'a> Compiler<'a> {
     }
 }
 
+/// Collect the names `blocks` declare in their own scope: `Dim`, `ReDim`
+/// and `Const` statements (including those nested in control flow) and the
+/// procedures defined at this level.
+fn collect_declarations(blocks: &[BlockStatement], names: &mut AHashSet<String>) {
+    for block in blocks {
+        match block {
+            BlockStatement::Syntax(syntax, _) => {
+                names.extend(syntax.declared_names().into_iter().map(str::to_lowercase));
+            }
+            BlockStatement::FunctionDef { name, .. } | BlockStatement::SubDef { name, .. } => {
+                names.insert(name.to_lowercase());
+            }
+            BlockStatement::If {
+                then_body,
+                else_if_blocks,
+                else_body,
+                ..
+            } => {
+                collect_declarations(then_body, names);
+                for elseif in else_if_blocks {
+                    collect_declarations(&elseif.body, names);
+                }
+                if let Some(else_body) = else_body {
+                    collect_declarations(else_body, names);
+                }
+            }
+            BlockStatement::SelectCase { cases, else_body, .. } => {
+                for case in cases {
+                    collect_declarations(&case.body, names);
+                }
+                if let Some(else_body) = else_body {
+                    collect_declarations(else_body, names);
+                }
+            }
+            BlockStatement::For { body, .. }
+            | BlockStatement::ForEach { body, .. }
+            | BlockStatement::While { body, .. }
+            | BlockStatement::Do { body, .. }
+            | BlockStatement::With { body, .. } => collect_declarations(body, names),
+            _ => {}
+        }
+    }
+}
+
+/// The local slots below `limit` that `code` can assign.  Code that calls
+/// `Execute` or `ExecuteGlobal` may assign any of them by name.
+fn assigned_slots(code: &CompiledCode, limit: usize) -> Vec<usize> {
+    let mut assigned = vec![false; limit];
+    for inst in &code.instructions {
+        let slot = match inst {
+            Instruction::StoreLocal(s)
+            | Instruction::IndexStoreLocal(s)
+            | Instruction::IndexStoreLocalMulti(s, _)
+            | Instruction::ReDim(VarSlot::Local(s), _, _)
+            | Instruction::ForPrep(VarSlot::Local(s), _)
+            | Instruction::ForStep(VarSlot::Local(s), _)
+            | Instruction::ForEachPrep(VarSlot::Local(s), _)
+            | Instruction::ForEachStep(VarSlot::Local(s), _)
+            | Instruction::Erase(s)
+            | Instruction::AddLocalConst(s, _)
+            | Instruction::ConcatLocalConst(s, _)
+            | Instruction::StoreLocalConst(s, _)
+            | Instruction::AppendLocal(s, _) => *s,
+            Instruction::Call(i, _) => {
+                let name = code.constants[*i as usize].to_string();
+                if name == "execute" || name == "executeglobal" {
+                    return (0..limit).collect();
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        if slot < limit {
+            assigned[slot] = true;
+        }
+    }
+    (0..limit).filter(|&s| assigned[s]).collect()
+}
+
 pub(crate) fn extract_properties_from_class_body(
     body_lines: &[Vec<Token>],
 ) -> Result<AHashMap<String, PropertyDef>, VBSError> {
@@ -775,7 +1263,10 @@ pub(crate) fn extract_properties_from_class_body(
                         t.token_type == TokenType::Let || t.value.eq_ignore_ascii_case("let")
                     })
                     .unwrap_or(false);
-                if is_get || is_let {
+                let is_set = get_let_set
+                    .map(|t| t.token_type == TokenType::Set || t.value.eq_ignore_ascii_case("set"))
+                    .unwrap_or(false);
+                if is_get || is_let || is_set {
                     let name_tok = match name_tok {
                         Some(t) if t.token_type == TokenType::Identifier => t,
                         _ => {
@@ -786,20 +1277,7 @@ pub(crate) fn extract_properties_from_class_body(
                     let prop_name = name_tok.value.to_lowercase();
                     i += 1;
 
-                    let mut param = None;
-                    if is_let && no_ws.len() > p_idx + 3 {
-                        let paren_open = no_ws.get(p_idx + 3);
-                        if paren_open
-                            .map(|t| t.token_type == TokenType::LeftParen)
-                            .unwrap_or(false)
-                        {
-                            if let Some(param_tok) = no_ws.get(p_idx + 4) {
-                                if param_tok.token_type == TokenType::Identifier {
-                                    param = Some(param_tok.value.to_lowercase());
-                                }
-                            }
-                        }
-                    }
+                    let params = parse_param_list(&no_ws[p_idx + 3..]);
 
                     let mut body: Vec<Vec<Token>> = Vec::new();
                     loop {
@@ -835,15 +1313,22 @@ pub(crate) fn extract_properties_from_class_body(
                         .or_insert(PropertyDef {
                             name: prop_name.clone(),
                             get_body: None,
+                            get_params: Vec::new(),
                             let_body: None,
-                            let_param: None,
+                            let_params: Vec::new(),
+                            set_body: None,
+                            set_params: Vec::new(),
                         });
 
                     if is_get {
                         entry.get_body = Some(body);
+                        entry.get_params = params;
                     } else if is_let {
                         entry.let_body = Some(body);
-                        entry.let_param = param;
+                        entry.let_params = params;
+                    } else {
+                        entry.set_body = Some(body);
+                        entry.set_params = params;
                     }
                     continue;
                 }
@@ -855,7 +1340,84 @@ pub(crate) fn extract_properties_from_class_body(
     Ok(properties)
 }
 
-pub(crate) fn extract_methods_from_class_body(body_lines: &[Vec<Token>]) -> AHashMap<String, MethodDef> {
+/// Find the member declared `Public Default` (a `Property Get`, `Function`
+/// or `Sub`) and return its lowercased name.
+pub(crate) fn find_default_member(body_lines: &[Vec<Token>]) -> Option<String> {
+    body_lines.iter().find_map(|line| {
+        let no_ws: Vec<&Token> = line
+            .iter()
+            .filter(|t| t.token_type != TokenType::WhiteSpace)
+            .collect();
+        if no_ws.len() < 4
+            || no_ws[0].token_type != TokenType::Public
+            || !no_ws[1].value.eq_ignore_ascii_case("default")
+        {
+            return None;
+        }
+        let name_idx = match no_ws[2].token_type {
+            TokenType::Property => 4,
+            TokenType::Function | TokenType::Sub => 3,
+            _ => return None,
+        };
+        no_ws
+            .get(name_idx)
+            .filter(|t| t.token_type == TokenType::Identifier)
+            .map(|t| t.value.to_lowercase())
+    })
+}
+
+/// Collect the lowercased names of the fields declared at class level with
+/// `Dim`, `Public` or `Private` (not inside a member body).
+pub(crate) fn extract_fields_from_class_body(body_lines: &[Vec<Token>]) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut in_member = false;
+
+    for line in body_lines {
+        let no_ws: Vec<&Token> = line
+            .iter()
+            .filter(|t| t.token_type != TokenType::WhiteSpace)
+            .collect();
+        let Some(first) = no_ws.first() else {
+            continue;
+        };
+        let is_member = |t: &Token| {
+            matches!(t.token_type, TokenType::Sub | TokenType::Function | TokenType::Property)
+        };
+        if in_member {
+            if first.token_type == TokenType::End && no_ws.get(1).is_some_and(|t| is_member(t)) {
+                in_member = false;
+            }
+            continue;
+        }
+        if no_ws.iter().take(3).any(|t| is_member(t)) {
+            in_member = true;
+            continue;
+        }
+        if !matches!(first.token_type, TokenType::Dim | TokenType::Public | TokenType::Private) {
+            continue;
+        }
+        let mut depth = 0;
+        let mut expect_name = true;
+        for tok in &no_ws[1..] {
+            match tok.token_type {
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen => depth -= 1,
+                TokenType::Comma if depth == 0 => expect_name = true,
+                TokenType::Identifier if depth == 0 && expect_name => {
+                    fields.push(tok.value.to_lowercase());
+                    expect_name = false;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fields
+}
+
+pub(crate) fn extract_methods_from_class_body(
+    body_lines: &[Vec<Token>],
+) -> AHashMap<String, MethodDef> {
     let mut methods: AHashMap<String, MethodDef> = AHashMap::new();
     let mut i = 0;
 
@@ -871,7 +1433,7 @@ pub(crate) fn extract_methods_from_class_body(body_lines: &[Vec<Token>]) -> AHas
             continue;
         }
 
-        let start_idx = if no_ws[0].token_type == TokenType::Public
+        let mut start_idx = if no_ws[0].token_type == TokenType::Public
             || no_ws[0].token_type == TokenType::Private
         {
             if no_ws.len() < 2 {
@@ -882,6 +1444,9 @@ pub(crate) fn extract_methods_from_class_body(body_lines: &[Vec<Token>]) -> AHas
         } else {
             0
         };
+        if start_idx == 1 && no_ws.len() > 2 && no_ws[1].value.eq_ignore_ascii_case("default") {
+            start_idx = 2;
+        }
 
         let is_func = no_ws[start_idx].token_type == TokenType::Function
             || no_ws[start_idx].value.eq_ignore_ascii_case("function");
@@ -900,16 +1465,7 @@ pub(crate) fn extract_methods_from_class_body(body_lines: &[Vec<Token>]) -> AHas
         }
         let method_name = no_ws[name_idx].value.to_lowercase();
 
-        let mut params = Vec::new();
-        if no_ws.len() > name_idx + 1 && no_ws[name_idx + 1].token_type == TokenType::LeftParen {
-            let mut p = name_idx + 2;
-            while p < no_ws.len() && no_ws[p].token_type != TokenType::RightParen {
-                if no_ws[p].token_type == TokenType::Identifier {
-                    params.push(no_ws[p].value.to_lowercase());
-                }
-                p += 1;
-            }
-        }
+        let params = parse_param_list(&no_ws[name_idx + 1..]);
 
         i += 1;
         let mut body: Vec<Vec<Token>> = Vec::new();
@@ -945,10 +1501,9 @@ pub(crate) fn extract_methods_from_class_body(body_lines: &[Vec<Token>]) -> AHas
             i += 1;
         }
 
-        let key = method_name.to_uppercase();
-        if !methods.contains_key(&key) {
+        if !methods.contains_key(&method_name) {
             methods.insert(
-                key,
+                method_name.clone(),
                 MethodDef {
                     name: method_name,
                     params,
@@ -1,15 +1,106 @@
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use ahash::AHashMap;
 
-use super::block::{BlockStatement, UserDefinedFunction};
-use super::compiler::CompiledCode;
+use super::block::{Param, UserDefinedFunction};
+use super::compiler::{CompiledCode, ProcedureCode};
 use super::debugger::Debugger;
 use super::store::Store;
 use super::tokenizer::Token;
-use super::vbs_error::VBSError;
+use super::vbobject::ClassLifecycle;
+use super::vbs_error::{codes, VBSError, VBSErrorType};
 use super::VBValue;
 
+/// `Server.ScriptTimeout` of a request, in seconds, unless configured.
+pub const DEFAULT_SCRIPT_TIMEOUT: u32 = 90;
+
+/// Message of the error that aborts a script that ran past its timeout.
+pub const SCRIPT_TIMED_OUT: &str = "ASP 0113: Script timed out";
+
+/// Message of the error that aborts a script that used up its instruction budget.
+pub const INSTRUCTION_LIMIT_EXCEEDED: &str = "Script exceeded its instruction limit";
+
+/// Per-request resource limits; a limit of 0 is no limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Instructions the VM may execute for the request.
+    pub max_instructions: u64,
+    /// Depth of nested procedure calls, beyond which a call fails with
+    /// "Out of stack space".
+    pub max_call_depth: usize,
+    /// Elements of a single array, beyond which `Dim`/`ReDim` fail with
+    /// "Out of memory".
+    pub max_array_elements: usize,
+    /// Bytes of a single string, beyond which building it fails with
+    /// "Out of memory".
+    pub max_string_length: usize,
+    /// Bytes the page may write to the response, beyond which
+    /// `Response.Write` fails with "Out of memory".
+    pub max_response_bytes: usize,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        ResourceLimits {
+            max_instructions: 100_000_000,
+            max_call_depth: 256,
+            max_array_elements: 10_000_000,
+            max_string_length: 64 * 1024 * 1024,
+            max_response_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+impl ResourceLimits {
+    /// No limits at all.
+    pub fn unlimited() -> Self {
+        ResourceLimits {
+            max_instructions: 0,
+            max_call_depth: 0,
+            max_array_elements: 0,
+            max_string_length: 0,
+            max_response_bytes: 0,
+        }
+    }
+
+    /// Whether `value` is past `limit`, where a limit of 0 is no limit.
+    pub(crate) fn exceeds(value: u64, limit: u64) -> bool {
+        limit != 0 && value > limit
+    }
+
+    /// Number of elements of an array with the given upper bounds, failing
+    /// with "Out of memory" past `max_array_elements`.
+    pub(crate) fn array_size(&self, bounds: &[usize]) -> Result<usize, VBSError> {
+        bounds
+            .iter()
+            .try_fold(1usize, |total, &bound| total.checked_mul(bound.checked_add(1)?))
+            .filter(|&total| !Self::exceeds(total as u64, self.max_array_elements as u64))
+            .ok_or_else(|| VBSError::runtime(codes::OUT_OF_MEMORY))
+    }
+}
+
+/// Why a script was stopped for good.  Unlike runtime errors these cannot
+/// be trapped with `On Error Resume Next`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptAbort {
+    /// It ran past `Server.ScriptTimeout` (ASP 0113).
+    TimedOut,
+    /// It executed more than `ResourceLimits::max_instructions`.
+    InstructionLimit,
+}
+
+impl ScriptAbort {
+    fn error(self) -> VBSError {
+        let message = match self {
+            ScriptAbort::TimedOut => SCRIPT_TIMED_OUT,
+            ScriptAbort::InstructionLimit => INSTRUCTION_LIMIT_EXCEEDED,
+        };
+        VBSErrorType::RuntimeError.into_error(message.to_string())
+    }
+}
+
 type ExecuteFileCallback =
     Arc<dyn Fn(&str, &mut ExecutionContext) -> Result<(), String> + Send + Sync>;
 
@@ -25,32 +116,84 @@ pub enum ErrorMode {
     ResumeNext,
 }
 
-/// Parsed definition of a VBScript `Property Get/Let/Set` block.
+/// Source of a VBScript `Property Get/Let/Set` block, as extracted from
+/// the class body before compilation.
 #[derive(Clone)]
 pub struct PropertyDef {
     pub name: String,
     pub get_body: Option<Vec<Vec<Token>>>,
+    /// Parameters of `Property Get` (e.g. `key` in `Item(key)`).
+    pub get_params: Vec<Param>,
     pub let_body: Option<Vec<Vec<Token>>>,
-    pub let_param: Option<String>,
+    /// Parameters of `Property Let`; the last one receives the assigned value.
+    pub let_params: Vec<Param>,
+    pub set_body: Option<Vec<Vec<Token>>>,
+    /// Parameters of `Property Set`; the last one receives the assigned object.
+    pub set_params: Vec<Param>,
 }
 
-/// Parsed definition of a `Sub` or `Function` method.
+/// Source of a `Sub` or `Function` method, as extracted from the class
+/// body before compilation.
 #[derive(Clone)]
 pub struct MethodDef {
     pub name: String,
-    pub params: Vec<String>,
+    pub params: Vec<Param>,
     pub body_lines: Vec<Vec<Token>>,
     pub is_function: bool,
 }
 
-/// Parsed `Class` definition with its properties and methods.
+/// A class method or property accessor, compiled once when the class is
+/// defined.
+///
+/// Its frame keeps the instance fields in the leading local slots (in
+/// [`ClassDefinition::fields`] order), followed by the parameters.
+pub struct ClassMember {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub code: ProcedureCode,
+    /// Local slot of the return value (`Function`, `Property Get`).
+    pub result_slot: Option<usize>,
+    /// Field slots the body can assign; only these are written back to the
+    /// instance after a call.
+    pub assigned_fields: Vec<usize>,
+}
+
+/// The compiled accessors of a class property.
+#[derive(Default)]
+pub struct ClassProperty {
+    pub get: Option<ClassMember>,
+    pub let_: Option<ClassMember>,
+    pub set: Option<ClassMember>,
+}
+
+/// Compiled `Class` definition with its properties and methods.
 pub struct ClassDefinition {
     pub name: String,
-    pub properties: AHashMap<String, PropertyDef>,
-    pub methods: AHashMap<String, MethodDef>,
+    /// Lowercased names of the fields declared at class level, in slot order.
+    pub fields: Vec<String>,
+    /// Properties by lowercased name.
+    pub properties: AHashMap<String, ClassProperty>,
+    /// Methods by lowercased name.
+    pub methods: AHashMap<String, ClassMember>,
+    /// Lowercased name of the `Public Default` property or method, if any.
+    pub default_member: Option<String>,
 }
 
+impl ClassDefinition {
+    /// Slot of the field `name` (lowercased).
+    pub fn field_slot(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f == name)
+    }
 
+    /// Every compiled method and property accessor.
+    pub(crate) fn members_mut(&mut self) -> impl Iterator<Item = &mut ClassMember> {
+        self.properties
+            .values_mut()
+            .flat_map(|p| [p.get.as_mut(), p.let_.as_mut(), p.set.as_mut()])
+            .flatten()
+            .chain(self.methods.values_mut())
+    }
+}
 
 /// Per-request HTTP data populated by the server before script execution.
 ///
@@ -58,7 +201,7 @@ pub struct ClassDefinition {
 /// runs.  `params` holds URL query parameters (`?a=1&b=2`), `form` holds
 /// POST body (URL-encoded or multipart), and `cookies` is the parsed
 /// `Cookie` header.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct RequestContext {
     /// HTTP method (GET, POST, HEAD, etc.).
     pub method: String,
@@ -138,30 +281,56 @@ impl ResponseContext {
 pub struct SessionContext {
     pub id: String,
     pub enabled: bool,
+    /// Contents of the session once `Session.Abandon` has ended it, kept
+    /// for `Session_OnEnd` to run with after the page.
+    pub abandoned: Option<AHashMap<String, VBValue>>,
 }
 
 /// Aggregate execution context that owns all per-request state.
 pub struct ExecutionContext {
-    /// All script-level variables (case-insensitive keys).
-    variables: AHashMap<String, VBValue>,
+    /// Values of the script-level variables by slot; `None` for a slot
+    /// whose name was bound by compiled code but never assigned.
+    globals: Vec<Option<VBValue>>,
+    /// Slot of each script-level variable, by lowercased name.
+    global_slots: AHashMap<String, usize>,
     /// User-defined `Sub` / `Function` definitions.
     functions: AHashMap<String, UserDefinedFunction>,
-    /// Cached parsed function bodies.
-    function_bodies: AHashMap<String, Vec<BlockStatement>>,
     /// Cached compiled function code.
     function_code: AHashMap<String, CompiledCode>,
     /// `Class` definitions (stored by class name).
-    classes: AHashMap<String, ClassDefinition>,
+    classes: AHashMap<String, Arc<ClassDefinition>>,
     /// Current `On Error` mode.
     error_mode: ErrorMode,
     /// The `Err.Number` value set by the last runtime error.
     pub err_number: f64,
     /// The `Err.Description` value set by the last runtime error.
     pub err_description: String,
+    /// The `Err.Source` value set by the last runtime error.
+    pub err_source: String,
+    /// The `Err.HelpFile` value set by the last `Err.Raise`.
+    pub err_help_file: String,
+    /// The `Err.HelpContext` value set by the last `Err.Raise`.
+    pub err_help_context: i32,
+    /// The script line of the last runtime error (`Err.Line`).
+    pub err_line: u32,
+    /// Script line of the statement being executed, kept by the VM.
+    pub(crate) current_line: u32,
     /// The object set by `With obj ... End With`.
     pub with_object: Option<VBValue>,
     /// The expression value set by `Select Case expr`.
     pub(crate) select_value: Option<VBValue>,
+    /// Final values of the last user procedure call's parameters, indexed by
+    /// argument position; `None` for `ByVal` parameters.  Consumed by the VM
+    /// to write `ByRef` arguments back to the caller.
+    pub(crate) byref_results: Vec<Option<VBValue>>,
+    /// Pending and live class instances awaiting `Class_Terminate`.
+    pub(crate) lifecycle: Arc<ClassLifecycle>,
+    /// Set once the page declares `Option Explicit`; class member bodies and
+    /// dynamic code compiled later in the request inherit it.
+    pub(crate) option_explicit: bool,
+    /// Whether code compiled for this context goes through the bytecode
+    /// optimizer (on by default).
+    pub(crate) optimize: bool,
     /// Incoming request data.
     pub request: RequestContext,
     /// Output buffer, status, headers, redirect state.
@@ -172,6 +341,9 @@ pub struct ExecutionContext {
     pub store: Option<Arc<Store>>,
     /// Path to the script being executed (for debugger file/breakpoint matching).
     pub script_path: String,
+    /// Physical directory of the application, against which relative
+    /// file paths such as a database `Data Source` resolve.
+    pub app_root: PathBuf,
     /// Optional DAP debugger (shared across requests via Arc).
     pub debugger: Option<Arc<Debugger>>,
     /// Callback for Server.Execute / Server.Transfer.
@@ -180,6 +352,19 @@ pub struct ExecutionContext {
     pub code_start_line: usize,
     /// Unique per-request ID for Application.Lock ownership tracking.
     pub request_id: u64,
+    /// `Server.ScriptTimeout`, in seconds.
+    script_timeout: u32,
+    /// When the request started, once its timeout is being enforced.
+    script_started: Option<Instant>,
+    /// Set once the script has been stopped for good, so that `On Error
+    /// Resume Next` cannot keep it running.
+    pub(crate) aborted: Option<ScriptAbort>,
+    /// Resource limits of the request.
+    pub limits: ResourceLimits,
+    /// Instructions executed so far, counted against `limits.max_instructions`.
+    pub(crate) instructions: u64,
+    /// Depth of procedure calls being executed.
+    pub(crate) call_depth: usize,
 }
 
 impl ExecutionContext {
@@ -191,11 +376,58 @@ impl ExecutionContext {
     /// and appropriate defaults for all other fields.
     /// Used by the debug adapter for evaluating expressions in a stack frame.
     pub fn from_variables(vars: AHashMap<String, VBValue>, script_path: &str) -> Self {
-        ExecutionContext {
-            variables: vars,
-            script_path: script_path.to_string(),
-            ..Self::default()
+        let mut context = Self::default();
+        for (name, value) in vars {
+            context.set_variable(&name, value);
+        }
+        context.script_path = script_path.to_string();
+        context
+    }
+
+    /// `Server.ScriptTimeout`, in seconds.
+    pub fn script_timeout(&self) -> u32 {
+        self.script_timeout
+    }
+
+    /// Change `Server.ScriptTimeout`; the limit still counts from the start
+    /// of the request.  A timeout under one second, which would fail every
+    /// page, is raised to one second.
+    pub fn set_script_timeout(&mut self, seconds: u32) {
+        self.script_timeout = seconds.max(1);
+    }
+
+    /// Start the clock for `Server.ScriptTimeout`; until this is called a
+    /// script may run for as long as it likes.
+    pub fn enforce_script_timeout(&mut self) {
+        self.script_started = Some(Instant::now());
+    }
+
+    /// Whether the script was aborted for running past its timeout.
+    pub fn script_timed_out(&self) -> bool {
+        self.aborted == Some(ScriptAbort::TimedOut)
+    }
+
+    /// Why the script was stopped for good, if it was.
+    pub fn aborted(&self) -> Option<ScriptAbort> {
+        self.aborted
+    }
+
+    /// Fail with ASP 0113 once the script has run past its timeout, or once
+    /// it used up its instruction budget.
+    pub(crate) fn check_budget(&mut self) -> Result<(), VBSError> {
+        if self.aborted.is_none() {
+            if ResourceLimits::exceeds(self.instructions, self.limits.max_instructions) {
+                self.aborted = Some(ScriptAbort::InstructionLimit);
+            } else if self
+                .script_started
+                .is_some_and(|started| started.elapsed() >= Duration::from_secs(self.script_timeout.into()))
+            {
+                self.aborted = Some(ScriptAbort::TimedOut);
+            } else {
+                return Ok(());
+            }
         }
+        Err(self.aborted.unwrap().error())
     }
 
     fn lc_key<'a>(&self, name: &'a str) -> std::borrow::Cow<'a, str> {
@@ -207,31 +439,53 @@ impl ExecutionContext {
     }
 
     pub fn get_variable(&self, name: &str) -> Option<&VBValue> {
-        self.variables.get(self.lc_key(name).as_ref())
+        let slot = *self.global_slots.get(self.lc_key(name).as_ref())?;
+        self.globals[slot].as_ref()
     }
 
     pub fn set_variable(&mut self, name: &str, value: VBValue) {
-        self.variables.insert(name.to_lowercase(), value);
+        let slot = self.global_slot(name);
+        self.globals[slot] = Some(value);
     }
 
     pub fn get_variable_mut(&mut self, name: &str) -> Option<&mut VBValue> {
-        self.variables.get_mut(self.lc_key(name).as_ref())
+        let slot = *self.global_slots.get(self.lc_key(name).as_ref())?;
+        self.globals[slot].as_mut()
     }
 
-    pub fn define_function(&mut self, func: UserDefinedFunction) {
-        self.functions.insert(func.name.to_lowercase(), func);
+    /// Undefine the variable `name`, returning its value.
+    pub fn remove_variable(&mut self, name: &str) -> Option<VBValue> {
+        let slot = *self.global_slots.get(self.lc_key(name).as_ref())?;
+        self.globals[slot].take()
     }
 
-    pub fn get_function(&self, name: &str) -> Option<&UserDefinedFunction> {
-        self.functions.get(self.lc_key(name).as_ref())
+    /// Slot of the variable `name`, allocated (unassigned) on first use.
+    /// Slots stay bound to their name for the life of the context.
+    pub(crate) fn global_slot(&mut self, name: &str) -> usize {
+        if let Some(&slot) = self.global_slots.get(self.lc_key(name).as_ref()) {
+            return slot;
+        }
+        let slot = self.globals.len();
+        self.globals.push(None);
+        self.global_slots.insert(name.to_lowercase(), slot);
+        slot
     }
 
-    pub fn get_function_body(&self, name: &str) -> Option<&Vec<BlockStatement>> {
-        self.function_bodies.get(self.lc_key(name).as_ref())
+    pub(crate) fn global(&self, slot: usize) -> Option<&VBValue> {
+        self.globals[slot].as_ref()
     }
 
-    pub fn set_function_body(&mut self, name: &str, body: Vec<BlockStatement>) {
-        self.function_bodies.insert(name.to_lowercase(), body);
+    /// The value in `slot`; `None` while the variable is undefined.
+    pub(crate) fn global_mut(&mut self, slot: usize) -> &mut Option<VBValue> {
+        &mut self.globals[slot]
+    }
+
+    pub fn define_function(&mut self, func: UserDefinedFunction) {
+        self.functions.insert(func.name.to_lowercase(), func);
+    }
+
+    pub fn get_function(&self, name: &str) -> Option<&UserDefinedFunction> {
+        self.functions.get(self.lc_key(name).as_ref())
     }
 
     pub fn set_function_code(&mut self, name: &str, code: CompiledCode) {
@@ -242,11 +496,11 @@ impl ExecutionContext {
         self.function_code.get(self.lc_key(name).as_ref())
     }
 
-    pub fn define_class(&mut self, class: ClassDefinition) {
+    pub fn define_class(&mut self, class: Arc<ClassDefinition>) {
         self.classes.insert(class.name.to_lowercase(), class);
     }
 
-    pub fn get_class(&self, name: &str) -> Option<&ClassDefinition> {
+    pub fn get_class(&self, name: &str) -> Option<&Arc<ClassDefinition>> {
         self.classes.get(self.lc_key(name).as_ref())
     }
 
@@ -260,20 +514,45 @@ impl ExecutionContext {
 
     pub fn set_err(&mut self, err: VBSError) {
         self.err_number = err.code as f64;
+        self.err_source = err.source().to_string();
+        let details = err.details.map(|d| *d).unwrap_or_default();
+        self.err_help_file = details.help_file;
+        self.err_help_context = details.help_context;
+        self.err_line = self.current_line;
         self.err_description = err.message;
     }
 
     pub fn clear_err(&mut self) {
         self.err_number = 0.0;
         self.err_description.clear();
+        self.err_source.clear();
+        self.err_help_file.clear();
+        self.err_help_context = 0;
+        self.err_line = 0;
     }
 
-    pub fn variables(&self) -> &AHashMap<String, VBValue> {
-        &self.variables
+    /// Snapshot of the defined variables by lowercased name, for the
+    /// debugger and tests.
+    pub fn variables(&self) -> AHashMap<String, VBValue> {
+        self.global_slots
+            .iter()
+            .filter_map(|(name, &slot)| Some((name.clone(), self.globals[slot].clone()?)))
+            .collect()
     }
 
-    pub fn variables_mut(&mut self) -> &mut AHashMap<String, VBValue> {
-        &mut self.variables
+    /// Raise `Class_Terminate` for class instances whose last reference
+    /// was released since the previous call.  Run by the VM between
+    /// instructions.
+    pub(crate) fn run_pending_terminators(&mut self) -> Result<(), VBSError> {
+        ClassLifecycle::run_released(self)
+    }
+
+    /// Raise `Class_Terminate` for every class instance still pending or
+    /// alive, in release then creation order.  Called when the request
+    /// finishes (and again, as a no-op, when the context is dropped).
+    /// Every terminator runs; the first error is returned.
+    pub fn terminate_class_instances(&mut self) -> Result<(), VBSError> {
+        ClassLifecycle::run_all(self)
     }
 
     /// Clear the response buffer.
@@ -281,59 +560,60 @@ impl ExecutionContext {
         self.response.flush_buffer();
     }
 
-    /// Write a string to the response buffer.
-    pub fn write(&mut self, content: &str) {
+    /// Append `content` to the response, failing with "Out of memory" once
+    /// the page's output would pass `limits.max_response_bytes`.
+    pub fn write(&mut self, content: &str) -> Result<(), VBSError> {
+        self.check_response_size(content.len())?;
         self.response.write(content);
+        Ok(())
+    }
+
+    /// Append raw bytes to the response, as `Response.BinaryWrite`.
+    pub fn write_binary(&mut self, data: &[u8]) -> Result<(), VBSError> {
+        self.check_response_size(data.len())?;
+        self.response.write_binary(data);
+        Ok(())
     }
 
-    /// Temporarily replace the current variables with `instance_vars`,
-    /// run closure `f`, then restore. Used for class Property Get/Let/Set.
-    pub fn with_instance_scope<T>(
-        &mut self,
-        instance_vars: &mut AHashMap<String, VBValue>,
-        f: impl FnOnce(&mut Self) -> Result<T, VBSError>,
-    ) -> Result<T, VBSError> {
-        let saved = std::mem::replace(&mut self.variables, std::mem::take(instance_vars));
-        let result = f(self);
-        *instance_vars = std::mem::replace(&mut self.variables, saved);
-        result
-    }
-
-    /// Run closure `f` with `instance_vars` merged into variables (instance vars
-    /// take priority). After closure, updated instance vars are extracted back.
-    pub fn with_class_method_scope<T>(
-        &mut self,
-        instance_vars: &mut AHashMap<String, VBValue>,
-        f: impl FnOnce(&mut Self) -> Result<T, VBSError>,
-    ) -> Result<T, VBSError> {
-        let saved = std::mem::take(&mut self.variables);
-        let mut merged = saved.clone();
-        for (k, v) in instance_vars.iter() {
-            merged.insert(k.clone(), v.clone());
+    fn check_response_size(&self, extra: usize) -> Result<(), VBSError> {
+        let response = &self.response;
+        let total = response.flushed.len() + response.buffer.len() + response.binary_buffer.len() + extra;
+        if ResourceLimits::exceeds(total as u64, self.limits.max_response_bytes as u64) {
+            return Err(VBSError::runtime(codes::OUT_OF_MEMORY));
         }
-        self.variables = merged;
-        let result = f(self);
-        for key in instance_vars.keys().cloned().collect::<Vec<_>>() {
-            if let Some(v) = self.variables.remove(&key) {
-                instance_vars.insert(key, v);
-            }
+        Ok(())
+    }
+}
+
+impl Drop for ExecutionContext {
+    fn drop(&mut self) {
+        if let Err(e) = self.terminate_class_instances() {
+            tracing::warn!(error = %e, "Class_Terminate failed during context teardown");
         }
-        result
     }
 }
 
 impl Default for ExecutionContext {
     fn default() -> Self {
         ExecutionContext {
-            variables: AHashMap::new(),
+            globals: Vec::new(),
+            global_slots: AHashMap::new(),
             functions: AHashMap::new(),
-            function_bodies: AHashMap::new(),
             classes: AHashMap::new(),
             error_mode: ErrorMode::Normal,
             err_number: 0.0,
             err_description: String::new(),
+            err_source: String::new(),
+            err_help_file: String::new(),
+            err_help_context: 0,
+            err_line: 0,
+            current_line: 0,
             with_object: None,
             select_value: None,
+            byref_results: Vec::new(),
+            lifecycle: Arc::new(ClassLifecycle::default()),
+            option_explicit: false,
+            optimize: true,
             request: RequestContext {
                 method: "GET".to_string(),
                 code_page: 65001,
@@ -350,11 +630,18 @@ impl Default for ExecutionContext {
             },
             store: None,
             script_path: String::new(),
+            app_root: PathBuf::new(),
             debugger: None,
             execute_file_callback: None,
             function_code: AHashMap::new(),
             code_start_line: 0,
             request_id: 0,
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            script_started: None,
+            aborted: None,
+            limits: ResourceLimits::default(),
+            instructions: 0,
+            call_depth: 0,
         }
     }
 }
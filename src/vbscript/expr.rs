@@ -4,8 +4,10 @@
 //! the interpreter.
 
 use super::builtins;
+use super::numeric;
 use super::value_utils;
-use super::vbs_error::{VBSError, VBSErrorType};
+use super::vbobject::ObjectRef;
+use super::vbs_error::{codes, VBSError, VBSErrorType};
 use super::{ExecutionContext, Token, TokenType, VBValue};
 
 /// Binary operators supported by VBScript expressions.
@@ -54,6 +56,9 @@ pub enum Expr {
     CaseComparison { op: BinOp, rhs: Box<Expr> },
     /// Used internally for `Case low To high` range in Select Case.
     Range { low: Box<Expr>, high: Box<Expr> },
+    /// A call argument wrapped in parentheses (`Inc((k))`), which VBScript
+    /// passes by value even to a `ByRef` parameter.
+    Parenthesized(Box<Expr>),
 }
 
 /// Parse a sequence of tokens into an `Expr` AST using a Pratt parser.
@@ -72,6 +77,49 @@ pub fn parse_expression(tokens: &[Token]) -> Result<Expr, VBSError> {
     Ok(result)
 }
 
+/// Parse the tokens of one call argument; see `parse_argument`.
+pub fn parse_argument_expression(tokens: &[Token]) -> Result<Expr, VBSError> {
+    let filtered: Vec<&Token> = tokens
+        .iter()
+        .filter(|t| t.token_type != TokenType::WhiteSpace)
+        .collect();
+    let mut pos = 0;
+    parse_argument(&filtered, &mut pos)
+}
+
+/// Parse one call argument.  A variable, element or property wrapped whole
+/// in parentheses becomes `Expr::Parenthesized`, so it is not written back.
+fn parse_argument(tokens: &[&Token], pos: &mut usize) -> Result<Expr, VBSError> {
+    let start = *pos;
+    let arg = parse_binary(tokens, pos, 0)?;
+    let is_reference = matches!(arg, Expr::Variable(_) | Expr::PropertyAccess { .. } | Expr::FunctionCall { .. });
+    if is_reference && encloses(&tokens[start..*pos]) {
+        return Ok(Expr::Parenthesized(Box::new(arg)));
+    }
+    Ok(arg)
+}
+
+/// Whether `tokens` open with a parenthesis that closes at their last token.
+fn encloses(tokens: &[&Token]) -> bool {
+    if tokens.first().is_none_or(|t| t.token_type != TokenType::LeftParen) {
+        return false;
+    }
+    let mut depth = 0;
+    for (i, t) in tokens.iter().enumerate() {
+        match t.token_type {
+            TokenType::LeftParen => depth += 1,
+            TokenType::RightParen => {
+                depth -= 1;
+                if depth == 0 {
+                    return i == tokens.len() - 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
 fn peek<'a>(tokens: &'a [&'a Token], pos: usize) -> Option<&'a Token> {
     tokens.get(pos).copied()
 }
@@ -175,7 +223,13 @@ fn parse_primary(tokens: &[&Token], pos: &mut usize) -> Result<Expr, VBSError> {
                 }
             }
         };
-        let expr = parse_primary(tokens, pos)?;
+        // Member access binds tighter than either operator; `Not` also
+        // takes in comparisons, so `Not a = b` is `Not (a = b)`.
+        let operand_prec = match op {
+            UnaryOp::Not => 25,
+            UnaryOp::Neg => precedence(&TokenType::Dot).0,
+        };
+        let expr = parse_binary(tokens, pos, operand_prec)?;
         return Ok(Expr::UnaryOp {
             op,
             expr: Box::new(expr),
@@ -220,7 +274,7 @@ fn parse_primary(tokens: &[&Token], pos: &mut usize) -> Result<Expr, VBSError> {
                         return Err(VBSErrorType::SyntaxError
                             .into_error("Unclosed parentheses in method call".to_string()));
                     }
-                    let arg = parse_binary(tokens, pos, 0)?;
+                    let arg = parse_argument(tokens, pos)?;
                     args.push(arg);
                     match peek(tokens, *pos) {
                         Some(t) if t.token_type == TokenType::Comma => {
@@ -258,8 +312,7 @@ fn parse_primary(tokens: &[&Token], pos: &mut usize) -> Result<Expr, VBSError> {
 
     match token.token_type {
         TokenType::IntegerLiteral | TokenType::HexLiteral | TokenType::OctLiteral => {
-            let num = parse_numeric_literal(token)?;
-            Ok(Expr::Literal(VBValue::Number(num)))
+            Ok(Expr::Literal(parse_numeric_literal(token)?))
         }
         TokenType::FloatLiteral => {
             let num: f64 = token.value.parse().map_err(|_| {
@@ -267,17 +320,17 @@ fn parse_primary(tokens: &[&Token], pos: &mut usize) -> Result<Expr, VBSError> {
             })?;
             Ok(Expr::Literal(VBValue::Number(num)))
         }
-        TokenType::StringLiteral => Ok(Expr::Literal(VBValue::String(token.value.clone()))),
+        TokenType::StringLiteral => Ok(Expr::Literal(VBValue::String((*token.value).into()))),
         TokenType::True => Ok(Expr::Literal(VBValue::Boolean(true))),
         TokenType::False => Ok(Expr::Literal(VBValue::Boolean(false))),
         TokenType::Null => Ok(Expr::Literal(VBValue::Null)),
         TokenType::Empty => Ok(Expr::Literal(VBValue::Empty)),
-        TokenType::Nothing => Ok(Expr::Literal(VBValue::Empty)),
+        TokenType::Nothing => Ok(Expr::Literal(VBValue::Nothing)),
         TokenType::DateLiteral => {
-            let dt = builtins::try_parse_date(&token.value).ok_or_else(|| {
+            let dt = builtins::parse_date_literal(&token.value).ok_or_else(|| {
                 VBSErrorType::RuntimeError.into_error(format!("Invalid date: {}", token.value))
             })?;
-            Ok(Expr::Literal(VBValue::Number(builtins::datetime_to_ole_auto(dt))))
+            Ok(Expr::Literal(VBValue::Date(builtins::datetime_to_ole_auto(dt))))
         }
         TokenType::New => {
             let class_name = advance(tokens, pos).ok_or_else(|| {
@@ -307,7 +360,7 @@ fn parse_primary(tokens: &[&Token], pos: &mut usize) -> Result<Expr, VBSError> {
                             return Err(VBSErrorType::SyntaxError
                                 .into_error("Unclosed parentheses in function call".to_string()));
                         }
-                        let arg = parse_binary(tokens, pos, 0)?;
+                        let arg = parse_argument(tokens, pos)?;
                         args.push(arg);
                         match peek(tokens, *pos) {
                             Some(t) if t.token_type == TokenType::Comma => {
@@ -342,28 +395,39 @@ fn parse_primary(tokens: &[&Token], pos: &mut usize) -> Result<Expr, VBSError> {
     }
 }
 
-fn parse_numeric_literal(token: &Token) -> Result<f64, VBSError> {
+/// Integer literals are Integer when they fit in 16 bits and Long when they
+/// fit in 32; `&H`/`&O` literals wrap as two's complement within that width.
+fn parse_numeric_literal(token: &Token) -> Result<VBValue, VBSError> {
+    let radix_literal = |digits: &str, radix: u32, kind: &str| {
+        let n = i64::from_str_radix(digits, radix).map_err(|_| {
+            VBSErrorType::ValueError.into_error(format!("Invalid {}: {}", kind, token.value))
+        })?;
+        Ok(if n <= 0xFFFF {
+            VBValue::Integer(n as u16 as i16)
+        } else if n <= 0xFFFF_FFFF {
+            VBValue::Long(n as u32 as i32)
+        } else {
+            VBValue::Number(n as f64)
+        })
+    };
     match token.token_type {
         TokenType::HexLiteral => {
             let hex = token
                 .value
                 .trim_start_matches("&H")
                 .trim_start_matches("&h");
-            i64::from_str_radix(hex, 16).map(|n| n as f64).map_err(|_| {
-                VBSErrorType::ValueError.into_error(format!("Invalid hex: {}", token.value))
-            })
+            radix_literal(hex, 16, "hex")
         }
         TokenType::OctLiteral => {
-            let oct = token.value.trim_start_matches('&');
-            i64::from_str_radix(oct, 8).map(|n| n as f64).map_err(|_| {
-                VBSErrorType::ValueError.into_error(format!("Invalid octal: {}", token.value))
-            })
+            let oct = token.value.trim_start_matches('&').trim_start_matches(['O', 'o']);
+            radix_literal(oct, 8, "octal")
         }
-        TokenType::IntegerLiteral | TokenType::FloatLiteral => {
-            token.value.parse::<f64>().map_err(|_| {
+        TokenType::IntegerLiteral => match token.value.parse::<i64>() {
+            Ok(n) => Ok(numeric::integer_literal(n)),
+            Err(_) => token.value.parse::<f64>().map(VBValue::Number).map_err(|_| {
                 VBSErrorType::ValueError.into_error(format!("Invalid number: {}", token.value))
-            })
-        }
+            }),
+        },
         _ => Err(VBSErrorType::ValueError.into_error("Not a number".to_string())),
     }
 }
@@ -458,7 +522,7 @@ fn parse_binary(tokens: &[&Token], pos: &mut usize, min_prec: u8) -> Result<Expr
                             return Err(VBSErrorType::SyntaxError
                                 .into_error("Unclosed parentheses in method call".to_string()));
                         }
-                        let arg = parse_binary(tokens, pos, 0)?;
+                        let arg = parse_argument(tokens, pos)?;
                         args.push(arg);
                         match peek(tokens, *pos) {
                             Some(t) if t.token_type == TokenType::Comma => {
@@ -523,27 +587,9 @@ fn evaluate_function_call(
     evaluated_args: Vec<VBValue>,
     context: &mut ExecutionContext,
 ) -> Result<VBValue, VBSError> {
-    if evaluated_args.len() == 1
-        && matches!(context.get_variable(name), Some(VBValue::Object(_)))
-    {
-        let mut obj_val = match context.get_variable_mut(name) {
-            Some(slot) => {
-                let mut replacement = VBValue::Empty;
-                std::mem::swap(slot, &mut replacement);
-                replacement
-            }
-            None => {
-                return Err(VBSErrorType::RuntimeError
-                    .into_error("Object variable disappeared during evaluation".to_string()))
-            }
-        };
-        match &mut obj_val {
-            VBValue::Object(ref mut obj) => {
-                let result = obj.indexed_get(&evaluated_args[0], context);
-                context.set_variable(name, obj_val);
-                return result;
-            }
-            _ => unreachable!(),
+    if evaluated_args.len() == 1 {
+        if let Some(VBValue::Object(obj)) = context.get_variable(name).cloned() {
+            return obj.indexed_get(&evaluated_args[0], context);
         }
     }
     if !evaluated_args.is_empty() {
@@ -551,29 +597,20 @@ fn evaluate_function_call(
             let flat_idx = if dims.is_empty() && evaluated_args.len() == 1 {
                 let idx = to_number(&evaluated_args[0]) as usize;
                 if idx >= items.len() {
-                    return Err(VBSErrorType::RuntimeError.into_error(format!(
-                        "Subscript out of range: index {} exceeds array size {}",
-                        idx,
-                        items.len()
-                    )));
+                    return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
                 }
                 idx
             } else if evaluated_args.len() == dims.len() {
                 let idx = value_utils::compute_flat_index(&evaluated_args, dims)
                     .ok_or_else(|| {
-                        VBSErrorType::RuntimeError.into_error("Subscript out of range".to_string())
+                        VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE)
                     })?;
                 if idx >= items.len() {
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error("Subscript out of range".to_string()));
+                    return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
                 }
                 idx
             } else {
-                return Err(VBSErrorType::RuntimeError.into_error(format!(
-                    "Array has {} dimensions but {} indices provided",
-                    dims.len(),
-                    evaluated_args.len()
-                )));
+                return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
             };
             return Ok(items[flat_idx].clone());
         }
@@ -582,8 +619,7 @@ fn evaluate_function_call(
         if func.is_function {
             return crate::vbscript::interpreter::execute_user_function_vm(&func, &evaluated_args, context);
         } else {
-            return Err(VBSErrorType::RuntimeError
-                .into_error(format!("Sub '{}' cannot be used as a function", name)));
+            return Err(VBSError::runtime_with(codes::TYPE_MISMATCH, name));
         }
     }
     crate::vbscript::builtins::call_builtin(name, evaluated_args)
@@ -607,18 +643,14 @@ fn evaluate_method_call(
             }
         }
     }
-    let mut obj_mut = obj_val;
-    match &mut obj_mut {
-        VBValue::Object(ref mut obj) => {
+    match &obj_val {
+        VBValue::Object(obj) => {
             let evaluated_args: Result<Vec<VBValue>, VBSError> =
                 args.iter().map(|arg| evaluate(arg, context)).collect();
             let evaluated_args = evaluated_args?;
             obj.call_method(method, &evaluated_args, context)
         }
-        _ => Err(VBSErrorType::RuntimeError.into_error(format!(
-            "Object doesn't support this property or method: '{}'",
-            method
-        ))),
+        _ => Err(VBSError::runtime_with(codes::OBJECT_REQUIRED, method)),
     }
 }
 
@@ -627,16 +659,14 @@ fn evaluate_new_object(
     context: &mut ExecutionContext,
 ) -> Result<VBValue, VBSError> {
     if let Some(class_def) = context.get_class(class_name) {
-        let instance = super::vbobject::ClassInstance::new(&class_def.name);
-        return Ok(VBValue::Object(Box::new(instance)));
+        let name = class_def.name.clone();
+        return super::vbobject::ClassInstance::create(&name, context).map(VBValue::Object);
     }
     match class_name.to_uppercase().as_str() {
-        "REGEXP" => Ok(VBValue::Object(Box::new(super::regexp::RegExpObject::new()))),
-        "DICTIONARY" => Ok(VBValue::Object(Box::new(super::vbobject::Dictionary::new()))),
-        "FILESYSTEMOBJECT" => Ok(VBValue::Object(Box::new(super::fso::FileSystemObject::new()))),
-        _ => Err(VBSErrorType::RuntimeError.into_error(format!(
-            "Class '{}' not defined", class_name
-        ))),
+        "REGEXP" => Ok(VBValue::Object(ObjectRef::new(super::regexp::RegExpObject::new()))),
+        "DICTIONARY" => Ok(VBValue::Object(ObjectRef::new(super::vbobject::Dictionary::new()))),
+        "FILESYSTEMOBJECT" => Ok(VBValue::Object(ObjectRef::new(super::fso::FileSystemObject::new()))),
+        _ => Err(VBSError::runtime_with(codes::CLASS_NOT_DEFINED, class_name)),
     }
 }
 
@@ -646,7 +676,7 @@ pub fn evaluate(expr: &Expr, context: &mut ExecutionContext) -> Result<VBValue,
         Expr::Variable(name) => {
             if name == "__with_obj__" {
                 return context.with_object.clone().ok_or_else(|| {
-                    VBSErrorType::RuntimeError.into_error("With object not set".to_string())
+                    VBSError::runtime(codes::OBJECT_VARIABLE_NOT_SET)
                 });
             }
             if let Some(val) = context.get_variable(name).cloned() {
@@ -655,12 +685,12 @@ pub fn evaluate(expr: &Expr, context: &mut ExecutionContext) -> Result<VBValue,
             // Fallback: try as a built-in function with no args (e.g. Rnd, Now, Timer)
             match crate::vbscript::builtins::call_builtin(name, Vec::new()) {
                 Ok(val) => Ok(val),
-                Err(_) => Err(VBSErrorType::RuntimeError
-                    .into_error(format!("Variable '{}' is not defined", name))),
+                Err(_) => Err(VBSError::runtime_with(codes::VARIABLE_UNDEFINED, name)),
             }
         }
+        Expr::Parenthesized(inner) => evaluate(inner, context),
         Expr::WithObject => context.with_object.clone().ok_or_else(|| {
-            VBSErrorType::RuntimeError.into_error("With object not set".to_string())
+            VBSError::runtime(codes::OBJECT_VARIABLE_NOT_SET)
         }),
         Expr::CaseComparison { op, rhs } => {
             let select_val = context.select_value.clone().ok_or_else(|| {
@@ -700,10 +730,7 @@ pub fn evaluate(expr: &Expr, context: &mut ExecutionContext) -> Result<VBValue,
             let obj_val = evaluate(object, context)?;
             match obj_val {
                 VBValue::Object(obj) => obj.get_property(property, context),
-                _ => Err(VBSErrorType::RuntimeError.into_error(format!(
-                    "Object doesn't support this property or method: '{}'",
-                    property
-                ))),
+                _ => Err(VBSError::runtime_with(codes::OBJECT_REQUIRED, property)),
             }
         }
         Expr::MethodCall { object, method, args } => {
@@ -721,65 +748,41 @@ fn to_bool(val: &VBValue) -> bool {
     match val {
         VBValue::Boolean(b) => *b,
         VBValue::Number(n) => *n != 0.0,
+        VBValue::Integer(_)
+        | VBValue::Long(_)
+        | VBValue::Byte(_)
+        | VBValue::Single(_)
+        | VBValue::Currency(_)
+        | VBValue::Date(_)
+        | VBValue::Decimal(_) => val.as_f64().is_some_and(|n| n != 0.0),
         VBValue::String(s) => !s.is_empty(),
-        VBValue::Null | VBValue::Empty => false,
+        VBValue::Null | VBValue::Empty | VBValue::Nothing => false,
         VBValue::Array(v, _) => !v.is_empty(),
         VBValue::Object(_) => true,
     }
 }
 
 fn to_string_val(val: &VBValue) -> String {
-    match val {
-        VBValue::String(s) => s.to_string(),
-        VBValue::Number(n) => n.to_string(),
-        VBValue::Boolean(true) => "True".to_string(),
-        VBValue::Boolean(false) => "False".to_string(),
-        VBValue::Null => "Null".to_string(),
-        VBValue::Empty => "".to_string(),
-        VBValue::Array(..) => "Array".to_string(),
-        VBValue::Object(_) => "Object".to_string(),
-    }
+    value_utils::to_arg_string(val)
 }
 
 fn negate(val: VBValue) -> Result<VBValue, VBSError> {
-    if matches!(val, VBValue::Array(..) | VBValue::Object(_)) {
-        return Err(VBSErrorType::ValueError.into_error("Type mismatch".to_string()));
-    }
     match val {
-        VBValue::Number(n) => Ok(VBValue::Number(-n)),
-        VBValue::Empty => Ok(VBValue::Number(-0.0)),
-        VBValue::Boolean(true) => Ok(VBValue::Number(1.0)),
-        VBValue::Boolean(false) => Ok(VBValue::Number(0.0)),
-        VBValue::Null => Ok(VBValue::Null),
-        VBValue::Array(..) => unreachable!(),
-        VBValue::Object(_) => unreachable!(),
-        VBValue::String(s) => {
-            if let Ok(n) = s.parse::<f64>() {
-                Ok(VBValue::Number(-n))
-            } else {
-                Ok(VBValue::Number(-0.0))
-            }
+        VBValue::Array(..) | VBValue::Object(_) | VBValue::Nothing => {
+            Err(VBSError::runtime(codes::TYPE_MISMATCH))
         }
+        VBValue::Null => Ok(VBValue::Null),
+        other => numeric::negate(&other),
     }
 }
 
 fn logical_not(val: VBValue) -> Result<VBValue, VBSError> {
-    if matches!(val, VBValue::Array(..) | VBValue::Object(_)) {
-        return Err(VBSErrorType::ValueError.into_error("Type mismatch".to_string()));
+    if matches!(val, VBValue::Array(..) | VBValue::Object(_) | VBValue::Nothing) {
+        return Err(VBSError::runtime(codes::TYPE_MISMATCH));
     }
     Ok(VBValue::Boolean(!to_bool(&val)))
 }
 
-fn checked_div(l: f64, r: f64, floor: bool) -> Result<VBValue, VBSError> {
-    if r == 0.0 {
-        Err(VBSErrorType::RuntimeError.into_error("Division by zero".to_string()))
-    } else if floor {
-        Ok(VBValue::Number((l / r).floor()))
-    } else {
-        Ok(VBValue::Number(l / r))
-    }
-}
-
 fn concat_str(left: &VBValue, right: &VBValue) -> String {
     match (left, right) {
         (VBValue::String(l), VBValue::String(r)) => {
@@ -800,40 +803,40 @@ fn concat_str(left: &VBValue, right: &VBValue) -> String {
 }
 
 fn cmp_result(left: &VBValue, right: &VBValue) -> std::cmp::Ordering {
-    let ln = to_number(left);
-    let rn = to_number(right);
+    let (ln, rn) = builtins::date_comparison_operands(left, right)
+        .unwrap_or_else(|| (to_number(left), to_number(right)));
     ln.partial_cmp(&rn).unwrap_or(std::cmp::Ordering::Equal)
 }
 
-fn bool_or_bitwise<B, I>(left: &VBValue, right: &VBValue, bool_op: B, int_op: I) -> VBValue
+fn bool_or_bitwise<B>(left: &VBValue, right: &VBValue, bool_op: B, int_op: fn(i64, i64) -> i64) -> Result<VBValue, VBSError>
 where
     B: Fn(bool, bool) -> bool,
-    I: Fn(i64, i64) -> i64,
 {
     match (left, right) {
-        (VBValue::Boolean(_), VBValue::Boolean(_)) => VBValue::Boolean(bool_op(to_bool(left), to_bool(right))),
-        _ => VBValue::Number(int_op(to_number(left) as i64, to_number(right) as i64) as f64),
+        (VBValue::Boolean(_), VBValue::Boolean(_)) => Ok(VBValue::Boolean(bool_op(to_bool(left), to_bool(right)))),
+        _ => numeric::bitwise(left, right, int_op),
     }
 }
 
 fn eval_binary(left: &VBValue, op: &BinOp, right: &VBValue) -> Result<VBValue, VBSError> {
-    if (matches!(left, VBValue::Array(..) | VBValue::Object(_))
-        || matches!(right, VBValue::Array(..) | VBValue::Object(_)))
+    if (matches!(left, VBValue::Array(..) | VBValue::Object(_) | VBValue::Nothing)
+        || matches!(right, VBValue::Array(..) | VBValue::Object(_) | VBValue::Nothing))
         && !matches!(op, BinOp::Is | BinOp::Eq | BinOp::Ne | BinOp::Concat)
     {
-        return Err(VBSErrorType::ValueError.into_error("Type mismatch".to_string()));
+        return Err(VBSError::runtime(codes::TYPE_MISMATCH));
     }
     match op {
         BinOp::Add => match (left, right) {
-            (VBValue::String(_), _) | (_, VBValue::String(_)) => Ok(VBValue::String(concat_str(left, right).into())),
-            _ => Ok(VBValue::Number(to_number(left) + to_number(right))),
+            (VBValue::String(_), VBValue::String(_) | VBValue::Empty | VBValue::Null)
+            | (VBValue::Empty | VBValue::Null, VBValue::String(_)) => Ok(VBValue::String(concat_str(left, right).into())),
+            _ => numeric::add(left, right),
         },
-        BinOp::Sub => Ok(VBValue::Number(to_number(left) - to_number(right))),
-        BinOp::Mul => Ok(VBValue::Number(to_number(left) * to_number(right))),
-        BinOp::Div => checked_div(to_number(left), to_number(right), false),
-        BinOp::IntDiv => checked_div(to_number(left), to_number(right), true),
-        BinOp::Pow => Ok(VBValue::Number(to_number(left).powf(to_number(right)))),
-        BinOp::Mod => Ok(VBValue::Number(to_number(left) % to_number(right))),
+        BinOp::Sub => numeric::sub(left, right),
+        BinOp::Mul => numeric::mul(left, right),
+        BinOp::Div => numeric::div(left, right),
+        BinOp::IntDiv => numeric::int_div(left, right),
+        BinOp::Pow => numeric::pow(left, right),
+        BinOp::Mod => numeric::modulo(left, right),
         BinOp::Concat => Ok(VBValue::String(concat_str(left, right).into())),
         BinOp::Eq => Ok(VBValue::Boolean(values_equal(left, right))),
         BinOp::Ne => Ok(VBValue::Boolean(!values_equal(left, right))),
@@ -841,17 +844,13 @@ fn eval_binary(left: &VBValue, op: &BinOp, right: &VBValue) -> Result<VBValue, V
         BinOp::Gt => Ok(VBValue::Boolean(cmp_result(left, right) == std::cmp::Ordering::Greater)),
         BinOp::Le => Ok(VBValue::Boolean(cmp_result(left, right) != std::cmp::Ordering::Greater)),
         BinOp::Ge => Ok(VBValue::Boolean(cmp_result(left, right) != std::cmp::Ordering::Less)),
-        BinOp::Is => Ok(VBValue::Boolean(values_equal(left, right))),
-        BinOp::And => Ok(bool_or_bitwise(left, right, |a, b| a && b, |a, b| a & b)),
-        BinOp::Or => Ok(bool_or_bitwise(left, right, |a, b| a || b, |a, b| a | b)),
-        BinOp::Xor => Ok(bool_or_bitwise(left, right, |a, b| a ^ b, |a, b| a ^ b)),
-        BinOp::Eqv => {
-            if matches!(left, VBValue::Boolean(_)) && matches!(right, VBValue::Boolean(_)) {
-                Ok(VBValue::Boolean(to_bool(left) == to_bool(right)))
-            } else {
-                Ok(VBValue::Number(!(to_number(left) as i64 ^ to_number(right) as i64) as f64))
-            }
-        }
+        BinOp::Is => left.is_same_object(right).map(VBValue::Boolean).ok_or_else(|| {
+            VBSError::runtime(codes::OBJECT_REQUIRED)
+        }),
+        BinOp::And => bool_or_bitwise(left, right, |a, b| a && b, |a, b| a & b),
+        BinOp::Or => bool_or_bitwise(left, right, |a, b| a || b, |a, b| a | b),
+        BinOp::Xor => bool_or_bitwise(left, right, |a, b| a ^ b, |a, b| a ^ b),
+        BinOp::Eqv => bool_or_bitwise(left, right, |a, b| a == b, |a, b| !(a ^ b)),
         BinOp::Imp => Ok(VBValue::Boolean(!to_bool(left) || to_bool(right))),
         BinOp::Like => Ok(VBValue::Boolean(like_match(&to_string_val(left), &to_string_val(right)))),
     }
@@ -859,13 +858,18 @@ fn eval_binary(left: &VBValue, op: &BinOp, right: &VBValue) -> Result<VBValue, V
 
 fn values_equal(left: &VBValue, right: &VBValue) -> bool {
     match (left, right) {
-        (VBValue::Number(a), VBValue::Number(b)) => (a - b).abs() < f64::EPSILON,
+        (a, b) if a.is_number() && b.is_number() => a == b,
+        (VBValue::Date(a), VBValue::Date(b)) => a == b,
+        (VBValue::Date(_), VBValue::String(_)) | (VBValue::String(_), VBValue::Date(_)) => {
+            builtins::date_comparison_operands(left, right).is_some_and(|(x, y)| x == y)
+        }
         (VBValue::String(a), VBValue::String(b)) => a == b,
         (VBValue::Boolean(a), VBValue::Boolean(b)) => a == b,
         (VBValue::Null, VBValue::Null) => true,
         (VBValue::Empty, VBValue::Empty) => true,
         (VBValue::Array(..), _) | (_, VBValue::Array(..)) => false,
         (VBValue::Object(_), _) | (_, VBValue::Object(_)) => false,
+        (VBValue::Nothing, _) | (_, VBValue::Nothing) => false,
         _ => to_string_val(left) == to_string_val(right),
     }
 }
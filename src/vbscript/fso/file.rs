@@ -1,19 +1,20 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 use super::super::execution_context::ExecutionContext;
 use super::super::textstream::TextStream;
 use super::super::value::VBValue;
 use super::super::value_utils;
-use super::super::vbs_error::{VBSError, VBSErrorType};
-use super::{format_datetime, infer_type};
-use crate::vbscript::vbobject::VBScriptObject;
+use super::super::vbs_error::{codes, VBSError};
+use super::{format_datetime, infer_type, io_error};
+use crate::vbscript::vbobject::{ObjectRef, VBScriptObject};
 use crate::{prop_not_found, method_not_found, cannot_set_property};
 
 #[derive(Debug)]
 pub struct FileObject {
-    path: PathBuf,
-    name: String,
+    path: Mutex<PathBuf>,
+    name: Mutex<String>,
     short_name: String,
     size: u64,
     date_created: String,
@@ -32,12 +33,13 @@ impl FileObject {
         let accessed = meta.as_ref().and_then(|m| m.accessed().ok());
 
         FileObject {
-            path: path.to_path_buf(),
-            name: path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("")
-                .to_string(),
+            path: Mutex::new(path.to_path_buf()),
+            name: Mutex::new(
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("")
+                    .to_string(),
+            ),
             short_name: path
                 .file_name()
                 .and_then(|n| n.to_str())
@@ -55,6 +57,14 @@ impl FileObject {
                 .to_string(),
         }
     }
+
+    fn path(&self) -> PathBuf {
+        self.path.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn name(&self) -> String {
+        self.name.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
 }
 
 impl VBScriptObject for FileObject {
@@ -62,29 +72,15 @@ impl VBScriptObject for FileObject {
         "File"
     }
 
-    fn clone_box(&self) -> Box<dyn VBScriptObject> {
-        Box::new(FileObject {
-            path: self.path.clone(),
-            name: self.name.clone(),
-            short_name: self.short_name.clone(),
-            size: self.size,
-            date_created: self.date_created.clone(),
-            date_last_modified: self.date_last_modified.clone(),
-            date_last_accessed: self.date_last_accessed.clone(),
-            file_type: self.file_type.clone(),
-            parent_folder: self.parent_folder.clone(),
-        })
-    }
-
     fn get_property(
         &self,
         name: &str,
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         match name.to_uppercase().as_str() {
-            "NAME" => Ok(VBValue::String(self.name.clone().into())),
+            "NAME" => Ok(VBValue::String(self.name().into())),
             "SHORTPATH" | "PATH" => Ok(VBValue::String(
-                self.path.to_str().unwrap_or("").to_string().into(),
+                self.path().to_str().unwrap_or("").to_string().into(),
             )),
             "SHORTNAME" => Ok(VBValue::String(self.short_name.clone().into())),
             "SIZE" => Ok(VBValue::Number(self.size as f64)),
@@ -94,7 +90,7 @@ impl VBScriptObject for FileObject {
             "DATELASTACCESSED" => Ok(VBValue::String(self.date_last_accessed.clone().into())),
             "PARENTFOLDER" => Ok(VBValue::String(self.parent_folder.clone().into())),
             "ATTRIBUTES" => {
-                let attrs = fs::metadata(&self.path)
+                let attrs = fs::metadata(self.path())
                     .map(|m| {
                         let mut a = 0i32;
                         if m.permissions().readonly() {
@@ -113,7 +109,7 @@ impl VBScriptObject for FileObject {
     }
 
     fn set_property(
-        &mut self,
+        &self,
         name: &str,
         value: VBValue,
         _context: &mut ExecutionContext,
@@ -121,12 +117,10 @@ impl VBScriptObject for FileObject {
         match name.to_uppercase().as_str() {
             "NAME" => {
                 let new_name = value_utils::to_arg_string(&value);
-                let new_path = self.path.with_file_name(&new_name);
-                fs::rename(&self.path, &new_path).map_err(|e| {
-                    VBSErrorType::RuntimeError.into_error(format!("Cannot rename file: {}", e))
-                })?;
-                self.path = new_path;
-                self.name = new_name;
+                let new_path = self.path().with_file_name(&new_name);
+                fs::rename(self.path(), &new_path).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
+                *self.path.lock().unwrap_or_else(|e| e.into_inner()) = new_path;
+                *self.name.lock().unwrap_or_else(|e| e.into_inner()) = new_name;
                 Ok(())
             }
             _ => cannot_set_property!("File", name),
@@ -134,7 +128,7 @@ impl VBScriptObject for FileObject {
     }
 
     fn call_method(
-        &mut self,
+        &self,
         name: &str,
         args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -147,13 +141,9 @@ impl VBScriptObject for FileObject {
                     false
                 };
                 if force {
-                    fs::remove_file(&self.path).map_err(|e| {
-                        VBSErrorType::RuntimeError.into_error(format!("Cannot delete file: {}", e))
-                    })?;
+                    fs::remove_file(self.path()).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
                 } else {
-                    fs::remove_file(&self.path).map_err(|e| {
-                        VBSErrorType::RuntimeError.into_error(format!("Cannot delete file: {}", e))
-                    })?;
+                    fs::remove_file(self.path()).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
                 }
                 Ok(VBValue::Empty)
             }
@@ -164,23 +154,20 @@ impl VBScriptObject for FileObject {
                     1
                 };
                 let file = match iomode {
-                    1 => fs::File::open(&self.path),
-                    2 => fs::File::create(&self.path),
-                    8 => fs::OpenOptions::new().append(true).open(&self.path),
+                    1 => fs::File::open(self.path()),
+                    2 => fs::File::create(self.path()),
+                    8 => fs::OpenOptions::new().append(true).open(self.path()),
                     _ => {
-                        return Err(VBSErrorType::RuntimeError
-                            .into_error(format!("Invalid IOMode: {}", iomode)))
+                        return Err(VBSError::runtime(codes::INVALID_PROCEDURE_CALL))
                     }
                 }
-                .map_err(|e| {
-                    VBSErrorType::RuntimeError.into_error(format!("Cannot open file: {}", e))
-                })?;
+                .map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
                 let ts = match iomode {
                     1 => TextStream::new_read(file),
                     2 => TextStream::new_write(file),
                     _ => TextStream::new_append(file),
                 };
-                Ok(VBValue::Object(Box::new(ts)))
+                Ok(VBValue::Object(ObjectRef::new(ts)))
             }
             _ => method_not_found!("File", name),
         }
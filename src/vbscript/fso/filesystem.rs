@@ -4,10 +4,10 @@ use super::super::execution_context::ExecutionContext;
 use super::super::textstream::TextStream;
 use super::super::value::VBValue;
 use super::super::value_utils;
-use super::super::vbs_error::{VBSError, VBSErrorType};
-use super::{copy_dir_recursive, resolve_path};
+use super::super::vbs_error::{codes, VBSError};
+use super::{copy_dir_recursive, io_error, resolve_path};
 use super::{FileObject, FolderObject};
-use crate::vbscript::vbobject::VBScriptObject;
+use crate::vbscript::vbobject::{ObjectRef, VBScriptObject};
 use crate::{prop_not_found, method_not_found};
 
 #[derive(Debug, Clone, Default)]
@@ -24,10 +24,6 @@ impl VBScriptObject for FileSystemObject {
         "FileSystemObject"
     }
 
-    fn clone_box(&self) -> Box<dyn VBScriptObject> {
-        Box::new(self.clone())
-    }
-
     fn get_property(
         &self,
         name: &str,
@@ -60,7 +56,7 @@ impl VBScriptObject for FileSystemObject {
     }
 
     fn call_method(
-        &mut self,
+        &self,
         name: &str,
         args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -80,22 +76,19 @@ impl VBScriptObject for FileSystemObject {
                 let path = value_utils::to_arg_string(&args[0]);
                 let p = resolve_path(&path);
                 if !p.is_file() {
-                    return Err(
-                        VBSErrorType::RuntimeError.into_error(format!("File not found: {}", path))
-                    );
+                    return Err(VBSError::runtime(codes::FILE_NOT_FOUND));
                 }
                 let file = FileObject::new(&p);
-                Ok(VBValue::Object(Box::new(file)))
+                Ok(VBValue::Object(ObjectRef::new(file)))
             }
             "GETFOLDER" => {
                 let path = value_utils::to_arg_string(&args[0]);
                 let p = resolve_path(&path);
                 if !p.is_dir() {
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error(format!("Folder not found: {}", path)));
+                    return Err(VBSError::runtime(codes::PATH_NOT_FOUND));
                 }
                 let folder = FolderObject::new(&p);
-                Ok(VBValue::Object(Box::new(folder)))
+                Ok(VBValue::Object(ObjectRef::new(folder)))
             }
             "CREATETEXTFILE" => {
                 let path = value_utils::to_arg_string(&args[0]);
@@ -106,17 +99,13 @@ impl VBScriptObject for FileSystemObject {
                 };
                 let p = resolve_path(&path);
                 if p.exists() && !overwrite {
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error(format!("File already exists: {}", path)));
+                    return Err(VBSError::runtime(codes::FILE_ALREADY_EXISTS));
                 }
                 if let Some(parent) = p.parent() {
                     fs::create_dir_all(parent).unwrap_or(());
                 }
-                let file = fs::File::create(&p).map_err(|e| {
-                    VBSErrorType::RuntimeError
-                        .into_error(format!("Cannot create file '{}': {}", path, e))
-                })?;
-                Ok(VBValue::Object(Box::new(TextStream::new_write(file))))
+                let file = fs::File::create(&p).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
+                Ok(VBValue::Object(ObjectRef::new(TextStream::new_write(file))))
             }
             "OPENTEXTFILE" => {
                 let path = value_utils::to_arg_string(&args[0]);
@@ -136,52 +125,37 @@ impl VBScriptObject for FileSystemObject {
                         if let Some(parent) = p.parent() {
                             fs::create_dir_all(parent).unwrap_or(());
                         }
-                        let file = fs::File::create(&p).map_err(|e| {
-                            VBSErrorType::RuntimeError
-                                .into_error(format!("Cannot create file '{}': {}", path, e))
-                        })?;
+                        let file = fs::File::create(&p).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
                         if iomode == 1 {
                             drop(file);
-                            let file2 = fs::File::open(&p).map_err(|_| {
-                                VBSErrorType::RuntimeError
-                                    .into_error(format!("Cannot open file: {}", path))
-                            })?;
-                            Ok(VBValue::Object(Box::new(TextStream::new_read(file2))))
+                            let file2 = fs::File::open(&p).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
+                            Ok(VBValue::Object(ObjectRef::new(TextStream::new_read(file2))))
                         } else if iomode == 2 {
-                            Ok(VBValue::Object(Box::new(TextStream::new_write(file))))
+                            Ok(VBValue::Object(ObjectRef::new(TextStream::new_write(file))))
                         } else {
-                            Ok(VBValue::Object(Box::new(TextStream::new_append(file))))
+                            Ok(VBValue::Object(ObjectRef::new(TextStream::new_append(file))))
                         }
                     } else {
-                        Err(VBSErrorType::RuntimeError
-                            .into_error(format!("File not found: {}", path)))
+                        Err(VBSError::runtime(codes::FILE_NOT_FOUND))
                     }
                 } else {
                     match iomode {
                         1 => {
-                            let file = fs::File::open(&p).map_err(|_| {
-                                VBSErrorType::RuntimeError
-                                    .into_error(format!("Cannot open file: {}", path))
-                            })?;
-                            Ok(VBValue::Object(Box::new(TextStream::new_read(file))))
+                            let file = fs::File::open(&p).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
+                            Ok(VBValue::Object(ObjectRef::new(TextStream::new_read(file))))
                         }
                         2 => {
-                            let file = fs::File::create(&p).map_err(|_| {
-                                VBSErrorType::RuntimeError
-                                    .into_error(format!("Cannot open file: {}", path))
-                            })?;
-                            Ok(VBValue::Object(Box::new(TextStream::new_write(file))))
+                            let file = fs::File::create(&p).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
+                            Ok(VBValue::Object(ObjectRef::new(TextStream::new_write(file))))
                         }
                         8 => {
-                            let file =
-                                fs::OpenOptions::new().append(true).open(&p).map_err(|_| {
-                                    VBSErrorType::RuntimeError
-                                        .into_error(format!("Cannot open file: {}", path))
-                                })?;
-                            Ok(VBValue::Object(Box::new(TextStream::new_append(file))))
+                            let file = fs::OpenOptions::new()
+                                .append(true)
+                                .open(&p)
+                                .map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
+                            Ok(VBValue::Object(ObjectRef::new(TextStream::new_append(file))))
                         }
-                        _ => Err(VBSErrorType::RuntimeError
-                            .into_error(format!("Invalid IOMode: {}", iomode))),
+                        _ => Err(VBSError::runtime(codes::INVALID_PROCEDURE_CALL)),
                     }
                 }
             }
@@ -194,14 +168,9 @@ impl VBScriptObject for FileSystemObject {
                 };
                 let p = resolve_path(&path);
                 if p.is_file() {
-                    fs::remove_file(&p).map_err(|e| {
-                        VBSErrorType::RuntimeError
-                            .into_error(format!("Cannot delete file '{}': {}", path, e))
-                    })?;
+                    fs::remove_file(&p).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
                 } else if !force {
-                    return Err(
-                        VBSErrorType::RuntimeError.into_error(format!("File not found: {}", path))
-                    );
+                    return Err(VBSError::runtime(codes::FILE_NOT_FOUND));
                 }
                 Ok(VBValue::Empty)
             }
@@ -215,19 +184,12 @@ impl VBScriptObject for FileSystemObject {
                 let p = resolve_path(&path);
                 if p.is_dir() {
                     if force {
-                        fs::remove_dir_all(&p).map_err(|e| {
-                            VBSErrorType::RuntimeError
-                                .into_error(format!("Cannot delete folder '{}': {}", path, e))
-                        })?;
+                        fs::remove_dir_all(&p).map_err(|e| io_error(e, codes::PATH_NOT_FOUND))?;
                     } else {
-                        fs::remove_dir(&p).map_err(|e| {
-                            VBSErrorType::RuntimeError
-                                .into_error(format!("Cannot delete folder '{}': {}", path, e))
-                        })?;
+                        fs::remove_dir(&p).map_err(|e| io_error(e, codes::PATH_NOT_FOUND))?;
                     }
                 } else if !force {
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error(format!("Folder not found: {}", path)));
+                    return Err(VBSError::runtime(codes::PATH_NOT_FOUND));
                 }
                 Ok(VBValue::Empty)
             }
@@ -242,18 +204,12 @@ impl VBScriptObject for FileSystemObject {
                 let src = resolve_path(&source);
                 let dst = resolve_path(&dest);
                 if dst.exists() && !overwrite {
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error(format!("Destination file already exists: {}", dest)));
+                    return Err(VBSError::runtime(codes::FILE_ALREADY_EXISTS));
                 }
                 if let Some(parent) = dst.parent() {
                     fs::create_dir_all(parent).unwrap_or(());
                 }
-                fs::copy(&src, &dst).map_err(|e| {
-                    VBSErrorType::RuntimeError.into_error(format!(
-                        "Cannot copy file '{}' to '{}': {}",
-                        source, dest, e
-                    ))
-                })?;
+                fs::copy(&src, &dst).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
                 Ok(VBValue::Empty)
             }
             "COPYFOLDER" => {
@@ -267,15 +223,9 @@ impl VBScriptObject for FileSystemObject {
                 let src = resolve_path(&source);
                 let dst = resolve_path(&dest);
                 if dst.exists() && !overwrite {
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error(format!("Destination folder already exists: {}", dest)));
+                    return Err(VBSError::runtime(codes::FILE_ALREADY_EXISTS));
                 }
-                copy_dir_recursive(&src, &dst).map_err(|e| {
-                    VBSErrorType::RuntimeError.into_error(format!(
-                        "Cannot copy folder '{}' to '{}': {}",
-                        source, dest, e
-                    ))
-                })?;
+                copy_dir_recursive(&src, &dst).map_err(|e| io_error(e, codes::PATH_NOT_FOUND))?;
                 Ok(VBValue::Empty)
             }
             "MOVEFILE" => {
@@ -286,12 +236,7 @@ impl VBScriptObject for FileSystemObject {
                 if let Some(parent) = dst.parent() {
                     fs::create_dir_all(parent).unwrap_or(());
                 }
-                fs::rename(&src, &dst).map_err(|e| {
-                    VBSErrorType::RuntimeError.into_error(format!(
-                        "Cannot move file '{}' to '{}': {}",
-                        source, dest, e
-                    ))
-                })?;
+                fs::rename(&src, &dst).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
                 Ok(VBValue::Empty)
             }
             "MOVEFOLDER" => {
@@ -302,21 +247,13 @@ impl VBScriptObject for FileSystemObject {
                 if let Some(parent) = dst.parent() {
                     fs::create_dir_all(parent).unwrap_or(());
                 }
-                fs::rename(&src, &dst).map_err(|e| {
-                    VBSErrorType::RuntimeError.into_error(format!(
-                        "Cannot move folder '{}' to '{}': {}",
-                        source, dest, e
-                    ))
-                })?;
+                fs::rename(&src, &dst).map_err(|e| io_error(e, codes::PATH_NOT_FOUND))?;
                 Ok(VBValue::Empty)
             }
             "CREATEFOLDER" => {
                 let path = value_utils::to_arg_string(&args[0]);
                 let p = resolve_path(&path);
-                fs::create_dir_all(&p).map_err(|e| {
-                    VBSErrorType::RuntimeError
-                        .into_error(format!("Cannot create folder '{}': {}", path, e))
-                })?;
+                fs::create_dir_all(&p).map_err(|e| io_error(e, codes::PATH_NOT_FOUND))?;
                 Ok(VBValue::Empty)
             }
             "GETPARENTFOLDERNAME" => {
@@ -396,8 +333,7 @@ impl VBScriptObject for FileSystemObject {
                     2 => Ok(VBValue::String(
                         std::env::temp_dir().to_str().unwrap_or("/tmp").to_string().into(),
                     )),
-                    _ => Err(VBSErrorType::RuntimeError
-                        .into_error(format!("Invalid SpecialFolder constant: {}", folder_type))),
+                    _ => Err(VBSError::runtime(codes::INVALID_PROCEDURE_CALL)),
                 }
             }
             _ => method_not_found!("FileSystemObject", name),
@@ -1,19 +1,20 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 use super::super::execution_context::ExecutionContext;
 use super::super::textstream::TextStream;
 use super::super::value::VBValue;
 use super::super::value_utils;
-use super::super::vbs_error::{VBSError, VBSErrorType};
-use super::{format_datetime, FileObject};
-use crate::vbscript::vbobject::VBScriptObject;
+use super::super::vbs_error::{codes, VBSError};
+use super::{format_datetime, io_error, FileObject};
+use crate::vbscript::vbobject::{ObjectRef, VBScriptObject};
 use crate::{prop_not_found, method_not_found, cannot_set_property};
 
 #[derive(Debug)]
 pub struct FolderObject {
-    path: PathBuf,
-    name: String,
+    path: Mutex<PathBuf>,
+    name: Mutex<String>,
     size: u64,
     date_created: String,
     date_last_modified: String,
@@ -37,8 +38,8 @@ impl FolderObject {
             .unwrap_or_else(|| path.to_str().unwrap_or("").to_string());
 
         FolderObject {
-            path: path.to_path_buf(),
-            name,
+            path: Mutex::new(path.to_path_buf()),
+            name: Mutex::new(name),
             size,
             date_created: format_datetime(created),
             date_last_modified: format_datetime(modified),
@@ -51,6 +52,14 @@ impl FolderObject {
                 .to_string(),
         }
     }
+
+    fn path(&self) -> PathBuf {
+        self.path.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn name(&self) -> String {
+        self.name.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
 }
 
 impl VBScriptObject for FolderObject {
@@ -58,30 +67,17 @@ impl VBScriptObject for FolderObject {
         "Folder"
     }
 
-    fn clone_box(&self) -> Box<dyn VBScriptObject> {
-        Box::new(FolderObject {
-            path: self.path.clone(),
-            name: self.name.clone(),
-            size: self.size,
-            date_created: self.date_created.clone(),
-            date_last_modified: self.date_last_modified.clone(),
-            date_last_accessed: self.date_last_accessed.clone(),
-            is_root: self.is_root,
-            parent_folder: self.parent_folder.clone(),
-        })
-    }
-
     fn get_property(
         &self,
         name: &str,
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         match name.to_uppercase().as_str() {
-            "NAME" => Ok(VBValue::String(self.name.clone().into())),
+            "NAME" => Ok(VBValue::String(self.name().into())),
             "SHORTPATH" | "PATH" => Ok(VBValue::String(
-                self.path.to_str().unwrap_or("").to_string().into(),
+                self.path().to_str().unwrap_or("").to_string().into(),
             )),
-            "SHORTNAME" => Ok(VBValue::String(self.name.clone().into())),
+            "SHORTNAME" => Ok(VBValue::String(self.name().into())),
             "SIZE" => Ok(VBValue::Number(self.size as f64)),
             "TYPE" => Ok(VBValue::String("File Folder".into())),
             "DATECREATED" => Ok(VBValue::String(self.date_created.clone().into())),
@@ -90,22 +86,22 @@ impl VBScriptObject for FolderObject {
             "ISROOTFOLDER" => Ok(VBValue::Boolean(self.is_root)),
             "PARENTFOLDER" => Ok(VBValue::String(self.parent_folder.clone().into())),
             "FILES" => {
-                let entries: Vec<VBValue> = match fs::read_dir(&self.path) {
+                let entries: Vec<VBValue> = match fs::read_dir(self.path()) {
                     Ok(dir) => dir
                         .filter_map(|e| e.ok())
                         .filter(|e| e.path().is_file())
-                        .map(|e| VBValue::Object(Box::new(FileObject::new(&e.path()))))
+                        .map(|e| VBValue::Object(ObjectRef::new(FileObject::new(&e.path()))))
                         .collect(),
                     Err(_) => vec![],
                 };
                 Ok(VBValue::Array(std::sync::Arc::new(entries), vec![]))
             }
             "SUBFOLDERS" => {
-                let entries: Vec<VBValue> = match fs::read_dir(&self.path) {
+                let entries: Vec<VBValue> = match fs::read_dir(self.path()) {
                     Ok(dir) => dir
                         .filter_map(|e| e.ok())
                         .filter(|e| e.path().is_dir())
-                        .map(|e| VBValue::Object(Box::new(FolderObject::new(&e.path()))))
+                        .map(|e| VBValue::Object(ObjectRef::new(FolderObject::new(&e.path()))))
                         .collect(),
                     Err(_) => vec![],
                 };
@@ -120,7 +116,7 @@ impl VBScriptObject for FolderObject {
     }
 
     fn set_property(
-        &mut self,
+        &self,
         name: &str,
         value: VBValue,
         _context: &mut ExecutionContext,
@@ -128,12 +124,10 @@ impl VBScriptObject for FolderObject {
         match name.to_uppercase().as_str() {
             "NAME" => {
                 let new_name = value_utils::to_arg_string(&value);
-                let new_path = self.path.with_file_name(&new_name);
-                fs::rename(&self.path, &new_path).map_err(|e| {
-                    VBSErrorType::RuntimeError.into_error(format!("Cannot rename folder: {}", e))
-                })?;
-                self.path = new_path;
-                self.name = new_name;
+                let new_path = self.path().with_file_name(&new_name);
+                fs::rename(self.path(), &new_path).map_err(|e| io_error(e, codes::PATH_NOT_FOUND))?;
+                *self.path.lock().unwrap_or_else(|e| e.into_inner()) = new_path;
+                *self.name.lock().unwrap_or_else(|e| e.into_inner()) = new_name;
                 Ok(())
             }
             _ => cannot_set_property!("Folder", name),
@@ -141,7 +135,7 @@ impl VBScriptObject for FolderObject {
     }
 
     fn call_method(
-        &mut self,
+        &self,
         name: &str,
         args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -154,15 +148,9 @@ impl VBScriptObject for FolderObject {
                     false
                 };
                 if force {
-                    fs::remove_dir_all(&self.path).map_err(|e| {
-                        VBSErrorType::RuntimeError
-                            .into_error(format!("Cannot delete folder: {}", e))
-                    })?;
+                    fs::remove_dir_all(self.path()).map_err(|e| io_error(e, codes::PATH_NOT_FOUND))?;
                 } else {
-                    fs::remove_dir(&self.path).map_err(|e| {
-                        VBSErrorType::RuntimeError
-                            .into_error(format!("Cannot delete folder: {}", e))
-                    })?;
+                    fs::remove_dir(self.path()).map_err(|e| io_error(e, codes::PATH_NOT_FOUND))?;
                 }
                 Ok(VBValue::Empty)
             }
@@ -173,15 +161,12 @@ impl VBScriptObject for FolderObject {
                 } else {
                     false
                 };
-                let file_path = self.path.join(&path);
+                let file_path = self.path().join(&path);
                 if file_path.exists() && !overwrite {
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error(format!("File already exists: {}", file_path.display())));
+                    return Err(VBSError::runtime(codes::FILE_ALREADY_EXISTS));
                 }
-                let file = fs::File::create(&file_path).map_err(|e| {
-                    VBSErrorType::RuntimeError.into_error(format!("Cannot create file: {}", e))
-                })?;
-                Ok(VBValue::Object(Box::new(TextStream::new_write(file))))
+                let file = fs::File::create(&file_path).map_err(|e| io_error(e, codes::FILE_NOT_FOUND))?;
+                Ok(VBValue::Object(ObjectRef::new(TextStream::new_write(file))))
             }
             _ => method_not_found!("Folder", name),
         }
@@ -6,6 +6,8 @@ mod file;
 mod filesystem;
 mod folder;
 
+use super::vbs_error::{codes, VBSError};
+
 fn resolve_path(path: &str) -> std::path::PathBuf {
     let p = std::path::Path::new(path);
     if p.is_absolute() {
@@ -16,6 +18,18 @@ fn resolve_path(path: &str) -> std::path::PathBuf {
     }
 }
 
+/// The runtime error for a failed file system call; `not_found` is 53 (File
+/// not found) or 76 (Path not found) depending on what the call looked for.
+fn io_error(e: std::io::Error, not_found: i32) -> VBSError {
+    let code = match e.kind() {
+        std::io::ErrorKind::NotFound => not_found,
+        std::io::ErrorKind::PermissionDenied => codes::PERMISSION_DENIED,
+        std::io::ErrorKind::AlreadyExists => codes::FILE_ALREADY_EXISTS,
+        _ => codes::PATH_FILE_ACCESS_ERROR,
+    };
+    VBSError::runtime(code)
+}
+
 fn format_datetime(t: Option<std::time::SystemTime>) -> String {
     match t {
         Some(time) => {
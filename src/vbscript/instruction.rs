@@ -1,9 +1,45 @@
 use std::fmt;
 
+use super::symbol::Symbol;
+
 type ConstantIdx = u32;
 type LocalSlot = usize;
+/// Index into the compiled script's [`GlobalNames`](crate::vbscript::compiler::GlobalNames).
+type GlobalSlot = u32;
 type CodeOffset = i32;
 
+/// Variable assigned by a loop or `ReDim`: a local of the current frame,
+/// or a global when the statement is at page level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VarSlot {
+    Local(LocalSlot),
+    Global(GlobalSlot),
+}
+
+impl fmt::Display for VarSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VarSlot::Local(s) => write!(f, "{}", s),
+            VarSlot::Global(g) => write!(f, "g{}", g),
+        }
+    }
+}
+
+/// Extra condition checked by [`Instruction::ByRefSkip`] before an argument
+/// is written back, so that expressions which only look like variables
+/// (e.g. a builtin called without parentheses, `dict(key)`) are left alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ByRefGuard {
+    /// No extra condition (local variables, object properties).
+    None,
+    /// The global must exist.
+    Global(GlobalSlot),
+    /// The local must hold an array.
+    LocalArray(LocalSlot),
+    /// The global must hold an array.
+    GlobalArray(GlobalSlot),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     // -- Constants --
@@ -16,8 +52,11 @@ pub enum Instruction {
     // -- Variables --
     LoadLocal(LocalSlot),
     StoreLocal(LocalSlot),
-    LoadGlobal(ConstantIdx),
-    StoreGlobal(ConstantIdx),
+    LoadGlobal(GlobalSlot),
+    StoreGlobal(GlobalSlot),
+    /// `Option Explicit`: raise error 500 unless the named global exists.
+    /// The second operand is the script line, for the error message.
+    CheckDeclared(ConstantIdx, u32),
 
     // -- Unary --
     Neg,
@@ -53,29 +92,34 @@ pub enum Instruction {
     Eqv,
 
     // -- Objects --
-    GetProp(ConstantIdx),
-    SetProp(ConstantIdx),
-    SetPropLocal(LocalSlot, ConstantIdx),
-    SetPropGlobal(ConstantIdx, ConstantIdx),
-    CallMethod(ConstantIdx, u8),
-    CallMethodLocal(LocalSlot, ConstantIdx, u8),
-    CallMethodGlobal(ConstantIdx, ConstantIdx, u8),
+    GetProp(Symbol),
+    SetProp(Symbol),
+    SetPropLocal(LocalSlot, Symbol),
+    SetPropGlobal(GlobalSlot, Symbol),
+    CallMethod(Symbol, u8),
+    CallMethodLocal(LocalSlot, Symbol, u8),
+    CallMethodGlobal(GlobalSlot, Symbol, u8),
     IndexGet,
     IndexSet,
     NewObject(ConstantIdx),
 
     // -- Arrays --
     NewArray(u8),
-    ReDim(LocalSlot, u8, bool),
+    ReDim(VarSlot, u8, bool),
     IndexStoreLocal(LocalSlot),
-    IndexStoreGlobal(ConstantIdx),
+    IndexStoreGlobal(GlobalSlot),
     IndexStoreLocalMulti(LocalSlot, u8),
-    IndexStoreGlobalMulti(ConstantIdx, u8),
+    IndexStoreGlobalMulti(GlobalSlot, u8),
 
     // -- Functions --
     Call(ConstantIdx, u8),
     CallLocal(LocalSlot, u8),
     Return(u8),
+    /// Skip the write-back of argument `n` of the preceding call unless the
+    /// callee bound it `ByRef` and the guard holds.
+    ByRefSkip(u8, ByRefGuard, CodeOffset),
+    /// Push the final value of `ByRef` argument `n` of the preceding call.
+    LoadByRef(u8),
 
     // -- Control flow --
     Jump(CodeOffset),
@@ -83,10 +127,10 @@ pub enum Instruction {
     JumpIfTrue(CodeOffset),
 
     // -- Loops --
-    ForPrep(LocalSlot, CodeOffset),
-    ForStep(LocalSlot, CodeOffset),
-    ForEachPrep(LocalSlot, CodeOffset),
-    ForEachStep(LocalSlot, CodeOffset),
+    ForPrep(VarSlot, CodeOffset),
+    ForStep(VarSlot, CodeOffset),
+    ForEachPrep(VarSlot, CodeOffset),
+    ForEachStep(VarSlot, CodeOffset),
 
     // -- Exit signals --
     ExitFor,
@@ -118,7 +162,65 @@ pub enum Instruction {
 
     // -- Variable management --
     Erase(LocalSlot),
-    EraseGlobal(ConstantIdx),
+    EraseGlobal(GlobalSlot),
+
+    // -- Superinstructions (emitted by the optimizer) --
+    /// `LoadLocal s; LoadConst c; Add; StoreLocal s`
+    AddLocalConst(LocalSlot, ConstantIdx),
+    /// `LoadLocal s; LoadConst c; Concat; StoreLocal s`
+    ConcatLocalConst(LocalSlot, ConstantIdx),
+    /// `LoadConst c; StoreLocal s`
+    StoreLocalConst(LocalSlot, ConstantIdx),
+    /// `LoadGlobal g; LoadConst c; Add; StoreGlobal g`
+    AddGlobalConst(GlobalSlot, ConstantIdx),
+    /// `LoadGlobal g; LoadConst c; Concat; StoreGlobal g`
+    ConcatGlobalConst(GlobalSlot, ConstantIdx),
+    /// `LoadConst c; StoreGlobal g`
+    StoreGlobalConst(GlobalSlot, ConstantIdx),
+    /// `LoadConst c; ResponseWrite`
+    WriteConst(ConstantIdx),
+    /// `(x; Concat) × n; StoreLocal s`, with each `x` a plain load, as
+    /// `x × n; AppendLocal s n`: concatenates the top `n + 1` values into
+    /// `s`, in place when the leftmost one is `s`'s own string
+    AppendLocal(LocalSlot, u8),
+    /// `(x; Concat) × n; StoreGlobal g`, like `AppendLocal`
+    AppendGlobal(GlobalSlot, u8),
+}
+
+impl Instruction {
+    /// Number of stack operands consumed as plain values.  The VM resolves
+    /// objects among them through their default member before executing.
+    pub fn value_operands(&self) -> usize {
+        match self {
+            Instruction::Neg
+            | Instruction::Not
+            | Instruction::JumpIfFalse(_)
+            | Instruction::JumpIfTrue(_)
+            | Instruction::ResponseWrite => 1,
+            Instruction::Add
+            | Instruction::Sub
+            | Instruction::Mul
+            | Instruction::Div
+            | Instruction::IntDiv
+            | Instruction::Mod
+            | Instruction::Pow
+            | Instruction::Concat
+            | Instruction::Eq
+            | Instruction::Ne
+            | Instruction::Lt
+            | Instruction::Le
+            | Instruction::Gt
+            | Instruction::Ge
+            | Instruction::Like
+            | Instruction::And
+            | Instruction::Or
+            | Instruction::Xor
+            | Instruction::Imp
+            | Instruction::Eqv => 2,
+            Instruction::AppendLocal(_, n) | Instruction::AppendGlobal(_, n) => *n as usize + 1,
+            _ => 0,
+        }
+    }
 }
 
 impl fmt::Display for Instruction {
@@ -133,6 +235,7 @@ impl fmt::Display for Instruction {
             Instruction::StoreLocal(s) => write!(f, "StoreLocal {}", s),
             Instruction::LoadGlobal(i) => write!(f, "LoadGlobal {}", i),
             Instruction::StoreGlobal(i) => write!(f, "StoreGlobal {}", i),
+            Instruction::CheckDeclared(i, l) => write!(f, "CheckDeclared {} {}", i, l),
             Instruction::Neg => write!(f, "Neg"),
             Instruction::Not => write!(f, "Not"),
             Instruction::Add => write!(f, "Add"),
@@ -175,6 +278,8 @@ impl fmt::Display for Instruction {
             Instruction::Call(i, n) => write!(f, "Call {} {}", i, n),
             Instruction::CallLocal(s, n) => write!(f, "CallLocal {} {}", s, n),
             Instruction::Return(n) => write!(f, "Return {}", n),
+            Instruction::ByRefSkip(n, g, o) => write!(f, "ByRefSkip {} {:?} {}", n, g, o),
+            Instruction::LoadByRef(n) => write!(f, "LoadByRef {}", n),
             Instruction::Jump(o) => write!(f, "Jump {}", o),
             Instruction::JumpIfFalse(o) => write!(f, "JumpIfFalse {}", o),
             Instruction::JumpIfTrue(o) => write!(f, "JumpIfTrue {}", o),
@@ -202,6 +307,15 @@ impl fmt::Display for Instruction {
             Instruction::ServerTransfer(i) => write!(f, "ServerTransfer {}", i),
             Instruction::Erase(s) => write!(f, "Erase {}", s),
             Instruction::EraseGlobal(i) => write!(f, "EraseGlobal {}", i),
+            Instruction::AddLocalConst(s, i) => write!(f, "AddLocalConst {} {}", s, i),
+            Instruction::ConcatLocalConst(s, i) => write!(f, "ConcatLocalConst {} {}", s, i),
+            Instruction::StoreLocalConst(s, i) => write!(f, "StoreLocalConst {} {}", s, i),
+            Instruction::AddGlobalConst(g, i) => write!(f, "AddGlobalConst {} {}", g, i),
+            Instruction::ConcatGlobalConst(g, i) => write!(f, "ConcatGlobalConst {} {}", g, i),
+            Instruction::AppendLocal(s, n) => write!(f, "AppendLocal {} {}", s, n),
+            Instruction::AppendGlobal(g, n) => write!(f, "AppendGlobal {} {}", g, n),
+            Instruction::StoreGlobalConst(g, i) => write!(f, "StoreGlobalConst {} {}", g, i),
+            Instruction::WriteConst(i) => write!(f, "WriteConst {}", i),
         }
     }
 }
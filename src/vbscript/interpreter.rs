@@ -1,46 +1,72 @@
 use crate::asp::parser::AspBlock;
 use crate::vbscript::block;
 use crate::vbscript::block::UserDefinedFunction;
-use crate::vbscript::vbobject::ErrObject;
+use crate::vbscript::compiler::CompiledCode;
+use crate::vbscript::vbobject::{ErrObject, ObjectRef};
 use crate::vbscript::vbs_error::VBSError;
 use crate::vbscript::ExecutionContext;
 use crate::vbscript::{Token, TokenType, Tokenizer, VBValue};
 
 pub struct VBScriptInterpreter;
 
+/// Compiled form of an ASP page's script, independent of any request.
+#[derive(Clone)]
+pub struct CompiledScript {
+    code: CompiledCode,
+    /// Whether the page declares `Option Explicit`, which also applies to
+    /// code it runs through `Execute`/`ExecuteGlobal`.
+    option_explicit: bool,
+}
+
+impl CompiledScript {
+    /// Whether the script defines a `Sub` or `Function` named `name`.
+    pub fn defines(&self, name: &str) -> bool {
+        self.code.function_defs.iter().any(|f| f.name.eq_ignore_ascii_case(name))
+    }
+}
+
 impl VBScriptInterpreter {
     pub fn execute(&self, code: &str, context: &mut ExecutionContext) -> Result<(), VBSError> {
         self.execute_vm(code, context)
     }
 
     pub fn execute_vm(&self, code: &str, context: &mut ExecutionContext) -> Result<(), VBSError> {
-        let code = code.trim().to_string();
-
-        let tokens = Tokenizer::tokenize(&code);
-        if tokens.iter().all(|t| t.token_type == TokenType::EOF) {
+        let Some(blocks) = self.parse_code(code)? else {
             return Ok(());
+        };
+
+        if context.get_variable("ERR").is_none() {
+            context.set_variable("ERR", VBValue::Object(ObjectRef::new(ErrObject::new())));
         }
 
-        tracing::trace!(token_count = tokens.len(), "Tokenized code block");
+        inject_vbscript_constants(context);
 
-        let lines = self.group_tokens_into_lines(&tokens)?;
+        self.run_compiled_blocks(&blocks, context)
+    }
 
-        if context.get_variable("ERR").is_none() {
-            context.set_variable("ERR", VBValue::Object(Box::new(ErrObject::new())));
+    /// Tokenize and parse VBScript source; `None` when it holds no code.
+    pub(crate) fn parse_code(&self, code: &str) -> Result<Option<Vec<block::BlockStatement>>, VBSError> {
+        // Only trailing space is trimmed: leading newlines keep statement
+        // lines aligned with the source
+        let tokens = Tokenizer::tokenize(code.trim_end());
+        if tokens.iter().all(|t| t.token_type == TokenType::EOF) {
+            return Ok(None);
         }
 
-        inject_vbscript_constants(context);
+        tracing::trace!(token_count = tokens.len(), "Tokenized code block");
+
+        let lines = self.group_tokens_into_lines(&tokens)?;
 
         let blocks = block::parse_blocks(&lines)?;
         tracing::trace!(block_count = blocks.len(), "Parsed VBScript blocks");
 
-        self.run_compiled_blocks(&blocks, context)
+        Ok(Some(blocks))
     }
 
     /// Execute pre-parsed `BlockStatement`s via the VM.
     pub fn execute_blocks_vm(&self, blocks: &[block::BlockStatement], context: &mut ExecutionContext) -> Result<(), VBSError> {
         if context.get_variable("ERR").is_none() {
-            context.set_variable("ERR", VBValue::Object(Box::new(ErrObject::new())));
+            context.set_variable("ERR", VBValue::Object(ObjectRef::new(ErrObject::new())));
         }
 
         inject_vbscript_constants(context);
@@ -50,7 +76,17 @@ impl VBScriptInterpreter {
 
     fn run_compiled_blocks(&self, blocks: &[block::BlockStatement], context: &mut ExecutionContext) -> Result<(), VBSError> {
         let mut compiler = crate::vbscript::compiler::Compiler::new(context);
-        let mut compiled = compiler.compile(blocks)?;
+        let compiled = compiler.compile(blocks)?;
+        self.run_compiled_code(compiled, context)
+    }
+
+    fn run_compiled_code(&self, mut compiled: CompiledCode, context: &mut ExecutionContext) -> Result<(), VBSError> {
+        crate::vbscript::builtins::set_locale(context.request.lcid);
+        let _string_limit = crate::vbscript::builtins::limit_string_length(context.limits.max_string_length);
+
+        for class in compiled.classes.drain(..) {
+            context.define_class(class);
+        }
 
         for func in compiled.function_defs.drain(..) {
             context.define_function(func);
@@ -60,38 +96,28 @@ impl VBScriptInterpreter {
             context.set_function_code(&name, code);
         }
 
-        let local_names = compiled.local_names.clone();
-        let local_count = compiled.local_count;
-
-        // Run VM and extract locals in a separate scope to release borrow on context
-        let (result, vm_locals) = {
-            let mut vm = crate::vbscript::vm::Vm::new(context);
-            let r = vm.run(compiled);
-            let l = std::mem::take(&mut vm.locals);
-            (r, l)
-        };
-
-        // Write back local variables to context
-        for slot in 0..local_count {
-            if slot < vm_locals.len() {
-                if let Some(name) = local_names.get(slot) {
-                    if !name.is_empty() {
-                        let val = vm_locals[slot].clone();
-                        context.set_variable(name, val);
-                    }
-                }
-            }
-        }
-
-        result
+        // Page-level variables live in the context's global slots
+        crate::vbscript::vm::Vm::new(context).run(compiled)
     }
 
     /// Execute multiple ASP blocks with a single VM to preserve variable state.
     /// Converts HTML blocks to Response.Write calls and combines all code.
     pub fn execute_vm_blocks(&self, asp_blocks: &[&AspBlock], context: &mut ExecutionContext) -> Result<(), VBSError> {
-        // Convert ASP blocks to a single VBScript code string
+        match self.compile_vm_blocks(asp_blocks)? {
+            Some(script) => self.run_script(&script, context),
+            None => Ok(()),
+        }
+    }
+
+    /// Compile ASP blocks into a script that can be run by any number of
+    /// requests; `None` when the page holds no code.
+    pub fn compile_vm_blocks(&self, asp_blocks: &[&AspBlock]) -> Result<Option<CompiledScript>, VBSError> {
+        // Convert ASP blocks to a single VBScript code string.  Each code
+        // block is padded down to its file line, so statement lines (for
+        // `Err.Line` and error messages) are the lines of the ASP file.
         let mut code_parts = Vec::new();
-        
+        let mut next_line = 1;
+
         for block in asp_blocks {
             match block {
                 AspBlock::Html(html) => {
@@ -102,10 +128,14 @@ impl VBScriptInterpreter {
                             .replace("\r", "")
                             .replace("\n", "\" & vbCrLf & \"");
                         code_parts.push(format!("Response.Write(\"{}\")", escaped));
+                        next_line += 1;
                     }
                 }
-                AspBlock::Code(code, _) => {
-                    code_parts.push(code.to_string());
+                AspBlock::Code(code, line) => {
+                    let padding = "\n".repeat(line.saturating_sub(next_line));
+                    let part = format!("{}{}", padding, code);
+                    next_line += part.matches('\n').count() + 1;
+                    code_parts.push(part);
                 }
                 AspBlock::Directive(_, _) => {
                     // Directives are handled at parse time, ignore here
@@ -114,11 +144,32 @@ impl VBScriptInterpreter {
         }
         
         let combined_code = code_parts.join("\n");
-        if combined_code.trim().is_empty() {
-            return Ok(());
+        let Some(blocks) = self.parse_code(&combined_code)? else {
+            return Ok(None);
+        };
+
+        // Compiling only consults the context for `Option Explicit`, so a
+        // blank one keeps the result independent of any request
+        let mut scratch = ExecutionContext::new();
+        let code = crate::vbscript::compiler::Compiler::new(&mut scratch).compile(&blocks)?;
+        Ok(Some(CompiledScript {
+            code,
+            option_explicit: scratch.option_explicit,
+        }))
+    }
+
+    /// Run a script produced by `compile_vm_blocks` in `context`.
+    pub fn run_script(&self, script: &CompiledScript, context: &mut ExecutionContext) -> Result<(), VBSError> {
+        if context.get_variable("ERR").is_none() {
+            context.set_variable("ERR", VBValue::Object(ObjectRef::new(ErrObject::new())));
         }
-        
-        self.execute_vm(&combined_code, context)
+
+        inject_vbscript_constants(context);
+
+        if script.option_explicit {
+            context.option_explicit = true;
+        }
+        self.run_compiled_code(script.code.clone(), context)
     }
 
     fn group_tokens_into_lines(&self, tokens: &[Token]) -> Result<Vec<Vec<Token>>, VBSError> {
@@ -259,23 +310,47 @@ impl VBScriptInterpreter {
     }
 }
 
+/// Intrinsic VBScript constants defined for every script.
+const VBSCRIPT_CONSTANTS: [&str; 12] = [
+    "vbCrLf",
+    "vbCr",
+    "vbLf",
+    "vbNewLine",
+    "vbTab",
+    "vbNullString",
+    "vbNull",
+    "vbEmpty",
+    "vbObject",
+    "vbTrue",
+    "vbFalse",
+    "vbObjectError",
+];
+
+/// Value of the intrinsic constant `name` (case-insensitive).
+pub(crate) fn vbscript_constant(name: &str) -> Option<VBValue> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "vbcrlf" => VBValue::String("\r\n".into()),
+        "vbcr" => VBValue::String("\r".into()),
+        "vblf" => VBValue::String("\n".into()),
+        "vbnewline" => VBValue::String("\n".into()),
+        "vbtab" => VBValue::String("\t".into()),
+        "vbnullstring" => VBValue::String("".into()),
+        "vbnull" => VBValue::Null,
+        "vbempty" => VBValue::Empty,
+        "vbobject" => VBValue::Empty,
+        "vbtrue" => VBValue::Boolean(true),
+        "vbfalse" => VBValue::Boolean(false),
+        "vbobjecterror" => VBValue::Long(-2147221504),
+        _ => return None,
+    })
+}
+
 pub fn inject_vbscript_constants(context: &mut ExecutionContext) {
-    let constants: Vec<(&str, VBValue)> = vec![
-        ("vbCrLf", VBValue::String("\r\n".into())),
-        ("vbCr", VBValue::String("\r".into())),
-        ("vbLf", VBValue::String("\n".into())),
-        ("vbNewLine", VBValue::String("\n".into())),
-        ("vbTab", VBValue::String("\t".into())),
-        ("vbNullString", VBValue::String("".into())),
-        ("vbNull", VBValue::Null),
-        ("vbEmpty", VBValue::Empty),
-        ("vbObject", VBValue::Empty),
-        ("vbTrue", VBValue::Boolean(true)),
-        ("vbFalse", VBValue::Boolean(false)),
-    ];
-    for (name, value) in constants {
+    for name in VBSCRIPT_CONSTANTS {
         if context.get_variable(name).is_none() {
-            context.set_variable(name, value);
+            if let Some(value) = vbscript_constant(name) {
+                context.set_variable(name, value);
+            }
         }
     }
 }
@@ -292,7 +367,7 @@ pub fn execute_user_function_vm(
 
     for (i, param) in func.params.iter().enumerate() {
         let val = args.get(i).cloned().unwrap_or(VBValue::Empty);
-        context.set_variable(param, val);
+        context.set_variable(&param.name, val);
     }
     if func.is_function {
         context.set_variable(&func.name, VBValue::Empty);
@@ -307,26 +382,7 @@ pub fn execute_user_function_vm(
         context.set_function_code(&name, code);
     }
 
-    let local_names = compiled.local_names.clone();
-    let local_count = compiled.local_count;
-
-    let (result, vm_locals) = {
-        let mut vm = crate::vbscript::vm::Vm::new(context);
-        let r = vm.run(compiled);
-        let l = std::mem::take(&mut vm.locals);
-        (r, l)
-    };
-
-    for slot in 0..local_count {
-        if slot < vm_locals.len() {
-            if let Some(name) = local_names.get(slot) {
-                if !name.is_empty() {
-                    let val = vm_locals[slot].clone();
-                    context.set_variable(name, val);
-                }
-            }
-        }
-    }
+    let result = crate::vbscript::vm::Vm::new(context).run(compiled);
 
     context.code_start_line = saved_code_start_line;
 
@@ -367,8 +423,8 @@ mod tests {
         assert!(result.is_ok());
         let val = ctx.get_variable("x").cloned().unwrap_or(VBValue::Empty);
         match val {
-            VBValue::Number(n) => assert!((n - 42.0).abs() < 1e-10),
-            _ => panic!("expected Number"),
+            VBValue::Integer(n) => assert_eq!(n, 42),
+            _ => panic!("expected Integer"),
         }
     }
 
@@ -380,8 +436,8 @@ mod tests {
         assert!(result.is_ok());
         let val = ctx.get_variable("x").cloned().unwrap_or(VBValue::Empty);
         match val {
-            VBValue::Number(n) => assert!((n - 5.0).abs() < 1e-10),
-            _ => panic!("expected Number"),
+            VBValue::Integer(n) => assert_eq!(n, 5),
+            _ => panic!("expected Integer"),
         }
     }
 
@@ -13,9 +13,12 @@ pub mod expr;
 pub mod instruction;
 pub mod fso;
 pub mod interpreter;
+pub mod numeric;
+pub(crate) mod optimizer;
 pub mod regexp;
 pub mod vm;
 pub mod store;
+pub mod symbol;
 pub mod syntax;
 pub mod textstream;
 pub mod tokenizer;
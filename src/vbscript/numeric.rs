@@ -0,0 +1,657 @@
+//! Variant numeric subtypes and the VBScript arithmetic promotion rules.
+//!
+//! Arithmetic keeps the subtype of the most precise operand, in the order
+//! Byte, Integer, Long, Single, Double, Currency, Decimal.  A Byte, Integer
+//! or Long result that overflows widens (Byte → Integer → Long → Double),
+//! and a Single combined with a Long gives a Double.  `Empty` and `Boolean`
+//! operands count as Integer, strings as Double.  A Date plus or minus a
+//! number stays a Date, and the difference of two Dates is a Double.
+
+use std::fmt;
+
+use super::value::VBValue;
+use super::vbs_error::{codes, VBSError};
+
+/// Currency values are stored as integers scaled by 10 000 (four decimals).
+pub const CURRENCY_SCALE: i64 = 10_000;
+
+/// Numeric subtype of a Variant, ordered from least to most precise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NumKind {
+    Byte,
+    Integer,
+    Long,
+    Single,
+    Double,
+    Currency,
+    Decimal,
+}
+
+/// A `Decimal` subtype value, `mantissa / 10^scale`, with the OLE Automation
+/// limits of a 96-bit mantissa and at most 28 decimal places.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u8,
+}
+
+impl Decimal {
+    pub const MAX_SCALE: u8 = 28;
+    const MAX_MANTISSA: i128 = (1 << 96) - 1;
+
+    /// Build `mantissa / 10^scale`, rounding away excess decimal places.
+    /// Returns `None` when the value is out of the Decimal range.
+    pub fn new(mantissa: i128, scale: u8) -> Option<Decimal> {
+        let mut d = Decimal { mantissa, scale };
+        while d.scale > 0 && (d.scale > Self::MAX_SCALE || d.mantissa.abs() > Self::MAX_MANTISSA) {
+            d = d.drop_digit();
+        }
+        if d.mantissa.abs() > Self::MAX_MANTISSA {
+            return None;
+        }
+        while d.scale > 0 && d.mantissa % 10 == 0 {
+            d.mantissa /= 10;
+            d.scale -= 1;
+        }
+        Some(d)
+    }
+
+    /// The value's digits, `mantissa` in `mantissa / 10^scale`.
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    /// Decimal places of the value, `scale` in `mantissa / 10^scale`.
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    pub fn from_i64(n: i64) -> Decimal {
+        Decimal { mantissa: n as i128, scale: 0 }
+    }
+
+    pub fn from_currency(c: i64) -> Decimal {
+        Decimal::new(c as i128, 4).unwrap_or(Decimal::from_i64(0))
+    }
+
+    /// The Decimal closest to the shortest decimal form of `n`.
+    pub fn from_f64(n: f64) -> Option<Decimal> {
+        if !n.is_finite() {
+            return None;
+        }
+        Decimal::parse(&n.to_string())
+    }
+
+    /// Parse a plain decimal literal such as `-12.50`.
+    pub fn parse(s: &str) -> Option<Decimal> {
+        let s = s.trim();
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return None;
+        }
+        let mut mantissa: i128 = 0;
+        let mut scale: u8 = 0;
+        for c in int_part.chars() {
+            mantissa = mantissa.checked_mul(10)?.checked_add(c.to_digit(10)? as i128)?;
+        }
+        for c in frac_part.chars() {
+            let digit = c.to_digit(10)? as i128;
+            if scale > Self::MAX_SCALE {
+                continue;
+            }
+            mantissa = mantissa.checked_mul(10)?.checked_add(digit)?;
+            scale += 1;
+        }
+        Decimal::new(if negative { -mantissa } else { mantissa }, scale)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.mantissa == 0
+    }
+
+    /// Drop the last decimal place, rounding half to even.
+    fn drop_digit(self) -> Decimal {
+        let q = self.mantissa / 10;
+        let r = (self.mantissa % 10).abs();
+        let round_up = r > 5 || (r == 5 && q % 2 != 0);
+        let q = if round_up { q + self.mantissa.signum() } else { q };
+        Decimal { mantissa: q, scale: self.scale - 1 }
+    }
+
+    /// Bring both operands to the same scale, giving up decimal places of
+    /// the more precise one if the mantissa would overflow.
+    fn align(a: Decimal, b: Decimal) -> (i128, i128, u8) {
+        let (mut a, mut b) = (a, b);
+        loop {
+            if a.scale == b.scale {
+                return (a.mantissa, b.mantissa, a.scale);
+            }
+            let (lo, hi) = if a.scale < b.scale { (&mut a, &mut b) } else { (&mut b, &mut a) };
+            match lo.mantissa.checked_mul(10) {
+                Some(m) if m.abs() <= Self::MAX_MANTISSA => {
+                    lo.mantissa = m;
+                    lo.scale += 1;
+                }
+                _ => *hi = hi.drop_digit(),
+            }
+        }
+    }
+
+    pub fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        let (a, b, scale) = Decimal::align(self, other);
+        Decimal::new(a.checked_add(b)?, scale)
+    }
+
+    pub fn checked_sub(self, other: Decimal) -> Option<Decimal> {
+        self.checked_add(-other)
+    }
+
+    pub fn checked_mul(self, other: Decimal) -> Option<Decimal> {
+        let (mut a, mut b) = (self, other);
+        loop {
+            if let Some(m) = a.mantissa.checked_mul(b.mantissa) {
+                return Decimal::new(m, a.scale + b.scale);
+            }
+            if a.scale == 0 && b.scale == 0 {
+                return None;
+            }
+            if a.scale >= b.scale {
+                a = a.drop_digit();
+            } else {
+                b = b.drop_digit();
+            }
+        }
+    }
+}
+
+impl std::ops::Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal { mantissa: -self.mantissa, scale: self.scale }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int_part, frac_part)
+    }
+}
+
+/// Format a Currency value (scaled by 10 000) without trailing zeros.
+pub fn format_currency(c: i64) -> String {
+    let sign = if c < 0 { "-" } else { "" };
+    let abs = c.unsigned_abs();
+    let frac = abs % CURRENCY_SCALE as u64;
+    if frac == 0 {
+        return format!("{}{}", sign, abs / CURRENCY_SCALE as u64);
+    }
+    let frac = format!("{:04}", frac);
+    format!("{}{}.{}", sign, abs / CURRENCY_SCALE as u64, frac.trim_end_matches('0'))
+}
+
+fn overflow() -> VBSError {
+    VBSError::runtime(codes::OVERFLOW)
+}
+
+fn division_by_zero() -> VBSError {
+    VBSError::runtime(codes::DIVISION_BY_ZERO)
+}
+
+/// Round half to even, as VBScript does when converting to an integer type.
+pub fn round_half_even(n: f64) -> f64 {
+    let r = n.round();
+    if (n - n.trunc()).abs() == 0.5 && r % 2.0 != 0.0 {
+        r - n.signum()
+    } else {
+        r
+    }
+}
+
+/// Convert a float to the Currency representation, or error 6 on overflow.
+pub fn f64_to_currency(n: f64) -> Result<i64, VBSError> {
+    let scaled = round_half_even(n * CURRENCY_SCALE as f64);
+    if !scaled.is_finite() || scaled.abs() >= i64::MAX as f64 {
+        return Err(overflow());
+    }
+    Ok(scaled as i64)
+}
+
+/// Round `n` to a whole number and convert it to an integer subtype,
+/// raising error 6 when it does not fit.
+pub fn to_integer_kind(kind: NumKind, n: f64) -> Result<VBValue, VBSError> {
+    let n = round_half_even(n);
+    let value = match kind {
+        NumKind::Byte if (0.0..=255.0).contains(&n) => VBValue::Byte(n as u8),
+        NumKind::Integer if (i16::MIN as f64..=i16::MAX as f64).contains(&n) => VBValue::Integer(n as i16),
+        NumKind::Long if (i32::MIN as f64..=i32::MAX as f64).contains(&n) => VBValue::Long(n as i32),
+        _ => return Err(overflow()),
+    };
+    Ok(value)
+}
+
+/// An integer result in the narrowest subtype at least as wide as `kind`,
+/// widening to Double when it no longer fits in a Long.
+pub fn int_value(kind: NumKind, n: i64) -> VBValue {
+    match kind {
+        NumKind::Byte if (0..=255).contains(&n) => VBValue::Byte(n as u8),
+        NumKind::Byte | NumKind::Integer if (i16::MIN as i64..=i16::MAX as i64).contains(&n) => {
+            VBValue::Integer(n as i16)
+        }
+        _ if (i32::MIN as i64..=i32::MAX as i64).contains(&n) => VBValue::Long(n as i32),
+        _ => VBValue::Number(n as f64),
+    }
+}
+
+/// The Integer, Long or Double subtype of an integer numeric literal.
+pub fn integer_literal(n: i64) -> VBValue {
+    int_value(NumKind::Integer, n)
+}
+
+/// A float converted back to `kind`, widening integer kinds that overflow.
+pub fn from_f64(kind: NumKind, n: f64) -> VBValue {
+    match kind {
+        NumKind::Byte | NumKind::Integer | NumKind::Long if n.fract() == 0.0 && n.abs() < 1e15 => {
+            int_value(kind, n as i64)
+        }
+        NumKind::Byte | NumKind::Integer | NumKind::Long | NumKind::Double => VBValue::Number(n),
+        NumKind::Single => VBValue::Single(n as f32),
+        NumKind::Currency => f64_to_currency(n).map_or(VBValue::Number(n), VBValue::Currency),
+        NumKind::Decimal => Decimal::from_f64(n).map_or(VBValue::Number(n), VBValue::Decimal),
+    }
+}
+
+/// A value as a Double for the conversion functions: error 94 for Null and
+/// error 13 for strings that are not numbers, arrays and objects.
+pub fn to_number(v: &VBValue) -> Result<f64, VBSError> {
+    match v {
+        VBValue::Null => Err(VBSError::runtime(codes::INVALID_USE_OF_NULL)),
+        v => Operand::of(v).map(Operand::to_f64),
+    }
+}
+
+/// Subtype of a `For` counter: the widest of the start, end and step values.
+pub fn loop_kind(values: &[&VBValue]) -> NumKind {
+    values
+        .iter()
+        .map(|v| Operand::of(v).map_or(NumKind::Double, Operand::kind))
+        .reduce(result_kind)
+        .unwrap_or(NumKind::Integer)
+}
+
+/// A numeric operand after the implicit Variant conversions.
+#[derive(Clone, Copy)]
+enum Operand {
+    Int(i64, NumKind),
+    Single(f32),
+    Double(f64),
+    Currency(i64),
+    Decimal(Decimal),
+    Date(f64),
+}
+
+impl Operand {
+    /// Error 13 for strings that are not numbers, arrays and objects.
+    fn of(v: &VBValue) -> Result<Operand, VBSError> {
+        let operand = match v {
+            VBValue::Byte(b) => Operand::Int(*b as i64, NumKind::Byte),
+            VBValue::Integer(i) => Operand::Int(*i as i64, NumKind::Integer),
+            VBValue::Long(l) => Operand::Int(*l as i64, NumKind::Long),
+            VBValue::Boolean(b) => Operand::Int(if *b { -1 } else { 0 }, NumKind::Integer),
+            VBValue::Empty | VBValue::Null => Operand::Int(0, NumKind::Integer),
+            VBValue::Single(s) => Operand::Single(*s),
+            VBValue::Number(n) => Operand::Double(*n),
+            VBValue::Currency(c) => Operand::Currency(*c),
+            VBValue::Decimal(d) => Operand::Decimal(*d),
+            VBValue::Date(d) => Operand::Date(*d),
+            VBValue::String(s) => match s.trim().parse::<f64>() {
+                Ok(n) => Operand::Double(n),
+                Err(_) => return Err(VBSError::runtime(codes::TYPE_MISMATCH)),
+            },
+            VBValue::Array(..) | VBValue::Object(_) | VBValue::Nothing => {
+                return Err(VBSError::runtime(codes::TYPE_MISMATCH))
+            }
+        };
+        Ok(operand)
+    }
+
+    /// Numeric kind, with a Date counting as a Double.
+    fn kind(self) -> NumKind {
+        match self {
+            Operand::Int(_, k) => k,
+            Operand::Single(_) => NumKind::Single,
+            Operand::Double(_) | Operand::Date(_) => NumKind::Double,
+            Operand::Currency(_) => NumKind::Currency,
+            Operand::Decimal(_) => NumKind::Decimal,
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        match self {
+            Operand::Int(n, _) => n as f64,
+            Operand::Single(s) => s as f64,
+            Operand::Double(n) | Operand::Date(n) => n,
+            Operand::Currency(c) => c as f64 / CURRENCY_SCALE as f64,
+            Operand::Decimal(d) => d.to_f64(),
+        }
+    }
+
+    fn to_currency(self) -> Result<i64, VBSError> {
+        match self {
+            Operand::Currency(c) => Ok(c),
+            Operand::Int(n, _) => n.checked_mul(CURRENCY_SCALE).ok_or_else(overflow),
+            other => f64_to_currency(other.to_f64()),
+        }
+    }
+
+    fn to_decimal(self) -> Result<Decimal, VBSError> {
+        match self {
+            Operand::Decimal(d) => Ok(d),
+            Operand::Int(n, _) => Ok(Decimal::from_i64(n)),
+            Operand::Currency(c) => Ok(Decimal::from_currency(c)),
+            other => Decimal::from_f64(other.to_f64()).ok_or_else(overflow),
+        }
+    }
+
+    /// Round to a whole number for `\`, `Mod` and the bitwise operators.
+    fn to_whole(self) -> Result<i64, VBSError> {
+        match self {
+            Operand::Int(n, _) => Ok(n),
+            other => {
+                let n = round_half_even(other.to_f64());
+                if (i32::MIN as f64..=i32::MAX as f64).contains(&n) {
+                    Ok(n as i64)
+                } else {
+                    Err(overflow())
+                }
+            }
+        }
+    }
+}
+
+/// Result kind of `+`, `-` and `*` for two non-Date operands.
+fn result_kind(l: NumKind, r: NumKind) -> NumKind {
+    match (l, r) {
+        (NumKind::Single, NumKind::Long) | (NumKind::Long, NumKind::Single) => NumKind::Double,
+        _ => l.max(r),
+    }
+}
+
+/// Result kind of `\`, `Mod` and the bitwise operators.
+fn whole_kind(l: NumKind, r: NumKind) -> NumKind {
+    match l.max(r) {
+        NumKind::Byte => NumKind::Byte,
+        NumKind::Integer => NumKind::Integer,
+        _ => NumKind::Long,
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+fn arith(op: ArithOp, l: &VBValue, r: &VBValue) -> Result<VBValue, VBSError> {
+    let (lo, ro) = (Operand::of(l)?, Operand::of(r)?);
+    match (op, lo, ro) {
+        (ArithOp::Sub, Operand::Date(a), Operand::Date(b)) => return Ok(VBValue::Number(a - b)),
+        (ArithOp::Add | ArithOp::Sub, Operand::Date(_), _) | (ArithOp::Add | ArithOp::Sub, _, Operand::Date(_)) => {
+            let (a, b) = (lo.to_f64(), ro.to_f64());
+            return Ok(VBValue::Date(if matches!(op, ArithOp::Add) { a + b } else { a - b }));
+        }
+        _ => {}
+    }
+    let kind = result_kind(lo.kind(), ro.kind());
+    let value = match kind {
+        NumKind::Byte | NumKind::Integer | NumKind::Long => {
+            let (Operand::Int(a, _), Operand::Int(b, _)) = (lo, ro) else {
+                unreachable!("integer kinds come from integer operands")
+            };
+            let n = match op {
+                ArithOp::Add => a + b,
+                ArithOp::Sub => a - b,
+                ArithOp::Mul => a * b,
+            };
+            int_value(kind, n)
+        }
+        NumKind::Single | NumKind::Double => {
+            let (a, b) = (lo.to_f64(), ro.to_f64());
+            let n = match op {
+                ArithOp::Add => a + b,
+                ArithOp::Sub => a - b,
+                ArithOp::Mul => a * b,
+            };
+            if kind == NumKind::Single && n.abs() <= f32::MAX as f64 {
+                VBValue::Single(n as f32)
+            } else {
+                VBValue::Number(n)
+            }
+        }
+        NumKind::Currency => {
+            let c = match op {
+                ArithOp::Add => lo.to_currency()?.checked_add(ro.to_currency()?),
+                ArithOp::Sub => lo.to_currency()?.checked_sub(ro.to_currency()?),
+                ArithOp::Mul => match (lo, ro) {
+                    (Operand::Currency(a), Operand::Currency(b)) => {
+                        let product = a as i128 * b as i128;
+                        let scaled = round_half_even_div(product, CURRENCY_SCALE as i128);
+                        i64::try_from(scaled).ok()
+                    }
+                    _ => Some(f64_to_currency(lo.to_f64() * ro.to_f64())?),
+                },
+            };
+            VBValue::Currency(c.ok_or_else(overflow)?)
+        }
+        NumKind::Decimal => {
+            let (a, b) = (lo.to_decimal()?, ro.to_decimal()?);
+            let d = match op {
+                ArithOp::Add => a.checked_add(b),
+                ArithOp::Sub => a.checked_sub(b),
+                ArithOp::Mul => a.checked_mul(b),
+            };
+            VBValue::Decimal(d.ok_or_else(overflow)?)
+        }
+    };
+    Ok(value)
+}
+
+fn round_half_even_div(n: i128, d: i128) -> i128 {
+    let q = n / d;
+    let r = (n % d).abs() * 2;
+    if r > d || (r == d && q % 2 != 0) {
+        q + n.signum()
+    } else {
+        q
+    }
+}
+
+/// `l + r` for non-string operands.
+pub fn add(l: &VBValue, r: &VBValue) -> Result<VBValue, VBSError> {
+    arith(ArithOp::Add, l, r)
+}
+
+/// `l - r`.
+pub fn sub(l: &VBValue, r: &VBValue) -> Result<VBValue, VBSError> {
+    arith(ArithOp::Sub, l, r)
+}
+
+/// `l * r`; Dates multiply as Doubles.
+pub fn mul(l: &VBValue, r: &VBValue) -> Result<VBValue, VBSError> {
+    arith(ArithOp::Mul, l, r)
+}
+
+/// `l / r`: a Decimal if either operand is one, otherwise a Double.
+pub fn div(l: &VBValue, r: &VBValue) -> Result<VBValue, VBSError> {
+    let (lo, ro) = (Operand::of(l)?, Operand::of(r)?);
+    if ro.to_f64() == 0.0 {
+        return Err(division_by_zero());
+    }
+    if lo.kind() == NumKind::Decimal || ro.kind() == NumKind::Decimal {
+        let d = Decimal::from_f64(lo.to_decimal()?.to_f64() / ro.to_decimal()?.to_f64());
+        return d.map(VBValue::Decimal).ok_or_else(overflow);
+    }
+    Ok(VBValue::Number(lo.to_f64() / ro.to_f64()))
+}
+
+/// `l \ r`: both operands rounded to whole numbers, truncating division.
+pub fn int_div(l: &VBValue, r: &VBValue) -> Result<VBValue, VBSError> {
+    let (lo, ro) = (Operand::of(l)?, Operand::of(r)?);
+    let (a, b) = (lo.to_whole()?, ro.to_whole()?);
+    if b == 0 {
+        return Err(division_by_zero());
+    }
+    Ok(int_value(whole_kind(lo.kind(), ro.kind()), a / b))
+}
+
+/// `l Mod r`: the remainder has the sign of `l`.
+pub fn modulo(l: &VBValue, r: &VBValue) -> Result<VBValue, VBSError> {
+    let (lo, ro) = (Operand::of(l)?, Operand::of(r)?);
+    let (a, b) = (lo.to_whole()?, ro.to_whole()?);
+    if b == 0 {
+        return Err(division_by_zero());
+    }
+    Ok(int_value(whole_kind(lo.kind(), ro.kind()), a % b))
+}
+
+/// `l ^ r`, always a Double.
+pub fn pow(l: &VBValue, r: &VBValue) -> Result<VBValue, VBSError> {
+    Ok(VBValue::Number(Operand::of(l)?.to_f64().powf(Operand::of(r)?.to_f64())))
+}
+
+/// Unary minus, widening when the negated value no longer fits.
+pub fn negate(v: &VBValue) -> Result<VBValue, VBSError> {
+    let value = match Operand::of(v)? {
+        Operand::Int(n, kind) => int_value(kind.max(NumKind::Integer), -n),
+        Operand::Single(s) => VBValue::Single(-s),
+        Operand::Double(n) => VBValue::Number(-n),
+        Operand::Currency(c) => VBValue::Currency(c.checked_neg().ok_or_else(overflow)?),
+        Operand::Decimal(d) => VBValue::Decimal(-d),
+        Operand::Date(d) => VBValue::Date(-d),
+    };
+    Ok(value)
+}
+
+/// A bitwise operator (`And`, `Or`, `Xor`, `Eqv`, `Imp`) on numeric
+/// operands: Byte for two Bytes, Integer up to Integer, otherwise Long.
+pub fn bitwise(l: &VBValue, r: &VBValue, f: fn(i64, i64) -> i64) -> Result<VBValue, VBSError> {
+    let (lo, ro) = (Operand::of(l)?, Operand::of(r)?);
+    let kind = whole_kind(lo.kind(), ro.kind());
+    let n = f(lo.to_whole()?, ro.to_whole()?);
+    // Two's complement results are truncated to the width of the subtype.
+    let value = match kind {
+        NumKind::Byte => VBValue::Byte(n as u8),
+        NumKind::Integer => VBValue::Integer(n as i16),
+        _ => VBValue::Long(n as i32),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_addition_widens_to_long() {
+        let v = add(&VBValue::Integer(32000), &VBValue::Integer(1000)).unwrap();
+        assert!(matches!(v, VBValue::Long(33000)));
+        let v = add(&VBValue::Integer(2), &VBValue::Integer(3)).unwrap();
+        assert!(matches!(v, VBValue::Integer(5)));
+    }
+
+    #[test]
+    fn test_long_multiplication_widens_to_double() {
+        let v = mul(&VBValue::Long(100_000), &VBValue::Long(100_000)).unwrap();
+        assert!(matches!(v, VBValue::Number(n) if n == 1e10));
+    }
+
+    #[test]
+    fn test_single_and_long_give_double() {
+        let v = add(&VBValue::Single(1.5), &VBValue::Long(1)).unwrap();
+        assert!(matches!(v, VBValue::Number(n) if n == 2.5));
+        let v = add(&VBValue::Single(1.5), &VBValue::Integer(1)).unwrap();
+        assert!(matches!(v, VBValue::Single(n) if n == 2.5));
+    }
+
+    #[test]
+    fn test_currency_is_exact() {
+        let tenth = VBValue::Currency(f64_to_currency(0.1).unwrap());
+        let mut total = VBValue::Currency(0);
+        for _ in 0..10 {
+            total = add(&total, &tenth).unwrap();
+        }
+        assert!(matches!(total, VBValue::Currency(c) if c == CURRENCY_SCALE));
+        let v = mul(&VBValue::Currency(15_000), &VBValue::Currency(15_000)).unwrap();
+        assert!(matches!(v, VBValue::Currency(22_500)));
+        assert_eq!(format_currency(-12_345), "-1.2345");
+        assert_eq!(format_currency(20_000), "2");
+    }
+
+    #[test]
+    fn test_currency_overflow_raises_error_6() {
+        let err = mul(&VBValue::Currency(i64::MAX / 2), &VBValue::Integer(4)).unwrap_err();
+        assert_eq!(err.code, 6);
+    }
+
+    #[test]
+    fn test_date_arithmetic() {
+        let v = add(&VBValue::Date(45000.0), &VBValue::Integer(1)).unwrap();
+        assert!(matches!(v, VBValue::Date(d) if d == 45001.0));
+        let v = sub(&VBValue::Date(45010.5), &VBValue::Date(45000.0)).unwrap();
+        assert!(matches!(v, VBValue::Number(d) if d == 10.5));
+    }
+
+    #[test]
+    fn test_int_div_and_mod_truncate() {
+        let v = int_div(&VBValue::Integer(-7), &VBValue::Integer(2)).unwrap();
+        assert!(matches!(v, VBValue::Integer(-3)));
+        let v = modulo(&VBValue::Integer(-7), &VBValue::Integer(2)).unwrap();
+        assert!(matches!(v, VBValue::Integer(-1)));
+        let v = int_div(&VBValue::Number(7.5), &VBValue::Integer(2)).unwrap();
+        assert!(matches!(v, VBValue::Long(4)));
+        assert!(modulo(&VBValue::Integer(1), &VBValue::Integer(0)).is_err());
+    }
+
+    #[test]
+    fn test_negate_widens() {
+        assert!(matches!(negate(&VBValue::Integer(i16::MIN)).unwrap(), VBValue::Long(32768)));
+        assert!(matches!(negate(&VBValue::Byte(3)).unwrap(), VBValue::Integer(-3)));
+    }
+
+    #[test]
+    fn test_decimal_arithmetic() {
+        let a = Decimal::parse("0.1").unwrap();
+        let b = Decimal::parse("0.2").unwrap();
+        assert_eq!(a.checked_add(b).unwrap().to_string(), "0.3");
+        assert_eq!(Decimal::parse("-1.50").unwrap().to_string(), "-1.5");
+        let big = Decimal::parse("79228162514264337593543950335").unwrap();
+        assert!(big.checked_add(Decimal::from_i64(1)).is_none());
+        let v = add(&VBValue::Decimal(a), &VBValue::Integer(2)).unwrap();
+        assert!(matches!(v, VBValue::Decimal(d) if d.to_string() == "2.1"));
+    }
+
+    #[test]
+    fn test_round_half_even() {
+        assert_eq!(round_half_even(2.5), 2.0);
+        assert_eq!(round_half_even(3.5), 4.0);
+        assert_eq!(round_half_even(-2.5), -2.0);
+        assert_eq!(round_half_even(2.4), 2.0);
+    }
+}
@@ -0,0 +1,404 @@
+//! Bytecode optimizer, run by the compiler on every script and procedure
+//! body before it reaches the VM.
+//!
+//! Passes, in order:
+//! 1. Intrinsic constants (`vbCrLf`, `vbTab`, ...) are loaded from the
+//!    constant pool instead of being looked up as globals.
+//! 2. Operators applied to constants are folded.  An operation that fails
+//!    is left for the VM to raise at run time, and `Date` operands are left
+//!    alone since their string form depends on the request's locale.
+//! 3. Conditional jumps on a constant are resolved, jumps to jumps are
+//!    threaded and jumps to the next instruction removed.
+//! 4. Common statement shapes are fused into superinstructions, and
+//!    string building (`s = s & x & y`) into an in-place append.
+//!
+//! Jump offsets are relative, so the passes replace instructions in place,
+//! leaving `None` for removed ones, and the code is compacted at the end.
+//! A sequence is only rewritten when no jump lands inside it.
+
+use crate::vbscript::instruction::{Instruction, VarSlot};
+use crate::vbscript::interpreter::vbscript_constant;
+use crate::vbscript::vm::Vm;
+use crate::vbscript::VBValue;
+
+/// Upper bound on the jumps followed when threading, against cycles.
+const MAX_THREADING: usize = 16;
+
+/// Optimize `instructions`, adding the constants it needs to `constants`.
+/// `globals` names the global slots the code refers to.
+pub(crate) fn optimize(instructions: &mut Vec<Instruction>, constants: &mut Vec<VBValue>, globals: &[String]) {
+    let mut optimizer = Optimizer::new(std::mem::take(instructions), constants, globals);
+    optimizer.load_intrinsic_constants();
+    optimizer.fold_constants();
+    optimizer.fold_constant_jumps();
+    optimizer.thread_jumps();
+    optimizer.fuse();
+    optimizer.fuse_appends();
+    *instructions = optimizer.finish();
+}
+
+struct Optimizer<'a> {
+    code: Vec<Option<Instruction>>,
+    /// Absolute target of the jump at each index.
+    targets: Vec<Option<usize>>,
+    /// Whether a jump lands on each index (one past the end included).
+    is_target: Vec<bool>,
+    constants: &'a mut Vec<VBValue>,
+    globals: &'a [String],
+    /// Constants from this index on were added by the optimizer, each for
+    /// a single instruction.
+    scratch_start: usize,
+}
+
+impl<'a> Optimizer<'a> {
+    fn new(instructions: Vec<Instruction>, constants: &'a mut Vec<VBValue>, globals: &'a [String]) -> Self {
+        let len = instructions.len();
+        let mut targets = vec![None; len];
+        let mut is_target = vec![false; len + 1];
+        for (i, inst) in instructions.iter().enumerate() {
+            if let Some(offset) = jump_offset(inst) {
+                let target = (i as isize + 1 + offset as isize).clamp(0, len as isize) as usize;
+                targets[i] = Some(target);
+                is_target[target] = true;
+            }
+        }
+        Optimizer {
+            code: instructions.into_iter().map(Some).collect(),
+            targets,
+            is_target,
+            scratch_start: constants.len(),
+            constants,
+            globals,
+        }
+    }
+
+    fn constant_name(&self, idx: u32) -> String {
+        self.constants[idx as usize].to_string()
+    }
+
+    fn add_constant(&mut self, value: VBValue) -> Instruction {
+        self.constants.push(value);
+        Instruction::LoadConst((self.constants.len() - 1) as u32)
+    }
+
+    /// The value pushed by a constant load.
+    fn constant_value(&self, inst: &Instruction) -> Option<VBValue> {
+        match inst {
+            Instruction::LoadConst(i) => Some(self.constants[*i as usize].clone()),
+            Instruction::LoadTrue => Some(VBValue::Boolean(true)),
+            Instruction::LoadFalse => Some(VBValue::Boolean(false)),
+            Instruction::LoadEmpty => Some(VBValue::Empty),
+            Instruction::LoadNil => Some(VBValue::Null),
+            _ => None,
+        }
+    }
+
+    /// The nearest instruction before `i` that was not removed.
+    fn prev_live(&self, i: usize) -> Option<usize> {
+        (0..i).rev().find(|&j| self.code[j].is_some())
+    }
+
+    /// The first instruction at or after `i` that was not removed.
+    fn resolve(&self, i: usize) -> usize {
+        (i..self.code.len()).find(|&j| self.code[j].is_some()).unwrap_or(self.code.len())
+    }
+
+    /// Indices of the next `n` instructions from `i` that were not removed.
+    fn live_run(&self, i: usize, n: usize) -> Option<Vec<usize>> {
+        let run: Vec<usize> = (i..self.code.len()).filter(|&j| self.code[j].is_some()).take(n).collect();
+        (run.len() == n).then_some(run)
+    }
+
+    /// Whether a jump lands anywhere in `start + 1 ..= end`.
+    fn has_target_within(&self, start: usize, end: usize) -> bool {
+        self.is_target[start + 1..=end].iter().any(|&t| t)
+    }
+
+    fn load_intrinsic_constants(&mut self) {
+        // Dynamic code or an assignment could redefine the name
+        let mut reassigned = Vec::new();
+        for inst in self.code.iter().flatten() {
+            match inst {
+                Instruction::StoreGlobal(g)
+                | Instruction::AppendGlobal(g, _)
+                | Instruction::IndexStoreGlobal(g)
+                | Instruction::IndexStoreGlobalMulti(g, _)
+                | Instruction::EraseGlobal(g)
+                | Instruction::ReDim(VarSlot::Global(g), _, _)
+                | Instruction::ForPrep(VarSlot::Global(g), _)
+                | Instruction::ForEachPrep(VarSlot::Global(g), _) => {
+                    reassigned.push(self.globals[*g as usize].clone())
+                }
+                Instruction::Call(i, _) => {
+                    let name = self.constant_name(*i);
+                    if name == "execute" || name == "executeglobal" {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for i in 0..self.code.len() {
+            let (name, load) = match &self.code[i] {
+                Some(Instruction::LoadGlobal(g)) => (self.globals[*g as usize].clone(), true),
+                Some(Instruction::CheckDeclared(idx, _)) => (self.constant_name(*idx), false),
+                _ => continue,
+            };
+            if reassigned.contains(&name) {
+                continue;
+            }
+            if let Some(value) = vbscript_constant(&name) {
+                // Intrinsic constants are always defined
+                self.code[i] = load.then(|| self.add_constant(value));
+            }
+        }
+    }
+
+    fn fold_constants(&mut self) {
+        for i in 0..self.code.len() {
+            let Some(op) = self.code[i].clone() else { continue };
+            let folded = match op.value_operands() {
+                1 => self.prev_live(i).and_then(|p| {
+                    let val = self.code[p].as_ref().and_then(|inst| self.constant_value(inst))?;
+                    Some((p, Vm::eval_unary(&op, val)?))
+                }),
+                2 => self.prev_live(i).and_then(|p2| {
+                    let p1 = self.prev_live(p2)?;
+                    let l = self.code[p1].as_ref().and_then(|inst| self.constant_value(inst))?;
+                    let r = self.code[p2].as_ref().and_then(|inst| self.constant_value(inst))?;
+                    if matches!(l, VBValue::Date(_)) || matches!(r, VBValue::Date(_)) {
+                        return None;
+                    }
+                    Some((p1, Vm::eval_binary(&op, l, r)?))
+                }),
+                _ => None,
+            };
+            let Some((start, Ok(value))) = folded else { continue };
+            if matches!(value, VBValue::Date(_)) || self.has_target_within(start, i) {
+                continue;
+            }
+            for slot in &mut self.code[start + 1..=i] {
+                *slot = None;
+            }
+            // Folding a chain reuses the constant of its previous step
+            match self.code[start] {
+                Some(Instruction::LoadConst(idx)) if idx as usize >= self.scratch_start => {
+                    self.constants[idx as usize] = value;
+                }
+                _ => self.code[start] = Some(self.add_constant(value)),
+            }
+        }
+    }
+
+    fn fold_constant_jumps(&mut self) {
+        for i in 0..self.code.len() {
+            let jump_if = match self.code[i] {
+                Some(Instruction::JumpIfTrue(_)) => true,
+                Some(Instruction::JumpIfFalse(_)) => false,
+                _ => continue,
+            };
+            let Some(p) = self.prev_live(i) else { continue };
+            let Some(value) = self.code[p].as_ref().and_then(|inst| self.constant_value(inst)) else {
+                continue;
+            };
+            if self.has_target_within(p, i) {
+                continue;
+            }
+            self.code[p] = None;
+            self.code[i] = (Vm::is_truthy(&value) == jump_if).then_some(Instruction::Jump(0));
+        }
+    }
+
+    fn thread_jumps(&mut self) {
+        for i in 0..self.code.len() {
+            if !matches!(
+                self.code[i],
+                Some(Instruction::Jump(_) | Instruction::JumpIfFalse(_) | Instruction::JumpIfTrue(_))
+            ) {
+                continue;
+            }
+            for _ in 0..MAX_THREADING {
+                let Some(target) = self.targets[i] else { break };
+                let next = self.resolve(target);
+                match self.code.get(next) {
+                    Some(Some(Instruction::Jump(_))) if next != i => self.targets[i] = self.targets[next],
+                    _ => break,
+                }
+            }
+            if let (Some(Instruction::Jump(_)), Some(target)) = (&self.code[i], self.targets[i]) {
+                if self.resolve(target) == self.resolve(i + 1) {
+                    self.code[i] = None;
+                }
+            }
+        }
+    }
+
+    fn fuse(&mut self) {
+        for i in 0..self.code.len() {
+            if self.code[i].is_none() {
+                continue;
+            }
+            let fused = self.live_run(i, 4).and_then(|run| {
+                let insts: Vec<&Instruction> = run.iter().filter_map(|&j| self.code[j].as_ref()).collect();
+                let fused = match insts[..] {
+                    [Instruction::LoadLocal(a), Instruction::LoadConst(c), Instruction::Add, Instruction::StoreLocal(b)]
+                        if a == b => Instruction::AddLocalConst(*a, *c),
+                    [Instruction::LoadLocal(a), Instruction::LoadConst(c), Instruction::Concat, Instruction::StoreLocal(b)]
+                        if a == b => Instruction::ConcatLocalConst(*a, *c),
+                    [Instruction::LoadGlobal(a), Instruction::LoadConst(c), Instruction::Add, Instruction::StoreGlobal(b)]
+                        if a == b => Instruction::AddGlobalConst(*a, *c),
+                    [Instruction::LoadGlobal(a), Instruction::LoadConst(c), Instruction::Concat, Instruction::StoreGlobal(b)]
+                        if a == b => Instruction::ConcatGlobalConst(*a, *c),
+                    _ => return None,
+                };
+                Some((fused, run[3]))
+            });
+            let fused = fused.or_else(|| {
+                let run = self.live_run(i, 2)?;
+                let fused = match (self.code[run[0]].as_ref()?, self.code[run[1]].as_ref()?) {
+                    (Instruction::LoadConst(c), Instruction::StoreLocal(s)) => Instruction::StoreLocalConst(*s, *c),
+                    (Instruction::LoadConst(c), Instruction::StoreGlobal(g)) => Instruction::StoreGlobalConst(*g, *c),
+                    (Instruction::LoadConst(c), Instruction::ResponseWrite) => Instruction::WriteConst(*c),
+                    _ => return None,
+                };
+                Some((fused, run[1]))
+            });
+            let Some((fused, end)) = fused else { continue };
+            if self.has_target_within(i, end) {
+                continue;
+            }
+            for slot in &mut self.code[i + 1..=end] {
+                *slot = None;
+            }
+            self.code[i] = Some(fused);
+        }
+    }
+
+    /// Rewrite `[Concat] (x; Concat) × k; Store v`, with each `x` a plain
+    /// load, as `x × k; Append v n` where `n` counts the `Concat`s.  Only
+    /// loads move ahead of the operand conversions, so the order in which
+    /// values are read and converted is unchanged.
+    fn fuse_appends(&mut self) {
+        let mut i = 0;
+        while i < self.code.len() {
+            let Some((concats, end)) = self.append_run(i) else {
+                i += 1;
+                continue;
+            };
+            let append = match self.code[end] {
+                Some(Instruction::StoreLocal(s)) => Instruction::AppendLocal(s, concats.len() as u8),
+                Some(Instruction::StoreGlobal(g)) => Instruction::AppendGlobal(g, concats.len() as u8),
+                _ => unreachable!(),
+            };
+            for j in concats {
+                self.code[j] = None;
+            }
+            self.code[end] = Some(append);
+            i = end + 1;
+        }
+    }
+
+    /// The `Concat`s and the store of an append run starting at `i`.
+    fn append_run(&self, i: usize) -> Option<(Vec<usize>, usize)> {
+        let mut live = (i..self.code.len()).filter(|&j| self.code[j].is_some());
+        let mut concats = Vec::new();
+        let mut j = live.next()?;
+        if matches!(self.code[j], Some(Instruction::Concat)) {
+            concats.push(j);
+            j = live.next()?;
+        }
+        loop {
+            match self.code[j].as_ref()? {
+                Instruction::LoadConst(_) | Instruction::LoadLocal(_) | Instruction::LoadGlobal(_) => {
+                    let concat = live.next()?;
+                    if !matches!(self.code[concat], Some(Instruction::Concat)) {
+                        return None;
+                    }
+                    concats.push(concat);
+                    j = live.next()?;
+                }
+                Instruction::StoreLocal(_) | Instruction::StoreGlobal(_) => break,
+                _ => return None,
+            }
+        }
+        let fits = !concats.is_empty() && concats.len() <= u8::MAX as usize;
+        (fits && !self.has_target_within(i, j)).then_some((concats, j))
+    }
+
+    /// Drop removed instructions and unused scratch constants, and
+    /// recompute jump offsets.
+    fn finish(self) -> Vec<Instruction> {
+        // New index of each old index, or of the next kept instruction
+        let mut new_index = Vec::with_capacity(self.code.len() + 1);
+        let mut kept = 0;
+        for inst in &self.code {
+            new_index.push(kept);
+            kept += inst.is_some() as usize;
+        }
+        new_index.push(kept);
+
+        let mut out = Vec::with_capacity(kept);
+        for (i, inst) in self.code.into_iter().enumerate() {
+            let Some(mut inst) = inst else { continue };
+            if let (Some(offset), Some(target)) = (jump_offset_mut(&mut inst), self.targets[i]) {
+                *offset = new_index[target] as i32 - (out.len() as i32 + 1);
+            }
+            out.push(inst);
+        }
+
+        let scratch = self.scratch_start;
+        let mut new_constant = vec![None; self.constants.len() - scratch];
+        let mut kept_constants = Vec::new();
+        for inst in &mut out {
+            let (Instruction::LoadConst(c)
+            | Instruction::AddLocalConst(_, c)
+            | Instruction::ConcatLocalConst(_, c)
+            | Instruction::StoreLocalConst(_, c)
+            | Instruction::AddGlobalConst(_, c)
+            | Instruction::ConcatGlobalConst(_, c)
+            | Instruction::StoreGlobalConst(_, c)
+            | Instruction::WriteConst(c)) = inst
+            else {
+                continue;
+            };
+            let Some(old) = (*c as usize).checked_sub(scratch) else { continue };
+            let new = *new_constant[old].get_or_insert_with(|| {
+                kept_constants.push(std::mem::replace(&mut self.constants[scratch + old], VBValue::Empty));
+                scratch + kept_constants.len() - 1
+            });
+            *c = new as u32;
+        }
+        self.constants.truncate(scratch);
+        self.constants.extend(kept_constants);
+        out
+    }
+}
+
+fn jump_offset(inst: &Instruction) -> Option<i32> {
+    match inst {
+        Instruction::Jump(o)
+        | Instruction::JumpIfFalse(o)
+        | Instruction::JumpIfTrue(o)
+        | Instruction::ByRefSkip(_, _, o)
+        | Instruction::ForPrep(_, o)
+        | Instruction::ForStep(_, o)
+        | Instruction::ForEachPrep(_, o)
+        | Instruction::ForEachStep(_, o) => Some(*o),
+        _ => None,
+    }
+}
+
+fn jump_offset_mut(inst: &mut Instruction) -> Option<&mut i32> {
+    match inst {
+        Instruction::Jump(o)
+        | Instruction::JumpIfFalse(o)
+        | Instruction::JumpIfTrue(o)
+        | Instruction::ByRefSkip(_, _, o)
+        | Instruction::ForPrep(_, o)
+        | Instruction::ForStep(_, o)
+        | Instruction::ForEachPrep(_, o)
+        | Instruction::ForEachStep(_, o) => Some(o),
+        _ => None,
+    }
+}
@@ -4,19 +4,25 @@
 use super::execution_context::ExecutionContext;
 use super::value::VBValue;
 use super::value_utils;
-use super::vbobject::VBScriptObject;
-use super::vbs_error::{VBSError, VBSErrorType};
+use super::vbobject::{ObjectRef, VBScriptObject};
+use super::vbs_error::{codes, VBSError};
 use crate::{impl_vbscript_object, prop_not_found, method_not_found, cannot_set_property};
 use regex::Regex;
 use regex::Match as RegexMatch;
+use std::sync::{Mutex, MutexGuard};
 
 /// `VBScript.RegExp` — regular expression matching and replacement.
 ///
 /// Properties: `Pattern`, `IgnoreCase`, `Global`.
 /// Methods: `Test(string)` → Boolean, `Execute(string)` → Matches,
 /// `Replace(string, replacement)` → String.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct RegExpObject {
+    settings: Mutex<RegExpSettings>,
+}
+
+#[derive(Debug, Default)]
+struct RegExpSettings {
     pattern: String,
     ignore_case: bool,
     global: bool,
@@ -31,20 +37,21 @@ impl Default for RegExpObject {
 impl RegExpObject {
     pub fn new() -> Self {
         RegExpObject {
-            pattern: String::new(),
-            ignore_case: false,
-            global: false,
+            settings: Mutex::new(RegExpSettings::default()),
         }
     }
 
+    fn settings(&self) -> MutexGuard<'_, RegExpSettings> {
+        self.settings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     fn compile(&self) -> Result<Regex, VBSError> {
-        let mut p = self.pattern.clone();
-        if self.ignore_case {
+        let settings = self.settings();
+        let mut p = settings.pattern.clone();
+        if settings.ignore_case {
             p = format!("(?i){}", p);
         }
-        Regex::new(&p).map_err(|e| {
-            VBSErrorType::RuntimeError.into_error(format!("Invalid regex pattern: {}", e))
-        })
+        Regex::new(&p).map_err(|_| VBSError::runtime(codes::REGEXP_SYNTAX))
     }
 }
 
@@ -57,30 +64,30 @@ impl VBScriptObject for RegExpObject {
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         match name.to_uppercase().as_str() {
-            "PATTERN" => Ok(VBValue::String(self.pattern.clone().into())),
-            "IGNORECASE" => Ok(VBValue::Boolean(self.ignore_case)),
-            "GLOBAL" => Ok(VBValue::Boolean(self.global)),
+            "PATTERN" => Ok(VBValue::String(self.settings().pattern.clone().into())),
+            "IGNORECASE" => Ok(VBValue::Boolean(self.settings().ignore_case)),
+            "GLOBAL" => Ok(VBValue::Boolean(self.settings().global)),
             _ => prop_not_found!("RegExp", name),
         }
     }
 
     fn set_property(
-        &mut self,
+        &self,
         name: &str,
         value: VBValue,
         _context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
         match name.to_uppercase().as_str() {
             "PATTERN" => {
-                self.pattern = value_utils::to_arg_string(&value);
+                self.settings().pattern = value_utils::to_arg_string(&value);
                 Ok(())
             }
             "IGNORECASE" => {
-                self.ignore_case = value_utils::to_boolean(&value);
+                self.settings().ignore_case = value_utils::to_boolean(&value);
                 Ok(())
             }
             "GLOBAL" => {
-                self.global = value_utils::to_boolean(&value);
+                self.settings().global = value_utils::to_boolean(&value);
                 Ok(())
             }
             _ => cannot_set_property!("RegExp", name),
@@ -88,7 +95,7 @@ impl VBScriptObject for RegExpObject {
     }
 
     fn call_method(
-        &mut self,
+        &self,
         name: &str,
         args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -96,8 +103,7 @@ impl VBScriptObject for RegExpObject {
         match name.to_uppercase().as_str() {
             "TEST" => {
                 if args.is_empty() {
-                    return Err(VBSErrorType::ValueError
-                        .into_error("RegExp.Test requires at least 1 argument".to_string()));
+                    return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Test"));
                 }
                 let input = value_utils::to_arg_string(&args[0]);
                 let re = self.compile()?;
@@ -105,19 +111,18 @@ impl VBScriptObject for RegExpObject {
             }
             "EXECUTE" => {
                 if args.is_empty() {
-                    return Err(VBSErrorType::ValueError
-                        .into_error("RegExp.Execute requires at least 1 argument".to_string()));
+                    return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Execute"));
                 }
                 let input = value_utils::to_arg_string(&args[0]);
                 let re = self.compile()?;
-                let matches: Vec<VBValue> = if self.global {
+                let matches: Vec<VBValue> = if self.settings().global {
                     re.captures_iter(&input)
                         .map(|c| {
                             let m = c.get(0).unwrap();
                             let subs: Vec<String> = c.iter().skip(1)
                                 .map(|opt| opt.map(|m| m.as_str().to_string()).unwrap_or_default())
                                 .collect();
-                            VBValue::Object(Box::new(MatchObject::with_submatches(m, &input, subs)))
+                            VBValue::Object(ObjectRef::new(MatchObject::with_submatches(m, &input, subs)))
                         })
                         .collect()
                 } else {
@@ -127,7 +132,7 @@ impl VBScriptObject for RegExpObject {
                             let subs: Vec<String> = c.iter().skip(1)
                                 .map(|opt| opt.map(|m| m.as_str().to_string()).unwrap_or_default())
                                 .collect();
-                            vec![VBValue::Object(Box::new(MatchObject::with_submatches(m, &input, subs)))]
+                            vec![VBValue::Object(ObjectRef::new(MatchObject::with_submatches(m, &input, subs)))]
                         })
                         .unwrap_or_default()
                 };
@@ -135,13 +140,12 @@ impl VBScriptObject for RegExpObject {
             }
             "REPLACE" => {
                 if args.len() < 2 {
-                    return Err(VBSErrorType::ValueError
-                        .into_error("RegExp.Replace requires at least 2 arguments".to_string()));
+                    return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Replace"));
                 }
                 let input = value_utils::to_arg_string(&args[0]);
                 let replacement = value_utils::to_arg_string(&args[1]);
                 let re = self.compile()?;
-                let result = if self.global {
+                let result = if self.settings().global {
                     re.replace_all(&input, replacement.as_str())
                 } else {
                     re.replace(&input, replacement.as_str())
@@ -187,11 +191,16 @@ impl VBScriptObject for MatchObject {
             "VALUE" => Ok(VBValue::String(self.value.clone().into())),
             "FIRSTINDEX" => Ok(VBValue::Number(self.first_index as f64)),
             "LENGTH" => Ok(VBValue::Number(self.length as f64)),
-            "SUBMATCHES" => Ok(VBValue::Object(Box::new(SubMatchesObject::new(self.sub_matches.clone())))),
+            "SUBMATCHES" => Ok(VBValue::Object(ObjectRef::new(SubMatchesObject::new(self.sub_matches.clone())))),
             _ => prop_not_found!("Match", name),
         }
     }
 
+    /// `Value` is the default member.
+    fn default_value(&self, _context: &mut ExecutionContext) -> Result<Option<VBValue>, VBSError> {
+        Ok(Some(VBValue::String(self.value.clone().into())))
+    }
+
     fn indexed_get(
         &self,
         index: &VBValue,
@@ -200,12 +209,11 @@ impl VBScriptObject for MatchObject {
         let i = value_utils::to_arg_f64(index) as usize;
         self.sub_matches.get(i).cloned()
             .map(|s| VBValue::String(s.into()))
-            .ok_or_else(|| VBSErrorType::RuntimeError
-                .into_error(format!("SubMatch index {} out of range", i)))
+            .ok_or_else(|| VBSError::runtime(codes::INVALID_PROCEDURE_CALL))
     }
 
     fn call_method(
-        &mut self,
+        &self,
         _name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -250,12 +258,11 @@ impl VBScriptObject for SubMatchesObject {
         let i = value_utils::to_arg_f64(index) as usize;
         self.items.get(i).cloned()
             .map(|s| VBValue::String(s.into()))
-            .ok_or_else(|| VBSErrorType::RuntimeError
-                .into_error(format!("SubMatches index {} out of range", i)))
+            .ok_or_else(|| VBSError::runtime(codes::INVALID_PROCEDURE_CALL))
     }
 
     fn call_method(
-        &mut self,
+        &self,
         _name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -0,0 +1,186 @@
+//! The [`StateBackend`] trait and the default in-memory backend.
+
+use std::io;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use ahash::AHashMap;
+
+use super::super::value::VBValue;
+
+/// Variables of a session or of the application, by uppercased name.
+pub type Vars = AHashMap<String, VBValue>;
+
+/// When a session was last used by any server instance sharing a backend,
+/// and how long it may then stay idle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastAccess {
+    pub at: SystemTime,
+    pub timeout_minutes: u32,
+}
+
+impl LastAccess {
+    pub fn expired(&self, now: SystemTime) -> bool {
+        now.duration_since(self.at).unwrap_or_default() >= Duration::from_secs(u64::from(self.timeout_minutes) * 60)
+    }
+}
+
+/// Where session and application variables live.
+///
+/// Session IDs and variable names reach the backend uppercased.  Session
+/// lifetime (timeouts, Global.asa events, static objects) and
+/// `Application.Lock` stay with the [`Store`](super::Store); a backend only
+/// keeps the variables and, when several server instances share it, when
+/// each session was last used.
+pub trait StateBackend: Send + Sync {
+    /// Register the session `id`; returns `true` if it did not exist yet.
+    fn create_session(&self, id: &str) -> io::Result<bool>;
+
+    /// Whether the session `id` is registered.
+    fn has_session(&self, id: &str) -> io::Result<bool>;
+
+    /// IDs of all sessions.
+    fn session_ids(&self) -> io::Result<Vec<String>>;
+
+    /// Remove the session `id`, returning its variables if it existed.
+    fn remove_session(&self, id: &str) -> io::Result<Option<Vars>>;
+
+    /// Register the session `id` with `vars` as its variables, replacing
+    /// any it had.
+    fn put_session(&self, id: &str, vars: Vars) -> io::Result<()>;
+
+    /// All variables of the session `id`.
+    fn session_vars(&self, id: &str) -> io::Result<Vars>;
+
+    fn get_session_var(&self, id: &str, name: &str) -> io::Result<Option<VBValue>>;
+
+    /// Set a variable of the session `id`, registering the session if needed.
+    fn set_session_var(&self, id: &str, name: &str, value: VBValue) -> io::Result<()>;
+
+    fn remove_session_var(&self, id: &str, name: &str) -> io::Result<()>;
+
+    /// All application variables.
+    fn application_vars(&self) -> io::Result<Vars>;
+
+    fn get_application_var(&self, name: &str) -> io::Result<Option<VBValue>>;
+
+    fn set_application_var(&self, name: &str, value: VBValue) -> io::Result<()>;
+
+    /// Discard every session and all application variables.
+    fn clear(&self) -> io::Result<()>;
+
+    /// Whether the state outlives the process, so that the application
+    /// should not be ended when the server shuts down.
+    fn persistent(&self) -> bool {
+        false
+    }
+
+    /// Record where other server instances can see it that the session
+    /// `id` was used; a backend that is not shared keeps nothing.
+    fn touch_session(&self, _id: &str, _access: LastAccess) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The last use of the session `id` recorded by any instance, or `None`
+    /// if the backend does not share it.
+    fn last_access(&self, _id: &str) -> io::Result<Option<LastAccess>> {
+        Ok(None)
+    }
+
+    /// Drop the recorded last use of the session `id` to end it; returns
+    /// `false` if another instance already did.
+    fn forget_access(&self, _id: &str) -> io::Result<bool> {
+        Ok(true)
+    }
+}
+
+/// The default backend: state in process memory, lost on restart.
+#[derive(Default)]
+pub struct MemoryBackend {
+    sessions: Mutex<AHashMap<String, Vars>>,
+    application: Mutex<Vars>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn lock_sessions(&self) -> MutexGuard<'_, AHashMap<String, Vars>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(super) fn lock_application(&self) -> MutexGuard<'_, Vars> {
+        self.application.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl StateBackend for MemoryBackend {
+    fn create_session(&self, id: &str) -> io::Result<bool> {
+        let mut sessions = self.lock_sessions();
+        if sessions.contains_key(id) {
+            return Ok(false);
+        }
+        sessions.insert(id.to_string(), Vars::new());
+        Ok(true)
+    }
+
+    fn has_session(&self, id: &str) -> io::Result<bool> {
+        Ok(self.lock_sessions().contains_key(id))
+    }
+
+    fn session_ids(&self) -> io::Result<Vec<String>> {
+        Ok(self.lock_sessions().keys().cloned().collect())
+    }
+
+    fn remove_session(&self, id: &str) -> io::Result<Option<Vars>> {
+        Ok(self.lock_sessions().remove(id))
+    }
+
+    fn put_session(&self, id: &str, vars: Vars) -> io::Result<()> {
+        self.lock_sessions().insert(id.to_string(), vars);
+        Ok(())
+    }
+
+    fn session_vars(&self, id: &str) -> io::Result<Vars> {
+        Ok(self.lock_sessions().get(id).cloned().unwrap_or_default())
+    }
+
+    fn get_session_var(&self, id: &str, name: &str) -> io::Result<Option<VBValue>> {
+        Ok(self.lock_sessions().get(id).and_then(|vars| vars.get(name).cloned()))
+    }
+
+    fn set_session_var(&self, id: &str, name: &str, value: VBValue) -> io::Result<()> {
+        self.lock_sessions()
+            .entry(id.to_string())
+            .or_default()
+            .insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn remove_session_var(&self, id: &str, name: &str) -> io::Result<()> {
+        if let Some(vars) = self.lock_sessions().get_mut(id) {
+            vars.remove(name);
+        }
+        Ok(())
+    }
+
+    fn application_vars(&self) -> io::Result<Vars> {
+        Ok(self.lock_application().clone())
+    }
+
+    fn get_application_var(&self, name: &str) -> io::Result<Option<VBValue>> {
+        Ok(self.lock_application().get(name).cloned())
+    }
+
+    fn set_application_var(&self, name: &str, value: VBValue) -> io::Result<()> {
+        self.lock_application().insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        self.lock_sessions().clear();
+        self.lock_application().clear();
+        Ok(())
+    }
+}
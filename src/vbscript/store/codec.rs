@@ -0,0 +1,323 @@
+//! Binary encoding of `VBValue`s for the persistent state backends.
+//!
+//! A value encodes as a version byte followed by a tagged value: scalars
+//! in little-endian form, strings as UTF-8 with a length prefix, arrays as
+//! their dimensions and elements, and a `Scripting.Dictionary` as its
+//! entries.  Other objects cannot be encoded; they stay in the memory of
+//! the process that stored them.
+
+use std::io;
+use std::sync::Arc;
+
+use ahash::AHashMap;
+
+use super::super::numeric::Decimal;
+use super::super::value::VBValue;
+use super::super::vbobject::{Dictionary, ObjectRef};
+use super::backend::Vars;
+
+/// Format version written ahead of every encoded value and variable set.
+const VERSION: u8 = 1;
+
+const EMPTY: u8 = 0;
+const NULL: u8 = 1;
+const NOTHING: u8 = 2;
+const BOOLEAN: u8 = 3;
+const INTEGER: u8 = 4;
+const LONG: u8 = 5;
+const BYTE: u8 = 6;
+const SINGLE: u8 = 7;
+const DOUBLE: u8 = 8;
+const CURRENCY: u8 = 9;
+const DATE: u8 = 10;
+const DECIMAL: u8 = 11;
+const STRING: u8 = 12;
+const ARRAY: u8 = 13;
+const DICTIONARY: u8 = 14;
+
+/// Encode `value`, or `None` if it is (or holds) an object other than a
+/// `Scripting.Dictionary`.
+pub fn encode(value: &VBValue) -> Option<Vec<u8>> {
+    let mut out = vec![VERSION];
+    write_value(&mut out, value).then_some(out)
+}
+
+/// Decode a value written by [`encode`].
+pub fn decode(bytes: &[u8]) -> io::Result<VBValue> {
+    let mut reader = Reader::new(bytes)?;
+    let value = reader.value()?;
+    reader.finish(value)
+}
+
+/// Encode the variables of a session or of the application, leaving out
+/// those that cannot be encoded.
+pub fn encode_vars(vars: &Vars) -> Vec<u8> {
+    let mut entries = Vec::new();
+    let mut count = 0u32;
+    for (name, value) in vars {
+        let start = entries.len();
+        write_str(&mut entries, name);
+        if write_value(&mut entries, value) {
+            count += 1;
+        } else {
+            entries.truncate(start);
+        }
+    }
+    let mut out = vec![VERSION];
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&entries);
+    out
+}
+
+/// Decode variables written by [`encode_vars`].
+pub fn decode_vars(bytes: &[u8]) -> io::Result<Vars> {
+    let mut reader = Reader::new(bytes)?;
+    let vars = reader.entries()?;
+    reader.finish(vars)
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// Append `value`; returns `false`, leaving `out` partly written, if the
+/// value cannot be encoded.
+fn write_value(out: &mut Vec<u8>, value: &VBValue) -> bool {
+    match value {
+        VBValue::Empty => out.push(EMPTY),
+        VBValue::Null => out.push(NULL),
+        VBValue::Nothing => out.push(NOTHING),
+        VBValue::Boolean(b) => out.extend_from_slice(&[BOOLEAN, u8::from(*b)]),
+        VBValue::Integer(n) => {
+            out.push(INTEGER);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        VBValue::Long(n) => {
+            out.push(LONG);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        VBValue::Byte(n) => out.extend_from_slice(&[BYTE, *n]),
+        VBValue::Single(n) => {
+            out.push(SINGLE);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        VBValue::Number(n) => {
+            out.push(DOUBLE);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        VBValue::Currency(n) => {
+            out.push(CURRENCY);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        VBValue::Date(n) => {
+            out.push(DATE);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        VBValue::Decimal(d) => {
+            out.push(DECIMAL);
+            out.extend_from_slice(&d.mantissa().to_le_bytes());
+            out.push(d.scale());
+        }
+        VBValue::String(s) => {
+            out.push(STRING);
+            write_str(out, s);
+        }
+        VBValue::Array(values, dims) => {
+            out.push(ARRAY);
+            out.extend_from_slice(&(dims.len() as u32).to_le_bytes());
+            for dim in dims {
+                out.extend_from_slice(&(*dim as u64).to_le_bytes());
+            }
+            out.extend_from_slice(&(values.len() as u32).to_le_bytes());
+            return values.iter().all(|v| write_value(out, v));
+        }
+        VBValue::Object(obj) => {
+            let Some(dictionary) = obj.as_dictionary() else {
+                return false;
+            };
+            let entries = dictionary.entries();
+            out.push(DICTIONARY);
+            out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+            for (key, value) in &entries {
+                write_str(out, key);
+                if !write_value(out, value) {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid state data: {}", what))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> io::Result<Self> {
+        match bytes.split_first() {
+            Some((&VERSION, rest)) => Ok(Reader { bytes: rest }),
+            Some((version, _)) => Err(invalid(&format!("unsupported version {}", version))),
+            None => Err(invalid("no data")),
+        }
+    }
+
+    /// `result`, if all input has been read.
+    fn finish<T>(self, result: T) -> io::Result<T> {
+        if self.bytes.is_empty() {
+            Ok(result)
+        } else {
+            Err(invalid("trailing bytes"))
+        }
+    }
+
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let (head, rest) = self.bytes.split_first_chunk::<N>().ok_or_else(|| invalid("truncated"))?;
+        self.bytes = rest;
+        Ok(*head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    /// A count of items that take at least `min_size` bytes each, checked
+    /// against the input left so that corrupt data cannot force a huge
+    /// allocation.
+    fn count(&mut self, min_size: usize) -> io::Result<usize> {
+        let count = u32::from_le_bytes(self.take()?) as usize;
+        if count.saturating_mul(min_size) > self.bytes.len() {
+            return Err(invalid("truncated"));
+        }
+        Ok(count)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.count(1)?;
+        let (s, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        String::from_utf8(s.to_vec()).map_err(|_| invalid("string is not UTF-8"))
+    }
+
+    fn entries(&mut self) -> io::Result<AHashMap<String, VBValue>> {
+        let count = self.count(5)?;
+        let mut entries = AHashMap::with_capacity(count);
+        for _ in 0..count {
+            let name = self.string()?;
+            let value = self.value()?;
+            entries.insert(name, value);
+        }
+        Ok(entries)
+    }
+
+    fn value(&mut self) -> io::Result<VBValue> {
+        Ok(match self.u8()? {
+            EMPTY => VBValue::Empty,
+            NULL => VBValue::Null,
+            NOTHING => VBValue::Nothing,
+            BOOLEAN => VBValue::Boolean(self.u8()? != 0),
+            INTEGER => VBValue::Integer(i16::from_le_bytes(self.take()?)),
+            LONG => VBValue::Long(i32::from_le_bytes(self.take()?)),
+            BYTE => VBValue::Byte(self.u8()?),
+            SINGLE => VBValue::Single(f32::from_le_bytes(self.take()?)),
+            DOUBLE => VBValue::Number(f64::from_le_bytes(self.take()?)),
+            CURRENCY => VBValue::Currency(i64::from_le_bytes(self.take()?)),
+            DATE => VBValue::Date(f64::from_le_bytes(self.take()?)),
+            DECIMAL => {
+                let mantissa = i128::from_le_bytes(self.take()?);
+                let scale = self.u8()?;
+                VBValue::Decimal(Decimal::new(mantissa, scale).ok_or_else(|| invalid("decimal out of range"))?)
+            }
+            STRING => VBValue::String(self.string()?.into()),
+            ARRAY => {
+                let rank = self.count(8)?;
+                let dims = (0..rank)
+                    .map(|_| Ok(u64::from_le_bytes(self.take()?) as usize))
+                    .collect::<io::Result<Vec<usize>>>()?;
+                let len = self.count(1)?;
+                let values = (0..len).map(|_| self.value()).collect::<io::Result<Vec<VBValue>>>()?;
+                VBValue::Array(Arc::new(values), dims)
+            }
+            DICTIONARY => VBValue::Object(ObjectRef::new(Dictionary::from_entries(self.entries()?))),
+            tag => return Err(invalid(&format!("unknown tag {}", tag))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(value: VBValue) -> VBValue {
+        decode(&encode(&value).expect("encodable")).unwrap()
+    }
+
+    #[test]
+    fn test_codec_round_trips_scalars() {
+        let values = [
+            VBValue::Empty,
+            VBValue::Null,
+            VBValue::Nothing,
+            VBValue::Boolean(true),
+            VBValue::Integer(-7),
+            VBValue::Long(70000),
+            VBValue::Byte(255),
+            VBValue::Single(1.5),
+            VBValue::Number(-2.25),
+            VBValue::Currency(12_3456),
+            VBValue::Date(45000.5),
+            VBValue::Decimal(Decimal::new(-123456789012345678901234567, 20).unwrap()),
+            VBValue::String("héllo".into()),
+        ];
+        for value in values {
+            assert_eq!(round_trip(value.clone()), value);
+        }
+    }
+
+    #[test]
+    fn test_codec_round_trips_arrays_and_dictionaries() {
+        let array = VBValue::Array(
+            Arc::new(vec![VBValue::Long(1), VBValue::String("two".into()), VBValue::Null, VBValue::Empty]),
+            vec![1, 1],
+        );
+        assert_eq!(round_trip(array.clone()), array);
+
+        let mut entries = AHashMap::new();
+        entries.insert("a".to_string(), VBValue::Long(1));
+        entries.insert("list".to_string(), array.clone());
+        let dictionary = VBValue::Object(ObjectRef::new(Dictionary::from_entries(entries.clone())));
+        let VBValue::Object(decoded) = round_trip(dictionary) else {
+            panic!("expected an object");
+        };
+        assert_eq!(decoded.as_dictionary().unwrap().entries(), entries);
+    }
+
+    #[test]
+    fn test_codec_skips_objects_it_cannot_encode() {
+        let object = VBValue::Object(ObjectRef::new(crate::vbscript::regexp::RegExpObject::new()));
+        assert!(encode(&object).is_none());
+        let mut vars = Vars::new();
+        vars.insert("OBJ".to_string(), object.clone());
+        vars.insert("N".to_string(), VBValue::Long(5));
+        vars.insert("ARR".to_string(), VBValue::Array(Arc::new(vec![object]), vec![0]));
+        let decoded = decode_vars(&encode_vars(&vars)).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded.get("N"), Some(&VBValue::Long(5)));
+    }
+
+    #[test]
+    fn test_codec_rejects_corrupt_data() {
+        assert!(decode(&[]).is_err());
+        assert!(decode(&[VERSION + 1, EMPTY]).is_err());
+        assert!(decode(&[VERSION, 99]).is_err());
+        assert!(decode(&[VERSION, STRING, 255, 255, 255, 255]).is_err());
+        let mut bytes = encode(&VBValue::Long(1)).unwrap();
+        bytes.push(0);
+        assert!(decode(&bytes).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! A state backend that keeps state in memory and writes it through to
+//! files, so that sessions and application variables survive a restart.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use super::super::value::VBValue;
+use super::backend::{MemoryBackend, StateBackend, Vars};
+use super::codec;
+
+const APPLICATION_FILE: &str = "application.state";
+const SESSIONS_DIR: &str = "sessions";
+const EXTENSION: &str = "state";
+
+/// State kept under a directory: `application.state` holds the application
+/// variables and `sessions/<hex of ID>.state` those of each session.
+///
+/// Every change rewrites the file it affects, through a temporary file so
+/// that a crash never leaves it half written.  Values that cannot be
+/// encoded (objects other than `Scripting.Dictionary`) are kept in memory
+/// only.
+pub struct FileBackend {
+    dir: PathBuf,
+    memory: MemoryBackend,
+    /// Serializes changes so that files are written in the order the
+    /// changes were made.
+    write: Mutex<()>,
+}
+
+impl FileBackend {
+    /// Open the state under `dir`, creating the directory if needed and
+    /// loading what an earlier run left there.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(dir.join(SESSIONS_DIR))?;
+        let memory = MemoryBackend::new();
+        match fs::read(dir.join(APPLICATION_FILE)) {
+            Ok(bytes) => *memory.lock_application() = codec::decode_vars(&bytes)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        for entry in fs::read_dir(dir.join(SESSIONS_DIR))? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != EXTENSION) {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|stem| from_hex(&stem.to_string_lossy())) else {
+                continue;
+            };
+            let vars = codec::decode_vars(&fs::read(&path)?)?;
+            memory.lock_sessions().insert(id, vars);
+        }
+        Ok(FileBackend { dir, memory, write: Mutex::new(()) })
+    }
+
+    fn lock_write(&self) -> MutexGuard<'_, ()> {
+        self.write.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn session_path(&self, id: &str) -> PathBuf {
+        self.dir.join(SESSIONS_DIR).join(format!("{}.{}", to_hex(id), EXTENSION))
+    }
+
+    fn save_session(&self, id: &str) -> io::Result<()> {
+        let bytes = codec::encode_vars(&self.memory.session_vars(id)?);
+        write_atomically(&self.session_path(id), &bytes)
+    }
+
+    fn save_application(&self) -> io::Result<()> {
+        let bytes = codec::encode_vars(&self.memory.lock_application());
+        write_atomically(&self.dir.join(APPLICATION_FILE), &bytes)
+    }
+}
+
+impl StateBackend for FileBackend {
+    fn create_session(&self, id: &str) -> io::Result<bool> {
+        let _write = self.lock_write();
+        let created = self.memory.create_session(id)?;
+        if created {
+            self.save_session(id)?;
+        }
+        Ok(created)
+    }
+
+    fn has_session(&self, id: &str) -> io::Result<bool> {
+        self.memory.has_session(id)
+    }
+
+    fn session_ids(&self) -> io::Result<Vec<String>> {
+        self.memory.session_ids()
+    }
+
+    fn remove_session(&self, id: &str) -> io::Result<Option<Vars>> {
+        let _write = self.lock_write();
+        match fs::remove_file(self.session_path(id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        self.memory.remove_session(id)
+    }
+
+    fn put_session(&self, id: &str, vars: Vars) -> io::Result<()> {
+        let _write = self.lock_write();
+        self.memory.put_session(id, vars)?;
+        self.save_session(id)
+    }
+
+    fn session_vars(&self, id: &str) -> io::Result<Vars> {
+        self.memory.session_vars(id)
+    }
+
+    fn get_session_var(&self, id: &str, name: &str) -> io::Result<Option<VBValue>> {
+        self.memory.get_session_var(id, name)
+    }
+
+    fn set_session_var(&self, id: &str, name: &str, value: VBValue) -> io::Result<()> {
+        let _write = self.lock_write();
+        self.memory.set_session_var(id, name, value)?;
+        self.save_session(id)
+    }
+
+    fn remove_session_var(&self, id: &str, name: &str) -> io::Result<()> {
+        let _write = self.lock_write();
+        self.memory.remove_session_var(id, name)?;
+        self.save_session(id)
+    }
+
+    fn application_vars(&self) -> io::Result<Vars> {
+        self.memory.application_vars()
+    }
+
+    fn get_application_var(&self, name: &str) -> io::Result<Option<VBValue>> {
+        self.memory.get_application_var(name)
+    }
+
+    fn set_application_var(&self, name: &str, value: VBValue) -> io::Result<()> {
+        let _write = self.lock_write();
+        self.memory.set_application_var(name, value)?;
+        self.save_application()
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        let _write = self.lock_write();
+        for id in self.memory.session_ids()? {
+            match fs::remove_file(self.session_path(&id)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        self.memory.clear()?;
+        self.save_application()
+    }
+
+    fn persistent(&self) -> bool {
+        true
+    }
+}
+
+/// Replace the file at `path` with `bytes` in one step.
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path)
+}
+
+/// Session IDs name files as hex, whatever characters they contain.
+fn to_hex(id: &str) -> String {
+    id.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(name: &str) -> Option<String> {
+    if !name.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..name.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(name.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vbscript::vbobject::{Dictionary, ObjectRef};
+    use ahash::AHashMap;
+
+    #[test]
+    fn test_file_backend_survives_reopen() {
+        let dir = std::env::temp_dir().join(format!("asp_file_backend_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        {
+            let backend = FileBackend::open(&dir).unwrap();
+            assert!(backend.create_session("S1").unwrap());
+            assert!(backend.create_session("S2").unwrap());
+            let mut entries = AHashMap::new();
+            entries.insert("k".to_string(), VBValue::String("v".into()));
+            let dictionary = VBValue::Object(ObjectRef::new(Dictionary::from_entries(entries)));
+            backend.set_session_var("S1", "CART", dictionary).unwrap();
+            backend.set_session_var("S1", "N", VBValue::Long(3)).unwrap();
+            backend.remove_session_var("S1", "N").unwrap();
+            backend.remove_session("S2").unwrap();
+            backend.set_application_var("HITS", VBValue::Long(42)).unwrap();
+            let regexp = VBValue::Object(ObjectRef::new(crate::vbscript::regexp::RegExpObject::new()));
+            backend.set_application_var("RE", regexp).unwrap();
+            assert_eq!(backend.application_vars().unwrap().len(), 2);
+        }
+
+        let backend = FileBackend::open(&dir).unwrap();
+        assert!(backend.persistent());
+        assert_eq!(backend.session_ids().unwrap(), ["S1"]);
+        assert!(!backend.create_session("S1").unwrap());
+        let vars = backend.session_vars("S1").unwrap();
+        assert_eq!(vars.len(), 1);
+        let Some(VBValue::Object(cart)) = vars.get("CART") else {
+            panic!("expected the dictionary");
+        };
+        assert_eq!(cart.as_dictionary().unwrap().entries().get("k"), Some(&VBValue::String("v".into())));
+        // Only the encodable application variable was written
+        assert_eq!(backend.application_vars().unwrap().len(), 1);
+        assert_eq!(backend.get_application_var("HITS").unwrap(), Some(VBValue::Long(42)));
+
+        backend.clear().unwrap();
+        let backend = FileBackend::open(&dir).unwrap();
+        assert!(backend.session_ids().unwrap().is_empty());
+        assert!(backend.application_vars().unwrap().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_session_ids_as_file_names() {
+        assert_eq!(to_hex("AB/1"), "41422f31");
+        assert_eq!(from_hex("41422f31").as_deref(), Some("AB/1"));
+        assert_eq!(from_hex("4"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+}
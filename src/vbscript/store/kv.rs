@@ -0,0 +1,618 @@
+//! A state backend kept in a key-value server speaking the Redis protocol
+//! (RESP), so that several server instances can share sessions.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::super::value::VBValue;
+use super::backend::{LastAccess, MemoryBackend, StateBackend, Vars};
+use super::codec;
+
+/// How long connecting, sending a command or waiting for its reply may take.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connections kept open for later commands; more are opened while all of
+/// them are in use, and closed again once done.
+const MAX_IDLE_CONNECTIONS: usize = 16;
+
+/// A reply of the key-value server.
+#[derive(Debug)]
+enum Reply {
+    Status,
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    fn integer(self) -> io::Result<i64> {
+        match self {
+            Reply::Integer(n) => Ok(n),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    fn bulk(self) -> io::Result<Option<Vec<u8>>> {
+        match self {
+            Reply::Bulk(bytes) => Ok(bytes),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    fn array(self) -> io::Result<Vec<Reply>> {
+        match self {
+            Reply::Array(items) => Ok(items),
+            other => Err(unexpected(&other)),
+        }
+    }
+}
+
+fn unexpected(reply: &Reply) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("unexpected reply from state server: {:?}", reply))
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn open(address: &str) -> io::Result<Self> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, format!("no address for {}", address));
+        for addr in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(TIMEOUT))?;
+                    stream.set_write_timeout(Some(TIMEOUT))?;
+                    stream.set_nodelay(true)?;
+                    return Ok(Connection { reader: BufReader::new(stream.try_clone()?), writer: stream });
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn send(&mut self, args: &[&[u8]]) -> io::Result<Reply> {
+        let mut request = format!("*{}\r\n", args.len()).into_bytes();
+        for arg in args {
+            request.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+            request.extend_from_slice(arg);
+            request.extend_from_slice(b"\r\n");
+        }
+        self.writer.write_all(&request)?;
+        self.read_reply()
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(line.trim_end_matches("\r\n").to_string())
+    }
+
+    fn read_reply(&mut self) -> io::Result<Reply> {
+        let line = self.read_line()?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid reply from state server: {}", line));
+        let (kind, rest) = line.split_at_checked(1).ok_or_else(invalid)?;
+        match kind {
+            "+" => Ok(Reply::Status),
+            "-" => Err(io::Error::other(format!("state server error: {}", rest))),
+            ":" => rest.parse().map(Reply::Integer).map_err(|_| invalid()),
+            "$" => {
+                let len: i64 = rest.parse().map_err(|_| invalid())?;
+                if len < 0 {
+                    return Ok(Reply::Bulk(None));
+                }
+                let mut bytes = vec![0; len as usize + 2];
+                io::Read::read_exact(&mut self.reader, &mut bytes)?;
+                bytes.truncate(len as usize);
+                Ok(Reply::Bulk(Some(bytes)))
+            }
+            "*" => {
+                let len: i64 = rest.parse().map_err(|_| invalid())?;
+                let items = (0..len.max(0)).map(|_| self.read_reply()).collect::<io::Result<_>>()?;
+                Ok(Reply::Array(items))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// State kept in a key-value server at `address`, under keys starting with
+/// `prefix`: the hash `<prefix>:application` holds the application
+/// variables, the hash `<prefix>:session:<ID>` those of a session, the
+/// set `<prefix>:sessions` the session IDs, and the hash `<prefix>:access`
+/// when each session was last used (milliseconds since the epoch) and its
+/// timeout, so that one instance does not end a session another is serving.
+///
+/// Values that cannot be encoded (objects other than `Scripting.Dictionary`)
+/// are kept in the memory of the instance that stored them.  Each command
+/// takes a connection of its own from a small pool, so requests running at
+/// once do not wait for each other; a lost connection is replaced.
+pub struct KvBackend {
+    address: String,
+    prefix: String,
+    idle: Mutex<Vec<Connection>>,
+    local: MemoryBackend,
+}
+
+impl KvBackend {
+    /// Connect to the key-value server at `address` (`host:port`).
+    pub fn connect(address: &str, prefix: &str) -> io::Result<Self> {
+        let backend = KvBackend {
+            address: address.to_string(),
+            prefix: prefix.to_string(),
+            idle: Mutex::new(vec![Connection::open(address)?]),
+            local: MemoryBackend::new(),
+        };
+        match backend.command(&[b"PING"])? {
+            Reply::Status => Ok(backend),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    fn lock_idle(&self) -> MutexGuard<'_, Vec<Connection>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Return a connection that is still usable to the pool.
+    fn put_back(&self, connection: Connection) {
+        let mut idle = self.lock_idle();
+        if idle.len() < MAX_IDLE_CONNECTIONS {
+            idle.push(connection);
+        }
+    }
+
+    /// Send a command on an idle connection, or a new one if none is idle,
+    /// reconnecting once if the connection has been lost.
+    fn command(&self, args: &[&[u8]]) -> io::Result<Reply> {
+        // The server answering with an error leaves the connection usable
+        let usable = |reply: &io::Result<Reply>| !reply.as_ref().is_err_and(|e| e.kind() != io::ErrorKind::Other);
+        let pooled = self.lock_idle().pop();
+        if let Some(mut conn) = pooled {
+            let reply = conn.send(args);
+            if usable(&reply) {
+                self.put_back(conn);
+                return reply;
+            }
+        }
+        let mut conn = Connection::open(&self.address)?;
+        let reply = conn.send(args);
+        if usable(&reply) {
+            self.put_back(conn);
+        }
+        reply
+    }
+
+    fn sessions_key(&self) -> String {
+        format!("{}:sessions", self.prefix)
+    }
+
+    fn session_key(&self, id: &str) -> String {
+        format!("{}:session:{}", self.prefix, id)
+    }
+
+    fn application_key(&self) -> String {
+        format!("{}:application", self.prefix)
+    }
+
+    fn access_key(&self) -> String {
+        format!("{}:access", self.prefix)
+    }
+
+    fn hash_vars(&self, key: &str) -> io::Result<Vars> {
+        let items = self.command(&[b"HGETALL", key.as_bytes()])?.array()?;
+        let mut vars = Vars::with_capacity(items.len() / 2);
+        let mut items = items.into_iter();
+        while let (Some(name), Some(value)) = (items.next(), items.next()) {
+            let (Some(name), Some(value)) = (name.bulk()?, value.bulk()?) else {
+                continue;
+            };
+            vars.insert(String::from_utf8_lossy(&name).into_owned(), codec::decode(&value)?);
+        }
+        Ok(vars)
+    }
+
+    fn hash_get(&self, key: &str, name: &str) -> io::Result<Option<VBValue>> {
+        match self.command(&[b"HGET", key.as_bytes(), name.as_bytes()])?.bulk()? {
+            Some(bytes) => codec::decode(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Store `value` under `name` in the hash `key` if it can be encoded;
+    /// returns it back otherwise, having removed any stored value.
+    fn hash_set(&self, key: &str, name: &str, value: VBValue) -> io::Result<Option<VBValue>> {
+        match codec::encode(&value) {
+            Some(bytes) => {
+                self.command(&[b"HSET", key.as_bytes(), name.as_bytes(), &bytes])?;
+                Ok(None)
+            }
+            None => {
+                self.command(&[b"HDEL", key.as_bytes(), name.as_bytes()])?;
+                Ok(Some(value))
+            }
+        }
+    }
+}
+
+impl StateBackend for KvBackend {
+    fn create_session(&self, id: &str) -> io::Result<bool> {
+        let added = self.command(&[b"SADD", self.sessions_key().as_bytes(), id.as_bytes()])?.integer()?;
+        Ok(added == 1)
+    }
+
+    fn has_session(&self, id: &str) -> io::Result<bool> {
+        let member = self.command(&[b"SISMEMBER", self.sessions_key().as_bytes(), id.as_bytes()])?.integer()?;
+        Ok(member == 1)
+    }
+
+    fn session_ids(&self) -> io::Result<Vec<String>> {
+        self.command(&[b"SMEMBERS", self.sessions_key().as_bytes()])?
+            .array()?
+            .into_iter()
+            .map(|item| Ok(String::from_utf8_lossy(&item.bulk()?.unwrap_or_default()).into_owned()))
+            .collect()
+    }
+
+    fn remove_session(&self, id: &str) -> io::Result<Option<Vars>> {
+        let key = self.session_key(id);
+        let mut vars = self.hash_vars(&key)?;
+        self.command(&[b"DEL", key.as_bytes()])?;
+        self.command(&[b"HDEL", self.access_key().as_bytes(), id.as_bytes()])?;
+        let removed = self.command(&[b"SREM", self.sessions_key().as_bytes(), id.as_bytes()])?.integer()?;
+        let local = self.local.remove_session(id)?;
+        if removed == 0 && local.is_none() {
+            return Ok(None);
+        }
+        vars.extend(local.unwrap_or_default());
+        Ok(Some(vars))
+    }
+
+    fn put_session(&self, id: &str, vars: Vars) -> io::Result<()> {
+        let key = self.session_key(id);
+        self.command(&[b"DEL", key.as_bytes()])?;
+        self.command(&[b"SADD", self.sessions_key().as_bytes(), id.as_bytes()])?;
+        let mut local = Vars::new();
+        for (name, value) in vars {
+            if let Some(value) = self.hash_set(&key, &name, value)? {
+                local.insert(name, value);
+            }
+        }
+        self.local.put_session(id, local)
+    }
+
+    fn session_vars(&self, id: &str) -> io::Result<Vars> {
+        let mut vars = self.hash_vars(&self.session_key(id))?;
+        vars.extend(self.local.session_vars(id)?);
+        Ok(vars)
+    }
+
+    fn get_session_var(&self, id: &str, name: &str) -> io::Result<Option<VBValue>> {
+        match self.local.get_session_var(id, name)? {
+            Some(value) => Ok(Some(value)),
+            None => self.hash_get(&self.session_key(id), name),
+        }
+    }
+
+    fn set_session_var(&self, id: &str, name: &str, value: VBValue) -> io::Result<()> {
+        self.command(&[b"SADD", self.sessions_key().as_bytes(), id.as_bytes()])?;
+        match self.hash_set(&self.session_key(id), name, value)? {
+            Some(value) => self.local.set_session_var(id, name, value),
+            None => self.local.remove_session_var(id, name),
+        }
+    }
+
+    fn remove_session_var(&self, id: &str, name: &str) -> io::Result<()> {
+        self.command(&[b"HDEL", self.session_key(id).as_bytes(), name.as_bytes()])?;
+        self.local.remove_session_var(id, name)
+    }
+
+    fn application_vars(&self) -> io::Result<Vars> {
+        let mut vars = self.hash_vars(&self.application_key())?;
+        vars.extend(self.local.application_vars()?);
+        Ok(vars)
+    }
+
+    fn get_application_var(&self, name: &str) -> io::Result<Option<VBValue>> {
+        match self.local.get_application_var(name)? {
+            Some(value) => Ok(Some(value)),
+            None => self.hash_get(&self.application_key(), name),
+        }
+    }
+
+    fn set_application_var(&self, name: &str, value: VBValue) -> io::Result<()> {
+        match self.hash_set(&self.application_key(), name, value)? {
+            Some(value) => self.local.set_application_var(name, value),
+            None => {
+                self.local.lock_application().remove(name);
+                Ok(())
+            }
+        }
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        for id in self.session_ids()? {
+            self.command(&[b"DEL", self.session_key(&id).as_bytes()])?;
+        }
+        let keys = [self.sessions_key(), self.application_key(), self.access_key()];
+        self.command(&[b"DEL", keys[0].as_bytes(), keys[1].as_bytes(), keys[2].as_bytes()])?;
+        self.local.clear()
+    }
+
+    fn persistent(&self) -> bool {
+        true
+    }
+
+    fn touch_session(&self, id: &str, access: LastAccess) -> io::Result<()> {
+        let millis = access.at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let value = format!("{} {}", millis, access.timeout_minutes);
+        self.command(&[b"HSET", self.access_key().as_bytes(), id.as_bytes(), value.as_bytes()])?;
+        Ok(())
+    }
+
+    fn last_access(&self, id: &str) -> io::Result<Option<LastAccess>> {
+        let Some(bytes) = self.command(&[b"HGET", self.access_key().as_bytes(), id.as_bytes()])?.bulk()? else {
+            return Ok(None);
+        };
+        let value = String::from_utf8_lossy(&bytes);
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid session access: {}", value));
+        let (millis, timeout) = value.split_once(' ').ok_or_else(invalid)?;
+        Ok(Some(LastAccess {
+            at: UNIX_EPOCH + Duration::from_millis(millis.parse().map_err(|_| invalid())?),
+            timeout_minutes: timeout.parse().map_err(|_| invalid())?,
+        }))
+    }
+
+    fn forget_access(&self, id: &str) -> io::Result<bool> {
+        let removed = self.command(&[b"HDEL", self.access_key().as_bytes(), id.as_bytes()])?.integer()?;
+        Ok(removed == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vbscript::vbobject::{Dictionary, ObjectRef};
+    use ahash::{AHashMap, AHashSet};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Data {
+        hashes: AHashMap<Vec<u8>, AHashMap<Vec<u8>, Vec<u8>>>,
+        sets: AHashMap<Vec<u8>, AHashSet<Vec<u8>>>,
+    }
+
+    fn bulk(out: &mut Vec<u8>, bytes: &[u8]) {
+        out.extend_from_slice(format!("${}\r\n", bytes.len()).as_bytes());
+        out.extend_from_slice(bytes);
+        out.extend_from_slice(b"\r\n");
+    }
+
+    /// Answer one command the way a Redis server would.
+    fn execute(data: &Mutex<Data>, args: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = data.lock().unwrap();
+        let mut out = Vec::new();
+        let integer = |n: usize| format!(":{}\r\n", n).into_bytes();
+        match (args[0].as_slice(), &args[1..]) {
+            (b"PING", _) => out.extend_from_slice(b"+PONG\r\n"),
+            (b"HGET", [key, field]) => match data.hashes.get(key).and_then(|h| h.get(field)) {
+                Some(value) => bulk(&mut out, value),
+                None => out.extend_from_slice(b"$-1\r\n"),
+            },
+            (b"HSET", [key, field, value]) => {
+                let added = data.hashes.entry(key.clone()).or_default().insert(field.clone(), value.clone());
+                out = integer(usize::from(added.is_none()));
+            }
+            (b"HDEL", [key, field]) => {
+                let removed = data.hashes.get_mut(key).and_then(|h| h.remove(field));
+                out = integer(usize::from(removed.is_some()));
+            }
+            (b"HGETALL", [key]) => {
+                let hash = data.hashes.get(key).cloned().unwrap_or_default();
+                out.extend_from_slice(format!("*{}\r\n", hash.len() * 2).as_bytes());
+                for (field, value) in &hash {
+                    bulk(&mut out, field);
+                    bulk(&mut out, value);
+                }
+            }
+            (b"DEL", keys) => {
+                let removed = keys
+                    .iter()
+                    .filter(|key| data.hashes.remove(*key).is_some() | data.sets.remove(*key).is_some())
+                    .count();
+                out = integer(removed);
+            }
+            (b"SADD", [key, member]) => {
+                out = integer(usize::from(data.sets.entry(key.clone()).or_default().insert(member.clone())));
+            }
+            (b"SREM", [key, member]) => {
+                out = integer(usize::from(data.sets.get_mut(key).is_some_and(|s| s.remove(member))));
+            }
+            (b"SISMEMBER", [key, member]) => {
+                out = integer(usize::from(data.sets.get(key).is_some_and(|s| s.contains(member))));
+            }
+            (b"SMEMBERS", [key]) => {
+                let set = data.sets.get(key).cloned().unwrap_or_default();
+                out.extend_from_slice(format!("*{}\r\n", set.len()).as_bytes());
+                for member in &set {
+                    bulk(&mut out, member);
+                }
+            }
+            _ => out.extend_from_slice(b"-ERR unknown command\r\n"),
+        }
+        out
+    }
+
+    fn serve(stream: TcpStream, data: Arc<Mutex<Data>>) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let count: usize = line.trim_start_matches('*').trim().parse().unwrap();
+            let mut args = Vec::with_capacity(count);
+            for _ in 0..count {
+                line.clear();
+                reader.read_line(&mut line)?;
+                let len: usize = line.trim_start_matches('$').trim().parse().unwrap();
+                let mut arg = vec![0; len + 2];
+                io::Read::read_exact(&mut reader, &mut arg)?;
+                arg.truncate(len);
+                args.push(arg);
+            }
+            writer.write_all(&execute(&data, &args))?;
+        }
+    }
+
+    /// Start a stand-in key-value server on a free local port.
+    fn start_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let data = Arc::new(Mutex::new(Data::default()));
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let data = Arc::clone(&data);
+                std::thread::spawn(move || serve(stream, data));
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn test_kv_backends_share_sessions() {
+        let address = start_server();
+        let a = KvBackend::connect(&address, "test").unwrap();
+        let b = KvBackend::connect(&address, "test").unwrap();
+
+        assert!(a.create_session("S1").unwrap());
+        assert!(!b.create_session("S1").unwrap());
+        let mut entries = AHashMap::new();
+        entries.insert("sku".to_string(), VBValue::String("A-1".into()));
+        let cart = VBValue::Object(ObjectRef::new(Dictionary::from_entries(entries)));
+        a.set_session_var("S1", "CART", cart).unwrap();
+        a.set_session_var("S1", "USER", VBValue::String("ann".into())).unwrap();
+        let regexp = VBValue::Object(ObjectRef::new(crate::vbscript::regexp::RegExpObject::new()));
+        a.set_session_var("S1", "RE", regexp).unwrap();
+
+        assert_eq!(b.get_session_var("S1", "USER").unwrap(), Some(VBValue::String("ann".into())));
+        let Some(VBValue::Object(cart)) = b.get_session_var("S1", "CART").unwrap() else {
+            panic!("expected the dictionary");
+        };
+        assert_eq!(cart.as_dictionary().unwrap().entries().get("sku"), Some(&VBValue::String("A-1".into())));
+        // An object that cannot be encoded stays with the instance that set it
+        assert_eq!(a.session_vars("S1").unwrap().len(), 3);
+        assert_eq!(b.session_vars("S1").unwrap().len(), 2);
+
+        b.set_application_var("HITS", VBValue::Long(7)).unwrap();
+        assert_eq!(a.get_application_var("HITS").unwrap(), Some(VBValue::Long(7)));
+
+        let removed = b.remove_session("S1").unwrap().unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(a.session_ids().unwrap().is_empty());
+        assert_eq!(a.get_session_var("S1", "USER").unwrap(), None);
+
+        // Other prefixes are separate
+        let c = KvBackend::connect(&address, "other").unwrap();
+        assert!(c.application_vars().unwrap().is_empty());
+        a.clear().unwrap();
+        assert!(b.application_vars().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_kv_stores_reap_only_sessions_idle_everywhere() {
+        use crate::vbscript::store::Store;
+        use std::time::{Instant, SystemTime};
+
+        let address = start_server();
+        let a = Store::with_backend(Box::new(KvBackend::connect(&address, "test").unwrap()));
+        let b = Store::with_backend(Box::new(KvBackend::connect(&address, "test").unwrap()));
+        assert!(!b.session_exists("S1"));
+        assert!(a.access_session("S1"));
+        assert!(b.session_exists("s1"));
+        b.set_session_timeout("S1", 30);
+        assert!(!b.access_session("S1"));
+        assert_eq!(a.session_timeout("S1"), 20);
+
+        // Instance B serves the session ten minutes later
+        let shared = KvBackend::connect(&address, "test").unwrap();
+        let later = LastAccess { at: SystemTime::now() + Duration::from_secs(10 * 60), timeout_minutes: 30 };
+        shared.touch_session("S1", later).unwrap();
+
+        let now = Instant::now();
+        assert!(a.claim_expired_sessions(now + Duration::from_secs(21 * 60)).is_empty());
+        assert_eq!(a.session_timeout("S1"), 30);
+        assert!(a.claim_expired_sessions(now + Duration::from_secs(39 * 60)).is_empty());
+        assert_eq!(a.claim_expired_sessions(now + Duration::from_secs(41 * 60)), ["S1"]);
+        // Only one instance ends it
+        assert!(!b.claim_expired_session("S1", now + Duration::from_secs(41 * 60)));
+    }
+
+    #[test]
+    fn test_kv_store_writes_back_changed_dictionary() {
+        use crate::vbscript::store::Store;
+        use crate::vbscript::{ExecutionContext, VBScriptInterpreter};
+
+        let address = start_server();
+        let run = |script: &str| {
+            let store = Store::with_backend(Box::new(KvBackend::connect(&address, "test").unwrap()));
+            let mut ctx = ExecutionContext::new();
+            ctx.store = Some(Arc::clone(&store));
+            ctx.session.id = "S1".to_string();
+            ctx.request_id = store.allocate_request_id();
+            crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+            VBScriptInterpreter.execute(script, &mut ctx).unwrap();
+            store.save_fetched(ctx.request_id);
+            ctx.response.buffer.clone()
+        };
+
+        run("Session(\"cart\") = CreateObject(\"Scripting.Dictionary\")");
+        // Both reads see the same copy, and both additions are written back
+        run("Dim a, b\nSet a = Session(\"cart\")\na.Add \"x\", 1\nSet b = Session(\"cart\")\nb.Add \"y\", 2");
+        assert_eq!(run("Response.Write Session(\"cart\").Count"), "2");
+    }
+
+    #[test]
+    fn test_kv_backend_runs_commands_side_by_side() {
+        let address = start_server();
+        let backend = KvBackend::connect(&address, "test").unwrap();
+        // A command in flight holds its connection; another one opens its own
+        let busy = backend.lock_idle().pop().unwrap();
+        backend.set_application_var("HITS", VBValue::Long(1)).unwrap();
+        backend.put_back(busy);
+        assert_eq!(backend.lock_idle().len(), 2);
+
+        let backend = Arc::new(backend);
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let backend = Arc::clone(&backend);
+                std::thread::spawn(move || {
+                    let name = format!("N{}", i);
+                    for n in 0..20 {
+                        backend.set_application_var(&name, VBValue::Long(n)).unwrap();
+                    }
+                    backend.get_application_var(&name).unwrap()
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), Some(VBValue::Long(19)));
+        }
+        assert!(backend.lock_idle().len() <= MAX_IDLE_CONNECTIONS);
+    }
+
+    #[test]
+    fn test_kv_backend_reports_unreachable_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        assert!(KvBackend::connect(&address, "test").is_err());
+    }
+}
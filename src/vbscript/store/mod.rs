@@ -0,0 +1,945 @@
+//! Thread-safe shared storage for session and application data,
+//! Global.asa state, and application-scoped static objects.
+//!
+//! Session and application variables live in a [`StateBackend`]: process
+//! memory by default, or a directory of files or a key-value server when
+//! they must survive restarts or be shared between server instances.
+
+use std::io;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+use ahash::{AHashMap, AHashSet};
+
+use super::value::VBValue;
+use super::vbs_error::{codes, VBSError, VBSErrorType};
+use crate::asp::global_asa::GlobalAsaState;
+
+pub use self::backend::{LastAccess, MemoryBackend, StateBackend, Vars};
+pub use self::file::FileBackend;
+pub use self::kv::KvBackend;
+
+mod backend;
+pub mod codec;
+mod file;
+mod kv;
+
+/// State tracked for the Application.Lock/Unlock mechanism.
+///
+/// VBScript's `Application.Lock` blocks the calling thread until the
+/// lock is released.  Since we serve requests from a thread pool, we
+/// use a `Condvar` to park the thread instead of spinning.
+/// Each request gets a unique `owner_id` so the lock is reentrant
+/// (the same request can lock multiple times without deadlocking).
+struct AppLockInfo {
+    /// Whether any request currently holds the lock.
+    locked: bool,
+    /// The request_id of the holder (or 0 if unlocked).
+    owner_id: u64,
+    /// Script of the holder, for diagnostics.
+    holder: String,
+    /// When the holder acquired the lock.
+    acquired_at: Option<Instant>,
+}
+
+/// Default wait for `Application.Lock` held by another request, in seconds.
+pub const DEFAULT_APP_LOCK_TIMEOUT: u32 = 90;
+
+/// Default `Session.Timeout`, in minutes.
+pub const DEFAULT_SESSION_TIMEOUT: u32 = 20;
+
+/// Longest `Session.Timeout` allowed, in minutes (a day, as in IIS).
+pub const MAX_SESSION_TIMEOUT: u32 = 1440;
+
+/// Default wait for the running request of a session, in seconds.
+pub const DEFAULT_SESSION_LOCK_TIMEOUT: u32 = 90;
+
+/// Default interval between checks of Global.asa for changes, in seconds.
+pub const DEFAULT_GLOBAL_ASA_CHECK_INTERVAL: u32 = 2;
+
+/// When a session was last used and how long it may then stay idle.
+struct SessionActivity {
+    last_access: Instant,
+    timeout_minutes: u32,
+}
+
+impl SessionActivity {
+    fn expired(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_access)
+            >= Duration::from_secs(u64::from(self.timeout_minutes) * 60)
+    }
+}
+
+/// An object a request read from a variable of a persistent backend.
+struct Fetched {
+    /// Uppercased ID of the session, or `None` for an application variable.
+    session: Option<String>,
+    /// Uppercased variable name.
+    name: String,
+    value: VBValue,
+    /// Encoding of `value` when it was read, to tell whether it changed.
+    encoded: Option<Vec<u8>>,
+}
+
+/// The runtime error a page gets when the state backend fails.
+fn state_error(e: io::Error) -> VBSError {
+    VBSError::runtime_with(codes::INTERNAL_ERROR, e)
+}
+
+/// Shared store containing session data, application data, an application-level
+/// mutex lock, Global.asa event handlers, and application-scoped static objects.
+pub struct Store {
+    /// Session and application variables.
+    backend: Box<dyn StateBackend>,
+    app_lock_mtx: Mutex<AppLockInfo>,
+    app_lock_cv: Condvar,
+    /// Seconds a request waits for `Application.Lock` held by another
+    /// request before failing with a script error (0 waits indefinitely).
+    pub app_lock_timeout_secs: AtomicU32,
+    /// Counter for generating unique per-request IDs.
+    next_request_id: AtomicU64,
+    /// Timeout of new sessions in minutes (default 20).
+    pub session_timeout_minutes: AtomicU32,
+    /// Sessions with a request running, which other requests of the same
+    /// session wait for.
+    busy_sessions: Mutex<AHashSet<String>>,
+    busy_sessions_cv: Condvar,
+    /// Wakes requests waiting on the async side for a busy session.
+    session_released: tokio::sync::Notify,
+    /// Seconds a request waits for the running request of its session
+    /// (0 waits indefinitely).
+    pub session_lock_timeout_secs: AtomicU32,
+    /// Last access and timeout of each session started by a request.
+    activity: Mutex<AHashMap<String, SessionActivity>>,
+    /// Objects read by each running request from a persistent backend,
+    /// written back when it ends.
+    fetched: Mutex<AHashMap<u64, Vec<Fetched>>>,
+    /// Application-scoped objects declared in Global.asa, by uppercased ID.
+    static_objects: Mutex<AHashMap<String, VBValue>>,
+    /// Session-scoped objects declared in Global.asa, by session.
+    session_objects: Mutex<AHashMap<String, AHashMap<String, VBValue>>>,
+    /// The loaded Global.asa and whether the application has started.
+    global_asa: Mutex<GlobalAsaState>,
+    /// Held while the application starts, restarts or ends, so only one
+    /// request does it and the others wait for the outcome.
+    application_restart: Mutex<()>,
+    /// Seconds between checks of Global.asa for changes (0 checks on every
+    /// request).
+    pub global_asa_check_secs: AtomicU32,
+}
+
+impl Store {
+    /// Create a new shared store wrapped in `Arc`, keeping state in memory.
+    pub fn new() -> Arc<Self> {
+        Self::with_backend(Box::new(MemoryBackend::new()))
+    }
+
+    /// Create a new shared store keeping session and application variables
+    /// in `backend`.
+    pub fn with_backend(backend: Box<dyn StateBackend>) -> Arc<Self> {
+        Arc::new(Store {
+            backend,
+            app_lock_mtx: Mutex::new(AppLockInfo { locked: false, owner_id: 0, holder: String::new(), acquired_at: None }),
+            app_lock_cv: Condvar::new(),
+            app_lock_timeout_secs: AtomicU32::new(DEFAULT_APP_LOCK_TIMEOUT),
+            next_request_id: AtomicU64::new(1),
+            session_timeout_minutes: AtomicU32::new(DEFAULT_SESSION_TIMEOUT),
+            busy_sessions: Mutex::new(AHashSet::new()),
+            busy_sessions_cv: Condvar::new(),
+            session_released: tokio::sync::Notify::new(),
+            session_lock_timeout_secs: AtomicU32::new(DEFAULT_SESSION_LOCK_TIMEOUT),
+            activity: Mutex::new(AHashMap::new()),
+            fetched: Mutex::new(AHashMap::new()),
+            static_objects: Mutex::new(AHashMap::new()),
+            session_objects: Mutex::new(AHashMap::new()),
+            global_asa: Mutex::new(GlobalAsaState::default()),
+            application_restart: Mutex::new(()),
+            global_asa_check_secs: AtomicU32::new(DEFAULT_GLOBAL_ASA_CHECK_INTERVAL),
+        })
+    }
+
+    /// Allocate a unique request ID for this request.
+    pub fn allocate_request_id(&self) -> u64 {
+        self.next_request_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Whether session and application variables outlive the process.
+    pub fn is_persistent(&self) -> bool {
+        self.backend.persistent()
+    }
+
+    /// Variable `name` of the session `session_id` (`Empty` if unset).
+    pub fn get_session_var(&self, session_id: &str, name: &str) -> Result<VBValue, VBSError> {
+        let value = self
+            .backend
+            .get_session_var(&session_id.to_uppercase(), &name.to_uppercase())
+            .map_err(state_error)?;
+        Ok(value.unwrap_or(VBValue::Empty))
+    }
+
+    /// Set variable `name` of the session `session_id`.
+    pub fn set_session_var(&self, session_id: &str, name: &str, value: VBValue) -> Result<(), VBSError> {
+        self.forget_fetched(Some(session_id), Some(name));
+        self.backend
+            .set_session_var(&session_id.to_uppercase(), &name.to_uppercase(), value)
+            .map_err(state_error)
+    }
+
+    /// Remove variable `name` of the session `session_id`.
+    pub fn remove_session_var(&self, session_id: &str, name: &str) -> Result<(), VBSError> {
+        self.forget_fetched(Some(session_id), Some(name));
+        self.backend
+            .remove_session_var(&session_id.to_uppercase(), &name.to_uppercase())
+            .map_err(state_error)
+    }
+
+    /// All variables of the session `session_id`.
+    pub fn session_vars(&self, session_id: &str) -> Result<Vars, VBSError> {
+        self.backend.session_vars(&session_id.to_uppercase()).map_err(state_error)
+    }
+
+    /// Remove every variable of the session `session_id`.
+    pub fn clear_session_vars(&self, session_id: &str) -> Result<(), VBSError> {
+        self.forget_fetched(Some(session_id), None);
+        self.backend
+            .put_session(&session_id.to_uppercase(), Vars::new())
+            .map_err(state_error)
+    }
+
+    /// Application variable `name` (`Empty` if unset).
+    pub fn get_app_var(&self, name: &str) -> Result<VBValue, VBSError> {
+        let value = self.backend.get_application_var(&name.to_uppercase()).map_err(state_error)?;
+        Ok(value.unwrap_or(VBValue::Empty))
+    }
+
+    /// Set application variable `name`.
+    pub fn set_app_var(&self, name: &str, value: VBValue) -> Result<(), VBSError> {
+        self.forget_fetched(None, Some(name));
+        self.backend
+            .set_application_var(&name.to_uppercase(), value)
+            .map_err(state_error)
+    }
+
+    /// All application variables.
+    pub fn app_vars(&self) -> Result<Vars, VBSError> {
+        self.backend.application_vars().map_err(state_error)
+    }
+
+    fn lock_fetched(&self) -> MutexGuard<'_, AHashMap<u64, Vec<Fetched>>> {
+        self.fetched.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Variable `name` of the session `session_id` (or of the application
+    /// for `None`) as read by the request `request_id`.
+    ///
+    /// A persistent backend hands out a decoded copy of an object, or one
+    /// that is not written again when changed, so the request keeps the
+    /// object it read first and [`save_fetched`](Self::save_fetched) writes
+    /// it back: `Session("cart").Add` then lasts like in process memory.
+    fn fetch(&self, request_id: u64, session_id: Option<&str>, name: &str) -> Result<VBValue, VBSError> {
+        let session = session_id.map(str::to_uppercase);
+        let name = name.to_uppercase();
+        if let Some(fetched) = self.lock_fetched().get(&request_id).and_then(|fetched| {
+            fetched.iter().find(|f| f.session == session && f.name == name).map(|f| f.value.clone())
+        }) {
+            return Ok(fetched);
+        }
+        let value = match session {
+            Some(ref id) => self.backend.get_session_var(id, &name),
+            None => self.backend.get_application_var(&name),
+        }
+        .map_err(state_error)?
+        .unwrap_or(VBValue::Empty);
+        if matches!(value, VBValue::Object(_)) && self.backend.persistent() {
+            let encoded = codec::encode(&value);
+            self.lock_fetched()
+                .entry(request_id)
+                .or_default()
+                .push(Fetched { session, name, value: value.clone(), encoded });
+        }
+        Ok(value)
+    }
+
+    /// Variable `name` of the session `session_id` as read by the request
+    /// `request_id`, which must call [`save_fetched`](Self::save_fetched)
+    /// when it ends.
+    pub fn fetch_session_var(&self, request_id: u64, session_id: &str, name: &str) -> Result<VBValue, VBSError> {
+        self.fetch(request_id, Some(session_id), name)
+    }
+
+    /// Application variable `name` as read by the request `request_id`,
+    /// which must call [`save_fetched`](Self::save_fetched) when it ends.
+    pub fn fetch_app_var(&self, request_id: u64, name: &str) -> Result<VBValue, VBSError> {
+        self.fetch(request_id, None, name)
+    }
+
+    /// Write back the objects the request `request_id` read that changed
+    /// since.
+    pub fn save_fetched(&self, request_id: u64) {
+        let Some(fetched) = self.lock_fetched().remove(&request_id) else {
+            return;
+        };
+        for f in fetched {
+            if codec::encode(&f.value) == f.encoded {
+                continue;
+            }
+            let saved = match f.session {
+                Some(ref id) => self.backend.set_session_var(id, &f.name, f.value),
+                None => self.backend.set_application_var(&f.name, f.value),
+            };
+            if let Err(e) = saved {
+                tracing::error!(error = %e, variable = %f.name, "Changed object could not be saved");
+            }
+        }
+    }
+
+    /// Stop writing back objects read from variable `name` (every variable
+    /// for `None`) of the session `session_id` or of the application, as the
+    /// variable is being replaced.
+    fn forget_fetched(&self, session_id: Option<&str>, name: Option<&str>) {
+        let mut fetched = self.lock_fetched();
+        if fetched.is_empty() {
+            return;
+        }
+        let session = session_id.map(str::to_uppercase);
+        let name = name.map(str::to_uppercase);
+        for objects in fetched.values_mut() {
+            objects.retain(|f| f.session != session || name.as_ref().is_some_and(|n| *n != f.name));
+        }
+    }
+
+    fn lock_app_info(&self) -> MutexGuard<'_, AppLockInfo> {
+        self.app_lock_mtx.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait while another request than `my_owner_id` holds the application
+    /// lock, for at most `app_lock_timeout_secs`.  Returns the guard once
+    /// the lock is free or held by `my_owner_id`, or the script error for
+    /// a timed-out wait.
+    fn wait_for_app_lock(&self, my_owner_id: u64) -> Result<MutexGuard<'_, AppLockInfo>, VBSError> {
+        let timeout = self.app_lock_timeout_secs.load(Ordering::Relaxed);
+        let deadline = (timeout > 0).then(|| Instant::now() + Duration::from_secs(u64::from(timeout)));
+        let mut info = self.lock_app_info();
+        while info.locked && info.owner_id != my_owner_id {
+            info = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        let held_for = info.acquired_at.map_or(Duration::ZERO, |t| t.elapsed());
+                        tracing::warn!(holder = %info.holder, held_ms = held_for.as_millis() as u64, "Timed out waiting for Application.Lock");
+                        return Err(VBSErrorType::RuntimeError.into_error(format!(
+                            "Application lock timed out after {} seconds; held by {} for {} seconds",
+                            timeout,
+                            info.holder,
+                            held_for.as_secs()
+                        )));
+                    }
+                    self.app_lock_cv.wait_timeout(info, left).unwrap_or_else(|e| e.into_inner()).0
+                }
+                None => self.app_lock_cv.wait(info).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+        Ok(info)
+    }
+
+    /// Block until the application lock is released by another request, then return.
+    ///
+    /// This is the "readers" side of the lock: every `Application("key")` access
+    /// must wait for any outstanding `Application.Lock` from a *different* request
+    /// to be released before reading.  The same request that holds the lock may
+    /// read freely (reentrant).  Fails after `app_lock_timeout_secs`.
+    ///
+    /// Must be called before every Application variable access
+    /// (indexed_get/set, CONTENTS methods).
+    pub fn wait_for_app_unlock(&self, my_owner_id: u64) -> Result<(), VBSError> {
+        self.wait_for_app_lock(my_owner_id).map(drop)
+    }
+
+    /// Acquire the application lock for the request `my_owner_id`, running
+    /// `script` (blocks until available, for at most `app_lock_timeout_secs`).
+    ///
+    /// This is the "writer" side: only one request may hold
+    /// `Application.Lock` at a time.  Returns `true` if the lock
+    /// was freshly acquired, `false` if already held by this owner
+    /// (reentrant).
+    pub fn lock_app_blocking(&self, my_owner_id: u64, script: &str) -> Result<bool, VBSError> {
+        let start = Instant::now();
+        let mut info = self.wait_for_app_lock(my_owner_id)?;
+        if info.locked {
+            return Ok(false);
+        }
+        info.locked = true;
+        info.owner_id = my_owner_id;
+        info.holder = script.to_string();
+        info.acquired_at = Some(Instant::now());
+        tracing::debug!(script, waited_ms = start.elapsed().as_millis() as u64, "Application locked");
+        Ok(true)
+    }
+
+    /// Release the application lock if held by this owner.
+    /// Returns `true` if the lock was released, `false` if not held.
+    ///
+    /// Wakes all waiters (both `wait_for_app_unlock` and
+    /// `lock_app_blocking`).  The first waiter to re-acquire
+    /// the mutex will see the unlocked state and proceed.
+    pub fn unlock_app(&self, my_owner_id: u64) -> bool {
+        let mut info = self.lock_app_info();
+        if info.locked && info.owner_id == my_owner_id {
+            let held_for = info.acquired_at.map_or(Duration::ZERO, |t| t.elapsed());
+            tracing::debug!(script = %info.holder, held_ms = held_for.as_millis() as u64, "Application unlocked");
+            info.locked = false;
+            info.owner_id = 0;
+            info.acquired_at = None;
+            self.app_lock_cv.notify_all();
+            return true;
+        }
+        false
+    }
+
+    /// Release the application lock if the finishing request `my_owner_id`
+    /// still holds it, as when a page fails or calls `Response.End` before
+    /// `Application.Unlock`.
+    pub fn release_app_lock(&self, my_owner_id: u64) {
+        let held = {
+            let info = self.lock_app_info();
+            (info.locked && info.owner_id == my_owner_id).then(|| {
+                (info.holder.clone(), info.acquired_at.map_or(Duration::ZERO, |t| t.elapsed()))
+            })
+        };
+        if let Some((script, held_for)) = held {
+            tracing::warn!(script = %script, held_ms = held_for.as_millis() as u64, "Releasing Application.Lock left by a finished request");
+            self.unlock_app(my_owner_id);
+        }
+    }
+
+    /// Guard that calls [`release_app_lock`](Self::release_app_lock) for
+    /// `my_owner_id` when dropped, so a request that panics cannot keep the
+    /// application locked.
+    pub fn app_lock_owner(&self, my_owner_id: u64) -> AppLockOwner<'_> {
+        AppLockOwner { store: self, owner_id: my_owner_id }
+    }
+
+    /// IDs of all sessions.
+    pub fn session_ids(&self) -> Vec<String> {
+        self.backend.session_ids().unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Session state could not be listed");
+            Vec::new()
+        })
+    }
+
+    /// Get the number of active sessions.
+    pub fn session_count(&self) -> usize {
+        self.session_ids().len()
+    }
+
+    /// Register every session kept by the backend as accessed now, so that
+    /// sessions restored from an earlier run time out like any other.
+    pub fn adopt_sessions(&self) -> io::Result<()> {
+        let ids = self.backend.session_ids()?;
+        let now = Instant::now();
+        let timeout_minutes = self.session_timeout_minutes.load(Ordering::Relaxed);
+        for id in ids {
+            let shared = self.backend.last_access(&id)?;
+            if shared.is_none() {
+                self.share_access(&id, timeout_minutes);
+            }
+            let timeout_minutes = shared.map_or(timeout_minutes, |s| s.timeout_minutes);
+            self.lock_activity().entry(id).or_insert(SessionActivity { last_access: now, timeout_minutes });
+        }
+        Ok(())
+    }
+
+    fn lock_busy_sessions(&self) -> MutexGuard<'_, AHashSet<String>> {
+        self.busy_sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// When a request that starts waiting for its session now gives up,
+    /// after `session_lock_timeout_secs` (`None` to wait indefinitely).
+    pub fn session_lock_deadline(&self) -> Option<Instant> {
+        let timeout = self.session_lock_timeout_secs.load(Ordering::Relaxed);
+        (timeout > 0).then(|| Instant::now() + Duration::from_secs(u64::from(timeout)))
+    }
+
+    /// Hold the session `session_id` until the returned lock is dropped, so
+    /// that requests of a session run one at a time as in IIS.
+    ///
+    /// Returns `None` if another request of the session is running.
+    pub fn try_lock_session(&self, session_id: &str) -> Option<SessionLock<'_>> {
+        let key = session_id.to_uppercase();
+        if !self.lock_busy_sessions().insert(key.clone()) {
+            return None;
+        }
+        Some(SessionLock { store: self, key })
+    }
+
+    /// Block until no request of the session `session_id` is running;
+    /// returns `false` if one still is at `deadline`.
+    pub fn wait_for_session(&self, session_id: &str, deadline: Option<Instant>) -> bool {
+        let key = session_id.to_uppercase();
+        let mut busy = self.lock_busy_sessions();
+        while busy.contains(&key) {
+            busy = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return false;
+                    }
+                    self.busy_sessions_cv.wait_timeout(busy, left).unwrap_or_else(|e| e.into_inner()).0
+                }
+                None => self.busy_sessions_cv.wait(busy).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+        true
+    }
+
+    /// Like [`wait_for_session`](Self::wait_for_session), but waits on the
+    /// async side instead of blocking a thread.
+    pub async fn session_released(&self, session_id: &str, deadline: Option<Instant>) -> bool {
+        let key = session_id.to_uppercase();
+        let released = async {
+            loop {
+                let notified = self.session_released.notified();
+                tokio::pin!(notified);
+                // Register before looking, so a release in between is not missed
+                notified.as_mut().enable();
+                if !self.lock_busy_sessions().contains(&key) {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), released).await.is_ok(),
+            None => {
+                released.await;
+                true
+            }
+        }
+    }
+
+    /// Wait until no other request of the session `session_id` is running,
+    /// then hold it as [`try_lock_session`](Self::try_lock_session) does.
+    ///
+    /// Returns `None` if the session is still busy after
+    /// `session_lock_timeout_secs`.
+    pub fn lock_session(&self, session_id: &str) -> Option<SessionLock<'_>> {
+        let deadline = self.session_lock_deadline();
+        loop {
+            if let Some(lock) = self.try_lock_session(session_id) {
+                return Some(lock);
+            }
+            if !self.wait_for_session(session_id, deadline) {
+                return None;
+            }
+        }
+    }
+
+    fn lock_activity(&self) -> MutexGuard<'_, AHashMap<String, SessionActivity>> {
+        self.activity.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether the session `session_id` is running here or known to the
+    /// backend.
+    pub fn session_exists(&self, session_id: &str) -> bool {
+        let key = session_id.to_uppercase();
+        if self.lock_activity().contains_key(&key) {
+            return true;
+        }
+        self.backend.has_session(&key).unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Session state could not be read");
+            false
+        })
+    }
+
+    /// Record a request in the session `session_id`, registering it if it
+    /// is not known yet; returns `true` when the request starts a new session.
+    pub fn access_session(&self, session_id: &str) -> bool {
+        let key = session_id.to_uppercase();
+        let now = Instant::now();
+        let mut activity = self.lock_activity();
+        if let Some(session) = activity.get_mut(&key) {
+            session.last_access = now;
+            let timeout_minutes = session.timeout_minutes;
+            drop(activity);
+            self.share_access(&key, timeout_minutes);
+            return false;
+        }
+        drop(activity);
+        // Another instance may have started the session and set its timeout
+        let timeout_minutes = match self.backend.last_access(&key) {
+            Ok(Some(shared)) => shared.timeout_minutes,
+            _ => self.session_timeout_minutes.load(Ordering::Relaxed),
+        };
+        self.lock_activity().insert(key.clone(), SessionActivity { last_access: now, timeout_minutes });
+        self.share_access(&key, timeout_minutes);
+        self.backend.create_session(&key).unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Session state could not be created");
+            true
+        })
+    }
+
+    /// Tell other instances sharing the backend that the session `key` was
+    /// used now.
+    fn share_access(&self, key: &str, timeout_minutes: u32) {
+        let access = LastAccess { at: SystemTime::now(), timeout_minutes };
+        if let Err(e) = self.backend.touch_session(key, access) {
+            tracing::error!(error = %e, "Session access could not be shared");
+        }
+    }
+
+    /// `Session.Timeout` of the session `session_id`, in minutes.
+    pub fn session_timeout(&self, session_id: &str) -> u32 {
+        self.lock_activity()
+            .get(&session_id.to_uppercase())
+            .map_or_else(|| self.session_timeout_minutes.load(Ordering::Relaxed), |s| s.timeout_minutes)
+    }
+
+    /// Set `Session.Timeout` of the session `session_id`, in minutes.
+    pub fn set_session_timeout(&self, session_id: &str, minutes: u32) {
+        let key = session_id.to_uppercase();
+        self.lock_activity()
+            .entry(key.clone())
+            .or_insert_with(|| SessionActivity { last_access: Instant::now(), timeout_minutes: minutes })
+            .timeout_minutes = minutes;
+        self.share_access(&key, minutes);
+    }
+
+    /// Claim the session `session_id` for ending if it has been idle past
+    /// its timeout at `now`.  Only one caller gets `true` for a session,
+    /// and it must then end it.
+    pub fn claim_expired_session(&self, session_id: &str, now: Instant) -> bool {
+        self.claim_if_expired(&session_id.to_uppercase(), now)
+    }
+
+    /// Claim every session that has been idle past its timeout at `now`,
+    /// returning their IDs; the caller must end them.
+    pub fn claim_expired_sessions(&self, now: Instant) -> Vec<String> {
+        let candidates: Vec<String> = self
+            .lock_activity()
+            .iter()
+            .filter(|(_, session)| session.expired(now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        candidates.into_iter().filter(|id| self.claim_if_expired(id, now)).collect()
+    }
+
+    /// Claim the session `key` if it is idle at `now` both here and for
+    /// every other instance sharing the backend.  A session another
+    /// instance used since takes its last access from the backend instead.
+    fn claim_if_expired(&self, key: &str, now: Instant) -> bool {
+        if !self.lock_activity().get(key).is_some_and(|s| s.expired(now)) {
+            return false;
+        }
+        let wall_now = SystemTime::now() + now.saturating_duration_since(Instant::now());
+        match self.backend.last_access(key) {
+            Ok(Some(shared)) if !shared.expired(wall_now) => {
+                let idle = wall_now.duration_since(shared.at).unwrap_or_default();
+                if let Some(session) = self.lock_activity().get_mut(key) {
+                    session.last_access = now.checked_sub(idle).unwrap_or(now);
+                    session.timeout_minutes = shared.timeout_minutes;
+                }
+                return false;
+            }
+            Ok(_) => match self.backend.forget_access(key) {
+                Ok(true) => {}
+                // Another instance claimed it and ends it
+                Ok(false) => {
+                    self.lock_activity().remove(key);
+                    return false;
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "Session access could not be released");
+                    return false;
+                }
+            },
+            Err(e) => {
+                tracing::error!(error = %e, "Session access could not be read");
+                return false;
+            }
+        }
+        let mut activity = self.lock_activity();
+        activity.get(key).is_some_and(|s| s.expired(now)) && activity.remove(key).is_some()
+    }
+
+    /// Remove a session's contents and return them, leaving its static
+    /// objects in place (used by Abandon).
+    pub fn take_session(&self, session_id: &str) -> Result<Option<Vars>, VBSError> {
+        self.forget_fetched(Some(session_id), None);
+        self.backend.remove_session(&session_id.to_uppercase()).map_err(state_error)
+    }
+
+    /// Put back the contents of a session taken by [`Store::take_session`].
+    pub fn restore_session(&self, session_id: &str, contents: Vars) {
+        if let Err(e) = self.backend.put_session(&session_id.to_uppercase(), contents) {
+            tracing::error!(error = %e, "Session state could not be restored");
+        }
+    }
+
+    /// Remove a session (used by Abandon and timeout sweep).
+    pub fn remove_session(&self, session_id: &str) {
+        self.forget_fetched(Some(session_id), None);
+        let key = session_id.to_uppercase();
+        if let Err(e) = self.backend.remove_session(&key) {
+            tracing::error!(error = %e, "Session state could not be removed");
+        }
+        self.lock_activity().remove(&key);
+        self.lock_session_objects().remove(&key);
+    }
+
+    /// Lock and return a mutable guard to the application's static objects.
+    pub fn lock_static_objects(&self) -> MutexGuard<'_, AHashMap<String, VBValue>> {
+        self.static_objects.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock and return a mutable guard to the sessions' static objects.
+    pub fn lock_session_objects(&self) -> MutexGuard<'_, AHashMap<String, AHashMap<String, VBValue>>> {
+        self.session_objects.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock and return a mutable guard to the application's Global.asa state.
+    pub fn lock_global_asa(&self) -> MutexGuard<'_, GlobalAsaState> {
+        self.global_asa.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock held while the application starts, restarts or ends.
+    pub fn lock_application_restart(&self) -> MutexGuard<'_, ()> {
+        self.application_restart.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Discard every session, the application's variables and all static
+    /// objects, as an application restart does.
+    pub fn clear(&self) {
+        if let Err(e) = self.backend.clear() {
+            tracing::error!(error = %e, "Session and application state could not be cleared");
+        }
+        self.lock_activity().clear();
+        self.clear_static_objects();
+    }
+
+    /// Discard the objects declared in Global.asa, keeping all variables.
+    pub fn clear_static_objects(&self) {
+        self.lock_static_objects().clear();
+        self.lock_session_objects().clear();
+    }
+}
+
+/// A session held by [`Store::lock_session`], released when dropped.
+pub struct SessionLock<'a> {
+    store: &'a Store,
+    key: String,
+}
+
+impl Drop for SessionLock<'_> {
+    fn drop(&mut self) {
+        self.store.lock_busy_sessions().remove(&self.key);
+        self.store.busy_sessions_cv.notify_all();
+        self.store.session_released.notify_waiters();
+    }
+}
+
+/// A request's claim on the application lock, made by
+/// [`Store::app_lock_owner`]; releases the lock if still held when dropped.
+pub struct AppLockOwner<'a> {
+    store: &'a Store,
+    owner_id: u64,
+}
+
+impl Drop for AppLockOwner<'_> {
+    fn drop(&mut self) {
+        self.store.release_app_lock(self.owner_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_session_write_read() {
+        let store = Store::new();
+        let key = "test_session";
+        store.set_session_var(key, "foo", VBValue::String("bar".into())).unwrap();
+        assert_eq!(store.get_session_var(key, "FOO").unwrap().to_string(), "bar");
+        assert_eq!(store.session_vars(key).unwrap().len(), 1);
+        store.remove_session_var(key, "Foo").unwrap();
+        assert_eq!(store.get_session_var(key, "foo").unwrap(), VBValue::Empty);
+    }
+
+    #[test]
+    fn test_store_session_remove() {
+        let store = Store::new();
+        let sid = "SES1";
+        store.set_session_var(sid, "x", VBValue::Long(1)).unwrap();
+        assert_eq!(store.session_count(), 1);
+        store.remove_session(sid);
+        assert_eq!(store.session_count(), 0);
+    }
+
+    #[test]
+    fn test_store_access_session_starts_once() {
+        let store = Store::new();
+        assert!(!store.session_exists("ses2"));
+        assert!(store.access_session("ses2"));
+        assert!(store.session_exists("SES2"));
+        assert!(!store.access_session("SES2"));
+        store.lock_session_objects().insert("SES2".to_string(), AHashMap::new());
+        let contents = store.take_session("ses2").unwrap();
+        assert!(contents.is_some());
+        assert_eq!(store.lock_session_objects().len(), 1);
+        store.remove_session("ses2");
+        assert!(store.lock_session_objects().is_empty());
+        assert!(store.access_session("ses2"));
+    }
+
+    #[test]
+    fn test_store_sessions_expire_after_their_timeout() {
+        let store = Store::new();
+        store.access_session("short");
+        store.access_session("long");
+        store.set_session_timeout("short", 1);
+        assert_eq!(store.session_timeout("short"), 1);
+        assert_eq!(store.session_timeout("long"), DEFAULT_SESSION_TIMEOUT);
+
+        let now = Instant::now();
+        assert!(store.claim_expired_sessions(now).is_empty());
+        let later = now + Duration::from_secs(2 * 60);
+        assert!(!store.claim_expired_session("long", later));
+        assert_eq!(store.claim_expired_sessions(later), ["SHORT"]);
+        // A claimed session is handed out once
+        assert!(!store.claim_expired_session("short", later));
+        assert!(store.claim_expired_session("long", later + Duration::from_secs(20 * 60)));
+    }
+
+    #[test]
+    fn test_store_app_write_read() {
+        let store = Store::new();
+        let owner = store.allocate_request_id();
+        store.set_app_var("counter", VBValue::Number(42.0)).unwrap();
+        store.wait_for_app_unlock(owner).unwrap();
+        assert_eq!(store.app_vars().unwrap().len(), 1);
+        let val = store.get_app_var("COUNTER").unwrap();
+        match val {
+            VBValue::Number(n) => assert!((n - 42.0).abs() < 1e-10),
+            _ => panic!("expected Number"),
+        }
+    }
+
+    #[test]
+    fn test_store_app_lock_unlock_blocks_other() {
+        let store = Store::new();
+        let id_a = store.allocate_request_id();
+        let id_b = store.allocate_request_id();
+
+        // A acquires the lock
+        assert!(store.lock_app_blocking(id_a, "a.asp").unwrap());
+
+        // B tries to acquire — would block, so use try_lock semantics via a thread
+        // Instead, verify B must wait by checking lock state
+        let info = store.app_lock_mtx.lock().unwrap();
+        assert!(info.locked);
+        assert_eq!(info.owner_id, id_a);
+        drop(info);
+
+        // A releases
+        assert!(store.unlock_app(id_a));
+
+        // B can now acquire
+        assert!(store.lock_app_blocking(id_b, "b.asp").unwrap());
+        assert!(store.unlock_app(id_b));
+    }
+
+    #[test]
+    fn test_store_app_lock_reentrant() {
+        let store = Store::new();
+        let id = store.allocate_request_id();
+        assert!(store.lock_app_blocking(id, "page.asp").unwrap());
+        // Reentrant lock returns false (already held)
+        assert!(!store.lock_app_blocking(id, "page.asp").unwrap());
+        assert!(store.unlock_app(id));
+    }
+
+    #[test]
+    fn test_store_app_lock_times_out_and_is_released_for_finished_requests() {
+        let store = Store::new();
+        store.app_lock_timeout_secs.store(1, Ordering::Relaxed);
+        let id_a = store.allocate_request_id();
+        let id_b = store.allocate_request_id();
+        assert!(store.lock_app_blocking(id_a, "/hold.asp").unwrap());
+        // The holder still reads freely
+        store.wait_for_app_unlock(id_a).unwrap();
+
+        let err = store.wait_for_app_unlock(id_b).unwrap_err();
+        assert!(err.message.contains("held by /hold.asp"), "{}", err.message);
+        assert!(store.lock_app_blocking(id_b, "/other.asp").is_err());
+
+        // Finishing another request leaves the lock alone
+        store.release_app_lock(id_b);
+        assert!(store.lock_app_info().locked);
+        store.release_app_lock(id_a);
+        assert!(store.lock_app_blocking(id_b, "/other.asp").unwrap());
+        assert!(store.unlock_app(id_b));
+    }
+
+    #[test]
+    fn test_store_app_lock_owner_releases_on_panic() {
+        let store = Store::new();
+        let id = store.allocate_request_id();
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _owner = store.app_lock_owner(id);
+            store.lock_app_blocking(id, "/panic.asp").unwrap();
+            panic!("page failed");
+        }));
+        assert!(panicked.is_err());
+        assert!(!store.lock_app_info().locked);
+    }
+
+    #[test]
+    fn test_store_multiple_sessions_independent() {
+        let store = Store::new();
+        store.set_session_var("A", "key", VBValue::String("val_a".into())).unwrap();
+        store.set_session_var("B", "key", VBValue::String("val_b".into())).unwrap();
+        assert_eq!(store.get_session_var("A", "key").unwrap().to_string(), "val_a");
+        assert_eq!(store.get_session_var("B", "key").unwrap().to_string(), "val_b");
+    }
+
+    #[test]
+    fn test_store_session_lock_waits_for_running_request() {
+        let store = Store::new();
+        store.session_lock_timeout_secs.store(1, Ordering::Relaxed);
+        let held = store.lock_session("ses4").unwrap();
+        // Other sessions are not held up
+        assert!(store.lock_session("ses5").is_some());
+        let start = Instant::now();
+        assert!(store.lock_session("SES4").is_none());
+        assert!(start.elapsed() >= Duration::from_secs(1));
+
+        let waiter = {
+            let store = Arc::clone(&store);
+            std::thread::spawn(move || store.lock_session("ses4").is_some())
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        drop(held);
+        assert!(waiter.join().unwrap());
+        assert!(store.lock_session("ses4").is_some());
+    }
+
+    #[test]
+    fn test_store_restores_persistent_sessions() {
+        let dir = std::env::temp_dir().join(format!("asp_store_persistent_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = Store::with_backend(Box::new(FileBackend::open(&dir).unwrap()));
+        assert!(store.is_persistent());
+        assert!(store.access_session("ses3"));
+        store.set_session_var("ses3", "user", VBValue::String("ann".into())).unwrap();
+        drop(store);
+
+        let store = Store::with_backend(Box::new(FileBackend::open(&dir).unwrap()));
+        store.adopt_sessions().unwrap();
+        assert!(!store.access_session("ses3"));
+        assert_eq!(store.get_session_var("ses3", "user").unwrap().to_string(), "ann");
+        let later = Instant::now() + Duration::from_secs(u64::from(DEFAULT_SESSION_TIMEOUT) * 60);
+        assert_eq!(store.claim_expired_sessions(later), ["SES3"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
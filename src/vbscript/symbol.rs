@@ -0,0 +1,133 @@
+//! Interned member names.
+//!
+//! Property and method names are resolved to a [`Symbol`] once, when the
+//! script is compiled, so that member access at run time compares integers
+//! instead of upper-casing and matching strings.  Symbols are
+//! case-insensitive: `Count`, `count` and `COUNT` are the same symbol.
+//!
+//! The members of the built-in objects are predefined in [`sym`] so that
+//! [`VBScriptObject`](super::vbobject::VBScriptObject) implementations can
+//! match on them directly.  Other names are added on first use and live for
+//! the rest of the process.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+use ahash::AHashMap;
+
+/// An interned, case-insensitive member name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+macro_rules! symbols {
+    ($($name:ident),* $(,)?) => {
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        #[repr(u32)]
+        enum Predefined {
+            $($name),*
+        }
+
+        /// Predefined symbols for the members of the built-in objects.
+        pub mod sym {
+            use super::{Predefined, Symbol};
+            $(pub const $name: Symbol = Symbol(Predefined::$name as u32);)*
+        }
+
+        const PREDEFINED: &[&str] = &[$(stringify!($name)),*];
+    };
+}
+
+symbols! {
+    ABANDON, ADD, ADDHEADER, BINARYREAD, BINARYWRITE, BOF, BUFFER, CLEAR,
+    CLOSE, CONNECTIONSTRING, CONTENTS, CONTENTTYPE, COOKIES, COUNT,
+    CREATEOBJECT, DESCRIPTION, END, EOF, EXECUTE, EXISTS, EXPIRES, FIELDS,
+    FLUSH, FORM, HELPCONTEXT, HELPFILE, HTMLENCODE, ITEM, ITEMS, KEYS, LCID,
+    LINE, LOCK, MAPPATH, MOVEFIRST, MOVELAST, MOVENEXT, MOVEPREVIOUS, NAME,
+    NUMBER, OPEN, QUERYSTRING, RAISE, RECORDCOUNT, REDIRECT, REMOVE,
+    REMOVEALL, SCRIPTPATH, SCRIPTTIMEOUT, SERVERVARIABLES, SESSIONID, SOURCE,
+    STATE, STATICOBJECTS, STATUS, TIMEOUT, TOTALBYTES, TRANSFER, UNLOCK,
+    URLENCODE, URLPATHENCODE, VALUE, WRITE,
+}
+
+struct Interner {
+    names: Vec<&'static str>,
+    index: AHashMap<&'static str, Symbol>,
+}
+
+impl Interner {
+    fn insert(&mut self, name: &'static str) -> Symbol {
+        let symbol = Symbol(self.names.len() as u32);
+        self.names.push(name);
+        self.index.insert(name, symbol);
+        symbol
+    }
+}
+
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(|| {
+        let mut interner = Interner {
+            names: Vec::with_capacity(PREDEFINED.len()),
+            index: AHashMap::with_capacity(PREDEFINED.len()),
+        };
+        for name in PREDEFINED {
+            interner.insert(Box::leak(name.to_lowercase().into_boxed_str()));
+        }
+        RwLock::new(interner)
+    })
+}
+
+/// `name` in the lower-case form the interner is keyed by.
+fn fold_case(name: &str) -> Cow<'_, str> {
+    if name.bytes().any(|b| b.is_ascii_uppercase() || !b.is_ascii()) {
+        Cow::Owned(name.to_lowercase())
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+impl Symbol {
+    /// The symbol for `name`, adding it to the table on first use.
+    pub fn intern(name: &str) -> Symbol {
+        let name = fold_case(name);
+        if let Some(symbol) = interner().read().unwrap_or_else(|e| e.into_inner()).index.get(name.as_ref()) {
+            return *symbol;
+        }
+        let mut interner = interner().write().unwrap_or_else(|e| e.into_inner());
+        match interner.index.get(name.as_ref()) {
+            Some(symbol) => *symbol,
+            None => interner.insert(Box::leak(name.into_owned().into_boxed_str())),
+        }
+    }
+
+    /// The member name, in lower case.
+    pub fn as_str(self) -> &'static str {
+        interner().read().unwrap_or_else(|e| e.into_inner()).names[self.0 as usize]
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_is_case_insensitive() {
+        assert_eq!(Symbol::intern("Count"), sym::COUNT);
+        assert_eq!(Symbol::intern("COUNT"), sym::COUNT);
+        assert_eq!(Symbol::intern("MyMember"), Symbol::intern("mymember"));
+        assert_ne!(Symbol::intern("MyMember"), sym::COUNT);
+    }
+
+    #[test]
+    fn test_symbol_names() {
+        assert_eq!(sym::MOVENEXT.as_str(), "movenext");
+        assert_eq!(Symbol::intern("SomeProp").to_string(), "someprop");
+    }
+}
@@ -4,7 +4,7 @@ use crate::vbscript::expr::{evaluate, to_number, Expr};
 use crate::vbscript::instruction::Instruction;
 use crate::vbscript::value::VBValue;
 use crate::vbscript::value_utils::compute_flat_index;
-use crate::vbscript::{vbs_error::VBSError, vbs_error::VBSErrorType, ExecutionContext};
+use crate::vbscript::{vbs_error::codes, vbs_error::VBSError, ExecutionContext};
 use std::sync::Arc;
 
 /// AST node for `arr(i) = value` or `arr(i, j) = value` (array element assignment).
@@ -36,62 +36,26 @@ impl VBSyntax for ArrayAssignment {
         let indices = indices?;
         let value = evaluate(&self.value_expr, context)?;
 
-        // Check type first to avoid borrow conflicts when swapping object out
-        let is_object = matches!(
-            context.get_variable(&self.var_name),
-            Some(VBValue::Object(_))
-        );
-        let is_array = matches!(
-            context.get_variable(&self.var_name),
-            Some(VBValue::Array(..))
-        );
-
-        if is_object {
-            let mut obj_val = {
-                let slot = match context.get_variable_mut(&self.var_name) {
-                    Some(v @ VBValue::Object(_)) => v,
-                    _ => unreachable!(),
-                };
-                let mut replacement = VBValue::Empty;
-                std::mem::swap(slot, &mut replacement);
-                replacement
-            };
-            match &mut obj_val {
-                VBValue::Object(ref mut obj) => {
-                    obj.indexed_set(&indices[0], value, context)?;
-                }
-                _ => unreachable!(),
-            }
-            context.set_variable(&self.var_name.to_uppercase(), obj_val);
-            Ok(())
-        } else if is_array {
+        if let Some(VBValue::Object(obj)) = context.get_variable(&self.var_name).cloned() {
+            obj.indexed_set(&indices[0], value, context)
+        } else if matches!(context.get_variable(&self.var_name), Some(VBValue::Array(..))) {
             match context.get_variable_mut(&self.var_name) {
                 Some(VBValue::Array(ref mut items, ref dims)) => {
                     let flat_idx = if dims.is_empty() {
                         // Dynamic array — use first index directly
                         let idx = to_number(&indices[0]) as usize;
                         if idx >= items.len() {
-                            return Err(VBSErrorType::RuntimeError.into_error(format!(
-                                "Subscript out of range: index {} exceeds array size {}",
-                                idx,
-                                items.len()
-                            )));
+                            return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
                         }
                         idx
                     } else {
                         compute_flat_index(&indices, dims).ok_or_else(|| {
-                            VBSErrorType::RuntimeError.into_error(
-                                "Subscript out of range".to_string(),
-                            )
+                            VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE)
                         })?
                     };
                     let items = Arc::make_mut(items);
                     if flat_idx >= items.len() {
-                        return Err(VBSErrorType::RuntimeError.into_error(format!(
-                            "Subscript out of range: index {} exceeds array size {}",
-                            flat_idx,
-                            items.len()
-                        )));
+                        return Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE));
                     }
                     items[flat_idx] = value;
                     Ok(())
@@ -100,12 +64,8 @@ impl VBSyntax for ArrayAssignment {
             }
         } else {
             match context.get_variable(&self.var_name) {
-                Some(_) => Err(VBSErrorType::ValueError.into_error(format!(
-                    "Variable '{}' does not support indexed assignment",
-                    self.var_name
-                ))),
-                None => Err(VBSErrorType::RuntimeError
-                    .into_error(format!("Variable '{}' is not defined", self.var_name))),
+                Some(_) => Err(VBSError::runtime_with(codes::TYPE_MISMATCH, &self.var_name)),
+                None => Err(VBSError::runtime_with(codes::VARIABLE_UNDEFINED, &self.var_name)),
             }
         }
     }
@@ -128,7 +88,7 @@ impl VBSyntax for ArrayAssignment {
                 compiler.emit(Instruction::IndexStoreLocal(slot));
             }
         } else {
-            let idx = compiler.add_constant(VBValue::String(name_lower.into()));
+            let idx = compiler.global_variable(name_lower);
             if n_indices > 1 {
                 for index_expr in &self.index_exprs {
                     compiler.compile_expr(index_expr);
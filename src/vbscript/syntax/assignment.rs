@@ -66,7 +66,7 @@ impl VBSyntax for Assignment {
         if let Some(slot) = compiler.local_slot(&name_lower) {
             compiler.emit(Instruction::StoreLocal(slot));
         } else {
-            let idx = compiler.add_constant(VBValue::String(name_lower.into()));
+            let idx = compiler.global_variable(name_lower);
             compiler.emit(Instruction::StoreGlobal(idx));
         }
         Ok(())
@@ -2,7 +2,7 @@ use super::VBSyntax;
 use crate::vbscript::compiler::Compiler;
 use crate::vbscript::expr::{evaluate, Expr};
 use crate::vbscript::instruction::Instruction;
-use crate::vbscript::{vbs_error::VBSError, ExecutionContext, VBValue};
+use crate::vbscript::{vbs_error::VBSError, ExecutionContext};
 
 /// AST node for `Const name = expr` declarations.
 ///
@@ -37,8 +37,8 @@ impl VBSyntax for Const {
             if let Some(slot) = compiler.local_slot(&name_lower) {
                 compiler.emit(Instruction::StoreLocal(slot));
             } else {
-                let idx = compiler.add_constant(VBValue::String(name_lower.into()));
-                compiler.emit(Instruction::StoreGlobal(idx));
+                let slot = compiler.global_slot(&name_lower);
+                compiler.emit(Instruction::StoreGlobal(slot));
             }
         }
         Ok(())
@@ -47,4 +47,8 @@ impl VBSyntax for Const {
     fn clone_box(&self) -> Box<dyn VBSyntax> {
         Box::new(self.clone())
     }
+
+    fn declared_names(&self) -> Vec<&str> {
+        self.var_names.iter().map(|(name, _)| name.as_str()).collect()
+    }
 }
@@ -32,13 +32,11 @@ impl VBSyntax for Dim {
                 }
                 Some(dim_exprs) => {
                     let mut dim_bounds = Vec::new();
-                    let mut total_size = 1usize;
                     for dim_expr in dim_exprs {
                         let val = evaluate(dim_expr, context)?;
-                        let bound = to_number(&val) as usize;
-                        dim_bounds.push(bound);
-                        total_size *= bound + 1;
+                        dim_bounds.push(to_number(&val) as usize);
                     }
+                    let total_size = context.limits.array_size(&dim_bounds)?;
                     context.set_variable(
                         var_name,
                         VBValue::Array(
@@ -54,22 +52,22 @@ impl VBSyntax for Dim {
 
     fn compile(&self, compiler: &mut Compiler) -> Result<(), VBSError> {
         for (var_name, dims) in &self.var_names {
-            let slot = compiler.allocate_local(var_name);
+            let slot = compiler.declare_variable(var_name);
             match dims {
                 None => {
                     compiler.emit(Instruction::LoadEmpty);
-                    compiler.emit(Instruction::StoreLocal(slot));
+                    compiler.emit_store(slot);
                 }
                 Some(dim_exprs) if dim_exprs.is_empty() => {
                     compiler.emit(Instruction::NewArray(0));
-                    compiler.emit(Instruction::StoreLocal(slot));
+                    compiler.emit_store(slot);
                 }
                 Some(dim_exprs) => {
                     for dim_expr in dim_exprs {
                         compiler.compile_expr(dim_expr);
                     }
                     compiler.emit(Instruction::NewArray(dim_exprs.len() as u8));
-                    compiler.emit(Instruction::StoreLocal(slot));
+                    compiler.emit_store(slot);
                 }
             }
         }
@@ -79,4 +77,8 @@ impl VBSyntax for Dim {
     fn clone_box(&self) -> Box<dyn VBSyntax> {
         Box::new(self.clone())
     }
+
+    fn declared_names(&self) -> Vec<&str> {
+        self.var_names.iter().map(|(name, _)| name.as_str()).collect()
+    }
 }
@@ -42,7 +42,7 @@ impl VBSyntax for Erase {
             if let Some(slot) = compiler.local_slot(&name_lower) {
                 compiler.emit(Instruction::Erase(slot));
             } else {
-                let idx = compiler.add_constant(VBValue::String(name_lower.into()));
+                let idx = compiler.global_variable(name_lower);
                 compiler.emit(Instruction::EraseGlobal(idx));
             }
         }
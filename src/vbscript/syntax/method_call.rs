@@ -1,9 +1,10 @@
 use super::super::compiler::Compiler;
 use super::super::expr::{evaluate, Expr};
 use super::super::instruction::Instruction;
+use super::super::symbol::Symbol;
 use super::super::value::VBValue;
 use super::super::value_utils;
-use super::super::vbs_error::{VBSError, VBSErrorType};
+use super::super::vbs_error::{codes, VBSError, VBSErrorType};
 use super::super::ExecutionContext;
 use super::VBSyntax;
 
@@ -52,45 +53,22 @@ fn try_property_indexed_access(
         return Ok(None);
     }
     let obj_ref = if object_name == "__with_obj__" {
-        context
-            .with_object
-            .take()
-            .ok_or_else(|| {
-                VBSErrorType::RuntimeError.into_error("With object not set".to_string())
-            })?
+        context.with_object.clone().ok_or_else(|| {
+            VBSError::runtime(codes::OBJECT_VARIABLE_NOT_SET)
+        })?
     } else {
-        let slot = context
-            .get_variable_mut(object_name)
-            .and_then(|v| match v {
-                VBValue::Object(_) => Some(v),
-                _ => None,
-            });
-        match slot {
-            Some(slot) => {
-                let mut replacement = VBValue::Empty;
-                std::mem::swap(slot, &mut replacement);
-                replacement
-            }
-            None => return Ok(None),
+        match context.get_variable(object_name) {
+            Some(v @ VBValue::Object(_)) => v.clone(),
+            _ => return Ok(None),
         }
     };
     if let VBValue::Object(ref obj) = obj_ref {
         if let Ok(VBValue::Object(sub_obj)) = obj.get_property(property, context).as_ref() {
             if let Ok(result) = sub_obj.indexed_get(&args[0], context) {
-                if object_name == "__with_obj__" {
-                    context.with_object = Some(obj_ref);
-                } else {
-                    context.set_variable(object_name, obj_ref);
-                }
                 return Ok(Some(result));
             }
         }
     }
-    if object_name == "__with_obj__" {
-        context.with_object = Some(obj_ref);
-    } else {
-        context.set_variable(object_name, obj_ref);
-    }
     Ok(None)
 }
 
@@ -98,7 +76,7 @@ fn write_binary_value(value: &VBValue) -> Vec<u8> {
     match value {
         VBValue::Array(items, _dims) => {
             items.iter().map(|v| match v {
-                VBValue::Number(n) => *n as u8,
+                n if n.is_number() => value_utils::to_arg_f64(n) as u8,
                 VBValue::Boolean(b) => *b as u8,
                 other => other.to_string().as_bytes().first().copied().unwrap_or(0),
             }).collect()
@@ -154,7 +132,7 @@ impl VBSyntax for MethodCall {
                 "BINARYWRITE" => {
                     if let Some(arg) = args.first() {
                         let bytes = write_binary_value(arg);
-                        context.response.write_binary(&bytes);
+                        context.write_binary(&bytes)?;
                     }
                     return Ok(());
                 }
@@ -196,53 +174,34 @@ impl VBSyntax for MethodCall {
 
         if self.object_name == "__with_obj__" {
             // With-block method call: use context.with_object
-            let mut obj_val = context.with_object.take().ok_or_else(|| {
-                VBSErrorType::RuntimeError.into_error("With object not set".to_string())
+            let obj_val = context.with_object.clone().ok_or_else(|| {
+                VBSError::runtime(codes::OBJECT_VARIABLE_NOT_SET)
             })?;
-            match &mut obj_val {
-                VBValue::Object(ref mut obj) => {
-                    obj.call_method(&self.method_name, &args, context)?;
-                }
-                _ => {
-                    context.with_object = Some(obj_val);
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error("With object is not an object".to_string()));
-                }
-            }
-            context.with_object = Some(obj_val);
-            return Ok(());
+            return match &obj_val {
+                VBValue::Object(obj) => obj.call_method(&self.method_name, &args, context).map(|_| ()),
+                _ => Err(VBSError::runtime(codes::OBJECT_REQUIRED)),
+            };
         }
 
-        match context.get_variable_mut(&self.object_name) {
-            Some(v @ VBValue::Object(_)) => {
-                let mut obj_val = VBValue::Empty;
-                std::mem::swap(v, &mut obj_val);
-                let result = match &mut obj_val {
-                    VBValue::Object(ref mut obj) => {
-                        obj.call_method(&self.method_name, &args, context)
-                    }
-                    _ => unreachable!(),
-                };
-                context.set_variable(&self.object_name.to_uppercase(), obj_val);
-                result.map(|_| ())
+        match context.get_variable(&self.object_name).cloned() {
+            Some(VBValue::Object(obj)) => {
+                obj.call_method(&self.method_name, &args, context).map(|_| ())
             }
-            _ => Err(VBSErrorType::RuntimeError
-                .into_error(format!("Object variable '{}' is not set", self.object_name))),
+            _ => Err(VBSError::runtime_with(codes::OBJECT_REQUIRED, &self.object_name)),
         }
     }
 
     fn compile(&self, compiler: &mut Compiler) -> Result<(), VBSError> {
         let name_lower = self.object_name.to_lowercase();
-        let method_idx = compiler.add_constant(VBValue::String(self.method_name.to_lowercase().into()));
-        for arg in &self.args {
-            compiler.compile_expr(arg);
-        }
+        let method = Symbol::intern(&self.method_name);
+        let call = compiler.compile_call_args(&self.args);
         if let Some(slot) = compiler.local_slot(&name_lower) {
-            compiler.emit(Instruction::CallMethodLocal(slot, method_idx, self.args.len() as u8));
+            compiler.emit(Instruction::CallMethodLocal(slot, method, self.args.len() as u8));
         } else {
-            let obj_idx = compiler.add_constant(VBValue::String(name_lower.into()));
-            compiler.emit(Instruction::CallMethodGlobal(obj_idx, method_idx, self.args.len() as u8));
+            let obj_idx = compiler.global_variable(name_lower);
+            compiler.emit(Instruction::CallMethodGlobal(obj_idx, method, self.args.len() as u8));
         }
+        compiler.emit_byref_writebacks(call);
         Ok(())
     }
 
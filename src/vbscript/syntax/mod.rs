@@ -6,6 +6,10 @@ pub trait VBSyntax {
     fn execute(&self, context: &mut crate::vbscript::ExecutionContext) -> Result<(), VBSError>;
     fn compile(&self, compiler: &mut Compiler) -> Result<(), VBSError>;
     fn clone_box(&self) -> Box<dyn VBSyntax>;
+    /// Variable names this statement declares (`Dim`, `ReDim`, `Const`).
+    fn declared_names(&self) -> Vec<&str> {
+        Vec::new()
+    }
 }
 
 // Re-export all syntax constructs
@@ -1,8 +1,9 @@
 use super::super::compiler::Compiler;
 use super::super::expr::{evaluate, Expr};
 use super::super::instruction::Instruction;
+use super::super::symbol::Symbol;
 use super::super::value::VBValue;
-use super::super::vbs_error::{VBSError, VBSErrorType};
+use super::super::vbs_error::{codes, VBSError};
 use super::super::ExecutionContext;
 use super::VBSyntax;
 
@@ -33,59 +34,30 @@ impl VBSyntax for PropertySet {
         let value = evaluate(&self.value_expr, context)?;
 
         if self.object_name == "__with_obj__" {
-            // With-block property set: swap with_object out, modify, put back
-            let mut obj_val = context.with_object.take().ok_or_else(|| {
-                VBSErrorType::RuntimeError.into_error("With object not set".to_string())
+            let obj_val = context.with_object.clone().ok_or_else(|| {
+                VBSError::runtime(codes::OBJECT_VARIABLE_NOT_SET)
             })?;
-            let result = match &mut obj_val {
-                VBValue::Object(ref mut obj) => obj.set_property(&self.property, value, context),
-                _ => {
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error("With object is not an object".to_string()));
-                }
+            return match &obj_val {
+                VBValue::Object(obj) => obj.set_property(&self.property, value, context),
+                _ => Err(VBSError::runtime(codes::OBJECT_REQUIRED)),
             };
-            context.with_object = Some(obj_val);
-            return result;
         }
 
-        let obj_key = self.object_name.to_uppercase();
-
-        // Take the object out of context, replacing with Empty temporarily
-        let mut obj_val = {
-            let slot: &mut VBValue = match context.get_variable_mut(&self.object_name) {
-                Some(v @ VBValue::Object(_)) => v,
-                _ => {
-                    return Err(VBSErrorType::RuntimeError
-                        .into_error(format!("Object variable '{}' is not set", self.object_name)));
-                }
-            };
-            let mut replacement = VBValue::Empty;
-            std::mem::swap(slot, &mut replacement);
-            replacement
-        };
-
-        // Now the borrow on slot is dropped. obj_val owns the VBValue::Object.
-        let result = match &mut obj_val {
-            VBValue::Object(ref mut obj) => {
-                obj.set_property(&self.property, value, context)
-            }
-            _ => unreachable!(),
-        };
-
-        // Put the object back (even on error, to avoid corrupting state)
-        context.set_variable(&obj_key, obj_val);
-        result
+        match context.get_variable(&self.object_name).cloned() {
+            Some(VBValue::Object(obj)) => obj.set_property(&self.property, value, context),
+            _ => Err(VBSError::runtime_with(codes::OBJECT_REQUIRED, &self.object_name)),
+        }
     }
 
     fn compile(&self, compiler: &mut Compiler) -> Result<(), VBSError> {
         let name_lower = self.object_name.to_lowercase();
         compiler.compile_expr(&self.value_expr);
-        let prop_idx = compiler.add_constant(VBValue::String(self.property.to_lowercase().into()));
+        let prop = Symbol::intern(&self.property);
         if let Some(slot) = compiler.local_slot(&name_lower) {
-            compiler.emit(Instruction::SetPropLocal(slot, prop_idx));
+            compiler.emit(Instruction::SetPropLocal(slot, prop));
         } else {
-            let obj_idx = compiler.add_constant(VBValue::String(name_lower.into()));
-            compiler.emit(Instruction::SetPropGlobal(obj_idx, prop_idx));
+            let obj_idx = compiler.global_variable(name_lower);
+            compiler.emit(Instruction::SetPropGlobal(obj_idx, prop));
         }
         Ok(())
     }
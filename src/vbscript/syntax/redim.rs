@@ -32,19 +32,16 @@ impl VBSyntax for ReDim {
     fn execute(&self, context: &mut ExecutionContext) -> Result<(), VBSError> {
         // Evaluate all dimension bounds
         let mut dim_bounds = Vec::new();
-        let mut total_size = 1usize;
         for expr in &self.size_exprs {
             let val = evaluate(expr, context)?;
-            let bound = to_number(&val) as usize;
-            dim_bounds.push(bound);
-            total_size *= bound + 1;
+            dim_bounds.push(to_number(&val) as usize);
         }
+        let total_size = context.limits.array_size(&dim_bounds)?;
 
         if self.preserve {
             if dim_bounds.len() > 1 {
-                return Err(crate::vbscript::vbs_error::VBSErrorType::RuntimeError.into_error(
-                    "ReDim Preserve can only change the last dimension of a multi-dimensional array"
-                        .to_string(),
+                return Err(crate::vbscript::vbs_error::VBSError::runtime(
+                    crate::vbscript::vbs_error::codes::SUBSCRIPT_OUT_OF_RANGE,
                 ));
             }
             let size = dim_bounds[0];
@@ -79,7 +76,7 @@ impl VBSyntax for ReDim {
 
     fn compile(&self, compiler: &mut Compiler) -> Result<(), VBSError> {
         let name_lower = self.var_name.to_lowercase();
-        let slot = compiler.allocate_local(&name_lower);
+        let slot = compiler.declare_variable(&name_lower);
         for size_expr in &self.size_exprs {
             compiler.compile_expr(size_expr);
         }
@@ -90,4 +87,8 @@ impl VBSyntax for ReDim {
     fn clone_box(&self) -> Box<dyn VBSyntax> {
         Box::new(self.clone())
     }
+
+    fn declared_names(&self) -> Vec<&str> {
+        vec![self.var_name.as_str()]
+    }
 }
@@ -4,7 +4,7 @@ use crate::vbscript::compiler::Compiler;
 use crate::vbscript::execution_context::CookieEntry;
 use crate::vbscript::expr::{evaluate, Expr};
 use crate::vbscript::instruction::Instruction;
-use crate::vbscript::value::VBValue;
+use crate::vbscript::symbol::{sym, Symbol};
 use crate::vbscript::{vbs_error::VBSError, ExecutionContext};
 
 #[derive(Clone)]
@@ -42,10 +42,9 @@ impl VBSyntax for ResponseCookiesSet {
     }
 
     fn compile(&self, compiler: &mut Compiler) -> Result<(), VBSError> {
-        let response_idx = compiler.add_constant(VBValue::String("response".into()));
-        compiler.emit(Instruction::LoadGlobal(response_idx));
-        let cookies_idx = compiler.add_constant(VBValue::String("cookies".into()));
-        compiler.emit(Instruction::GetProp(cookies_idx));
+        let response_slot = compiler.global_slot("response");
+        compiler.emit(Instruction::LoadGlobal(response_slot));
+        compiler.emit(Instruction::GetProp(sym::COOKIES));
         compiler.compile_expr(&self.key);
         compiler.compile_expr(&self.value);
         compiler.emit(Instruction::IndexSet);
@@ -100,15 +99,13 @@ impl VBSyntax for ResponseCookiesSetProp {
     }
 
     fn compile(&self, compiler: &mut Compiler) -> Result<(), VBSError> {
-        let response_idx = compiler.add_constant(VBValue::String("response".into()));
-        compiler.emit(Instruction::LoadGlobal(response_idx));
-        let cookies_idx = compiler.add_constant(VBValue::String("cookies".into()));
-        compiler.emit(Instruction::GetProp(cookies_idx));
+        let response_slot = compiler.global_slot("response");
+        compiler.emit(Instruction::LoadGlobal(response_slot));
+        compiler.emit(Instruction::GetProp(sym::COOKIES));
         compiler.compile_expr(&self.key);
         compiler.emit(Instruction::IndexGet);
         compiler.compile_expr(&self.value);
-        let prop_idx = compiler.add_constant(VBValue::String(self.property.to_lowercase().into()));
-        compiler.emit(Instruction::SetProp(prop_idx));
+        compiler.emit(Instruction::SetProp(Symbol::intern(&self.property)));
         Ok(())
     }
 
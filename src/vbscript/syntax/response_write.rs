@@ -22,8 +22,7 @@ impl ResponseWrite {
 impl VBSyntax for ResponseWrite {
     fn execute(&self, context: &mut ExecutionContext) -> Result<(), VBSError> {
         let value = evaluate(&self.expr, context)?;
-        context.write(&value.to_string());
-        Ok(())
+        context.write(&value.to_string())
     }
 
     fn compile(&self, compiler: &mut Compiler) -> Result<(), VBSError> {
@@ -125,7 +125,8 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
         let result = interpreter.execute("For Each x In 42\nNext", &mut context);
         assert!(result.is_err());
         let err = result.unwrap_err();
-        assert!(err.message.contains("Object doesn't support"));
+        assert_eq!(err.code, 451);
+        assert_eq!(err.message, "Object not a collection");
     }
 
     #[test]
@@ -545,13 +546,36 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
             .execute("Session(\"username\") = \"Alice\"", &mut ctx)
             .unwrap();
         // Check stored value via local store
-        let session_val = {
-            let data = store.lock_sessions();
-            data.get("TEST-SESSION-001")
-                .and_then(|d| d.get("USERNAME"))
-                .cloned()
+        let session_val = store.get_session_var("TEST-SESSION-001", "USERNAME").unwrap();
+        assert_eq!(session_val, VBValue::String("Alice".into()));
+    }
+
+    #[test]
+    fn test_asp_session_dictionary_changes_persist() {
+        use crate::vbscript::store::{FileBackend, Store};
+        let dir = tmp_asp_dir();
+        let run = |script: &str| {
+            let store = Store::with_backend(Box::new(FileBackend::open(&dir).unwrap()));
+            let mut ctx = ExecutionContext::new();
+            ctx.store = Some(Arc::clone(&store));
+            ctx.session.id = "cart-session".to_string();
+            ctx.request_id = store.allocate_request_id();
+            crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+            VBScriptInterpreter.execute(script, &mut ctx).unwrap();
+            store.save_fetched(ctx.request_id);
+            ctx.response.buffer.clone()
         };
-        assert_eq!(session_val, Some(VBValue::String("Alice".into())));
+
+        run("Dim d\nSet d = CreateObject(\"Scripting.Dictionary\")\nd.Add \"a\", 1\nSession(\"cart\") = d\nApplication(\"seen\") = CreateObject(\"Scripting.Dictionary\")");
+        run("Dim cart, again, seen\nSet cart = Session(\"cart\")\ncart.Add \"b\", 2\nSet again = Session(\"cart\")\nagain.Add \"c\", 3\nSet seen = Application(\"seen\")\nseen.Add \"x\", True");
+        assert_eq!(
+            run("Response.Write Session(\"cart\").Count & \" \" & Session(\"cart\").Item(\"c\") & \" \" & Application(\"seen\").Count"),
+            "3 3 1"
+        );
+        // Replacing the variable wins over the object read before
+        run("Dim cart\nSet cart = Session(\"cart\")\ncart.Add \"d\", 4\nSession(\"cart\") = \"gone\"");
+        assert_eq!(run("Response.Write Session(\"cart\")"), "gone");
+        cleanup_dir(&dir);
     }
 
     #[test]
@@ -580,6 +604,25 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
         assert_eq!(ctx.get_variable("t"), Some(&VBValue::Number(20.0)));
     }
 
+    #[test]
+    fn test_asp_session_timeout_set() {
+        let store = crate::vbscript::store::Store::new();
+        let mut ctx = ExecutionContext::new();
+        ctx.store = Some(Arc::clone(&store));
+        ctx.session.id = "timeout-test".to_string();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute("Dim t\nSession.Timeout = 5\nt = Session.Timeout", &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.get_variable("t"), Some(&VBValue::Number(5.0)));
+        assert_eq!(store.session_timeout("timeout-test"), 5);
+        let err = interp.execute("Session.Timeout = 0", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 5);
+        let err = interp.execute("Session.Timeout = 1441", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 5);
+    }
+
     #[test]
     fn test_asp_session_contents_count() {
         let store = crate::vbscript::store::Store::new();
@@ -678,8 +721,7 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
             .execute("Session(\"data\") = \"keep me\"", &mut ctx)
             .unwrap();
         interp.execute("Session.Abandon", &mut ctx).unwrap();
-        let data = store.lock_sessions();
-        assert!(!data.contains_key("ABANDON-TEST"));
+        assert!(!store.session_ids().contains(&"ABANDON-TEST".to_string()));
     }
 
     #[test]
@@ -754,6 +796,154 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
         assert_eq!(ctx.get_variable("t"), Some(&VBValue::Number(90.0)));
     }
 
+    #[test]
+    fn test_asp_server_scripttimeout_set() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute("Dim t\nServer.ScriptTimeout = 300\nt = Server.ScriptTimeout", &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.get_variable("t"), Some(&VBValue::Number(300.0)));
+        assert_eq!(ctx.script_timeout(), 300);
+        let err = interp.execute("Server.ScriptTimeout = -1", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 5);
+    }
+
+    #[test]
+    fn test_asp_server_scripttimeout_zero_is_one_second() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        ctx.enforce_script_timeout();
+        VBScriptInterpreter
+            .execute("Server.ScriptTimeout = 0\nDim i, n\nFor i = 1 To 1000\nn = n + i\nNext", &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.script_timeout(), 1);
+        assert_eq!(ctx.get_variable("n"), Some(&VBValue::Number(500500.0)));
+    }
+
+    #[test]
+    fn test_script_timeout_aborts_runaway_loop() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_script_timeout(0);
+        ctx.enforce_script_timeout();
+        let err = VBScriptInterpreter
+            .execute("Dim n\nn = 0\nDo\nn = n + 1\nLoop", &mut ctx)
+            .unwrap_err();
+        assert!(err.message.contains("ASP 0113"), "got: {}", err);
+        assert!(ctx.script_timed_out());
+    }
+
+    #[test]
+    fn test_script_timeout_not_trapped_by_on_error() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_script_timeout(0);
+        ctx.enforce_script_timeout();
+        let err = VBScriptInterpreter
+            .execute(
+                "On Error Resume Next\nDim after\nSub Spin()\nDo\nLoop\nEnd Sub\nCall Spin()\nafter = True",
+                &mut ctx,
+            )
+            .unwrap_err();
+        assert!(err.message.contains("ASP 0113"), "got: {}", err);
+        assert_ne!(ctx.get_variable("after"), Some(&VBValue::Boolean(true)));
+    }
+
+    #[test]
+    fn test_script_timeout_needs_enforcing() {
+        let mut ctx = ExecutionContext::new();
+        ctx.set_script_timeout(0);
+        VBScriptInterpreter
+            .execute("Dim i, n\nFor i = 1 To 5000\nn = n + i\nNext", &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.get_variable("n"), Some(&VBValue::Number(12502500.0)));
+    }
+
+    #[test]
+    fn test_limit_call_depth_raises_out_of_stack_space() {
+        let mut ctx = ExecutionContext::new();
+        ctx.limits.max_call_depth = 20;
+        let err = VBScriptInterpreter
+            .execute("Function F(n)\nF = F(n + 1)\nEnd Function\nDim x\nx = F(0)", &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.code, 28);
+        assert_eq!(err.message, "Out of stack space");
+
+        // Recursion within the limit still works, and the depth is unwound
+        VBScriptInterpreter
+            .execute("Function G(n)\nIf n > 0 Then\nG = G(n - 1) + 1\nElse\nG = 0\nEnd If\nEnd Function\nDim y\ny = G(15)", &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.get_variable("y"), Some(&VBValue::Integer(15)));
+    }
+
+    #[test]
+    fn test_limit_array_size_raises_out_of_memory() {
+        let mut ctx = ExecutionContext::new();
+        ctx.limits.max_array_elements = 1000;
+        let interp = VBScriptInterpreter;
+        interp.execute("Dim a\nReDim a(999)", &mut ctx).unwrap();
+        let err = interp.execute("ReDim a(1000)", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 7);
+        let err = interp.execute("ReDim a(100, 100)", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 7);
+        let err = interp.execute("ReDim a(1E+18, 1E+18)", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 7);
+        interp
+            .execute("On Error Resume Next\nReDim Preserve a(5000)\nDim n\nn = Err.Number", &mut ctx)
+            .unwrap();
+        assert_eq!(ctx.get_variable("n"), Some(&VBValue::Number(7.0)));
+    }
+
+    #[test]
+    fn test_limit_string_length_raises_out_of_memory() {
+        let mut ctx = ExecutionContext::new();
+        let interp = VBScriptInterpreter;
+        let err = interp.execute("Dim s\ns = String(1E+9, \"x\")", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 7);
+        assert_eq!(err.message, "Out of memory");
+
+        ctx.limits.max_string_length = 1000;
+        interp.execute("s = String(600, \"a\")", &mut ctx).unwrap();
+        let err = interp.execute("s = s & String(600, \"b\")", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 7);
+        let err = interp.execute("s = Space(1001)", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 7);
+
+        // The limit ends with the script; the next one on this thread has
+        // its own
+        assert!(crate::vbscript::builtins::check_string_length(2000).is_ok());
+        let mut other = ExecutionContext::new();
+        other.limits.max_string_length = 0;
+        interp.execute("s = Space(2000)", &mut other).unwrap();
+    }
+
+    #[test]
+    fn test_limit_response_bytes_raises_out_of_memory() {
+        let mut ctx = ExecutionContext::new();
+        ctx.limits.max_response_bytes = 10;
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let err = VBScriptInterpreter
+            .execute("Response.Write \"12345\"\nResponse.Write \"678901\"", &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.code, 7);
+        assert_eq!(ctx.response.buffer, "12345");
+    }
+
+    #[test]
+    fn test_limit_instructions_aborts_script() {
+        let mut ctx = ExecutionContext::new();
+        ctx.limits.max_instructions = 10_000;
+        let err = VBScriptInterpreter
+            .execute("On Error Resume Next\nDo\nLoop", &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.message, crate::vbscript::execution_context::INSTRUCTION_LIMIT_EXCEEDED);
+        assert_eq!(
+            ctx.aborted(),
+            Some(crate::vbscript::execution_context::ScriptAbort::InstructionLimit)
+        );
+        assert!(!ctx.script_timed_out());
+    }
+
     #[test]
     fn test_asp_server_scriptpath() {
         let mut ctx = ExecutionContext::new();
@@ -851,11 +1041,8 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
                 &mut ctx,
             )
             .unwrap();
-        let val = {
-            let data = store.lock_apps();
-            data.get("KEY").cloned()
-        };
-        assert_eq!(val, Some(VBValue::String("val".into())));
+        let val = store.get_app_var("KEY").unwrap();
+        assert_eq!(val, VBValue::String("val".into()));
     }
 
     #[test]
@@ -1141,6 +1328,10 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
             enable_directory_listing: false,
             default_documents: None,
             log_level: None,
+            disable_page_cache: true,
+            page_cache_size: None,
+            worker_threads: None,
+            request_queue_max: None,
         };
         let server = crate::asp::server::AspServer::new(config);
         let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -1159,17 +1350,20 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
                         let folder = folder.clone();
                         tokio::spawn(async move {
                             let mut stream = stream;
-                            let dir_cache = crate::asp::config::DirConfigCache::new(
+                            let dir_cache = Arc::new(crate::asp::config::DirConfigCache::new(
                                 crate::asp::config::AspDirConfig {
                                     default_documents: vec!["index.asp".to_string()],
                                     directory_listing: false,
+                                    script_timeout: 90,
+                                    limits: Default::default(),
                                 },
                                 std::path::Path::new(&folder)
                                     .canonicalize()
                                     .unwrap_or_else(|_| std::path::Path::new(&folder).to_path_buf()),
-                            );
+                            ));
                             let _ = crate::asp::server::AspServer::handle_connection(
-                                &mut stream, &folder, &dir_cache, &store,
+                                &mut stream, &folder, dir_cache,
+                                Arc::new(crate::asp::page_cache::PageCache::disabled()), &store,
                             ).await;
                         });
                     }
@@ -1373,3 +1567,101 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 
         cleanup_dir(&dir);
     }
+
+    // ===== PAGE CACHE =====
+
+    fn get_with_cache(
+        dir: &std::path::Path,
+        path: &str,
+        page_cache: &crate::asp::page_cache::PageCache,
+    ) -> String {
+        let folder = dir.to_str().unwrap();
+        let dir_cache = crate::asp::config::DirConfigCache::new(
+            crate::asp::config::AspDirConfig {
+                default_documents: vec!["index.asp".to_string()],
+                directory_listing: false,
+                script_timeout: 90,
+                limits: Default::default(),
+            },
+            dir.canonicalize().unwrap(),
+        );
+        let request = crate::asp::server::HttpRequest {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query_string: String::new(),
+            headers: ahash::AHashMap::new(),
+            body: Vec::new(),
+            cookies: ahash::AHashMap::new(),
+        };
+        let store = crate::vbscript::store::Store::new();
+        let response = crate::asp::server::AspServer::process_request(
+            request, folder, &dir_cache, page_cache, &store, None,
+        )
+        .unwrap();
+        String::from_utf8(response.body).unwrap()
+    }
+
+    #[test]
+    fn test_page_cache_reuses_compiled_page() {
+        let dir = tmp_asp_dir();
+        write_asp(
+            &dir,
+            "page.asp",
+            "<% Option Explicit\nClass Greeter\nPublic Function Hello(n)\n Hello = \"Hi \" & n\nEnd Function\nEnd Class\n\
+             Function Twice(x)\n Twice = x * 2\nEnd Function\n\
+             Dim g\nSet g = New Greeter\nResponse.Write g.Hello(Twice(21)) %>",
+        );
+        let cache = crate::asp::page_cache::PageCache::new(8);
+
+        let first = get_with_cache(&dir, "page.asp", &cache);
+        assert_eq!(first, "Hi 42");
+        assert_eq!(cache.len(), 1);
+        let second = get_with_cache(&dir, "page.asp", &cache);
+        assert_eq!(second, first);
+        assert_eq!(cache.len(), 1);
+
+        cleanup_dir(&dir);
+    }
+
+    #[test]
+    fn test_page_cache_keys_pages_by_canonical_path() {
+        let dir = tmp_asp_dir();
+        write_asp(&dir, "page.asp", "<%= \"hi\" %>");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let cache = crate::asp::page_cache::PageCache::new(8);
+
+        for path in ["page.asp", "./page.asp", "sub/../page.asp"] {
+            assert_eq!(get_with_cache(&dir, path, &cache), "hi");
+        }
+        assert_eq!(cache.len(), 1);
+
+        cleanup_dir(&dir);
+    }
+
+    #[test]
+    fn test_page_cache_recompiles_after_include_change() {
+        let dir = tmp_asp_dir();
+        write_asp(&dir, "page.asp", "[<!-- #include file=\"part.inc\" -->]");
+        std::fs::write(dir.join("part.inc"), "<%= \"one\" %>").unwrap();
+        let cache = crate::asp::page_cache::PageCache::new(8);
+
+        assert_eq!(get_with_cache(&dir, "page.asp", &cache), "[one]");
+        std::fs::write(dir.join("part.inc"), "<%= \"two, changed\" %>").unwrap();
+        assert_eq!(get_with_cache(&dir, "page.asp", &cache), "[two, changed]");
+
+        cleanup_dir(&dir);
+    }
+
+    #[test]
+    fn test_page_cache_keeps_option_explicit() {
+        let dir = tmp_asp_dir();
+        write_asp(&dir, "page.asp", "<% Option Explicit\nExecute \"y = 1\" %>");
+        let cache = crate::asp::page_cache::PageCache::new(8);
+
+        for _ in 0..2 {
+            let body = get_with_cache(&dir, "page.asp", &cache);
+            assert!(body.contains("Variable is undefined"), "got: {}", body);
+        }
+
+        cleanup_dir(&dir);
+    }
@@ -853,3 +853,238 @@ use super::*;
         // Erase on undefined variable should not error
         interp.execute("Erase nonexistent", &mut ctx).unwrap();
     }
+
+    // ===== BYREF / BYVAL PARAMETERS =====
+
+    fn run_byref(code: &str) -> ExecutionContext {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        VBScriptInterpreter.execute(code, &mut ctx).unwrap();
+        ctx
+    }
+
+    #[test]
+    fn test_byref_is_default_for_sub_params() {
+        let ctx = run_byref(
+            "Sub Swap(a, b)\n    Dim t\n    t = a\n    a = b\n    b = t\nEnd Sub\nx = 1\ny = 2\nSwap x, y",
+        );
+        assert_eq!(ctx.get_variable("x"), Some(&VBValue::Number(2.0)));
+        assert_eq!(ctx.get_variable("y"), Some(&VBValue::Number(1.0)));
+    }
+
+    #[test]
+    fn test_byval_leaves_caller_unchanged() {
+        let ctx = run_byref("Sub Bump(ByVal n)\n    n = n + 1\nEnd Sub\nx = 5\nCall Bump(x)");
+        assert_eq!(ctx.get_variable("x"), Some(&VBValue::Number(5.0)));
+    }
+
+    #[test]
+    fn test_explicit_byref_with_call_statement() {
+        let ctx = run_byref(
+            "Sub AppendLog(ByRef buf, ByVal msg)\n    buf = buf & msg & \";\"\n    msg = \"\"\nEnd Sub\nlog = \"\"\nm = \"a\"\nCall AppendLog(log, m)\nAppendLog log, \"b\"",
+        );
+        assert_eq!(ctx.get_variable("log"), Some(&VBValue::String("a;b;".into())));
+        assert_eq!(ctx.get_variable("m"), Some(&VBValue::String("a".into())));
+    }
+
+    #[test]
+    fn test_byref_function_updates_arg_and_returns() {
+        let ctx = run_byref(
+            "Function TakeOne(count)\n    count = count - 1\n    TakeOne = count * 10\nEnd Function\nn = 3\nr = TakeOne(n)",
+        );
+        assert_eq!(ctx.get_variable("n"), Some(&VBValue::Number(2.0)));
+        assert_eq!(ctx.get_variable("r"), Some(&VBValue::Number(20.0)));
+    }
+
+    #[test]
+    fn test_byref_page_level_dim_variable() {
+        let ctx = run_byref(
+            "Dim total\ntotal = 1\nSub Double(v)\n    v = v * 2\nEnd Sub\nDouble total\nDouble total\nresult = total",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(4.0)));
+    }
+
+    #[test]
+    fn test_byref_local_variable_inside_function() {
+        let ctx = run_byref(
+            "Sub Inc(v)\n    v = v + 1\nEnd Sub\nFunction Outer()\n    Dim k\n    k = 10\n    Inc k\n    Inc k\n    Outer = k\nEnd Function\nresult = Outer()",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(12.0)));
+    }
+
+    #[test]
+    fn test_byref_array_element() {
+        let ctx = run_byref(
+            "Dim arr(2)\narr(1) = 7\nSub Inc(v)\n    v = v + 1\nEnd Sub\nInc arr(1)\nresult = arr(1)",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(8.0)));
+    }
+
+    #[test]
+    fn test_byref_object_property() {
+        let ctx = run_byref(
+            "Class Counter\n    Public Count\nEnd Class\nSub Inc(v)\n    v = v + 1\nEnd Sub\nSet c = New Counter\nc.Count = 1\nInc c.Count\nresult = c.Count",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(2.0)));
+    }
+
+    #[test]
+    fn test_byref_target_is_evaluated_once() {
+        let ctx = run_byref(
+            "Dim arr(3), calls\nFunction NextIdx()\n    calls = calls + 1\n    NextIdx = calls\nEnd Function\nSub Inc(v)\n    v = v + 1\nEnd Sub\nInc arr(NextIdx())\nSub Deeper()\n    Inc arr(NextIdx() * 1)\nEnd Sub\nCall Deeper()\nresult = calls & \":\" & arr(1) & arr(2) & arr(3)",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("2:11".into())));
+    }
+
+    #[test]
+    fn test_byref_writes_back_to_element_bound_before_call() {
+        let ctx = run_byref(
+            "Dim arr(2), i\nSub SetBoth(j, v)\n    v = \"set\"\n    j = 2\nEnd Sub\ni = 1\nSetBoth i, arr(i)\nresult = i & \":\" & arr(1) & \":\" & arr(2)",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("2:set:".into())));
+    }
+
+    #[test]
+    fn test_byref_property_of_returned_object_is_evaluated_once() {
+        let ctx = run_byref(
+            "Class Counter\n    Public Count\nEnd Class\nDim c, calls\nSet c = New Counter\nc.Count = 1\nFunction Target()\n    calls = calls + 1\n    Set Target = c\nEnd Function\nSub Inc(v)\n    v = v + 1\nEnd Sub\nInc Target().Count\nresult = calls & \":\" & c.Count",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("1:2".into())));
+    }
+
+    #[test]
+    fn test_byref_class_method_param() {
+        let ctx = run_byref(
+            "Class Parser\n    Public Sub NextToken(ByRef pos, ByVal delta)\n        pos = pos + delta\n        delta = 0\n    End Sub\nEnd Class\nSet p = New Parser\ni = 1\ns = 3\np.NextToken i, s\nresult = i",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(4.0)));
+        assert_eq!(ctx.get_variable("s"), Some(&VBValue::Number(3.0)));
+    }
+
+    #[test]
+    fn test_byref_literal_argument_is_ignored() {
+        let ctx = run_byref("Sub Inc(v)\n    v = v + 1\nEnd Sub\nInc 5\nresult = \"ok\"");
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("ok".into())));
+    }
+
+    #[test]
+    fn test_parenthesized_argument_is_passed_by_value() {
+        let ctx = run_byref(
+            "Sub Inc(v)\n    v = v + 1\nEnd Sub\nFunction TakeOne(count)\n    count = count - 1\n    TakeOne = count\nEnd Function\n\
+             Dim arr(1)\nk = 1\nn = 3\nInc((k))\nInc (k)\nCall Inc((k))\nInc((arr(1)))\nr = TakeOne((n))\nCall Inc(k)\na = IsEmpty(arr(1))",
+        );
+        assert_eq!(ctx.get_variable("k"), Some(&VBValue::Number(2.0)));
+        assert_eq!(ctx.get_variable("n"), Some(&VBValue::Number(3.0)));
+        assert_eq!(ctx.get_variable("r"), Some(&VBValue::Number(2.0)));
+        assert_eq!(ctx.get_variable("a"), Some(&VBValue::Boolean(true)));
+    }
+
+    #[test]
+    fn test_parse_param_list_modifiers() {
+        use crate::vbscript::block::parse_param_list;
+        use crate::vbscript::Token;
+        let tokens = Tokenizer::tokenize("(ByVal a, ByRef B, c())");
+        let refs: Vec<&Token> = tokens.iter().filter(|t| t.token_type != TokenType::WhiteSpace).collect();
+        let params = parse_param_list(&refs);
+        let summary: Vec<(&str, bool)> = params.iter().map(|p| (p.name.as_str(), p.by_val)).collect();
+        assert_eq!(summary, vec![("a", true), ("b", false), ("c", false)]);
+    }
+
+    // ===== OPTION EXPLICIT =====
+
+    fn run_explicit(code: &str) -> (ExecutionContext, Result<(), crate::vbscript::vbs_error::VBSError>) {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        ctx.script_path = "/site/page.asp".to_string();
+        let result = VBScriptInterpreter.execute(code, &mut ctx);
+        (ctx, result)
+    }
+
+    #[test]
+    fn test_option_explicit_undeclared_write_raises_500() {
+        let (ctx, result) = run_explicit("Option Explicit\nDim total\ntotal = 1\ntotl = 2");
+        let err = result.unwrap_err();
+        assert_eq!(err.code, 500);
+        assert_eq!(err.message, "Variable is undefined: 'totl' (/site/page.asp, line 4)");
+        assert!(ctx.get_variable("totl").is_none());
+    }
+
+    #[test]
+    fn test_option_explicit_undeclared_read_raises_500() {
+        let (ctx, result) = run_explicit("Option Explicit\nDim a\na = 1\nResponse.Write a & b");
+        let err = result.unwrap_err();
+        assert_eq!(err.code, 500);
+        assert!(err.message.starts_with("Variable is undefined: 'b'"));
+        assert_eq!(ctx.response.buffer, "");
+    }
+
+    #[test]
+    fn test_option_explicit_accepts_declared_names() {
+        let (ctx, result) = run_explicit(
+            "Option Explicit\nConst LIMIT = 3\nDim i, s\nReDim arr(2)\n\
+             Function Twice(n)\n    Dim r\n    r = n * 2\n    Twice = r\nEnd Function\n\
+             For i = 1 To LIMIT\n    arr(i - 1) = Twice(i)\n    s = s & arr(i - 1)\nNext\n\
+             Response.Write s & vbCrLf\nErr.Clear",
+        );
+        result.unwrap();
+        assert_eq!(ctx.response.buffer, "246\r\n");
+    }
+
+    #[test]
+    fn test_option_explicit_undeclared_loop_counter() {
+        let (_, result) = run_explicit("Option Explicit\nFor k = 1 To 2\nNext");
+        let err = result.unwrap_err();
+        assert_eq!(err.code, 500);
+        assert!(err.message.starts_with("Variable is undefined: 'k'"));
+    }
+
+    #[test]
+    fn test_option_explicit_reports_line_inside_procedure() {
+        let (_, result) = run_explicit(
+            "Option Explicit\nSub Work(n)\n    Dim x\n    x = n\n    y = x\nEnd Sub\nCall Work(1)",
+        );
+        let err = result.unwrap_err();
+        assert_eq!(err.message, "Variable is undefined: 'y' (/site/page.asp, line 5)");
+    }
+
+    #[test]
+    fn test_option_explicit_reports_file_line_of_page() {
+        let blocks = crate::asp::parser::AspParser::new(
+            "<html>\n<% Option Explicit\n\n%><p>x</p>\n<% b = 2 %>\n</html>".to_string(),
+        )
+        .parse();
+        let blocks: Vec<_> = blocks.iter().collect();
+        let script = VBScriptInterpreter.compile_vm_blocks(&blocks).unwrap().unwrap();
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        ctx.script_path = "/site/page.asp".to_string();
+        let err = VBScriptInterpreter.run_script(&script, &mut ctx).unwrap_err();
+        assert_eq!(err.message, "Variable is undefined: 'b' (/site/page.asp, line 5)");
+    }
+
+    #[test]
+    fn test_option_explicit_in_class_method() {
+        let (ctx, result) = run_explicit(
+            "Option Explicit\nClass Counter\n    Private m_n\n    Public Sub Add(d)\n        m_n = m_n + d\n        cnt = m_n\n    End Sub\nEnd Class\n\
+             Dim c\nSet c = New Counter\nc.Add 2",
+        );
+        let err = result.unwrap_err();
+        assert_eq!(err.message, "Variable is undefined: 'cnt' (/site/page.asp, line 6)");
+        assert!(ctx.get_variable("cnt").is_none());
+    }
+
+    #[test]
+    fn test_option_explicit_error_is_trappable() {
+        let (ctx, result) = run_explicit(
+            "Option Explicit\nOn Error Resume Next\nmissing = 1\nResponse.Write Err.Number",
+        );
+        result.unwrap();
+        assert_eq!(ctx.response.buffer, "500");
+    }
+
+    #[test]
+    fn test_undeclared_variables_allowed_without_option_explicit() {
+        let (ctx, result) = run_explicit("x = 1\nFor k = 1 To 2\nNext");
+        result.unwrap();
+        assert_eq!(ctx.get_variable("x"), Some(&VBValue::Number(1.0)));
+    }
@@ -305,8 +305,8 @@ use super::*;
         let interp = VBScriptInterpreter;
         interp.execute("result = Now()", &mut ctx).unwrap();
         let val = ctx.get_variable("result");
-        assert!(matches!(val, Some(VBValue::Number(_))));
-        if let Some(VBValue::Number(n)) = val {
+        assert!(matches!(val, Some(VBValue::Date(_))));
+        if let Some(VBValue::Date(n)) = val {
             assert!(*n > 0.0);
         }
     }
@@ -316,14 +316,14 @@ use super::*;
         let mut ctx = ExecutionContext::new();
         crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
         let interp = VBScriptInterpreter;
-        interp.execute("result = Date()", &mut ctx).unwrap();
+        interp.execute("result = Date()\ntext = CStr(result)", &mut ctx).unwrap();
         assert!(matches!(
             ctx.get_variable("result"),
-            Some(VBValue::String(_))
+            Some(VBValue::Date(d)) if d.fract() == 0.0
         ));
-        if let Some(VBValue::String(s)) = ctx.get_variable("result") {
-            assert!(!s.is_empty());
+        if let Some(VBValue::String(s)) = ctx.get_variable("text") {
             assert!(s.contains('/'));
+            assert!(!s.contains(':'));
         }
     }
 
@@ -332,14 +332,14 @@ use super::*;
         let mut ctx = ExecutionContext::new();
         crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
         let interp = VBScriptInterpreter;
-        interp.execute("result = Time()", &mut ctx).unwrap();
+        interp.execute("result = Time()\ntext = CStr(result)", &mut ctx).unwrap();
         assert!(matches!(
             ctx.get_variable("result"),
-            Some(VBValue::String(_))
+            Some(VBValue::Date(d)) if d.trunc() == 0.0
         ));
-        if let Some(VBValue::String(s)) = ctx.get_variable("result") {
-            assert!(!s.is_empty());
+        if let Some(VBValue::String(s)) = ctx.get_variable("text") {
             assert!(s.contains(':'));
+            assert!(!s.contains('/'));
         }
     }
 
@@ -513,13 +513,13 @@ use super::*;
                 &mut ctx,
             )
             .unwrap();
-        if let Some(VBValue::Number(n)) = ctx.get_variable("result") {
+        if let Some(VBValue::Date(n)) = ctx.get_variable("result") {
             let dt = crate::vbscript::builtins::ole_auto_to_datetime(*n).unwrap();
             assert_eq!(dt.day(), 11);
             assert_eq!(dt.month(), 1);
             assert_eq!(dt.year(), 2024);
         } else {
-            panic!("Expected Number");
+            panic!("Expected Date");
         }
     }
 
@@ -534,13 +534,13 @@ use super::*;
                 &mut ctx,
             )
             .unwrap();
-        if let Some(VBValue::Number(n)) = ctx.get_variable("result") {
+        if let Some(VBValue::Date(n)) = ctx.get_variable("result") {
             let dt = crate::vbscript::builtins::ole_auto_to_datetime(*n).unwrap();
             // Jan 31 + 1 month = Feb 28 (or 29 in leap year; 2024 is leap)
             assert_eq!(dt.month(), 2);
             assert_eq!(dt.day(), 29);
         } else {
-            panic!("Expected Number");
+            panic!("Expected Date");
         }
     }
 
@@ -570,13 +570,13 @@ use super::*;
         interp
             .execute("result = DateSerial(2024, 7, 4)", &mut ctx)
             .unwrap();
-        if let Some(VBValue::Number(n)) = ctx.get_variable("result") {
+        if let Some(VBValue::Date(n)) = ctx.get_variable("result") {
             let dt = crate::vbscript::builtins::ole_auto_to_datetime(*n).unwrap();
             assert_eq!(dt.year(), 2024);
             assert_eq!(dt.month(), 7);
             assert_eq!(dt.day(), 4);
         } else {
-            panic!("Expected Number");
+            panic!("Expected Date");
         }
     }
 
@@ -588,13 +588,13 @@ use super::*;
         interp
             .execute("result = DateValue(\"2024-07-04\")", &mut ctx)
             .unwrap();
-        if let Some(VBValue::Number(n)) = ctx.get_variable("result") {
+        if let Some(VBValue::Date(n)) = ctx.get_variable("result") {
             let dt = crate::vbscript::builtins::ole_auto_to_datetime(*n).unwrap();
             assert_eq!(dt.year(), 2024);
             assert_eq!(dt.month(), 7);
             assert_eq!(dt.day(), 4);
         } else {
-            panic!("Expected Number");
+            panic!("Expected Date");
         }
     }
 
@@ -606,12 +606,12 @@ use super::*;
         interp
             .execute("result = TimeSerial(10, 30, 0)", &mut ctx)
             .unwrap();
-        if let Some(VBValue::Number(n)) = ctx.get_variable("result") {
+        if let Some(VBValue::Date(n)) = ctx.get_variable("result") {
             let dt = crate::vbscript::builtins::ole_auto_to_datetime(*n).unwrap();
             assert_eq!(dt.hour(), 10);
             assert_eq!(dt.minute(), 30);
         } else {
-            panic!("Expected Number");
+            panic!("Expected Date");
         }
     }
 
@@ -623,12 +623,12 @@ use super::*;
         interp
             .execute("result = TimeValue(\"14:30:00\")", &mut ctx)
             .unwrap();
-        if let Some(VBValue::Number(n)) = ctx.get_variable("result") {
+        if let Some(VBValue::Date(n)) = ctx.get_variable("result") {
             let dt = crate::vbscript::builtins::ole_auto_to_datetime(*n).unwrap();
             assert_eq!(dt.hour(), 14);
             assert_eq!(dt.minute(), 30);
         } else {
-            panic!("Expected Number");
+            panic!("Expected Date");
         }
     }
 
@@ -762,7 +762,7 @@ use super::*;
             Some(VBValue::Number(n)) => *n,
             _ => panic!("expected Number"),
         };
-        assert!((val - 0.7853981633974483).abs() < 1e-10);
+        assert!((val - std::f64::consts::FRAC_PI_4).abs() < 1e-10);
     }
 
     #[test]
@@ -885,7 +885,7 @@ use super::*;
             .unwrap();
         assert!(matches!(
             ctx.get_variable("result"),
-            Some(VBValue::Number(_))
+            Some(VBValue::Date(_))
         ));
     }
 
@@ -906,8 +906,8 @@ use super::*;
         interp
             .execute("r1 = CLng(3.14)\nr2 = CLng(-3.9)", &mut ctx)
             .unwrap();
-        assert_eq!(ctx.get_variable("r1"), Some(&VBValue::Number(3.0)));
-        assert_eq!(ctx.get_variable("r2"), Some(&VBValue::Number(-3.0)));
+        assert!(matches!(ctx.get_variable("r1"), Some(VBValue::Long(3))));
+        assert!(matches!(ctx.get_variable("r2"), Some(VBValue::Long(-4))));
     }
 
     #[test]
@@ -1200,3 +1200,187 @@ use super::*;
             Some(&VBValue::String("12.3%".into()))
         );
     }
+
+    // ===== EVAL / EXECUTE / EXECUTEGLOBAL =====
+
+    fn run_dynamic(code: &str) -> ExecutionContext {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        VBScriptInterpreter.execute(code, &mut ctx).unwrap();
+        ctx
+    }
+
+    #[test]
+    fn test_builtin_eval_expression() {
+        let ctx = run_dynamic("a = 6\nresult = Eval(\"a * 7\")\nsame = Eval(\"a = 6\")");
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(42.0)));
+        // `=` inside Eval is a comparison, not an assignment
+        assert_eq!(ctx.get_variable("same"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("a"), Some(&VBValue::Number(6.0)));
+    }
+
+    #[test]
+    fn test_builtin_eval_sees_locals() {
+        let ctx = run_dynamic(
+            "Function Calc(n)\n    Dim k\n    k = 10\n    Calc = Eval(\"n + k\")\nEnd Function\nDim base\nbase = 2\nresult = Calc(5) + Eval(\"base\")",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(17.0)));
+    }
+
+    #[test]
+    fn test_builtin_execute_statements_in_local_scope() {
+        let ctx = run_dynamic(
+            "Function Build()\n    Dim s\n    s = \"a\"\n    Execute \"s = s & \"\"b\"\"\"\n    Build = s\nEnd Function\ns = \"outer\"\nresult = Build()",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("ab".into())));
+        assert_eq!(ctx.get_variable("s"), Some(&VBValue::String("outer".into())));
+    }
+
+    #[test]
+    fn test_builtin_execute_page_level_dim() {
+        let ctx = run_dynamic("Dim total\ntotal = 1\nExecute \"total = total + 1\"\nresult = total");
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(2.0)));
+    }
+
+    #[test]
+    fn test_builtin_executeglobal_defines_function_and_class() {
+        let ctx = run_dynamic(
+            "Sub Load()\n    ExecuteGlobal \"Function Plugin(x)\" & vbCrLf & \"Plugin = x & \"\"!\"\"\" & vbCrLf & \"End Function\"\n    ExecuteGlobal \"Class Greeter\" & vbCrLf & \"Public Name\" & vbCrLf & \"End Class\"\nEnd Sub\nCall Load()\nresult = Plugin(\"hi\")\nSet g = New Greeter\ng.Name = \"x\"\nname = g.Name",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("hi!".into())));
+        assert_eq!(ctx.get_variable("name"), Some(&VBValue::String("x".into())));
+    }
+
+    #[test]
+    fn test_builtin_executeglobal_ignores_procedure_locals() {
+        let ctx = run_dynamic(
+            "counter = 1\nSub Bump()\n    Dim counter\n    counter = 100\n    ExecuteGlobal \"counter = counter + 1\"\nEnd Sub\nCall Bump()\nresult = counter",
+        );
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(2.0)));
+    }
+
+    #[test]
+    fn test_builtin_eval_syntax_error_is_raised() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let result = VBScriptInterpreter.execute("result = Eval(\"1 +\")", &mut ctx);
+        assert!(result.is_err());
+    }
+
+    // ===== VARIANT SUBTYPES =====
+
+    fn var_type(ctx: &ExecutionContext, name: &str) -> i16 {
+        match ctx.get_variable(name) {
+            Some(VBValue::Integer(vt)) => *vt,
+            other => panic!("expected VarType result for {}, got {:?}", name, other),
+        }
+    }
+
+    #[test]
+    fn test_builtin_vartype_subtypes() {
+        let ctx = run_dynamic(
+            "r1 = VarType(CInt(1))\nr2 = VarType(CLng(1))\nr3 = VarType(CSng(1))\nr4 = VarType(CDbl(1))\nr5 = VarType(CCur(1))\nr6 = VarType(CDate(\"2024-07-04\"))\nr7 = VarType(CByte(1))\nr8 = VarType(Len(\"abc\"))",
+        );
+        let expected = [2, 3, 4, 5, 6, 7, 17, 3];
+        for (i, vt) in expected.iter().enumerate() {
+            assert_eq!(var_type(&ctx, &format!("r{}", i + 1)), *vt, "r{}", i + 1);
+        }
+    }
+
+    #[test]
+    fn test_builtin_integer_overflow_promotes_to_long() {
+        let ctx = run_dynamic("a = 32000\nb = a + 1000\nt = TypeName(b)\nc = TypeName(a + 1)");
+        assert!(matches!(ctx.get_variable("b"), Some(VBValue::Long(33000))));
+        assert_eq!(ctx.get_variable("t"), Some(&VBValue::String("Long".into())));
+        assert_eq!(ctx.get_variable("c"), Some(&VBValue::String("Integer".into())));
+    }
+
+    #[test]
+    fn test_builtin_cint_overflow_and_rounding() {
+        let ctx = run_dynamic("r1 = CInt(2.5)\nr2 = CInt(3.5)\nOn Error Resume Next\nr3 = CInt(40000)\nn = Err.Number");
+        assert!(matches!(ctx.get_variable("r1"), Some(VBValue::Integer(2))));
+        assert!(matches!(ctx.get_variable("r2"), Some(VBValue::Integer(4))));
+        assert_eq!(ctx.get_variable("n"), Some(&VBValue::Number(6.0)));
+    }
+
+    #[test]
+    fn test_builtin_numeric_conversions_parse_strings() {
+        let ctx = run_dynamic(
+            "r1 = CInt(\" 12 \")\nr2 = CDbl(\"2.5\")\nr3 = CLng(Empty)\nr4 = \"4\" + 1\nr5 = \"4\" + \"1\"\nOn Error Resume Next\nr6 = CInt(\"abc\")\nn = Err.Number",
+        );
+        assert!(matches!(ctx.get_variable("r1"), Some(VBValue::Integer(12))));
+        assert_eq!(ctx.get_variable("r2"), Some(&VBValue::Number(2.5)));
+        assert!(matches!(ctx.get_variable("r3"), Some(VBValue::Long(0))));
+        assert_eq!(ctx.get_variable("r4"), Some(&VBValue::Number(5.0)));
+        assert_eq!(ctx.get_variable("r5"), Some(&VBValue::String("41".into())));
+        assert_eq!(ctx.get_variable("r6"), Some(&VBValue::Empty));
+        assert_eq!(ctx.get_variable("n"), Some(&VBValue::Number(13.0)));
+    }
+
+    #[test]
+    fn test_builtin_ccur_is_exact() {
+        let ctx = run_dynamic("total = CCur(0)\nFor i = 1 To 10\n    total = total + CCur(0.1)\nNext\nok = (total = 1)\nt = TypeName(total)\ns = CStr(CCur(\"19.999\"))");
+        assert_eq!(ctx.get_variable("ok"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("t"), Some(&VBValue::String("Currency".into())));
+        assert_eq!(ctx.get_variable("s"), Some(&VBValue::String("19.999".into())));
+    }
+
+    #[test]
+    fn test_builtin_date_subtype_arithmetic() {
+        let ctx = run_dynamic("d = CDate(\"2024-07-04\") + 1\nt = TypeName(d)\ndiff = TypeName(d - CDate(\"2024-07-04\"))\nisd = IsDate(d)");
+        assert_eq!(ctx.get_variable("t"), Some(&VBValue::String("Date".into())));
+        assert_eq!(ctx.get_variable("diff"), Some(&VBValue::String("Double".into())));
+        assert_eq!(ctx.get_variable("isd"), Some(&VBValue::Boolean(true)));
+    }
+
+    #[test]
+    fn test_builtin_for_counter_keeps_integer_subtype() {
+        let ctx = run_dynamic("For i = 1 To 3\nNext\nt = TypeName(i)\nFor j = 1 To 2 Step 0.5\nNext\nu = TypeName(j)");
+        assert_eq!(ctx.get_variable("t"), Some(&VBValue::String("Integer".into())));
+        assert_eq!(ctx.get_variable("u"), Some(&VBValue::String("Double".into())));
+    }
+
+    // ===== DATE VALUES =====
+
+    #[test]
+    fn test_date_literal_is_date_and_displays_in_us_format() {
+        let ctx = run_dynamic("d = #1/2/2024#\nt = TypeName(d)\ns = CStr(d)\ndt = CStr(#1/2/2024 13:05:09#)\ntm = CStr(#13:05:09#)");
+        assert_eq!(ctx.get_variable("t"), Some(&VBValue::String("Date".into())));
+        assert_eq!(ctx.get_variable("s"), Some(&VBValue::String("1/2/2024".into())));
+        assert_eq!(ctx.get_variable("dt"), Some(&VBValue::String("1/2/2024 1:05:09 PM".into())));
+        assert_eq!(ctx.get_variable("tm"), Some(&VBValue::String("1:05:09 PM".into())));
+    }
+
+    #[test]
+    fn test_date_plus_number_stays_a_date() {
+        let ctx = run_dynamic("d = #1/31/2024# + 1\ns = \"Due: \" & d\nt = TypeName(Date + 1)\nback = TypeName(d - 1.5)");
+        assert_eq!(ctx.get_variable("s"), Some(&VBValue::String("Due: 2/1/2024".into())));
+        assert_eq!(ctx.get_variable("t"), Some(&VBValue::String("Date".into())));
+        assert_eq!(ctx.get_variable("back"), Some(&VBValue::String("Date".into())));
+    }
+
+    #[test]
+    fn test_date_comparisons() {
+        let ctx = run_dynamic("d = DateSerial(2024, 1, 2)\nr1 = d > #1/1/2024#\nr2 = d = \"1/2/2024\"\nr3 = Now > d\nr4 = d < \"12/31/2023\"");
+        assert_eq!(ctx.get_variable("r1"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("r2"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("r3"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("r4"), Some(&VBValue::Boolean(false)));
+    }
+
+    #[test]
+    fn test_date_display_follows_session_lcid() {
+        let ctx = run_dynamic("Session.LCID = 1040\ns = CStr(#7/4/2024 13:05:09#)\nm = Month(CDate(\"04/07/2024\"))\nlcid = Session.LCID");
+        assert_eq!(ctx.get_variable("s"), Some(&VBValue::String("04/07/2024 13:05:09".into())));
+        assert_eq!(ctx.get_variable("m"), Some(&VBValue::Integer(7)));
+        assert_eq!(ctx.get_variable("lcid"), Some(&VBValue::Long(1040)));
+    }
+
+    #[test]
+    fn test_date_display_uses_page_lcid() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        ctx.request.lcid = 1031;
+        VBScriptInterpreter.execute("s = CStr(DateSerial(2024, 3, 9))", &mut ctx).unwrap();
+        assert_eq!(ctx.get_variable("s"), Some(&VBValue::String("09.03.2024".into())));
+    }
@@ -98,6 +98,27 @@ use super::*;
         );
     }
 
+    #[test]
+    fn test_parse_not_takes_member_access_and_comparison() {
+        let tokens = Tokenizer::tokenize("Not rs.EOF And a = b");
+        let expr = parse_expression(&tokens).unwrap();
+        let Expr::BinaryOp { left, op: BinOp::And, right } = expr else {
+            panic!("expected And at the top, got {:?}", expr);
+        };
+        assert!(matches!(
+            *left,
+            Expr::UnaryOp { op: UnaryOp::Not, expr } if matches!(*expr, Expr::PropertyAccess { .. })
+        ));
+        assert!(matches!(*right, Expr::BinaryOp { op: BinOp::Eq, .. }));
+
+        let tokens = Tokenizer::tokenize("Not a = b");
+        let expr = parse_expression(&tokens).unwrap();
+        assert!(matches!(
+            expr,
+            Expr::UnaryOp { op: UnaryOp::Not, expr } if matches!(*expr, Expr::BinaryOp { op: BinOp::Eq, .. })
+        ));
+    }
+
     #[test]
     fn test_parse_chained_comparison() {
         let tokens = Tokenizer::tokenize("1 < 2 And 3 > 1");
@@ -541,11 +562,11 @@ use super::*;
     fn test_evaluate_is_operator() {
         let mut context = ExecutionContext::new();
         crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut context);
-        // Is compares values — two nulls are equivalent
+        // Is compares object identity — Nothing is Nothing
         let expr = Expr::BinaryOp {
-            left: Box::new(Expr::Literal(VBValue::Null)),
+            left: Box::new(Expr::Literal(VBValue::Nothing)),
             op: BinOp::Is,
-            right: Box::new(Expr::Literal(VBValue::Null)),
+            right: Box::new(Expr::Literal(VBValue::Nothing)),
         };
         assert_eq!(
             evaluate(&expr, &mut context).unwrap(),
@@ -553,6 +574,18 @@ use super::*;
         );
     }
 
+    #[test]
+    fn test_evaluate_is_operator_requires_objects() {
+        let mut context = ExecutionContext::new();
+        let expr = Expr::BinaryOp {
+            left: Box::new(Expr::Literal(VBValue::Null)),
+            op: BinOp::Is,
+            right: Box::new(Expr::Literal(VBValue::Null)),
+        };
+        let err = evaluate(&expr, &mut context).unwrap_err();
+        assert_eq!(err.code, 424);
+    }
+
     // ===== LIKE OPERATOR =====
 
     #[test]
@@ -631,16 +664,20 @@ use super::*;
     fn test_evaluate_add_string_coercion() {
         let mut context = ExecutionContext::new();
         crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut context);
-        // Number + String → string concat in VBScript-semantics
-        // Our implementation: if both are numeric, add; else concat
-        let expr = Expr::BinaryOp {
-            left: Box::new(Expr::Literal(VBValue::String("a".into()))),
+        let add = |l: &str, r: VBValue| Expr::BinaryOp {
+            left: Box::new(Expr::Literal(VBValue::String(l.into()))),
             op: BinOp::Add,
-            right: Box::new(Expr::Literal(VBValue::Number(1.0))),
+            right: Box::new(Expr::Literal(r)),
         };
-        // String + Number → concat as strings
-        let result = evaluate(&expr, &mut context).unwrap();
+        // String + String concatenates
+        let result = evaluate(&add("a", VBValue::String("1".into())), &mut context).unwrap();
         assert_eq!(result, VBValue::String("a1".into()));
+        // String + Number adds, converting the string
+        let result = evaluate(&add("4", VBValue::Number(1.0)), &mut context).unwrap();
+        assert_eq!(result, VBValue::Number(5.0));
+        // ... and a string that is not a number is a Type mismatch
+        let err = evaluate(&add("a", VBValue::Number(1.0)), &mut context).unwrap_err();
+        assert_eq!(err.code, 13);
     }
 
     #[test]
@@ -263,11 +263,12 @@ use super::*;
         ctx.set_error_mode(crate::vbscript::execution_context::ErrorMode::ResumeNext);
         interp
             .execute(
-                "On Error Resume Next\nErr.Raise 42, \"custom error\"",
+                "On Error Resume Next\nErr.Raise 42, \"MyApp\", \"custom error\"",
                 &mut ctx,
             )
             .unwrap();
         assert_eq!(ctx.err_number, 42.0);
+        assert_eq!(ctx.err_source, "MyApp");
         assert_eq!(ctx.err_description, "custom error");
     }
 
@@ -293,6 +294,133 @@ use super::*;
             .execute("On Error Resume Next\nErr.Raise 5", &mut ctx)
             .unwrap();
         assert_eq!(ctx.err_number, 5.0);
+        assert_eq!(ctx.err_description, "Invalid procedure call or argument");
+    }
+
+    #[test]
+    fn test_err_raise_help_fields_and_clear() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        VBScriptInterpreter
+            .execute(
+                "On Error Resume Next\n\
+                 Err.Raise vbObjectError + 513, \"Orders\", \"Bad order\", \"orders.chm\", 12\n\
+                 n = Err.Number\nsrc = Err.Source\nhf = Err.HelpFile\nhc = Err.HelpContext\n\
+                 Err.Clear\nafter = Err.Number & \"|\" & Err.Source & \"|\" & Err.Description",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("n"), Some(&VBValue::Long(-2147220991)));
+        assert_eq!(ctx.get_variable("src"), Some(&VBValue::String("Orders".into())));
+        assert_eq!(ctx.get_variable("hf"), Some(&VBValue::String("orders.chm".into())));
+        assert_eq!(ctx.get_variable("hc"), Some(&VBValue::Long(12)));
+        assert_eq!(ctx.get_variable("after"), Some(&VBValue::String("0||".into())));
+    }
+
+    #[test]
+    fn test_err_line_reports_failing_statement() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        VBScriptInterpreter
+            .execute(
+                "On Error Resume Next\nx = 1\ny = x / 0\nline = Err.Line\nsrc = Err.Source",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("line"), Some(&VBValue::Long(3)));
+        assert_eq!(
+            ctx.get_variable("src"),
+            Some(&VBValue::String("Microsoft VBScript runtime error".into()))
+        );
+    }
+
+    fn err_line_of_page(source: &str) -> VBValue {
+        let blocks = crate::asp::parser::AspParser::new(source.to_string()).parse();
+        let blocks: Vec<_> = blocks.iter().collect();
+        let script = VBScriptInterpreter.compile_vm_blocks(&blocks).unwrap().unwrap();
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        VBScriptInterpreter.run_script(&script, &mut ctx).unwrap();
+        ctx.get_variable("line").cloned().unwrap()
+    }
+
+    #[test]
+    fn test_err_line_counts_blank_lines() {
+        let line = err_line_of_page("<%\nDim q\nOn Error Resume Next\n\n\nx = 1/0\nline = Err.Line %>");
+        assert_eq!(line, VBValue::Long(6));
+    }
+
+    #[test]
+    fn test_err_line_counts_markup_lines() {
+        let line = err_line_of_page("hello\n<p>\n<% On Error Resume Next\nx = 1/0\nline = Err.Line %>");
+        assert_eq!(line, VBValue::Long(4));
+    }
+
+    // ===== ERROR NUMBERS =====
+
+    fn err_number_of(code: &str) -> (f64, String) {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let script = format!("On Error Resume Next\n{}", code);
+        VBScriptInterpreter.execute(&script, &mut ctx).unwrap();
+        (ctx.err_number, ctx.err_description.clone())
+    }
+
+    #[test]
+    fn test_runtime_errors_use_vbscript_numbers() {
+        assert_eq!(err_number_of("x = 1 / 0"), (11.0, "Division by zero".to_string()));
+        assert_eq!(err_number_of("x = 5 Mod 0").0, 11.0);
+        assert_eq!(err_number_of("x = \"abc\" * 2"), (13.0, "Type mismatch".to_string()));
+        assert_eq!(err_number_of("x = CInt(40000)").0, 6.0);
+        assert_eq!(err_number_of("x = CInt(\"abc\")"), (13.0, "Type mismatch".to_string()));
+        assert_eq!(err_number_of("x = CLng(\"12abc\")").0, 13.0);
+        assert_eq!(err_number_of("x = CDbl(\"\")").0, 13.0);
+        assert_eq!(err_number_of("x = CInt(Null)").0, 94.0);
+        assert_eq!(err_number_of("x = \"abc\" + 1").0, 13.0);
+        assert_eq!(err_number_of("Dim a(2)\nx = a(5)").0, 9.0);
+        assert_eq!(err_number_of("x = UBound(42)").0, 13.0);
+        assert_eq!(err_number_of("x = Year(\"not a date\")").0, 13.0);
+        assert_eq!(err_number_of("x = Mid(\"abc\")").0, 450.0);
+        assert_eq!(err_number_of("x = NoSuchFunction(1)").0, 13.0);
+    }
+
+    #[test]
+    fn test_object_errors_use_vbscript_numbers() {
+        let (number, description) =
+            err_number_of("Set d = CreateObject(\"Scripting.Dictionary\")\nx = d.NoSuchMember");
+        assert_eq!(number, 438.0);
+        assert!(description.starts_with("Object doesn't support this property or method"));
+        assert_eq!(err_number_of("x = 5\ny = x.Name").0, 424.0);
+        assert_eq!(err_number_of("Set x = Nothing\ny = x.Name").0, 91.0);
+        assert_eq!(err_number_of("Set o = CreateObject(\"No.Such.Thing\")").0, 429.0);
+        assert_eq!(
+            err_number_of("Set d = CreateObject(\"Scripting.Dictionary\")\nd.Add \"k\", 1\nd.Add \"k\", 2").0,
+            457.0
+        );
+        assert_eq!(err_number_of("Set o = New NoSuchClass").0, 506.0);
+        assert_eq!(
+            err_number_of(
+                "Set fso = CreateObject(\"Scripting.FileSystemObject\")\n\
+                 Set f = fso.OpenTextFile(\"/nonexistent/asperger/missing.txt\", 1)"
+            )
+            .0,
+            53.0
+        );
+    }
+
+    #[test]
+    fn test_err_number_comparison_in_script() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        VBScriptInterpreter
+            .execute(
+                "On Error Resume Next\nDim arr(1)\nv = arr(3)\n\
+                 If Err.Number = 9 Then Response.Write \"subscript\"\nErr.Clear\n\
+                 v = 1 / 0\nIf Err.Number = 11 Then Response.Write \" zero\"",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.response.buffer, "subscript zero");
     }
 
     // ===== REGEXP OBJECT =====
@@ -486,6 +614,149 @@ use super::*;
         );
     }
 
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_adodb_sqlite_insert_update_delete_select() {
+        let db = tmp_path(&format!("adodb_{}.db", std::process::id()));
+        cleanup_path(&db);
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        let script = format!(
+            "Set conn = CreateObject(\"ADODB.Connection\")\n\
+             conn.Open \"Provider=SQLite;Data Source={}\"\n\
+             conn.Execute \"CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT, age INTEGER)\"\n\
+             conn.Execute \"INSERT INTO people (name, age) VALUES ('Ann', 31)\"\n\
+             conn.Execute \"INSERT INTO people (name, age) VALUES ('Bob', 25)\"\n\
+             conn.Execute \"INSERT INTO people (name, age) VALUES ('Cy', 40)\"\n\
+             conn.Execute \"UPDATE people SET age = age + 1 WHERE name = 'Bob'\"\n\
+             conn.Execute \"DELETE FROM people WHERE name = 'Cy'\"\n\
+             Set rs = conn.Execute(\"SELECT name, age FROM people ORDER BY id\")\n\
+             out = \"\"\n\
+             Do While Not rs.EOF\n\
+             out = out & rs(\"name\") & \"=\" & rs.Fields(\"age\").Value & \";\"\n\
+             rs.MoveNext\n\
+             Loop\n\
+             count = rs.RecordCount\n\
+             rs.MoveFirst\n\
+             first = rs(0)\n\
+             age = rs(\"AGE\")\n\
+             names = \"\"\n\
+             For Each fld In rs.Fields\n\
+             names = names & fld.Name & \",\"\n\
+             Next\n\
+             rs.Close\n\
+             conn.Close",
+            db
+        );
+        interp.execute(&script, &mut ctx).unwrap();
+        assert_eq!(ctx.get_variable("out"), Some(&VBValue::String("Ann=31;Bob=26;".into())));
+        assert_eq!(ctx.get_variable("count"), Some(&VBValue::Number(2.0)));
+        assert_eq!(ctx.get_variable("first"), Some(&VBValue::String("Ann".into())));
+        assert_eq!(ctx.get_variable("age"), Some(&VBValue::Long(31)));
+        assert_eq!(ctx.get_variable("names"), Some(&VBValue::String("name,age,".into())));
+
+        // The rows are in the file, for a connection opened ODBC-style.
+        let script = format!(
+            "Set conn = CreateObject(\"ADODB.Connection\")\n\
+             conn.Open \"Driver={{SQLite3 ODBC Driver}};Database={}\"\n\
+             Set rs = conn.Execute(\"SELECT COUNT(*) AS n FROM people\")\n\
+             n = rs.Fields(\"n\").Value",
+            db
+        );
+        interp.execute(&script, &mut ctx).unwrap();
+        assert_eq!(ctx.get_variable("n"), Some(&VBValue::Long(2)));
+        cleanup_path(&db);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_adodb_execute_options_and_records_affected() {
+        let root = tmp_asp_dir();
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        ctx.app_root = root.clone();
+        let interp = VBScriptInterpreter;
+        interp
+            .execute(
+                "Dim added, changed, selected\n\
+                 Set conn = CreateObject(\"ADODB.Connection\")\n\
+                 conn.Open \"Provider=SQLite;Data Source=app.db\"\n\
+                 conn.Execute \"CREATE TABLE t (x INTEGER)\"\n\
+                 conn.Execute \"INSERT INTO t VALUES (1), (2), (3)\", added\n\
+                 conn.Execute \"UPDATE t SET x = x * 10 WHERE x > 1\", changed, 1\n\
+                 Set none = conn.Execute(\"DELETE FROM t WHERE x = 1\", Empty, 129)\n\
+                 is_nothing = none Is Nothing\n\
+                 Set rs = conn.Execute(\"t\", selected, 2)\n\
+                 total = 0\n\
+                 Do While Not rs.EOF\n\
+                 total = total + rs(\"x\")\n\
+                 rs.MoveNext\n\
+                 Loop\n\
+                 On Error Resume Next\n\
+                 conn.Execute \"sp_who\", Empty, 4\n\
+                 bad_option = Err.Number\n\
+                 Err.Clear\n\
+                 conn.Execute \"SELECT 1\", n, 1, 0\n\
+                 too_many = Err.Number",
+                &mut ctx,
+            )
+            .unwrap();
+        assert!(root.join("app.db").is_file());
+        assert_eq!(ctx.get_variable("added"), Some(&VBValue::Long(3)));
+        assert_eq!(ctx.get_variable("changed"), Some(&VBValue::Long(2)));
+        assert_eq!(ctx.get_variable("is_nothing"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("selected"), Some(&VBValue::Long(-1)));
+        assert_eq!(ctx.get_variable("total"), Some(&VBValue::Number(50.0)));
+        assert_eq!(ctx.get_variable("bad_option"), Some(&VBValue::Number(5.0)));
+        assert_eq!(ctx.get_variable("too_many"), Some(&VBValue::Number(450.0)));
+        cleanup_path(root.to_str().unwrap());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_adodb_errors() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute(
+                "On Error Resume Next\n\
+                 Set conn = CreateObject(\"ADODB.Connection\")\n\
+                 conn.Open \"Provider=NoSuchProvider\"\n\
+                 missing = Err.Number\n\
+                 Err.Clear\n\
+                 conn.Open \"Provider=SQLite;Data Source=:memory:\"\n\
+                 conn.Execute \"SELECT * FROM nowhere\"\n\
+                 bad_sql = Err.Number\n\
+                 src = Err.Source\n\
+                 Err.Clear\n\
+                 Set rs = conn.Execute(\"SELECT 1 AS one WHERE 0\")\n\
+                 none = rs.EOF And rs.BOF\n\
+                 rs.MoveNext\n\
+                 no_record = Err.Number\n\
+                 Err.Clear\n\
+                 Set rs = conn.Execute(\"CREATE TABLE t (x)\")\n\
+                 closed_state = rs.State\n\
+                 e = rs.EOF\n\
+                 closed = Err.Number\n\
+                 Err.Clear\n\
+                 conn.Close\n\
+                 conn.Execute \"SELECT 1\"\n\
+                 not_open = Err.Number",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("missing"), Some(&VBValue::Number(-2146824582.0)));
+        assert_eq!(ctx.get_variable("bad_sql"), Some(&VBValue::Number(-2147467259.0)));
+        assert_eq!(ctx.get_variable("src"), Some(&VBValue::String("SQLite".into())));
+        assert_eq!(ctx.get_variable("none"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("no_record"), Some(&VBValue::Number(-2146825267.0)));
+        assert_eq!(ctx.get_variable("closed_state"), Some(&VBValue::Number(0.0)));
+        assert_eq!(ctx.get_variable("closed"), Some(&VBValue::Number(-2146824584.0)));
+        assert_eq!(ctx.get_variable("not_open"), Some(&VBValue::Number(-2146824579.0)));
+    }
+
     // ===== LSET / RSET =====
 
     #[test]
@@ -543,3 +814,448 @@ use super::*;
             Some(&VBValue::String("hel".into()))
         );
     }
+
+    // ===== OBJECT REFERENCE SEMANTICS =====
+
+    #[test]
+    fn test_object_set_shares_class_instance() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute(
+                "Class Person\nPublic Name\nEnd Class\n\
+                 Set a = New Person\nSet b = a\nb.Name = \"x\"\nresult = a.Name",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("x".into())));
+    }
+
+    #[test]
+    fn test_object_set_shares_dictionary() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute(
+                "Set a = CreateObject(\"Scripting.Dictionary\")\nSet b = a\n\
+                 b.Add \"k\", 1\nresult = a.Count",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(1.0)));
+    }
+
+    #[test]
+    fn test_object_mutated_inside_sub() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute(
+                "Sub Fill(d)\n d.Add \"a\", 1\n d.Add \"b\", 2\nEnd Sub\n\
+                 Set dict = CreateObject(\"Scripting.Dictionary\")\nFill dict\nresult = dict.Count",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(2.0)));
+    }
+
+    #[test]
+    fn test_object_is_identity() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute(
+                "Set a = CreateObject(\"Scripting.Dictionary\")\nSet b = a\n\
+                 Set c = CreateObject(\"Scripting.Dictionary\")\n\
+                 same = (a Is b)\ndifferent = (a Is c)",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("same"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("different"), Some(&VBValue::Boolean(false)));
+    }
+
+    #[test]
+    fn test_object_set_nothing_releases_reference() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute(
+                "Set a = CreateObject(\"Scripting.Dictionary\")\nSet b = a\nSet a = Nothing\n\
+                 released = (a Is Nothing)\nkept = (b Is Nothing)",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("released"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("kept"), Some(&VBValue::Boolean(false)));
+        match ctx.get_variable("b") {
+            Some(VBValue::Object(obj)) => assert_eq!(obj.ref_count(), 1),
+            other => panic!("expected object, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_object_nothing_type_functions() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute(
+                "Set x = Nothing\nisobj = IsObject(x)\ntname = TypeName(x)",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("isobj"), Some(&VBValue::Boolean(true)));
+        assert_eq!(ctx.get_variable("tname"), Some(&VBValue::String("Nothing".into())));
+    }
+
+    #[test]
+    fn test_object_is_non_object_raises_424() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        let err = interp.execute("x = 1\nresult = (x Is Nothing)", &mut ctx).unwrap_err();
+        assert_eq!(err.code, 424);
+    }
+
+    #[test]
+    fn test_object_nested_dictionary_shares_identity() {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        let interp = VBScriptInterpreter;
+        interp
+            .execute(
+                "Set outer = CreateObject(\"Scripting.Dictionary\")\n\
+                 Set inner = CreateObject(\"Scripting.Dictionary\")\n\
+                 outer.Add \"child\", inner\ninner.Add \"k\", \"v\"\n\
+                 Set fetched = outer.Item(\"child\")\n\
+                 result = fetched.Item(\"k\")\nsame = (fetched Is inner)",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("v".into())));
+        assert_eq!(ctx.get_variable("same"), Some(&VBValue::Boolean(true)));
+    }
+
+    // ===== CLASS_INITIALIZE / CLASS_TERMINATE =====
+
+    const LIFECYCLE_CLASS: &str = "Class Tracked\n\
+         Public Name\n\
+         Private Sub Class_Initialize()\n Name = \"unnamed\"\n log = log & \"+\"\nEnd Sub\n\
+         Private Sub Class_Terminate()\n log = log & \"-\" & Name\nEnd Sub\n\
+         End Class\nlog = \"\"\n";
+
+    fn lifecycle_ctx() -> ExecutionContext {
+        let mut ctx = ExecutionContext::new();
+        crate::asp::server::AspServer::inject_asp_intrinsic_objects(&mut ctx);
+        ctx
+    }
+
+    #[test]
+    fn test_class_initialize_runs_on_new() {
+        let mut ctx = lifecycle_ctx();
+        let code = format!("{}Set t = New Tracked\nresult = t.Name", LIFECYCLE_CLASS);
+        VBScriptInterpreter.execute(&code, &mut ctx).unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("unnamed".into())));
+        assert_eq!(ctx.get_variable("log"), Some(&VBValue::String("+".into())));
+    }
+
+    #[test]
+    fn test_class_terminate_runs_on_set_nothing() {
+        let mut ctx = lifecycle_ctx();
+        let code = format!(
+            "{}Set t = New Tracked\nt.Name = \"a\"\nSet t = Nothing\nlog = log & \"|\"",
+            LIFECYCLE_CLASS
+        );
+        VBScriptInterpreter.execute(&code, &mut ctx).unwrap();
+        assert_eq!(ctx.get_variable("log"), Some(&VBValue::String("+-a|".into())));
+    }
+
+    #[test]
+    fn test_class_terminate_waits_for_last_reference() {
+        let mut ctx = lifecycle_ctx();
+        let code = format!(
+            "{}Set a = New Tracked\na.Name = \"x\"\nSet b = a\nSet a = Nothing\nlog = log & \"|\"\nSet b = Nothing",
+            LIFECYCLE_CLASS
+        );
+        VBScriptInterpreter.execute(&code, &mut ctx).unwrap();
+        assert_eq!(ctx.get_variable("log"), Some(&VBValue::String("+|-x".into())));
+    }
+
+    #[test]
+    fn test_class_terminate_runs_when_local_goes_out_of_scope() {
+        let mut ctx = lifecycle_ctx();
+        let code = format!(
+            "{}Sub Work()\n Dim t\n Set t = New Tracked\n t.Name = \"w\"\nEnd Sub\nCall Work()\nlog = log & \"|\"",
+            LIFECYCLE_CLASS
+        );
+        VBScriptInterpreter.execute(&code, &mut ctx).unwrap();
+        assert_eq!(ctx.get_variable("log"), Some(&VBValue::String("+-w|".into())));
+    }
+
+    #[test]
+    fn test_class_terminate_on_teardown_in_creation_order() {
+        let mut ctx = lifecycle_ctx();
+        let code = format!(
+            "{}Set z = New Tracked\nz.Name = \"first\"\nSet a = New Tracked\na.Name = \"second\"",
+            LIFECYCLE_CLASS
+        );
+        VBScriptInterpreter.execute(&code, &mut ctx).unwrap();
+        assert_eq!(ctx.get_variable("log"), Some(&VBValue::String("++".into())));
+        ctx.terminate_class_instances().unwrap();
+        assert_eq!(
+            ctx.get_variable("log"),
+            Some(&VBValue::String("++-first-second".into()))
+        );
+        // Already terminated objects are not terminated again
+        ctx.set_variable("z", VBValue::Nothing);
+        ctx.terminate_class_instances().unwrap();
+        assert_eq!(
+            ctx.get_variable("log"),
+            Some(&VBValue::String("++-first-second".into()))
+        );
+    }
+
+    #[test]
+    fn test_class_initialize_error_is_raised() {
+        let mut ctx = lifecycle_ctx();
+        let err = VBScriptInterpreter
+            .execute(
+                "Class Broken\nPrivate Sub Class_Initialize()\n Err.Raise 5\nEnd Sub\nEnd Class\nSet b = New Broken",
+                &mut ctx,
+            )
+            .unwrap_err();
+        assert_eq!(err.code, 5);
+    }
+
+    #[test]
+    fn test_class_terminate_error_is_raised() {
+        let mut ctx = lifecycle_ctx();
+        let err = VBScriptInterpreter
+            .execute(
+                "Class Broken\nPrivate Sub Class_Terminate()\n Err.Raise 5\nEnd Sub\nEnd Class\nSet b = New Broken\nSet b = Nothing",
+                &mut ctx,
+            )
+            .unwrap_err();
+        assert_eq!(err.code, 5);
+    }
+
+    // ===== DEFAULT MEMBERS =====
+
+    const DEFAULT_LIST_CLASS: &str = "Class List\n\
+         Private items\n\
+         Private Sub Class_Initialize()\n Set items = CreateObject(\"Scripting.Dictionary\")\nEnd Sub\n\
+         Public Default Property Get Item(key)\n Item = items(key)\nEnd Property\n\
+         Public Property Let Item(key, value)\n items(key) = value\nEnd Property\n\
+         Public Property Get Count\n Count = items.Count\nEnd Property\n\
+         End Class\n";
+
+    #[test]
+    fn test_default_property_get_with_index() {
+        let mut ctx = lifecycle_ctx();
+        let code = format!(
+            "{}Set list = New List\nlist(3) = \"three\"\nlist(\"Name\") = \"Bob\"\n\
+             a = list(3)\nb = list.Item(\"Name\")\nc = list.Count",
+            DEFAULT_LIST_CLASS
+        );
+        VBScriptInterpreter.execute(&code, &mut ctx).unwrap();
+        assert_eq!(ctx.get_variable("a"), Some(&VBValue::String("three".into())));
+        assert_eq!(ctx.get_variable("b"), Some(&VBValue::String("Bob".into())));
+        assert_eq!(ctx.get_variable("c"), Some(&VBValue::Number(2.0)));
+    }
+
+    #[test]
+    fn test_default_function_with_index() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "Class Doubler\nPublic Default Function Apply(n)\n Apply = n * 2\nEnd Function\nEnd Class\n\
+                 Set d = New Doubler\nresult = d(21)",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(42.0)));
+    }
+
+    #[test]
+    fn test_default_property_used_as_value() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "Class Field\nPublic Name\n\
+                 Public Default Property Get Value\n Value = \"v:\" & Name\nEnd Property\nEnd Class\n\
+                 Set f = New Field\nf.Name = \"id\"\nResponse.Write f\ns = \"[\" & f & \"]\"\nsame = (f = \"v:id\")",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.response.buffer, "v:id");
+        assert_eq!(ctx.get_variable("s"), Some(&VBValue::String("[v:id]".into())));
+        assert_eq!(ctx.get_variable("same"), Some(&VBValue::Boolean(true)));
+    }
+
+    #[test]
+    fn test_no_default_member_raises_438() {
+        let mut ctx = lifecycle_ctx();
+        let err = VBScriptInterpreter
+            .execute("Class Plain\nPublic x\nEnd Class\nSet p = New Plain\ny = p(1)", &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.code, 438);
+    }
+
+    #[test]
+    fn test_err_default_member_is_number() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "On Error Resume Next\nErr.Raise 13\nIf Err Then result = \"failed \" & Err",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("failed 13".into())));
+    }
+
+    // ===== COMPILED CLASS MEMBERS =====
+
+    #[test]
+    fn test_class_members_compiled_at_definition() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "Class Counter\nPrivate label\nPrivate n\nPublic Sub Add(k)\n n = n + k\nEnd Sub\n\
+                 Public Property Get Total\n Total = n\nEnd Property\nEnd Class",
+                &mut ctx,
+            )
+            .unwrap();
+        let class = ctx.get_class("counter").unwrap();
+        assert_eq!(class.fields, vec!["label".to_string(), "n".to_string()]);
+        let add = &class.methods["add"];
+        assert!(!add.code.instructions.is_empty());
+        assert_eq!(add.assigned_fields, vec![1]);
+        assert_eq!(add.result_slot, None);
+        let total = class.properties["total"].get.as_ref().unwrap();
+        assert!(total.assigned_fields.is_empty());
+        assert_eq!(total.result_slot, Some(2));
+    }
+
+    #[test]
+    fn test_class_member_fields_and_locals() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "total = \"global\"\n\
+                 Class Counter\nPrivate n\nPublic Sub Add(k)\n n = n + k\nEnd Sub\n\
+                 Public Sub Reset(n)\n n = 0\nEnd Sub\n\
+                 Public Function Total()\n Total = n\nEnd Function\nEnd Class\n\
+                 Set c = New Counter\nFor i = 1 To 100\n c.Add i\nNext\nc.Reset 5\nresult = c.Total()",
+                &mut ctx,
+            )
+            .unwrap();
+        // `Reset(n)` shadows the field; the return value is local to `Total`
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(5050.0)));
+        assert_eq!(ctx.get_variable("total"), Some(&VBValue::String("global".into())));
+    }
+
+    #[test]
+    fn test_nested_call_keeps_other_field_updates() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "Class Node\nPublic a\nPublic b\n\
+                 Public Sub SetA(other)\n a = 1\n other.SetB\nEnd Sub\n\
+                 Public Sub SetB()\n b = 2\nEnd Sub\nEnd Class\n\
+                 Set n = New Node\nn.SetA n\nresult = n.a & n.b",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("12".into())));
+    }
+
+    #[test]
+    fn test_property_set_receives_objects() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "Class Holder\nPrivate mode\nPrivate item\n\
+                 Public Property Let Value(v)\n mode = \"let\"\n item = v\nEnd Property\n\
+                 Public Property Set Value(v)\n mode = \"set\"\n Set item = v\nEnd Property\n\
+                 Public Property Get Kind\n Kind = mode\nEnd Property\nEnd Class\n\
+                 Set h = New Holder\nh.Value = 1\na = h.Kind\n\
+                 h.Value = CreateObject(\"Scripting.Dictionary\")\nb = h.Kind",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("a"), Some(&VBValue::String("let".into())));
+        assert_eq!(ctx.get_variable("b"), Some(&VBValue::String("set".into())));
+    }
+
+    // ===== GETREF =====
+
+    #[test]
+    fn test_getref_call_stored_reference() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "Function OnSave(a, b)\n OnSave = a & \"+\" & b\nEnd Function\n\
+                 Set handler = GetRef(\"OnSave\")\nresult = handler(\"x\", \"y\")\ntname = TypeName(handler)",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("x+y".into())));
+        assert_eq!(ctx.get_variable("tname"), Some(&VBValue::String("Object".into())));
+    }
+
+    #[test]
+    fn test_getref_sub_as_statement() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "Sub Log(msg)\n logged = logged & msg\nEnd Sub\nlogged = \"\"\n\
+                 Set h = GetRef(\"log\")\nh \"a\"\nCall h(\"b\")",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("logged"), Some(&VBValue::String("ab".into())));
+    }
+
+    #[test]
+    fn test_getref_passed_as_parameter() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "Function Twice(n)\n Twice = n * 2\nEnd Function\n\
+                 Function Apply(fn, v)\n Apply = fn(v) + 1\nEnd Function\n\
+                 result = Apply(GetRef(\"Twice\"), 20)",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::Number(41.0)));
+    }
+
+    #[test]
+    fn test_getref_stored_in_dictionary() {
+        let mut ctx = lifecycle_ctx();
+        VBScriptInterpreter
+            .execute(
+                "Function Upper(s)\n Upper = UCase(s)\nEnd Function\n\
+                 Set handlers = CreateObject(\"Scripting.Dictionary\")\n\
+                 handlers.Add \"upper\", GetRef(\"Upper\")\n\
+                 Set h = handlers(\"upper\")\nresult = h(\"abc\")",
+                &mut ctx,
+            )
+            .unwrap();
+        assert_eq!(ctx.get_variable("result"), Some(&VBValue::String("ABC".into())));
+    }
+
+    #[test]
+    fn test_getref_unknown_procedure_raises_5() {
+        let mut ctx = lifecycle_ctx();
+        let err = VBScriptInterpreter
+            .execute("Set h = GetRef(\"Missing\")", &mut ctx)
+            .unwrap_err();
+        assert_eq!(err.code, 5);
+    }
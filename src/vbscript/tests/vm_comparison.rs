@@ -353,3 +353,226 @@ fn test_vm_date_parts() {
 }
 
 
+
+/// Run `code` with and without the bytecode optimizer and check that both
+/// runs fail or succeed alike and leave the same variables and output.
+fn compare_optimized(code: &str) {
+    let mut plain = ExecutionContext::new();
+    plain.optimize = false;
+    let mut optimized = ExecutionContext::new();
+
+    let interp = crate::vbscript::VBScriptInterpreter;
+    let result1 = interp.execute(code, &mut plain);
+    let result2 = interp.execute(code, &mut optimized);
+
+    let msg = format!("\ncode: {}\nplain:     {:?}\noptimized: {:?}", code, result1, result2);
+    assert_eq!(result1.is_ok(), result2.is_ok(), "{}", msg);
+    if let (Err(e1), Err(e2)) = (&result1, &result2) {
+        assert_eq!(e1.to_string(), e2.to_string(), "{}", msg);
+    }
+    assert_eq!(plain.response.buffer, optimized.response.buffer, "{}", msg);
+
+    let vars1 = plain.variables();
+    let vars2 = optimized.variables();
+    let mut keys: Vec<&String> = vars1.keys().chain(vars2.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let v1 = vars1.get(key);
+        let v2 = vars2.get(key);
+        if matches!(v1, Some(VBValue::Object(_))) || matches!(v2, Some(VBValue::Object(_))) {
+            continue;
+        }
+        let msg = format!("\ncode: {}\nkey: {}\nplain:     {:?}\noptimized: {:?}", code, key, v1, v2);
+        assert_eq!(v1, v2, "{}", msg);
+    }
+}
+
+fn compile_optimized(code: &str) -> crate::vbscript::compiler::CompiledCode {
+    let blocks = VBScriptInterpreter.parse_code(code).unwrap().unwrap();
+    let mut ctx = ExecutionContext::new();
+    crate::vbscript::compiler::Compiler::new(&mut ctx).compile(&blocks).unwrap()
+}
+
+#[test]
+fn test_optimizer_constant_expressions() {
+    compare_optimized("x = 1 + 2 * 3");
+    compare_optimized("x = (10 - 4) / 4 ^ 2 Mod 3");
+    compare_optimized("x = 7 \\ 2 & \"-\" & -3.5");
+    compare_optimized("x = 32767 + 1\ny = CInt(1) * 1.5");
+    compare_optimized("x = (1 < 2) And Not False\ny = 5 Xor 3\nz = True Imp False");
+    compare_optimized("x = \"abc\" Like \"a*\"\ny = \"b\" = \"B\"");
+    compare_optimized("x = \"a\" & vbCrLf & \"b\" & vbTab & vbNullString");
+    compare_optimized("x = vbLf & Chr(65)\ny = vbTrue + 1");
+}
+
+#[test]
+fn test_optimizer_constant_errors_keep_runtime_behaviour() {
+    compare_optimized("x = 1 / 0");
+    compare_optimized("On Error Resume Next\nx = 1 / 0\ne = Err.Number");
+    compare_optimized("On Error Resume Next\nx = \"a\" * 2\ne = Err.Number");
+    compare_optimized("Option Explicit\nDim x\nx = \"a\" & vbCrLf");
+    compare_optimized("Option Explicit\nx = 1 + 1");
+    compare_optimized("vbCrLf = 1\nx = vbCrLf");
+}
+
+#[test]
+fn test_optimizer_constant_conditions() {
+    compare_optimized("If True Then\n    x = 1\nElse\n    x = 2\nEnd If");
+    compare_optimized("If 1 > 2 Then x = 1 Else x = 2");
+    compare_optimized("n = 0\nDo While False\n    n = n + 1\nLoop");
+    compare_optimized("n = 0\nDo While True\n    n = n + 1\n    If n > 5 Then Exit Do\nLoop");
+    compare_optimized("Select Case 2 + 1\nCase 1\n    x = \"one\"\nCase 3\n    x = \"three\"\nCase Else\n    x = \"other\"\nEnd Select");
+}
+
+#[test]
+fn test_optimizer_superinstructions() {
+    compare_optimized("Dim i, s\nFor i = 1 To 10\n    s = s & \"x\"\nNext\nr = s");
+    compare_optimized("Dim n\nn = 0\nDo While n < 100\n    n = n + 1\nLoop\nr = n");
+    compare_optimized("Dim n\nn = \"4\"\nn = n + 1\nm = \"4\"\nm = m + \"1\"\nr = n & m");
+    compare_optimized("Dim s\ns = \"a\"\nOn Error Resume Next\ns = s + 1\ne = Err.Number\nr = IsEmpty(s)");
+    compare_optimized("Dim s\ns = Null\ns = s & \"x\"\nt = Null\nt = t + 1\nr = s & IsNull(t)");
+    compare_optimized("Response.Write \"<p>\" & vbCrLf\nResponse.Write(1 + 1)");
+}
+
+#[test]
+fn test_optimizer_procedures_and_classes() {
+    compare_optimized("Function F(n)\n    Dim k\n    k = 0\n    Do While k < n\n        k = k + 2\n    Loop\n    F = k & vbCrLf\nEnd Function\nx = F(7)");
+    compare_optimized("Sub Bump(ByRef v)\n    v = v + 1\nEnd Sub\nDim a\na = 1\nBump a\nBump a\nx = a");
+    compare_optimized("Class C\n    Public Default Property Get V\n        V = 5\n    End Property\nEnd Class\nDim o\nSet o = New C\nDim r\nr = 1\nr = r + o\nx = r");
+    compare_optimized("Class Counter\n    Public Total\n    Public Sub Add\n        Total = Total + 1\n    End Sub\nEnd Class\nSet c = New Counter\nc.Add\nc.Add\nx = c.Total");
+    compare_optimized("If a Then\n    If b Then\n        x = 1\n    End If\nElse\n    For i = 1 To 3\n        If i = 2 Then\n            y = y & i\n        End If\n    Next\nEnd If");
+}
+
+#[test]
+fn test_optimizer_emits_folded_bytecode() {
+    use crate::vbscript::instruction::Instruction;
+
+    let code = compile_optimized("Response.Write(\"a\" & vbCrLf & \"b\")");
+    let writes: Vec<_> = code.instructions.iter().filter(|i| matches!(i, Instruction::WriteConst(_))).collect();
+    assert_eq!(writes.len(), 1, "{:?}", code.instructions);
+    if let Instruction::WriteConst(c) = writes[0] {
+        assert_eq!(code.constants[*c as usize], VBValue::String("a\r\nb".into()));
+    }
+    assert!(!code.instructions.iter().any(|i| matches!(i, Instruction::Concat)));
+
+    let code = compile_optimized("Dim i\ni = 0\ni = i + 1\nIf True Then i = 2");
+    assert!(code.instructions.iter().any(|i| matches!(i, Instruction::StoreGlobalConst(..))), "{:?}", code.instructions);
+    assert!(code.instructions.iter().any(|i| matches!(i, Instruction::AddGlobalConst(..))), "{:?}", code.instructions);
+    assert!(!code.instructions.iter().any(|i| matches!(i, Instruction::JumpIfFalse(_))), "{:?}", code.instructions);
+
+    let code = compile_optimized("Sub Work\n    Dim i\n    i = 0\n    i = i & \"x\"\nEnd Sub");
+    let body = &code.compiled_functions[0].1.instructions;
+    assert!(body.iter().any(|i| matches!(i, Instruction::StoreLocalConst(..))), "{:?}", body);
+    assert!(body.iter().any(|i| matches!(i, Instruction::ConcatLocalConst(..))), "{:?}", body);
+}
+
+#[test]
+fn test_optimizer_threads_jumps() {
+    use crate::vbscript::instruction::Instruction;
+
+    let code = compile_optimized("If a Then\n    If b Then\n        x = 1\n    Else\n        x = 2\n    End If\nElse\n    x = 3\nEnd If\ny = 1");
+    for (ip, inst) in code.instructions.iter().enumerate() {
+        if let Instruction::Jump(offset) = inst {
+            assert_ne!(*offset, 0, "jump to next instruction at {}", ip);
+            let target = (ip as i64 + 1 + *offset as i64) as usize;
+            assert!(
+                !matches!(code.instructions.get(target), Some(Instruction::Jump(_))),
+                "jump at {} lands on another jump: {:?}",
+                ip,
+                code.instructions
+            );
+        }
+    }
+}
+
+fn run_page(code: &str) -> ExecutionContext {
+    let mut ctx = ExecutionContext::new();
+    VBScriptInterpreter.execute(code, &mut ctx).unwrap();
+    ctx
+}
+
+#[test]
+fn test_global_slots_page_variables_in_procedures() {
+    let ctx = run_page("Dim total\ntotal = 5\nSub Bump(n)\n    total = total + n\nEnd Sub\nBump 2\nBump 3");
+    assert_eq!(ctx.get_variable("total"), Some(&VBValue::Integer(10)));
+
+    let ctx = run_page("Function Twice()\n    Twice = base * 2\nEnd Function\nDim base\nbase = 21\nx = Twice()");
+    assert_eq!(ctx.get_variable("x"), Some(&VBValue::Integer(42)));
+
+    let ctx = run_page("Dim arr(2)\nSub Fill()\n    arr(1) = \"b\"\nEnd Sub\nCall Fill()\nx = arr(1)");
+    assert_eq!(ctx.get_variable("x"), Some(&VBValue::String("b".into())));
+}
+
+#[test]
+fn test_global_slots_hoisting_and_loops() {
+    let ctx = run_page("x = IsEmpty(v)\nDim v\nv = 1");
+    assert_eq!(ctx.get_variable("x"), Some(&VBValue::Boolean(true)));
+    assert_eq!(ctx.get_variable("v"), Some(&VBValue::Integer(1)));
+
+    let ctx = run_page("s = \"\"\nFor i = 1 To 3\n    s = s & i\nNext");
+    assert_eq!(ctx.get_variable("i"), Some(&VBValue::Integer(4)));
+    assert_eq!(ctx.get_variable("s"), Some(&VBValue::String("123".into())));
+}
+
+#[test]
+fn test_global_slots_name_map_interop() {
+    let mut ctx = ExecutionContext::new();
+    ctx.set_variable("preset", VBValue::Integer(7));
+    VBScriptInterpreter.execute("Dim y\ny = preset + 1\nExecute \"z = y * 2\"\nw = Eval(\"z + 1\")", &mut ctx).unwrap();
+    assert_eq!(ctx.get_variable("y"), Some(&VBValue::Integer(8)));
+    assert_eq!(ctx.get_variable("z"), Some(&VBValue::Integer(16)));
+    assert_eq!(ctx.get_variable("w"), Some(&VBValue::Integer(17)));
+    assert!(ctx.variables().contains_key("y"));
+}
+
+#[test]
+fn test_optimizer_global_superinstructions() {
+    compare_optimized("Dim s\ns = \"\"\nn = 0\nFor i = 1 To 5\n    s = s & \"x\"\n    n = n + 2\nNext");
+    compare_optimized("Dim n\nn = 1\nSub Add()\n    n = n + 1\nEnd Sub\nAdd\nAdd");
+}
+
+#[test]
+fn test_members_compile_to_symbols() {
+    use crate::vbscript::instruction::Instruction;
+    use crate::vbscript::symbol::{sym, Symbol};
+
+    let code = compile_optimized("Set d = CreateObject(\"Scripting.Dictionary\")\nd.Add \"k\", 1\nn = d.COUNT\nx = d.Item(\"k\")");
+    assert!(code.instructions.contains(&Instruction::CallMethodGlobal(0, sym::ADD, 2)), "{:?}", code.instructions);
+    assert!(code.instructions.contains(&Instruction::GetProp(sym::COUNT)), "{:?}", code.instructions);
+    assert!(code.instructions.contains(&Instruction::CallMethod(sym::ITEM, 1)), "{:?}", code.instructions);
+
+    let code = compile_optimized("Set o = New Thing\no.SomeField = 1");
+    assert!(code.instructions.contains(&Instruction::SetPropGlobal(0, Symbol::intern("somefield"))), "{:?}", code.instructions);
+}
+
+#[test]
+fn test_member_dispatch_is_case_insensitive() {
+    let ctx = run_page("Set d = CreateObject(\"Scripting.Dictionary\")\nd.ADD \"a\", 1\nd.add \"b\", 2\nx = d.Count + d.COUNT\ny = d.exists(\"a\") And d.EXISTS(\"b\")\nErr.NUMBER = 5\nz = Err.number");
+    assert_eq!(ctx.get_variable("x"), Some(&VBValue::Number(4.0)));
+    assert_eq!(ctx.get_variable("y"), Some(&VBValue::Boolean(true)));
+    assert_eq!(ctx.get_variable("z"), Some(&VBValue::Long(5)));
+}
+
+#[test]
+fn test_optimizer_string_appends() {
+    compare_optimized("Dim i, s\ns = \"\"\nFor i = 1 To 20\n    s = s & \"<td>\" & i & \"</td>\"\nNext");
+    compare_optimized("Dim s, t\ns = \"ab\"\nt = s\ns = s & \"c\" & s\nu = t");
+    compare_optimized("Dim s\ns = 1\ns = s & Null & 2.5 & True & Empty");
+    compare_optimized("Function Grow()\n    s = s & \"!\"\n    Grow = \"?\"\nEnd Function\ns = \"a\"\ns = s & Grow() & \"b\"");
+    compare_optimized("Sub Build()\n    Dim i, s\n    For i = 1 To 5\n        s = s & i & \",\"\n    Next\n    out = s\nEnd Sub\nCall Build()");
+    compare_optimized("On Error Resume Next\ns = \"a\"\ns = s & undefinedThing & \"b\"");
+}
+
+#[test]
+fn test_optimizer_emits_appends() {
+    use crate::vbscript::instruction::Instruction;
+
+    let code = compile_optimized("Dim s\ns = s & \"<td>\" & i & \"</td>\"");
+    assert!(code.instructions.contains(&Instruction::AppendGlobal(0, 3)), "{:?}", code.instructions);
+    assert!(!code.instructions.contains(&Instruction::Concat), "{:?}", code.instructions);
+
+    let code = compile_optimized("Sub Work\n    Dim s, t\n    s = s & t\nEnd Sub");
+    let body = &code.compiled_functions[0].1.instructions;
+    assert!(body.iter().any(|i| matches!(i, Instruction::AppendLocal(_, 1))), "{:?}", body);
+}
@@ -8,7 +8,7 @@ use std::sync::{Arc, Mutex};
 use super::execution_context::ExecutionContext;
 use super::value::VBValue;
 use super::value_utils;
-use super::vbs_error::{VBSError, VBSErrorType};
+use super::vbs_error::{codes, VBSError};
 use crate::vbscript::vbobject::VBScriptObject;
 use crate::{impl_vbscript_object, prop_not_found, method_not_found};
 
@@ -80,23 +80,21 @@ impl TextStream {
 
     fn check_closed(inner: &TextStreamInner) -> Result<(), VBSError> {
         if inner.closed {
-            return Err(VBSErrorType::RuntimeError.into_error("TextStream is closed".to_string()));
+            return Err(VBSError::runtime(codes::BAD_FILE_MODE));
         }
         Ok(())
     }
 
     fn check_reader(inner: &TextStreamInner) -> Result<(), VBSError> {
         if inner.reader.is_none() {
-            return Err(VBSErrorType::RuntimeError
-                .into_error("TextStream is not open for reading".to_string()));
+            return Err(VBSError::runtime(codes::BAD_FILE_MODE));
         }
         Ok(())
     }
 
     fn check_writer(inner: &TextStreamInner) -> Result<(), VBSError> {
         if inner.writer.is_none() {
-            return Err(VBSErrorType::RuntimeError
-                .into_error("TextStream is not open for writing".to_string()));
+            return Err(VBSError::runtime(codes::BAD_FILE_MODE));
         }
         Ok(())
     }
@@ -122,7 +120,7 @@ impl VBScriptObject for TextStream {
     }
 
     fn call_method(
-        &mut self,
+        &self,
         name: &str,
         args: &[VBValue],
         _context: &mut ExecutionContext,
@@ -151,8 +149,7 @@ impl VBScriptObject for TextStream {
                     inner.at_end_of_line = buf.last() == Some(&b'\n');
                     Ok(VBValue::String(String::from_utf8_lossy(&buf).to_string().into()))
                 } else {
-                    Err(VBSErrorType::RuntimeError
-                        .into_error("TextStream is not open for reading".to_string()))
+                    Err(VBSError::runtime(codes::BAD_FILE_MODE))
                 }
             }
             "READLINE" => {
@@ -176,8 +173,7 @@ impl VBScriptObject for TextStream {
                     inner.column = 1;
                     Ok(VBValue::String(line.into()))
                 } else {
-                    Err(VBSErrorType::RuntimeError
-                        .into_error("TextStream is not open for reading".to_string()))
+                    Err(VBSError::runtime(codes::BAD_FILE_MODE))
                 }
             }
             "READALL" => {
@@ -189,8 +185,7 @@ impl VBScriptObject for TextStream {
                     inner.at_end_of_line = true;
                     Ok(VBValue::String(content.into()))
                 } else {
-                    Err(VBSErrorType::RuntimeError
-                        .into_error("TextStream is not open for reading".to_string()))
+                    Err(VBSError::runtime(codes::BAD_FILE_MODE))
                 }
             }
             "WRITE" => {
@@ -206,8 +201,7 @@ impl VBScriptObject for TextStream {
                     }
                     Ok(VBValue::Empty)
                 } else {
-                    Err(VBSErrorType::RuntimeError
-                        .into_error("TextStream is not open for writing".to_string()))
+                    Err(VBSError::runtime(codes::BAD_FILE_MODE))
                 }
             }
             "WRITELINE" => {
@@ -223,8 +217,7 @@ impl VBScriptObject for TextStream {
                     inner.column = 1;
                     Ok(VBValue::Empty)
                 } else {
-                    Err(VBSErrorType::RuntimeError
-                        .into_error("TextStream is not open for writing".to_string()))
+                    Err(VBSError::runtime(codes::BAD_FILE_MODE))
                 }
             }
             "WRITEBLANKLINES" => {
@@ -238,8 +231,7 @@ impl VBScriptObject for TextStream {
                     inner.column = 1;
                     Ok(VBValue::Empty)
                 } else {
-                    Err(VBSErrorType::RuntimeError
-                        .into_error("TextStream is not open for writing".to_string()))
+                    Err(VBSError::runtime(codes::BAD_FILE_MODE))
                 }
             }
             "SKIP" => {
@@ -254,8 +246,7 @@ impl VBScriptObject for TextStream {
                     }
                     Ok(VBValue::Empty)
                 } else {
-                    Err(VBSErrorType::RuntimeError
-                        .into_error("TextStream is not open for reading".to_string()))
+                    Err(VBSError::runtime(codes::BAD_FILE_MODE))
                 }
             }
             "SKIPLINE" => {
@@ -270,8 +261,7 @@ impl VBScriptObject for TextStream {
                     inner.column = 1;
                     Ok(VBValue::Empty)
                 } else {
-                    Err(VBSErrorType::RuntimeError
-                        .into_error("TextStream is not open for reading".to_string()))
+                    Err(VBSError::runtime(codes::BAD_FILE_MODE))
                 }
             }
             "CLOSE" => {
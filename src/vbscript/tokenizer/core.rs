@@ -22,8 +22,8 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    fn tok(tt: TokenType, value: String) -> Token {
-        Token { token_type: tt, value: Arc::from(value) }
+    fn tok(&self, tt: TokenType, value: String) -> Token {
+        Token { token_type: tt, value: Arc::from(value), line: self.current_line as u32 }
     }
 
     pub fn tokenize(code: &'a str) -> Vec<Token> {
@@ -85,7 +85,7 @@ impl<'a> Tokenizer<'a> {
             }
         }
 
-        Some(Self::tok(TokenType::EOF, String::new()))
+        Some(self.tok(TokenType::EOF, String::new()))
     }
 
     fn consume_whitespace(&mut self) {
@@ -99,6 +99,7 @@ impl<'a> Tokenizer<'a> {
 
     fn handle_newline(&mut self) -> Token {
         let mut value = String::new();
+        let line = self.current_line as u32;
 
         while let Some(&c) = self.input.peek() {
             if c != '\n' && c != '\r' {
@@ -111,7 +112,7 @@ impl<'a> Tokenizer<'a> {
                 self.current_column = 1;
             }
         }
-        Self::tok(TokenType::NewLine, value)
+        Token { token_type: TokenType::NewLine, value: Arc::from(value), line }
     }
 
     fn tokenize_string(&mut self) -> Token {
@@ -133,7 +134,7 @@ impl<'a> Tokenizer<'a> {
             }
         }
 
-        Self::tok(TokenType::StringLiteral, value)
+        self.tok(TokenType::StringLiteral, value)
     }
 
     fn tokenize_number(&mut self) -> Token {
@@ -200,7 +201,7 @@ impl<'a> Tokenizer<'a> {
             TokenType::IntegerLiteral
         };
 
-        Self::tok(token_type, value)
+        self.tok(token_type, value)
     }
 
     fn tokenize_identifier(&mut self) -> Token {
@@ -215,7 +216,7 @@ impl<'a> Tokenizer<'a> {
             }
         }
 
-        Self::tok(kw(&value), value)
+        self.tok(kw(&value), value)
     }
 
     fn is_identifier_start(&self, c: char) -> bool {
@@ -289,7 +290,7 @@ impl<'a> Tokenizer<'a> {
             self.advance();
         }
 
-        Self::tok(TokenType::Comment, value)
+        self.tok(TokenType::Comment, value)
     }
 
     fn tokenize_date(&mut self) -> Token {
@@ -306,7 +307,7 @@ impl<'a> Tokenizer<'a> {
             self.advance();
         }
 
-        Self::tok(TokenType::DateLiteral, value)
+        self.tok(TokenType::DateLiteral, value)
     }
 
     fn tokenize_operator(&mut self) -> Token {
@@ -317,12 +318,12 @@ impl<'a> Tokenizer<'a> {
             self.advance();
 
             match c {
-                '+' => Self::tok(TokenType::Plus, value),
-                '-' => Self::tok(TokenType::Minus, value),
-                '*' => Self::tok(TokenType::Multiply, value),
-                '/' => Self::tok(TokenType::Divide, value),
-                '\\' => Self::tok(TokenType::IntDivide, value),
-                '^' => Self::tok(TokenType::Power, value),
+                '+' => self.tok(TokenType::Plus, value),
+                '-' => self.tok(TokenType::Minus, value),
+                '*' => self.tok(TokenType::Multiply, value),
+                '/' => self.tok(TokenType::Divide, value),
+                '\\' => self.tok(TokenType::IntDivide, value),
+                '^' => self.tok(TokenType::Power, value),
                 '&' => {
                     if let Some(&next) = self.input.peek() {
                         if next == 'H' || next == 'h' {
@@ -336,7 +337,7 @@ impl<'a> Tokenizer<'a> {
                                     break;
                                 }
                             }
-                            return Self::tok(TokenType::HexLiteral, value);
+                            return self.tok(TokenType::HexLiteral, value);
                         }
                         if next.is_ascii_digit() || next == 'O' || next == 'o' {
                             if next == 'O' || next == 'o' {
@@ -351,51 +352,51 @@ impl<'a> Tokenizer<'a> {
                                     break;
                                 }
                             }
-                            return Self::tok(TokenType::OctLiteral, value);
+                            return self.tok(TokenType::OctLiteral, value);
                         }
                     }
-                    Self::tok(TokenType::Concat, value)
+                    self.tok(TokenType::Concat, value)
                 }
                 '=' => {
                     if self.input.peek() == Some(&'=') {
                         value.push('=');
                         self.advance();
-                        return Self::tok(TokenType::Equal, value);
+                        return self.tok(TokenType::Equal, value);
                     }
-                    Self::tok(TokenType::Assign, value)
+                    self.tok(TokenType::Assign, value)
                 }
-                '.' => Self::tok(TokenType::Dot, value),
-                ',' => Self::tok(TokenType::Comma, value),
-                ':' => Self::tok(TokenType::Colon, value),
-                '(' => Self::tok(TokenType::LeftParen, value),
-                ')' => Self::tok(TokenType::RightParen, value),
+                '.' => self.tok(TokenType::Dot, value),
+                ',' => self.tok(TokenType::Comma, value),
+                ':' => self.tok(TokenType::Colon, value),
+                '(' => self.tok(TokenType::LeftParen, value),
+                ')' => self.tok(TokenType::RightParen, value),
                 '>' => {
                     if self.input.peek() == Some(&'=') {
                         value.push('=');
                         self.advance();
-                        return Self::tok(TokenType::GreaterEqual, value);
+                        return self.tok(TokenType::GreaterEqual, value);
                     }
-                    Self::tok(TokenType::GreaterThan, value)
+                    self.tok(TokenType::GreaterThan, value)
                 }
                 '<' => {
                     if self.input.peek() == Some(&'=') {
                         value.push('=');
                         self.advance();
-                        return Self::tok(TokenType::LessEqual, value);
+                        return self.tok(TokenType::LessEqual, value);
                     } else if self.input.peek() == Some(&'>') {
                         value.push('>');
                         self.advance();
-                        return Self::tok(TokenType::NotEqual, value);
+                        return self.tok(TokenType::NotEqual, value);
                     }
-                    Self::tok(TokenType::LessThan, value)
+                    self.tok(TokenType::LessThan, value)
                 }
                 _ => {
                     self.advance();
-                    Self::tok(TokenType::Invalid, value)
+                    self.tok(TokenType::Invalid, value)
                 }
             }
         } else {
-            Self::tok(TokenType::EOF, value)
+            self.tok(TokenType::EOF, value)
         }
     }
 }
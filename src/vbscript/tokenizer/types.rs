@@ -82,6 +82,8 @@ pub enum TokenType {
 pub struct Token {
     pub token_type: TokenType,
     pub value: Arc<str>,
+    /// Line of the tokenized source the token is on, counted from 1.
+    pub line: u32,
 }
 
 pub(crate) fn kw(word: &str) -> TokenType {
@@ -1,31 +1,172 @@
 //! Core VBScript value type (`VBValue`) representing all script-level
 //! data: strings, numbers, booleans, null, empty, arrays, and objects.
 
-use super::vbobject::VBScriptObject;
+use super::numeric::{self, Decimal};
+use super::vbobject::ObjectRef;
+use std::borrow::{Borrow, Cow};
+use std::ops::Deref;
 use std::sync::Arc;
 use std::fmt;
 
-#[derive(Debug)]
+/// String payload of [`VBValue::String`].
+///
+/// Shared like an `Arc<str>`, but a string with no other references can be
+/// appended to in place, so the `s = s & x` idiom builds its result in
+/// amortized linear time instead of copying the whole string every time.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VBString(Arc<String>);
+
+impl VBString {
+    /// Append `tail`, in place unless the string is shared.
+    pub fn push_str(&mut self, tail: &str) {
+        match Arc::get_mut(&mut self.0) {
+            Some(s) => s.push_str(tail),
+            None => {
+                let mut s = String::with_capacity(self.len() + tail.len());
+                s.push_str(&self.0);
+                s.push_str(tail);
+                self.0 = Arc::new(s);
+            }
+        }
+    }
+
+    /// Whether both refer to the same string buffer.
+    pub fn ptr_eq(&self, other: &VBString) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for VBString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for VBString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for VBString {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for VBString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.0.as_str(), f)
+    }
+}
+
+impl fmt::Display for VBString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for VBString {
+    fn from(s: String) -> Self {
+        VBString(Arc::new(s))
+    }
+}
+
+impl From<&str> for VBString {
+    fn from(s: &str) -> Self {
+        VBString(Arc::new(s.to_string()))
+    }
+}
+
+impl From<&String> for VBString {
+    fn from(s: &String) -> Self {
+        VBString(Arc::new(s.clone()))
+    }
+}
+
+impl From<Cow<'_, str>> for VBString {
+    fn from(s: Cow<'_, str>) -> Self {
+        VBString(Arc::new(s.into_owned()))
+    }
+}
+
+impl From<char> for VBString {
+    fn from(c: char) -> Self {
+        VBString(Arc::new(c.to_string()))
+    }
+}
+
+/// Cloning a `VBValue` is cheap: strings and arrays share their backing
+/// storage, and `Object` clones add a reference to the same object.
+#[derive(Debug, Clone)]
 pub enum VBValue {
-    String(Arc<str>),
+    String(VBString),
+    /// The `Double` subtype.
     Number(f64),
+    Integer(i16),
+    Long(i32),
+    Byte(u8),
+    Single(f32),
+    /// Fixed-point value scaled by [`numeric::CURRENCY_SCALE`].
+    Currency(i64),
+    /// OLE Automation date: days since 1899-12-30, time as the fraction.
+    Date(f64),
+    Decimal(Decimal),
     Boolean(bool),
     Null,
     Empty,
     Array(Arc<Vec<VBValue>>, Vec<usize>),
-    Object(Box<dyn VBScriptObject>),
+    Object(ObjectRef),
+    /// The `Nothing` object reference (an object variable that refers to no object).
+    Nothing,
 }
 
-impl Clone for VBValue {
-    fn clone(&self) -> Self {
+impl VBValue {
+    /// Whether the value has one of the numeric subtypes (not `Date`).
+    pub fn is_number(&self) -> bool {
+        matches!(
+            self,
+            VBValue::Number(_)
+                | VBValue::Integer(_)
+                | VBValue::Long(_)
+                | VBValue::Byte(_)
+                | VBValue::Single(_)
+                | VBValue::Currency(_)
+                | VBValue::Decimal(_)
+        )
+    }
+
+    /// The value of a numeric or `Date` subtype as a float.
+    pub fn as_f64(&self) -> Option<f64> {
         match self {
-            VBValue::String(s) => VBValue::String(s.clone()),
-            VBValue::Number(n) => VBValue::Number(*n),
-            VBValue::Boolean(b) => VBValue::Boolean(*b),
-            VBValue::Null => VBValue::Null,
-            VBValue::Empty => VBValue::Empty,
-            VBValue::Array(v, dims) => VBValue::Array(Arc::clone(v), dims.clone()),
-            VBValue::Object(obj) => VBValue::Object(obj.clone_box()),
+            VBValue::Number(n) | VBValue::Date(n) => Some(*n),
+            VBValue::Integer(i) => Some(*i as f64),
+            VBValue::Long(l) => Some(*l as f64),
+            VBValue::Byte(b) => Some(*b as f64),
+            VBValue::Single(s) => Some(*s as f64),
+            VBValue::Currency(c) => Some(*c as f64 / numeric::CURRENCY_SCALE as f64),
+            VBValue::Decimal(d) => Some(d.to_f64()),
+            _ => None,
+        }
+    }
+
+    /// Whether the value is an object reference (including `Nothing`).
+    pub fn is_object(&self) -> bool {
+        matches!(self, VBValue::Object(_) | VBValue::Nothing)
+    }
+
+    /// VBScript `Is`: object identity.  `Empty` is accepted as `Nothing` so
+    /// that never-`Set` variables compare equal to `Nothing`.  Returns `None`
+    /// when either operand is not an object reference.
+    pub fn is_same_object(&self, other: &VBValue) -> Option<bool> {
+        match (self, other) {
+            (VBValue::Object(a), VBValue::Object(b)) => Some(a.ptr_eq(b)),
+            (VBValue::Object(_), VBValue::Nothing | VBValue::Empty)
+            | (VBValue::Nothing | VBValue::Empty, VBValue::Object(_)) => Some(false),
+            (VBValue::Nothing | VBValue::Empty, VBValue::Nothing | VBValue::Empty) => Some(true),
+            _ => None,
         }
     }
 }
@@ -34,12 +175,19 @@ impl PartialEq for VBValue {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (VBValue::String(a), VBValue::String(b)) => a == b,
-            (VBValue::Number(a), VBValue::Number(b)) => (a - b).abs() < f64::EPSILON,
+            (VBValue::Date(a), VBValue::Date(b)) => a == b,
+            (VBValue::Currency(a), VBValue::Currency(b)) => a == b,
+            (VBValue::Decimal(a), VBValue::Decimal(b)) => a == b,
+            (a, b) if a.is_number() && b.is_number() => match (a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b)) => (a - b).abs() < f64::EPSILON,
+                _ => false,
+            },
             (VBValue::Boolean(a), VBValue::Boolean(b)) => a == b,
             (VBValue::Null, VBValue::Null) => true,
             (VBValue::Empty, VBValue::Empty) => true,
             (VBValue::Array(a, _), VBValue::Array(b, _)) => a == b,
-            (VBValue::Object(_), VBValue::Object(_)) => false,
+            (VBValue::Object(a), VBValue::Object(b)) => a.ptr_eq(b),
+            (VBValue::Nothing, VBValue::Nothing) => true,
             _ => false,
         }
     }
@@ -50,6 +198,13 @@ impl fmt::Display for VBValue {
         match self {
             VBValue::String(s) => write!(f, "{}", s),
             VBValue::Number(n) => write!(f, "{}", n),
+            VBValue::Integer(n) => write!(f, "{}", n),
+            VBValue::Long(n) => write!(f, "{}", n),
+            VBValue::Byte(n) => write!(f, "{}", n),
+            VBValue::Single(n) => write!(f, "{}", n),
+            VBValue::Currency(c) => write!(f, "{}", numeric::format_currency(*c)),
+            VBValue::Date(d) => write!(f, "{}", crate::vbscript::builtins::format_date(*d)),
+            VBValue::Decimal(d) => write!(f, "{}", d),
             VBValue::Boolean(b) => {
                 if *b {
                     write!(f, "True")
@@ -61,6 +216,7 @@ impl fmt::Display for VBValue {
             VBValue::Empty => write!(f, "Empty"),
             VBValue::Array(v, _) => write!(f, "Array({})", v.len()),
             VBValue::Object(_) => write!(f, "Object"),
+            VBValue::Nothing => write!(f, "Nothing"),
         }
     }
 }
@@ -98,6 +254,18 @@ mod tests {
         assert_eq!(v, c);
     }
 
+    #[test]
+    fn test_vb_string_push_str_keeps_shared_copies() {
+        let mut a = VBString::from("ab");
+        let b = a.clone();
+        a.push_str("c");
+        assert_eq!(&*a, "abc");
+        assert_eq!(&*b, "ab");
+        assert!(!a.ptr_eq(&b));
+        a.push_str("d");
+        assert_eq!(a.to_string(), "abcd");
+    }
+
     #[test]
     fn test_vb_value_clone_number() {
         let v = VBValue::Number(42.5);
@@ -214,10 +382,44 @@ mod tests {
         assert_eq!(v.to_string(), "Array(1)");
     }
 
+    #[test]
+    fn test_vb_value_clone_object_shares_identity() {
+        let a = VBValue::Object(ObjectRef::new(crate::vbscript::vbobject::Dictionary::new()));
+        let b = a.clone();
+        assert_eq!(a, b);
+        assert_eq!(a.is_same_object(&b), Some(true));
+        let c = VBValue::Object(ObjectRef::new(crate::vbscript::vbobject::Dictionary::new()));
+        assert_ne!(a, c);
+        assert_eq!(a.is_same_object(&c), Some(false));
+    }
+
+    #[test]
+    fn test_vb_value_is_same_object_nothing() {
+        assert_eq!(VBValue::Nothing.is_same_object(&VBValue::Nothing), Some(true));
+        assert_eq!(VBValue::Empty.is_same_object(&VBValue::Nothing), Some(true));
+        assert_eq!(VBValue::Number(1.0).is_same_object(&VBValue::Nothing), None);
+    }
+
     #[test]
     fn test_vb_value_number_partial_eq() {
         let a = VBValue::Number(1.0);
         let b = VBValue::Number(1.0 + f64::EPSILON / 2.0);
         assert_eq!(a, b);
     }
+
+    #[test]
+    fn test_vb_value_numeric_subtypes_compare_by_value() {
+        assert_eq!(VBValue::Integer(2), VBValue::Number(2.0));
+        assert_eq!(VBValue::Long(70000), VBValue::Number(70000.0));
+        assert_ne!(VBValue::Date(1.0), VBValue::Number(1.0));
+        assert_ne!(VBValue::Boolean(true), VBValue::Integer(-1));
+    }
+
+    #[test]
+    fn test_vb_value_display_subtypes() {
+        assert_eq!(VBValue::Integer(-5).to_string(), "-5");
+        assert_eq!(VBValue::Single(1.5).to_string(), "1.5");
+        assert_eq!(VBValue::Currency(12_345).to_string(), "1.2345");
+        assert_eq!(VBValue::Date(45000.0).to_string(), "3/15/2023");
+    }
 }
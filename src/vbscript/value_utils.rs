@@ -9,20 +9,35 @@ pub fn to_arg_string(val: &VBValue) -> String {
         VBValue::Null => "Null".to_string(),
         VBValue::Empty => "".to_string(),
         VBValue::Number(n) => n.to_string(),
+        VBValue::Integer(_)
+        | VBValue::Long(_)
+        | VBValue::Byte(_)
+        | VBValue::Single(_)
+        | VBValue::Currency(_)
+        | VBValue::Date(_)
+        | VBValue::Decimal(_) => val.to_string(),
         VBValue::Boolean(true) => "True".to_string(),
         VBValue::Boolean(false) => "False".to_string(),
         VBValue::Array(..) => "Array".to_string(),
         VBValue::Object(_) => "Object".to_string(),
+        VBValue::Nothing => "Nothing".to_string(),
     }
 }
 
 pub fn to_arg_f64(val: &VBValue) -> f64 {
     match val {
         VBValue::Number(n) => *n,
+        VBValue::Integer(_)
+        | VBValue::Long(_)
+        | VBValue::Byte(_)
+        | VBValue::Single(_)
+        | VBValue::Currency(_)
+        | VBValue::Date(_)
+        | VBValue::Decimal(_) => val.as_f64().unwrap_or(0.0),
         VBValue::String(s) => s.parse::<f64>().unwrap_or(0.0),
         VBValue::Boolean(true) => -1.0,
         VBValue::Boolean(false) => 0.0,
-        VBValue::Null | VBValue::Empty | VBValue::Array(..) | VBValue::Object(_) => 0.0,
+        VBValue::Null | VBValue::Empty | VBValue::Array(..) | VBValue::Object(_) | VBValue::Nothing => 0.0,
     }
 }
 
@@ -47,8 +62,15 @@ pub fn to_boolean(val: &VBValue) -> bool {
     match val {
         VBValue::Boolean(b) => *b,
         VBValue::Number(n) => *n != 0.0,
+        VBValue::Integer(_)
+        | VBValue::Long(_)
+        | VBValue::Byte(_)
+        | VBValue::Single(_)
+        | VBValue::Currency(_)
+        | VBValue::Date(_)
+        | VBValue::Decimal(_) => val.as_f64().is_some_and(|n| n != 0.0),
         VBValue::String(s) => !s.is_empty() && !s.eq_ignore_ascii_case("false") && s.as_ref() != "0",
-        VBValue::Null | VBValue::Empty => false,
+        VBValue::Null | VBValue::Empty | VBValue::Nothing => false,
         VBValue::Array(..) | VBValue::Object(_) => true,
     }
 }
@@ -1,31 +1,35 @@
 use std::borrow::Cow;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
 
-use super::block::parse_blocks;
-use super::execution_context::ExecutionContext;
-use super::interpreter::VBScriptInterpreter;
+use super::execution_context::{ClassDefinition, ExecutionContext};
+use super::symbol::{sym, Symbol};
 use super::value::VBValue;
 use super::value_utils;
-use super::vbs_error::{VBSError, VBSErrorType};
+use super::vbs_error::{codes, description, ErrDetails, VBSError, VBSErrorType};
+use super::vm::Vm;
 use ahash::AHashMap;
 
 #[macro_export]
 macro_rules! impl_vbscript_object {
     ($ty:ty, $name:expr) => {
         fn type_name(&self) -> &'static str { $name }
-        fn clone_box(&self) -> Box<dyn $crate::vbscript::vbobject::VBScriptObject> { Box::new(self.clone()) }
     };
 }
 
 #[macro_export]
 macro_rules! prop_not_found {
     ($ty:literal) => {
-        |name: &str| Err($crate::vbscript::vbs_error::VBSErrorType::RuntimeError.into_error(
-            format!("Property '{}' not found on {}", name, $ty)
+        |name: &str| Err($crate::vbscript::vbs_error::VBSError::runtime_with(
+            $crate::vbscript::vbs_error::codes::NO_SUCH_PROPERTY_OR_METHOD,
+            format!("{}.{}", $ty, name),
         ))
     };
     ($ty:literal, $name:expr) => {
-        Err($crate::vbscript::vbs_error::VBSErrorType::RuntimeError.into_error(
-            format!("Property '{}' not found on {}", $name, $ty)
+        Err($crate::vbscript::vbs_error::VBSError::runtime_with(
+            $crate::vbscript::vbs_error::codes::NO_SUCH_PROPERTY_OR_METHOD,
+            format!("{}.{}", $ty, $name),
         ))
     };
 }
@@ -33,13 +37,15 @@ macro_rules! prop_not_found {
 #[macro_export]
 macro_rules! method_not_found {
     ($ty:literal) => {
-        |name: &str| Err($crate::vbscript::vbs_error::VBSErrorType::RuntimeError.into_error(
-            format!("Method '{}' not found on {}", name, $ty)
+        |name: &str| Err($crate::vbscript::vbs_error::VBSError::runtime_with(
+            $crate::vbscript::vbs_error::codes::NO_SUCH_PROPERTY_OR_METHOD,
+            format!("{}.{}", $ty, name),
         ))
     };
     ($ty:literal, $name:expr) => {
-        Err($crate::vbscript::vbs_error::VBSErrorType::RuntimeError.into_error(
-            format!("Method '{}' not found on {}", $name, $ty)
+        Err($crate::vbscript::vbs_error::VBSError::runtime_with(
+            $crate::vbscript::vbs_error::codes::NO_SUCH_PROPERTY_OR_METHOD,
+            format!("{}.{}", $ty, $name),
         ))
     };
 }
@@ -47,17 +53,59 @@ macro_rules! method_not_found {
 #[macro_export]
 macro_rules! cannot_set_property {
     ($ty:literal) => {
-        |name: &str| Err($crate::vbscript::vbs_error::VBSErrorType::RuntimeError.into_error(
-            format!("Cannot set property '{}' on {} object", name, $ty)
+        |name: &str| Err($crate::vbscript::vbs_error::VBSError::runtime_with(
+            $crate::vbscript::vbs_error::codes::WRONG_ARGUMENT_COUNT,
+            format!("{}.{}", $ty, name),
         ))
     };
     ($ty:literal, $name:expr) => {
-        Err($crate::vbscript::vbs_error::VBSErrorType::RuntimeError.into_error(
-            format!("Cannot set property '{}' on {} object", $name, $ty)
+        Err($crate::vbscript::vbs_error::VBSError::runtime_with(
+            $crate::vbscript::vbs_error::codes::WRONG_ARGUMENT_COUNT,
+            format!("{}.{}", $ty, $name),
         ))
     };
 }
 
+/// Implement the string-keyed `get_property` / `set_property` /
+/// `call_method` of [`VBScriptObject`] by interning the name and calling the
+/// `_sym` variant, for objects that dispatch on [`Symbol`]s.  The object
+/// must override the `_sym` variant of every method it names here.
+#[macro_export]
+macro_rules! dispatch_by_symbol {
+    ($($kind:ident),+) => {
+        $($crate::dispatch_by_symbol!(@$kind);)+
+    };
+    (@get) => {
+        fn get_property(
+            &self,
+            name: &str,
+            context: &mut $crate::vbscript::ExecutionContext,
+        ) -> Result<$crate::vbscript::VBValue, $crate::vbscript::vbs_error::VBSError> {
+            self.get_property_sym($crate::vbscript::symbol::Symbol::intern(name), context)
+        }
+    };
+    (@set) => {
+        fn set_property(
+            &self,
+            name: &str,
+            value: $crate::vbscript::VBValue,
+            context: &mut $crate::vbscript::ExecutionContext,
+        ) -> Result<(), $crate::vbscript::vbs_error::VBSError> {
+            self.set_property_sym($crate::vbscript::symbol::Symbol::intern(name), value, context)
+        }
+    };
+    (@call) => {
+        fn call_method(
+            &self,
+            name: &str,
+            args: &[$crate::vbscript::VBValue],
+            context: &mut $crate::vbscript::ExecutionContext,
+        ) -> Result<$crate::vbscript::VBValue, $crate::vbscript::vbs_error::VBSError> {
+            self.call_method_sym($crate::vbscript::symbol::Symbol::intern(name), args, context)
+        }
+    };
+}
+
 /// Trait for VBScript COM / intrinsic objects that can expose properties,
 /// methods, and indexed access to scripts.
 ///
@@ -65,9 +113,16 @@ macro_rules! cannot_set_property {
 /// `Dictionary`, `FileSystemObject`, class instances) implements this trait.
 /// The interpreter dispatches property/method/indexed access through these
 /// methods rather than operating on internal fields directly.
+///
+/// Compiled scripts access members through the `_sym` methods, which take
+/// the name as an interned [`Symbol`].  By default they fall back to the
+/// string API; hot objects override them and match on [`sym`] constants,
+/// implementing the string API with [`dispatch_by_symbol!`](crate::dispatch_by_symbol).
+///
+/// Objects are shared between variables through [`ObjectRef`], so every
+/// method takes `&self`; implementations that carry mutable state keep it
+/// behind a `Mutex` and must not hold that lock while running script code.
 pub trait VBScriptObject: std::fmt::Debug + Send + Sync {
-    /// Clone the object into a new boxed trait object (deep copy).
-    fn clone_box(&self) -> Box<dyn VBScriptObject>;
     /// Return a human-readable type name for debugging (e.g. `"Dictionary"`).
     fn type_name(&self) -> &'static str {
         "VBScriptObject"
@@ -80,39 +135,138 @@ pub trait VBScriptObject: std::fmt::Debug + Send + Sync {
     ) -> Result<VBValue, VBSError>;
     /// Set a named property value (e.g. `obj.Key = value`).
     fn set_property(
-        &mut self,
+        &self,
         _name: &str,
         _value: VBValue,
         _context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
-        Err(VBSErrorType::RuntimeError
-            .into_error("Object does not support setting properties".to_string()))
+        Err(VBSError::runtime(codes::NO_SUCH_PROPERTY_OR_METHOD))
     }
     /// Call a method on the object (e.g. `obj.Add key, value`).
     fn call_method(
-        &mut self,
+        &self,
         name: &str,
         _args: &[VBValue],
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError>;
+    /// [`get_property`](Self::get_property) with an interned name.
+    fn get_property_sym(
+        &self,
+        member: Symbol,
+        context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        self.get_property(member.as_str(), context)
+    }
+    /// [`set_property`](Self::set_property) with an interned name.
+    fn set_property_sym(
+        &self,
+        member: Symbol,
+        value: VBValue,
+        context: &mut ExecutionContext,
+    ) -> Result<(), VBSError> {
+        self.set_property(member.as_str(), value, context)
+    }
+    /// [`call_method`](Self::call_method) with an interned name.
+    fn call_method_sym(
+        &self,
+        member: Symbol,
+        args: &[VBValue],
+        context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        self.call_method(member.as_str(), args, context)
+    }
     /// Indexed read access — `obj(key)` in expression context.
     fn indexed_get(
         &self,
         _index: &VBValue,
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        Err(VBSErrorType::RuntimeError
-            .into_error("Object does not support indexed access".to_string()))
+        Err(VBSError::runtime(codes::NO_SUCH_PROPERTY_OR_METHOD))
+    }
+    /// Value of the default member, used where the object stands for a
+    /// plain value (e.g. `Response.Write obj`, `obj & ""`).  `Ok(None)` when
+    /// the object has no default member.
+    fn default_value(&self, _context: &mut ExecutionContext) -> Result<Option<VBValue>, VBSError> {
+        Ok(None)
+    }
+    /// For a `GetRef` function reference, the name of the procedure it
+    /// calls; the VM invokes it directly for `ref(args)`.
+    fn function_name(&self) -> Option<&str> {
+        None
     }
     /// Indexed write access — `obj(key) = value`.
     fn indexed_set(
-        &mut self,
+        &self,
         _index: &VBValue,
         _value: VBValue,
         _context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
-        Err(VBSErrorType::RuntimeError
-            .into_error("Object does not support indexed access".to_string()))
+        Err(VBSError::runtime(codes::NO_SUCH_PROPERTY_OR_METHOD))
+    }
+    /// The elements `For Each` visits, or `None` if the object is not a
+    /// collection.
+    fn for_each_items(&self, _context: &mut ExecutionContext) -> Result<Option<Vec<VBValue>>, VBSError> {
+        Ok(None)
+    }
+    /// For an instance of a user `Class`, the instance itself; the VM runs
+    /// its members in its own frames instead of going through this trait.
+    fn as_class_instance(&self) -> Option<&ClassInstance> {
+        None
+    }
+    /// For a `Scripting.Dictionary`, the dictionary itself, so that session
+    /// and application state can persist its entries.
+    fn as_dictionary(&self) -> Option<&Dictionary> {
+        None
+    }
+}
+
+// ---- ObjectRef ----
+
+/// Shared, reference-counted handle to a script object.
+///
+/// Cloning an `ObjectRef` (e.g. `Set b = a`, passing an object to a Sub,
+/// storing it in a `Dictionary`) yields another reference to the *same*
+/// object, as with COM.  The object is released when the last reference
+/// is dropped, e.g. by `Set x = Nothing`.
+#[derive(Clone)]
+pub struct ObjectRef(Arc<dyn VBScriptObject>);
+
+impl ObjectRef {
+    pub fn new<T: VBScriptObject + 'static>(obj: T) -> Self {
+        ObjectRef(Arc::new(obj))
+    }
+
+    /// Object identity, as tested by the VBScript `Is` operator.
+    pub fn ptr_eq(&self, other: &ObjectRef) -> bool {
+        std::ptr::addr_eq(Arc::as_ptr(&self.0), Arc::as_ptr(&other.0))
+    }
+
+    /// Number of live references to the underlying object.
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+}
+
+impl Deref for ObjectRef {
+    type Target = dyn VBScriptObject;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl std::fmt::Debug for ObjectRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Resolve an object used as a plain value through its default member.
+/// Other values, and objects without a default member, are returned as is.
+pub fn to_default_value(value: VBValue, context: &mut ExecutionContext) -> Result<VBValue, VBSError> {
+    match &value {
+        VBValue::Object(obj) => Ok(obj.default_value(context)?.unwrap_or(value)),
+        _ => Ok(value),
     }
 }
 
@@ -122,11 +276,11 @@ pub trait VBScriptObject: std::fmt::Debug + Send + Sync {
 /// string keys.  Supports `Add`, `Remove`, `Exists`, `Keys`, `Items`,
 /// `Count`, `RemoveAll`, and indexed access via `dict(key)`.
 ///
-/// Note: real VBScript `Dictionary.Add` throws on duplicate key; this
-/// implementation silently overwrites (like `HashMap::insert`).
-#[derive(Debug, Clone)]
+/// `Add` on an existing key raises an error; indexed assignment
+/// (`dict(key) = value`) inserts or overwrites.
+#[derive(Debug)]
 pub struct Dictionary {
-    items: AHashMap<String, VBValue>,
+    items: Mutex<AHashMap<String, VBValue>>,
 }
 
 impl Default for Dictionary {
@@ -138,106 +292,131 @@ impl Default for Dictionary {
 impl Dictionary {
     pub fn new() -> Self {
         Dictionary {
-            items: AHashMap::new(),
+            items: Mutex::new(AHashMap::new()),
         }
     }
+
+    /// A dictionary holding `entries`.
+    pub fn from_entries(entries: AHashMap<String, VBValue>) -> Self {
+        Dictionary {
+            items: Mutex::new(entries),
+        }
+    }
+
+    /// A copy of the dictionary's entries.
+    pub fn entries(&self) -> AHashMap<String, VBValue> {
+        self.items().clone()
+    }
+
+    fn items(&self) -> MutexGuard<'_, AHashMap<String, VBValue>> {
+        self.items.lock().unwrap_or_else(|e| e.into_inner())
+    }
 }
 
 /// Like `to_arg_string` but returns `Cow<str>` to avoid allocation
 /// when the value is already a `String`.
 fn key_to_cow(val: &VBValue) -> Cow<'_, str> {
     match val {
-        VBValue::String(s) => Cow::Borrowed(&*s),
+        VBValue::String(s) => Cow::Borrowed(s),
         VBValue::Null => Cow::Owned("Null".to_string()),
         VBValue::Empty => Cow::Owned(String::new()),
         VBValue::Number(n) => Cow::Owned(n.to_string()),
+        VBValue::Integer(_)
+        | VBValue::Long(_)
+        | VBValue::Byte(_)
+        | VBValue::Single(_)
+        | VBValue::Currency(_)
+        | VBValue::Date(_)
+        | VBValue::Decimal(_) => Cow::Owned(val.to_string()),
         VBValue::Boolean(true) => Cow::Owned("True".to_string()),
         VBValue::Boolean(false) => Cow::Owned("False".to_string()),
         VBValue::Array(..) => Cow::Owned("Array".to_string()),
         VBValue::Object(_) => Cow::Owned("Object".to_string()),
+        VBValue::Nothing => Cow::Owned("Nothing".to_string()),
     }
 }
 
 impl VBScriptObject for Dictionary {
     impl_vbscript_object!(Dictionary, "Dictionary");
+    dispatch_by_symbol!(get, call);
 
-    fn get_property(
+    fn as_dictionary(&self) -> Option<&Dictionary> {
+        Some(self)
+    }
+
+    /// `For Each` visits the keys.
+    fn for_each_items(&self, _context: &mut ExecutionContext) -> Result<Option<Vec<VBValue>>, VBSError> {
+        Ok(Some(self.items().keys().map(|k| VBValue::String(k.clone().into())).collect()))
+    }
+
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "COUNT" => Ok(VBValue::Number(self.items.len() as f64)),
-            "KEYS" => Ok(VBValue::Array(std::sync::Arc::new(
-                self.items
+        match member {
+            sym::COUNT => Ok(VBValue::Number(self.items().len() as f64)),
+            sym::KEYS => Ok(VBValue::Array(std::sync::Arc::new(
+                self.items()
                     .keys()
                     .map(|k| VBValue::String(k.clone().into()))
                     .collect(),
             ), vec![])),
-            "ITEMS" => Ok(VBValue::Array(std::sync::Arc::new(
-                self.items.values().cloned().collect(),
+            sym::ITEMS => Ok(VBValue::Array(std::sync::Arc::new(
+                self.items().values().cloned().collect(),
             ), vec![])),
-            _ => prop_not_found!("Dictionary", name),
+            _ => prop_not_found!("Dictionary", member),
         }
     }
 
-    fn call_method(
-        &mut self,
-        name: &str,
+    fn call_method_sym(
+        &self,
+        member: Symbol,
         args: &[VBValue],
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "ADD" => {
+        match member {
+            sym::ADD => {
                 if args.len() < 2 {
-                    return Err(VBSErrorType::ValueError.into_error(
-                        "Dictionary.Add requires 2 arguments (key, value)".to_string(),
-                    ));
+                    return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Add"));
                 }
                 let key = key_to_cow(&args[0]).into_owned();
-                if self.items.contains_key(&key) {
-                    return Err(VBSErrorType::RuntimeError.into_error(format!(
-                        "The key '{}' is already associated with an element of this collection",
-                        key
-                    )));
+                if self.items().contains_key(&key) {
+                    return Err(VBSError::runtime(codes::KEY_ALREADY_EXISTS));
                 }
                 let value = args[1].clone();
-                self.items.insert(key, value);
+                self.items().insert(key, value);
                 Ok(VBValue::Empty)
             }
-            "REMOVE" => {
+            sym::REMOVE => {
                 if args.is_empty() {
-                    return Err(VBSErrorType::ValueError
-                        .into_error("Dictionary.Remove requires 1 argument (key)".to_string()));
+                    return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Remove"));
                 }
                 let key = key_to_cow(&args[0]);
-                self.items.remove(key.as_ref());
+                self.items().remove(key.as_ref());
                 Ok(VBValue::Empty)
             }
-            "EXISTS" => {
+            sym::EXISTS => {
                 if args.is_empty() {
-                    return Err(VBSErrorType::ValueError
-                        .into_error("Dictionary.Exists requires 1 argument (key)".to_string()));
+                    return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Exists"));
                 }
                 let key = key_to_cow(&args[0]);
-                Ok(VBValue::Boolean(self.items.contains_key(key.as_ref())))
+                Ok(VBValue::Boolean(self.items().contains_key(key.as_ref())))
             }
-            "ITEM" => {
+            sym::ITEM => {
                 if args.is_empty() {
-                    return Err(VBSErrorType::ValueError
-                        .into_error("Dictionary.Item requires 1 argument (key)".to_string()));
+                    return Err(VBSError::runtime_with(codes::WRONG_ARGUMENT_COUNT, "Item"));
                 }
                 let key = key_to_cow(&args[0]);
-                self.items.get(key.as_ref()).cloned().ok_or_else(|| {
-                    VBSErrorType::RuntimeError
-                        .into_error(format!("Key '{}' not found in Dictionary", key))
+                self.items().get(key.as_ref()).cloned().ok_or_else(|| {
+                    VBSError::runtime_with(codes::ELEMENT_NOT_FOUND, key)
                 })
             }
-            "REMOVEALL" => {
-                self.items.clear();
+            sym::REMOVEALL => {
+                self.items().clear();
                 Ok(VBValue::Empty)
             }
-            _ => method_not_found!("Dictionary", name),
+            _ => method_not_found!("Dictionary", member),
         }
     }
 
@@ -247,20 +426,19 @@ impl VBScriptObject for Dictionary {
         _context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
         let key = key_to_cow(index);
-        self.items.get(key.as_ref()).cloned().ok_or_else(|| {
-            VBSErrorType::RuntimeError
-                .into_error(format!("Key '{}' not found in Dictionary", key))
+        self.items().get(key.as_ref()).cloned().ok_or_else(|| {
+            VBSError::runtime_with(codes::ELEMENT_NOT_FOUND, key)
         })
     }
 
     fn indexed_set(
-        &mut self,
+        &self,
         index: &VBValue,
         value: VBValue,
         _context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
         let key = key_to_cow(index).into_owned();
-        self.items.insert(key, value);
+        self.items().insert(key, value);
         Ok(())
     }
 }
@@ -277,12 +455,12 @@ mod dictionary_tests {
     #[test]
     fn test_dictionary_new_empty() {
         let d = Dictionary::new();
-        assert_eq!(d.items.len(), 0);
+        assert_eq!(d.items().len(), 0);
     }
 
     #[test]
     fn test_dictionary_add_and_indexed_get() {
-        let mut d = Dictionary::new();
+        let d = Dictionary::new();
         let mut c = ctx();
         d.call_method("ADD", &[VBValue::String("key1".into()), VBValue::String("val1".into())], &mut c).unwrap();
         let val = d.indexed_get(&VBValue::String("key1".into()), &mut c).unwrap();
@@ -291,7 +469,7 @@ mod dictionary_tests {
 
     #[test]
     fn test_dictionary_add_duplicate_key_errors() {
-        let mut d = Dictionary::new();
+        let d = Dictionary::new();
         let mut c = ctx();
         d.call_method("ADD", &[VBValue::String("k".into()), VBValue::Number(1.0)], &mut c).unwrap();
         let err = d.call_method("ADD", &[VBValue::String("k".into()), VBValue::Number(2.0)], &mut c).unwrap_err();
@@ -301,7 +479,7 @@ mod dictionary_tests {
 
     #[test]
     fn test_dictionary_count() {
-        let mut d = Dictionary::new();
+        let d = Dictionary::new();
         let mut c = ctx();
         assert_eq!(d.get_property("COUNT", &mut c).unwrap(), VBValue::Number(0.0));
         d.call_method("ADD", &[VBValue::String("a".into()), VBValue::Empty], &mut c).unwrap();
@@ -310,7 +488,7 @@ mod dictionary_tests {
 
     #[test]
     fn test_dictionary_exists() {
-        let mut d = Dictionary::new();
+        let d = Dictionary::new();
         let mut c = ctx();
         d.call_method("ADD", &[VBValue::String("k".into()), VBValue::Empty], &mut c).unwrap();
         let exists = d.call_method("EXISTS", &[VBValue::String("k".into())], &mut c).unwrap();
@@ -321,7 +499,7 @@ mod dictionary_tests {
 
     #[test]
     fn test_dictionary_keys() {
-        let mut d = Dictionary::new();
+        let d = Dictionary::new();
         let mut c = ctx();
         d.call_method("ADD", &[VBValue::String("x".into()), VBValue::Number(1.0)], &mut c).unwrap();
         let keys = d.get_property("KEYS", &mut c).unwrap();
@@ -336,7 +514,7 @@ mod dictionary_tests {
 
     #[test]
     fn test_dictionary_remove_all() {
-        let mut d = Dictionary::new();
+        let d = Dictionary::new();
         let mut c = ctx();
         d.call_method("ADD", &[VBValue::String("a".into()), VBValue::Empty], &mut c).unwrap();
         d.call_method("ADD", &[VBValue::String("b".into()), VBValue::Empty], &mut c).unwrap();
@@ -346,7 +524,7 @@ mod dictionary_tests {
 
     #[test]
     fn test_dictionary_remove() {
-        let mut d = Dictionary::new();
+        let d = Dictionary::new();
         let mut c = ctx();
         d.call_method("ADD", &[VBValue::String("k".into()), VBValue::Number(1.0)], &mut c).unwrap();
         d.call_method("REMOVE", &[VBValue::String("k".into())], &mut c).unwrap();
@@ -355,236 +533,462 @@ mod dictionary_tests {
 
     #[test]
     fn test_dictionary_add_requires_two_args() {
-        let mut d = Dictionary::new();
+        let d = Dictionary::new();
         let mut c = ctx();
         let result = d.call_method("ADD", &[VBValue::String("k".into())], &mut c);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_dictionary_symbol_dispatch() {
+        let d = Dictionary::new();
+        let mut c = ctx();
+        d.call_method_sym(sym::ADD, &[VBValue::String("k".into()), VBValue::Number(1.0)], &mut c).unwrap();
+        assert_eq!(d.get_property("Count", &mut c).unwrap(), VBValue::Number(1.0));
+        assert_eq!(d.get_property_sym(Symbol::intern("count"), &mut c).unwrap(), VBValue::Number(1.0));
+        let err = d.get_property_sym(Symbol::intern("Frobnicate"), &mut c).unwrap_err();
+        assert_eq!(err.code, codes::NO_SUCH_PROPERTY_OR_METHOD);
+    }
 }
 
 // ---- ClassInstance ----
 
 /// A runtime instance of a user-defined `Class`.
 ///
-/// Created by `Set obj = New ClassName`.  Stores the class name for
-/// method resolution and a mutable map of instance variables
-/// (declared with `Dim`/`Private`/`Public` inside the class body).
-#[derive(Debug)]
+/// Created by `Set obj = New ClassName`.  Holds the compiled class
+/// definition and the values of the fields declared with
+/// `Dim`/`Private`/`Public` in the class body, in slot order.
+///
+/// Members run in a VM frame whose leading local slots are a copy of the
+/// fields (see [`Vm::call_class_member`]); only the fields a member can
+/// assign are written back, so a nested call on the same object (through
+/// another reference) keeps its own updates to the other fields.
+///
+/// Instances of classes that define `Class_Terminate` are tracked by the
+/// context's [`ClassLifecycle`] so the event runs exactly once: when the
+/// last reference is released, or when the context is torn down.
 pub struct ClassInstance {
-    pub class_name: String,
-    pub instance_vars: AHashMap<String, VBValue>,
+    class: Arc<ClassDefinition>,
+    fields: Mutex<Vec<VBValue>>,
+    /// Set for instances whose class defines `Class_Terminate`.
+    lifecycle: Option<Arc<ClassLifecycle>>,
+    /// Whether `Class_Terminate` has already been raised (or claimed).
+    terminated: AtomicBool,
 }
 
 impl ClassInstance {
-    pub fn new(class_name: &str) -> Self {
+    pub fn new(class: Arc<ClassDefinition>) -> Self {
+        let fields = vec![VBValue::Empty; class.fields.len()];
         ClassInstance {
-            class_name: class_name.to_string(),
-            instance_vars: AHashMap::new(),
+            class,
+            fields: Mutex::new(fields),
+            lifecycle: None,
+            terminated: AtomicBool::new(false),
         }
     }
-}
 
-impl VBScriptObject for ClassInstance {
-    fn clone_box(&self) -> Box<dyn VBScriptObject> {
-        Box::new(ClassInstance {
-            class_name: self.class_name.clone(),
-            instance_vars: self.instance_vars.clone(),
-        })
+    /// Create an instance for `New ClassName`: registers the instance for
+    /// `Class_Terminate` and runs `Class_Initialize` when the class defines
+    /// them.  An error raised by `Class_Initialize` is returned and the new
+    /// instance is discarded without being terminated.
+    pub fn create(class_name: &str, context: &mut ExecutionContext) -> Result<ObjectRef, VBSError> {
+        let class = context
+            .get_class(class_name)
+            .cloned()
+            .ok_or_else(|| VBSError::runtime_with(codes::CLASS_NOT_DEFINED, class_name))?;
+        let has_init = class.methods.contains_key("class_initialize");
+        let has_term = class.methods.contains_key("class_terminate");
+        let mut instance = ClassInstance::new(class);
+        if has_term {
+            instance.lifecycle = Some(context.lifecycle.clone());
+        }
+        let instance = Arc::new(instance);
+        if has_term {
+            context.lifecycle.register(&instance);
+        }
+        if has_init {
+            if let Err(e) = instance.call_method("Class_Initialize", &[], context) {
+                instance.terminated.store(true, Ordering::Relaxed);
+                return Err(e);
+            }
+        }
+        Ok(ObjectRef(instance))
     }
 
-    fn get_property(
+    pub fn class_name(&self) -> &str {
+        &self.class.name
+    }
+
+    /// Raise `Class_Terminate` on this instance.
+    fn terminate(&self, context: &mut ExecutionContext) -> Result<(), VBSError> {
+        self.call_method("Class_Terminate", &[], context).map(|_| ())
+    }
+
+    fn lock_fields(&self) -> MutexGuard<'_, Vec<VBValue>> {
+        self.fields.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Value of the field `name` (lowercased), if the class declares it.
+    pub fn field(&self, name: &str) -> Option<VBValue> {
+        let slot = self.class.field_slot(name)?;
+        self.lock_fields().get(slot).cloned()
+    }
+
+    /// Copy the fields into the leading slots of a member frame and return
+    /// the number of fields.
+    pub(crate) fn load_fields(&self, locals: &mut [VBValue]) -> usize {
+        let fields = self.lock_fields();
+        locals[..fields.len()].clone_from_slice(&fields);
+        fields.len()
+    }
+
+    /// Store the given field slots back from a finished member frame.
+    pub(crate) fn store_fields(&self, locals: &[VBValue], slots: &[usize]) {
+        if slots.is_empty() {
+            return;
+        }
+        let mut fields = self.lock_fields();
+        for &slot in slots {
+            fields[slot] = locals[slot].clone();
+        }
+    }
+
+    /// Look up a member by name, without allocating for lowercase names.
+    fn lookup<'a, T>(map: &'a AHashMap<String, T>, name: &str) -> Option<&'a T> {
+        if name.bytes().any(|b| b.is_ascii_uppercase()) {
+            map.get(&name.to_lowercase())
+        } else {
+            map.get(name)
+        }
+    }
+
+    /// Call method `name`; `obj.Item(key)` on a parameterized
+    /// `Property Get` runs the getter.
+    pub(crate) fn invoke_method(
         &self,
+        vm: &mut Vm,
         name: &str,
-        context: &mut ExecutionContext,
+        args: &[VBValue],
     ) -> Result<VBValue, VBSError> {
-        let class_def = context.get_class(&self.class_name).ok_or_else(|| {
-            VBSErrorType::RuntimeError.into_error(format!("Class '{}' not found", self.class_name))
-        })?;
-        if let Some(prop_def) = class_def.properties.get(&name.to_lowercase()) {
-            if let Some(ref body_lines) = prop_def.get_body {
-                let body_blocks = super::block::parse_blocks(body_lines).map_err(|_| {
-                    VBSErrorType::RuntimeError
-                        .into_error(format!("Error parsing Property Get '{}' body", name))
-                })?;
-                let mut instance_vars = self.instance_vars.clone();
-                context.set_variable(name, VBValue::Empty);
-                instance_vars.insert(name.to_lowercase(), VBValue::Empty);
-                let interp = VBScriptInterpreter;
-                let result = context.with_instance_scope(&mut instance_vars, |ctx| {
-                    interp.execute_blocks_vm(&body_blocks, ctx)
-                });
-                match result {
-                    Ok(()) => {
-                        let val = instance_vars
-                            .get(&name.to_lowercase())
-                            .cloned()
-                            .unwrap_or(VBValue::Empty);
-                        Ok(val)
-                    }
-                    Err(e) => Err(e),
-                }
-            } else {
-                let val = self
-                    .instance_vars
-                    .get(&name.to_lowercase())
-                    .cloned()
-                    .unwrap_or(VBValue::Empty);
-                Ok(val)
-            }
-        } else {
-            let val = self
-                .instance_vars
-                .get(&name.to_lowercase())
-                .cloned()
-                .unwrap_or(VBValue::Empty);
-            Ok(val)
+        let class = self.class.clone();
+        if let Some(method) = Self::lookup(&class.methods, name) {
+            return vm.call_class_member(self, method, args);
+        }
+        if Self::lookup(&class.properties, name).is_some() {
+            return self.invoke_property_get(vm, name, args);
         }
+        Err(VBSError::runtime_with(
+            codes::NO_SUCH_PROPERTY_OR_METHOD,
+            format!("{}.{}", class.name, name),
+        ))
     }
 
-    fn set_property(
-        &mut self,
+    /// Run `Property Get name(args)`, or read the field `name` when the
+    /// class has no getter for it.
+    pub(crate) fn invoke_property_get(
+        &self,
+        vm: &mut Vm,
         name: &str,
+        args: &[VBValue],
+    ) -> Result<VBValue, VBSError> {
+        let class = self.class.clone();
+        if let Some(getter) = Self::lookup(&class.properties, name).and_then(|p| p.get.as_ref()) {
+            return vm.call_class_member(self, getter, args);
+        }
+        if let Some(method) = Self::lookup(&class.methods, name).filter(|m| m.result_slot.is_some()) {
+            return vm.call_class_member(self, method, args);
+        }
+        match class.field_slot(&name.to_lowercase()) {
+            Some(slot) => Ok(self.lock_fields()[slot].clone()),
+            None => Err(VBSError::runtime_with(
+                codes::NO_SUCH_PROPERTY_OR_METHOD,
+                format!("{}.{}", class.name, name),
+            )),
+        }
+    }
+
+    /// Run `Property Let name(args, value)` — or `Property Set` when the
+    /// value is an object and the class defines one — or store the field
+    /// `name` when the class has no setter for it.
+    pub(crate) fn invoke_property_let(
+        &self,
+        vm: &mut Vm,
+        name: &str,
+        args: &[VBValue],
         value: VBValue,
-        context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
-        let class_def = context.get_class(&self.class_name).ok_or_else(|| {
-            VBSErrorType::RuntimeError.into_error(format!("Class '{}' not found", self.class_name))
-        })?;
-        if let Some(prop_def) = class_def.properties.get(&name.to_lowercase()) {
-            if let Some(ref body_lines) = prop_def.let_body {
-                let body_blocks = super::block::parse_blocks(body_lines).map_err(|_| {
-                    VBSErrorType::RuntimeError
-                        .into_error(format!("Error parsing Property Let '{}' body", name))
-                })?;
-                let mut instance_vars = std::mem::take(&mut self.instance_vars);
-                if let Some(ref param) = prop_def.let_param {
-                    instance_vars.insert(param.to_lowercase(), value.clone());
-                }
-                let interp = VBScriptInterpreter;
-                let result = context.with_instance_scope(&mut instance_vars, |ctx| {
-                    interp.execute_blocks_vm(&body_blocks, ctx)
-                });
-                self.instance_vars = instance_vars;
-                result
+        let class = self.class.clone();
+        let setter = Self::lookup(&class.properties, name).and_then(|p| {
+            if matches!(value, VBValue::Object(_)) {
+                p.set.as_ref().or(p.let_.as_ref())
             } else {
-                self.instance_vars.insert(name.to_lowercase(), value);
+                p.let_.as_ref().or(p.set.as_ref())
+            }
+        });
+        if let Some(setter) = setter {
+            // Index arguments first; the last parameter receives the value
+            let index_count = setter.params.len().saturating_sub(1);
+            let mut call_args: Vec<VBValue> = args.iter().take(index_count).cloned().collect();
+            call_args.resize(index_count, VBValue::Empty);
+            call_args.push(value);
+            return vm.call_class_member(self, setter, &call_args).map(|_| ());
+        }
+        match class.field_slot(&name.to_lowercase()) {
+            Some(slot) => {
+                self.lock_fields()[slot] = value;
                 Ok(())
             }
-        } else {
-            self.instance_vars.insert(name.to_lowercase(), value);
-            Ok(())
+            None => Err(VBSError::runtime_with(
+                codes::NO_SUCH_PROPERTY_OR_METHOD,
+                format!("{}.{}", class.name, name),
+            )),
         }
     }
 
-    fn call_method(
-        &mut self,
-        name: &str,
-        args: &[VBValue],
-        context: &mut ExecutionContext,
-    ) -> Result<VBValue, VBSError> {
-        // Clone method data upfront to avoid borrow conflicts with context
-        let method = context
-            .get_class(&self.class_name)
-            .and_then(|c| {
-                // Try exact match first, then case-insensitive lookup
-                c.methods.get(name).cloned().or_else(|| {
-                    let upper = name.to_uppercase();
-                    c.methods.get(&upper).cloned()
-                })
-            })
-            .ok_or_else(|| {
-                VBSErrorType::RuntimeError.into_error(format!(
-                    "Method '{}' not found on class '{}'",
-                    name, self.class_name
-                ))
-            })?;
-
-        // Parse and cache method body
-        let cache_key = format!("__cls_{}_{}", self.class_name, method.name);
-        let body_blocks = match context.get_function_body(&cache_key) {
-            Some(cached) => cached.clone(),
-            None => {
-                let blocks = parse_blocks(&method.body_lines).map_err(|_| {
-                    VBSErrorType::RuntimeError.into_error(format!(
-                        "Error parsing method '{}' body",
-                        method.name
-                    ))
-                })?;
-                let name = cache_key.clone();
-                context.set_function_body(&name, blocks.clone());
-                blocks
-            }
-        };
+    /// The class's `Public Default` member, or error 438 when it has none.
+    fn default_member(&self) -> Result<&str, VBSError> {
+        self.class.default_member.as_deref().ok_or_else(|| {
+            VBSError::runtime_with(codes::NO_SUCH_PROPERTY_OR_METHOD, &self.class.name)
+        })
+    }
+}
 
-        // Build instance vars map with method params
-        let mut instance_vars = self.instance_vars.clone();
-        for (i, param) in method.params.iter().enumerate() {
-            let val = args.get(i).cloned().unwrap_or(VBValue::Empty);
-            instance_vars.insert(param.to_lowercase(), val);
+impl std::fmt::Debug for ClassInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClassInstance")
+            .field("class_name", &self.class.name)
+            .field("fields", &*self.lock_fields())
+            .finish()
+    }
+}
+
+impl Drop for ClassInstance {
+    /// The last reference is gone: hand the instance state to the lifecycle
+    /// queue so `Class_Terminate` can run against it once a context is
+    /// available again.
+    fn drop(&mut self) {
+        let Some(lifecycle) = self.lifecycle.take() else {
+            return;
+        };
+        if *self.terminated.get_mut() {
+            return;
         }
+        let fields = std::mem::take(self.fields.get_mut().unwrap_or_else(|e| e.into_inner()));
+        lifecycle.release(ClassInstance {
+            class: self.class.clone(),
+            fields: Mutex::new(fields),
+            lifecycle: None,
+            terminated: AtomicBool::new(true),
+        });
+    }
+}
 
-        // For Functions, init return value variable
-        if method.is_function {
-            instance_vars.insert(method.name.to_lowercase(), VBValue::Empty);
+/// Per-context bookkeeping for `Class_Terminate`.
+///
+/// Released instances are queued in release order and run by the VM at the
+/// next instruction boundary (see [`ExecutionContext::run_pending_terminators`]).
+/// Instances still alive when the context is torn down are terminated in
+/// creation order (see [`ExecutionContext::terminate_class_instances`]).
+#[derive(Debug, Default)]
+pub struct ClassLifecycle {
+    released: Mutex<Vec<ClassInstance>>,
+    has_released: AtomicBool,
+    live: Mutex<Vec<Weak<ClassInstance>>>,
+}
+
+impl ClassLifecycle {
+    fn register(&self, instance: &Arc<ClassInstance>) {
+        let mut live = self.live.lock().unwrap_or_else(|e| e.into_inner());
+        if live.len() == live.capacity() {
+            live.retain(|w| w.strong_count() > 0);
         }
+        live.push(Arc::downgrade(instance));
+    }
 
-        // Execute with merged scope (globals + instance vars)
-        let interp = VBScriptInterpreter;
-        let result = context.with_class_method_scope(&mut instance_vars, |ctx| {
-            interp.execute_blocks_vm(&body_blocks, ctx)
-        });
+    fn release(&self, instance: ClassInstance) {
+        self.released.lock().unwrap_or_else(|e| e.into_inner()).push(instance);
+        self.has_released.store(true, Ordering::Release);
+    }
+
+    /// Whether any released instance is waiting for `Class_Terminate`.
+    pub fn has_released(&self) -> bool {
+        self.has_released.load(Ordering::Acquire)
+    }
+
+    fn take_released(&self) -> Vec<ClassInstance> {
+        let mut released = self.released.lock().unwrap_or_else(|e| e.into_inner());
+        self.has_released.store(false, Ordering::Release);
+        std::mem::take(&mut *released)
+    }
 
-        // Capture updated instance vars
-        self.instance_vars = instance_vars;
-
-        match result {
-            Ok(()) => {
-                if method.is_function {
-                    Ok(self
-                        .instance_vars
-                        .get(&method.name.to_lowercase())
-                        .cloned()
-                        .unwrap_or(VBValue::Empty))
-                } else {
-                    Ok(VBValue::Empty)
+    /// Claim every instance that is still alive, in creation order.
+    fn take_live(&self) -> Vec<Arc<ClassInstance>> {
+        let live = std::mem::take(&mut *self.live.lock().unwrap_or_else(|e| e.into_inner()));
+        live.iter()
+            .filter_map(Weak::upgrade)
+            .filter(|i| !i.terminated.swap(true, Ordering::AcqRel))
+            .collect()
+    }
+
+    /// Run `Class_Terminate` for queued instances until the queue is empty
+    /// (a terminator may release further objects).  All of them run; the
+    /// first error is returned.
+    pub(crate) fn run_released(context: &mut ExecutionContext) -> Result<(), VBSError> {
+        let mut first_err = None;
+        loop {
+            let released = context.lifecycle.take_released();
+            if released.is_empty() {
+                break;
+            }
+            for instance in released {
+                if let Err(e) = instance.terminate(context) {
+                    first_err.get_or_insert(e);
                 }
             }
-            Err(e) if e.is_exit_function() || e.is_exit_sub() => {
-                if method.is_function {
-                    Ok(self
-                        .instance_vars
-                        .get(&method.name.to_lowercase())
-                        .cloned()
-                        .unwrap_or(VBValue::Empty))
-                } else {
-                    Ok(VBValue::Empty)
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+
+    /// Run `Class_Terminate` for every instance still pending or alive.
+    pub(crate) fn run_all(context: &mut ExecutionContext) -> Result<(), VBSError> {
+        let mut first_err = Self::run_released(context).err();
+        loop {
+            let live = context.lifecycle.take_live();
+            if live.is_empty() {
+                break;
+            }
+            for instance in live {
+                if let Err(e) = instance.terminate(context) {
+                    first_err.get_or_insert(e);
                 }
             }
-            Err(e) => Err(e),
+            if let Err(e) = Self::run_released(context) {
+                first_err.get_or_insert(e);
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+}
+
+impl VBScriptObject for ClassInstance {
+    fn get_property(
+        &self,
+        name: &str,
+        context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        self.invoke_property_get(&mut Vm::new(context), name, &[])
+    }
+
+    fn set_property(
+        &self,
+        name: &str,
+        value: VBValue,
+        context: &mut ExecutionContext,
+    ) -> Result<(), VBSError> {
+        self.invoke_property_let(&mut Vm::new(context), name, &[], value)
+    }
+
+    fn default_value(&self, context: &mut ExecutionContext) -> Result<Option<VBValue>, VBSError> {
+        match self.default_member() {
+            Ok(member) => self.call_method(member, &[], context).map(Some),
+            Err(_) => Ok(None),
         }
     }
 
+    fn call_method(
+        &self,
+        name: &str,
+        args: &[VBValue],
+        context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        let mut vm = Vm::new(context);
+        let result = self.invoke_method(&mut vm, name, args);
+        let byref_results = std::mem::take(&mut vm.byref_results);
+        context.byref_results = byref_results;
+        result
+    }
+
+    /// `obj(key)` calls the default member with `key`.
     fn indexed_get(
         &self,
-        _index: &VBValue,
-        _context: &mut ExecutionContext,
+        index: &VBValue,
+        context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        Err(VBSErrorType::RuntimeError.into_error(format!(
-            "Class '{}' does not support indexed access",
-            self.class_name
-        )))
+        let member = self.default_member()?;
+        self.call_method(member, std::slice::from_ref(index), context)
     }
 
+    /// `obj(key) = value` calls the default member's `Property Let`.
     fn indexed_set(
-        &mut self,
-        _index: &VBValue,
-        _value: VBValue,
-        _context: &mut ExecutionContext,
+        &self,
+        index: &VBValue,
+        value: VBValue,
+        context: &mut ExecutionContext,
     ) -> Result<(), VBSError> {
-        Err(VBSErrorType::RuntimeError.into_error(format!(
-            "Class '{}' does not support indexed access",
-            self.class_name
-        )))
+        let member = self.default_member()?;
+        self.invoke_property_let(&mut Vm::new(context), member, std::slice::from_ref(index), value)
+    }
+
+    fn as_class_instance(&self) -> Option<&ClassInstance> {
+        Some(self)
+    }
+}
+
+// ---- FunctionRef ----
+
+/// A reference to a user `Sub` or `Function`, returned by `GetRef(name)`.
+///
+/// Like any object it can be stored in variables and collections or
+/// passed as an argument; calling it (`handler(args)`) runs the
+/// referenced procedure.
+#[derive(Debug)]
+pub struct FunctionRef {
+    name: String,
+}
+
+impl FunctionRef {
+    pub fn new(name: &str) -> Self {
+        FunctionRef {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl VBScriptObject for FunctionRef {
+    impl_vbscript_object!(FunctionRef, "Object");
+
+    fn get_property(
+        &self,
+        name: &str,
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        prop_not_found!("function reference", name)
+    }
+
+    fn call_method(
+        &self,
+        name: &str,
+        _args: &[VBValue],
+        _context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        method_not_found!("function reference", name)
+    }
+
+    /// Call paths that only carry one argument (e.g. `obj.Prop(x)` chains)
+    /// still invoke the procedure.
+    fn indexed_get(
+        &self,
+        index: &VBValue,
+        context: &mut ExecutionContext,
+    ) -> Result<VBValue, VBSError> {
+        let func = context.get_function(&self.name).cloned().ok_or_else(|| {
+            VBSError::runtime_with(codes::SUB_OR_FUNCTION_NOT_DEFINED, &self.name)
+        })?;
+        super::interpreter::execute_user_function_vm(&func, std::slice::from_ref(index), context)
+    }
+
+    fn function_name(&self) -> Option<&str> {
+        Some(&self.name)
     }
 }
 
@@ -592,8 +996,11 @@ impl VBScriptObject for ClassInstance {
 
 /// VBScript `Err` object — records runtime error state.
 ///
-/// Properties: `Err.Number`, `Err.Description`.
-/// Methods: `Err.Clear`, `Err.Raise number[, description]`.
+/// Properties: `Err.Number`, `Err.Description`, `Err.Source`,
+/// `Err.HelpFile`, `Err.HelpContext`, and the ASPerger-specific `Err.Line`
+/// (script line of the last error).
+/// Methods: `Err.Clear`,
+/// `Err.Raise number[, source[, description[, helpfile[, helpcontext]]]]`.
 /// The interpreter injects an `Err` object into every execution context.
 #[derive(Debug, Clone, Default)]
 pub struct ErrObject;
@@ -606,47 +1013,80 @@ impl ErrObject {
 
 impl VBScriptObject for ErrObject {
     impl_vbscript_object!(ErrObject, "Err");
+    dispatch_by_symbol!(get, set, call);
 
-    fn get_property(
+    fn get_property_sym(
         &self,
-        name: &str,
+        member: Symbol,
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "NUMBER" => Ok(VBValue::Number(context.err_number)),
-            "DESCRIPTION" => Ok(VBValue::String(context.err_description.clone().into())),
-            _ => prop_not_found!("Err", name),
+        match member {
+            sym::NUMBER => Ok(VBValue::Long(context.err_number as i32)),
+            sym::DESCRIPTION => Ok(VBValue::String(context.err_description.clone().into())),
+            sym::SOURCE => Ok(VBValue::String(context.err_source.clone().into())),
+            sym::HELPFILE => Ok(VBValue::String(context.err_help_file.clone().into())),
+            sym::HELPCONTEXT => Ok(VBValue::Long(context.err_help_context)),
+            sym::LINE => Ok(VBValue::Long(context.err_line as i32)),
+            _ => prop_not_found!("Err", member),
         }
     }
 
-    fn call_method(
-        &mut self,
-        name: &str,
+    fn set_property_sym(
+        &self,
+        member: Symbol,
+        value: VBValue,
+        context: &mut ExecutionContext,
+    ) -> Result<(), VBSError> {
+        match member {
+            sym::NUMBER => context.err_number = value_utils::to_arg_f64(&value).trunc(),
+            sym::DESCRIPTION => context.err_description = value_utils::to_arg_string(&value),
+            sym::SOURCE => context.err_source = value_utils::to_arg_string(&value),
+            sym::HELPFILE => context.err_help_file = value_utils::to_arg_string(&value),
+            sym::HELPCONTEXT => context.err_help_context = value_utils::to_arg_f64(&value) as i32,
+            _ => return cannot_set_property!("Err", member),
+        }
+        Ok(())
+    }
+
+    /// `Number` is the default member (`If Err Then ...`).
+    fn default_value(&self, context: &mut ExecutionContext) -> Result<Option<VBValue>, VBSError> {
+        Ok(Some(VBValue::Long(context.err_number as i32)))
+    }
+
+    fn call_method_sym(
+        &self,
+        member: Symbol,
         args: &[VBValue],
         context: &mut ExecutionContext,
     ) -> Result<VBValue, VBSError> {
-        match name.to_uppercase().as_str() {
-            "CLEAR" => {
+        match member {
+            sym::CLEAR => {
                 context.clear_err();
                 Ok(VBValue::Empty)
             }
-            "RAISE" => {
+            sym::RAISE => {
                 if args.is_empty() {
-                    return Err(VBSErrorType::ValueError.into_error(
-                        "Err.Raise requires at least 1 argument (number)".to_string(),
-                    ));
+                    return Err(VBSError::runtime(codes::WRONG_ARGUMENT_COUNT));
                 }
                 let number = value_utils::to_arg_f64(&args[0]) as i32;
-                let description = if args.len() > 1 {
-                    value_utils::to_arg_string(&args[1])
-                } else {
-                    "".to_string()
+                let text_arg = |i: usize| match args.get(i) {
+                    None | Some(VBValue::Empty) => None,
+                    Some(v) => Some(value_utils::to_arg_string(v)),
+                };
+                let message = text_arg(2).unwrap_or_else(|| {
+                    description(number).unwrap_or("Unknown runtime error").to_string()
+                });
+                let details = ErrDetails {
+                    source: text_arg(1),
+                    help_file: text_arg(3).unwrap_or_default(),
+                    help_context: args.get(4).map(|v| value_utils::to_arg_f64(v) as i32).unwrap_or(0),
                 };
                 Err(VBSErrorType::RuntimeError
-                    .into_error(description)
-                    .with_code(number))
+                    .into_error(message)
+                    .with_code(number)
+                    .with_details(details))
             }
-            _ => method_not_found!("Err", name),
+            _ => method_not_found!("Err", member),
         }
     }
 }
@@ -1,18 +1,141 @@
 //! VBScript error types and error-handling primitives.
 
+/// Standard VBScript runtime error numbers, as reported by `Err.Number`.
+pub mod codes {
+    pub const INVALID_PROCEDURE_CALL: i32 = 5;
+    pub const OVERFLOW: i32 = 6;
+    pub const OUT_OF_MEMORY: i32 = 7;
+    pub const SUBSCRIPT_OUT_OF_RANGE: i32 = 9;
+    pub const ARRAY_FIXED_OR_LOCKED: i32 = 10;
+    pub const DIVISION_BY_ZERO: i32 = 11;
+    pub const TYPE_MISMATCH: i32 = 13;
+    pub const OUT_OF_STRING_SPACE: i32 = 14;
+    pub const OUT_OF_STACK_SPACE: i32 = 28;
+    pub const SUB_OR_FUNCTION_NOT_DEFINED: i32 = 35;
+    pub const INTERNAL_ERROR: i32 = 51;
+    pub const BAD_FILE_NAME_OR_NUMBER: i32 = 52;
+    pub const FILE_NOT_FOUND: i32 = 53;
+    pub const BAD_FILE_MODE: i32 = 54;
+    pub const FILE_ALREADY_OPEN: i32 = 55;
+    pub const FILE_ALREADY_EXISTS: i32 = 58;
+    pub const INPUT_PAST_END_OF_FILE: i32 = 62;
+    pub const PERMISSION_DENIED: i32 = 70;
+    pub const PATH_FILE_ACCESS_ERROR: i32 = 75;
+    pub const PATH_NOT_FOUND: i32 = 76;
+    pub const OBJECT_VARIABLE_NOT_SET: i32 = 91;
+    pub const FOR_LOOP_NOT_INITIALIZED: i32 = 92;
+    pub const INVALID_USE_OF_NULL: i32 = 94;
+    pub const OBJECT_REQUIRED: i32 = 424;
+    pub const CANNOT_CREATE_OBJECT: i32 = 429;
+    pub const NO_SUCH_PROPERTY_OR_METHOD: i32 = 438;
+    pub const WRONG_ARGUMENT_COUNT: i32 = 450;
+    pub const OBJECT_NOT_A_COLLECTION: i32 = 451;
+    pub const KEY_ALREADY_EXISTS: i32 = 457;
+    pub const VARIABLE_UNDEFINED: i32 = 500;
+    pub const ILLEGAL_ASSIGNMENT: i32 = 501;
+    pub const CLASS_NOT_DEFINED: i32 = 506;
+    pub const REGEXP_SYNTAX: i32 = 5017;
+    pub const ELEMENT_NOT_FOUND: i32 = 32811;
+}
+
+/// `Err.Source` of errors raised by the runtime itself.
+pub const RUNTIME_ERROR_SOURCE: &str = "Microsoft VBScript runtime error";
+/// `Err.Source` of errors reported while compiling dynamic code.
+pub const COMPILATION_ERROR_SOURCE: &str = "Microsoft VBScript compilation error";
+
+/// Standard description of a VBScript runtime error number, as shown by
+/// `Err.Description` when a script raises the error without one.
+pub fn description(code: i32) -> Option<&'static str> {
+    use codes::*;
+    Some(match code {
+        INVALID_PROCEDURE_CALL => "Invalid procedure call or argument",
+        OVERFLOW => "Overflow",
+        OUT_OF_MEMORY => "Out of memory",
+        SUBSCRIPT_OUT_OF_RANGE => "Subscript out of range",
+        ARRAY_FIXED_OR_LOCKED => "This array is fixed or temporarily locked",
+        DIVISION_BY_ZERO => "Division by zero",
+        TYPE_MISMATCH => "Type mismatch",
+        OUT_OF_STRING_SPACE => "Out of string space",
+        OUT_OF_STACK_SPACE => "Out of stack space",
+        SUB_OR_FUNCTION_NOT_DEFINED => "Sub or Function not defined",
+        INTERNAL_ERROR => "Internal error",
+        BAD_FILE_NAME_OR_NUMBER => "Bad file name or number",
+        FILE_NOT_FOUND => "File not found",
+        BAD_FILE_MODE => "Bad file mode",
+        FILE_ALREADY_OPEN => "File already open",
+        FILE_ALREADY_EXISTS => "File already exists",
+        INPUT_PAST_END_OF_FILE => "Input past end of file",
+        PERMISSION_DENIED => "Permission denied",
+        PATH_FILE_ACCESS_ERROR => "Path/File access error",
+        PATH_NOT_FOUND => "Path not found",
+        OBJECT_VARIABLE_NOT_SET => "Object variable not set",
+        FOR_LOOP_NOT_INITIALIZED => "For loop not initialized",
+        INVALID_USE_OF_NULL => "Invalid use of Null",
+        OBJECT_REQUIRED => "Object required",
+        CANNOT_CREATE_OBJECT => "ActiveX component can't create object",
+        NO_SUCH_PROPERTY_OR_METHOD => "Object doesn't support this property or method",
+        WRONG_ARGUMENT_COUNT => "Wrong number of arguments or invalid property assignment",
+        OBJECT_NOT_A_COLLECTION => "Object not a collection",
+        KEY_ALREADY_EXISTS => "This key is already associated with an element of this collection",
+        VARIABLE_UNDEFINED => "Variable is undefined",
+        ILLEGAL_ASSIGNMENT => "Illegal assignment",
+        CLASS_NOT_DEFINED => "Class not defined",
+        REGEXP_SYNTAX => "Syntax error in regular expression",
+        ELEMENT_NOT_FOUND => "Element not found",
+        _ => return None,
+    })
+}
+
+/// `Err.Source`, `Err.HelpFile` and `Err.HelpContext` of an error that does
+/// not come from the VBScript runtime itself (e.g. `Err.Raise`, ADO).
+#[derive(Debug, Clone, Default)]
+pub struct ErrDetails {
+    pub source: Option<String>,
+    pub help_file: String,
+    pub help_context: i32,
+}
+
 #[derive(Debug, Clone)]
 pub struct VBSError {
-    pub code: u16,
+    pub code: i32,
     pub message: String,
     pub error_type: VBSErrorType,
+    /// Set for `Err.Raise` and component errors; `None` for runtime errors.
+    pub details: Option<Box<ErrDetails>>,
 }
 
 impl VBSError {
-    pub fn new(code: u16, message: String, error_type: VBSErrorType) -> Self {
+    pub fn new(code: i32, message: String, error_type: VBSErrorType) -> Self {
         VBSError {
             code,
             message,
             error_type,
+            details: None,
+        }
+    }
+
+    /// A runtime error with its standard VBScript description.
+    pub fn runtime(code: i32) -> Self {
+        let message = description(code).unwrap_or("Unknown runtime error").to_string();
+        VBSError::new(code, message, VBSErrorType::RuntimeError)
+    }
+
+    /// A runtime error whose description names the offending item, in the
+    /// `Type mismatch: 'name'` form used by the VBScript engine.
+    pub fn runtime_with(code: i32, detail: impl std::fmt::Display) -> Self {
+        let message = match description(code) {
+            Some(desc) => format!("{}: '{}'", desc, detail),
+            None => detail.to_string(),
+        };
+        VBSError::new(code, message, VBSErrorType::RuntimeError)
+    }
+
+    /// The `Err.Source` reported for this error.
+    pub fn source(&self) -> &str {
+        match self.details.as_ref().and_then(|d| d.source.as_deref()) {
+            Some(source) => source,
+            None if matches!(self.error_type, VBSErrorType::SyntaxError) => COMPILATION_ERROR_SOURCE,
+            None => RUNTIME_ERROR_SOURCE,
         }
     }
 
@@ -33,7 +156,12 @@ impl VBSError {
     }
 
     pub fn with_code(mut self, code: i32) -> Self {
-        self.code = code as u16;
+        self.code = code;
+        self
+    }
+
+    pub fn with_details(mut self, details: ErrDetails) -> Self {
+        self.details = Some(Box::new(details));
         self
     }
 }
@@ -63,6 +191,6 @@ pub enum VBSErrorType {
 
 impl VBSErrorType {
     pub fn into_error(self, message: String) -> VBSError {
-        VBSError::new(self as u16, message, self)
+        VBSError::new(self as i32, message, self)
     }
 }
@@ -1,10 +1,15 @@
 use std::sync::Arc;
 use crate::vbscript::builtins;
-use crate::vbscript::compiler::CompiledCode;
-use crate::vbscript::execution_context::ErrorMode;
-use crate::vbscript::instruction::Instruction;
+use crate::vbscript::compiler::{CompiledCode, GlobalNames, ProcedureCode};
+use crate::vbscript::execution_context::ClassMember;
+use crate::vbscript::execution_context::{ErrorMode, ResourceLimits};
+use crate::vbscript::instruction::{ByRefGuard, Instruction, VarSlot};
+use crate::vbscript::numeric;
+use crate::vbscript::symbol::Symbol;
+use crate::vbscript::value::VBString;
 use crate::vbscript::value_utils;
-use crate::vbscript::vbs_error::{VBSError, VBSErrorType};
+use crate::vbscript::vbobject::{ClassInstance, FunctionRef, ObjectRef};
+use crate::vbscript::vbs_error::{codes, VBSError, VBSErrorType};
 use crate::vbscript::{ExecutionContext, VBValue};
 
 pub struct Vm<'a> {
@@ -13,11 +18,22 @@ pub struct Vm<'a> {
     ip: usize,
     stack: Vec<VBValue>,
     pub(crate) locals: Vec<VBValue>,
+    /// Names of the current locals by slot (`""` for unnamed slots).
+    local_names: Arc<Vec<String>>,
+    /// Globals of the running code, and the context slot bound to each.
+    globals: Arc<GlobalNames>,
+    global_slots: Arc<[usize]>,
+    /// Bindings made so far, for procedures of other scripts.
+    bindings: Vec<(Arc<GlobalNames>, Arc<[usize]>)>,
+    /// Number of user procedure calls in progress on this VM.
+    call_depth: usize,
     frames: Vec<CallFrame>,
     for_states: Vec<ForState>,
     for_each_states: Vec<ForEachState>,
     select_value: Option<VBValue>,
     with_stack: Vec<VBValue>,
+    /// `ByRef` results of the most recent call, read by `ByRefSkip`/`LoadByRef`.
+    pub(crate) byref_results: Vec<Option<VBValue>>,
     context: &'a mut ExecutionContext,
     should_exit: bool,
 }
@@ -31,15 +47,42 @@ struct CallFrame {
     locals_count: usize,
 }
 
+/// Variable that receives the value of an `Eval` expression.
+const EVAL_RESULT: &str = "__eval_result__";
+
+/// The dynamic code execution functions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DynamicCode {
+    Eval,
+    Execute,
+    ExecuteGlobal,
+}
+
+impl DynamicCode {
+    fn from_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("eval") {
+            Some(DynamicCode::Eval)
+        } else if name.eq_ignore_ascii_case("execute") {
+            Some(DynamicCode::Execute)
+        } else if name.eq_ignore_ascii_case("executeglobal") {
+            Some(DynamicCode::ExecuteGlobal)
+        } else {
+            None
+        }
+    }
+}
+
 struct ForState {
-    counter_slot: usize,
+    counter_slot: VarSlot,
     end: f64,
     step: f64,
     current: f64,
+    /// Subtype of the counter, from the start, end and step values.
+    kind: numeric::NumKind,
 }
 
 struct ForEachState {
-    element_slot: usize,
+    element_slot: VarSlot,
     array: Arc<Vec<VBValue>>,
     index: usize,
 }
@@ -52,11 +95,17 @@ impl<'a> Vm<'a> {
             ip: 0,
             stack: Vec::new(),
             locals: Vec::new(),
+            local_names: Arc::new(Vec::new()),
+            globals: Arc::default(),
+            global_slots: Arc::new([]),
+            bindings: Vec::new(),
+            call_depth: 0,
             frames: Vec::new(),
             for_states: Vec::new(),
             for_each_states: Vec::new(),
             select_value: None,
             with_stack: Vec::new(),
+            byref_results: Vec::new(),
             context,
             should_exit: false,
         }
@@ -66,6 +115,14 @@ impl<'a> Vm<'a> {
         self.code = Arc::new(compiled.instructions);
         self.constants = Arc::new(compiled.constants);
         self.locals = vec![VBValue::Empty; compiled.local_count];
+        self.local_names = Arc::new(compiled.local_names);
+        self.bind_globals(&compiled.globals);
+        // Page-level declarations are hoisted, as with procedure locals
+        for &g in &compiled.globals.declared {
+            self.context
+                .global_mut(self.global_slots[g as usize])
+                .get_or_insert(VBValue::Empty);
+        }
         self.ip = 0;
         self.stack.clear();
         self.frames.clear();
@@ -75,28 +132,54 @@ impl<'a> Vm<'a> {
         self.with_stack.clear();
         self.should_exit = false;
 
-        self.execute_loop()
+        // On failure the line stays at the failing statement, for the caller's `Err.Line`
+        let saved_line = self.context.current_line;
+        self.execute_loop()?;
+        self.context.current_line = saved_line;
+        // Objects released by the final instructions terminate before returning
+        self.context.run_pending_terminators()
     }
 
     fn execute_loop(&mut self) -> Result<(), VBSError> {
-        let mut iterations = 0u64;
         loop {
             if self.should_exit {
                 return Ok(());
             }
-            iterations += 1;
-            if iterations > 10_000_000 {
-                return Err(VBSError::new(0, format!("VM iteration limit exceeded at ip={}", self.ip), VBSErrorType::RuntimeError));
+            self.context.instructions += 1;
+            // Reading the clock on every instruction would be costly; an
+            // aborted script is stopped at once, wherever it resumes
+            if self.context.instructions.is_multiple_of(1024) || self.context.aborted.is_some() {
+                self.context.check_budget()?;
             }
             if self.context.response.ended {
                 return Ok(());
             }
+            if self.context.lifecycle.has_released() {
+                if let Err(e) = self.context.run_pending_terminators() {
+                    if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                        self.context.set_err(e);
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
             if self.ip >= self.code.len() {
                 return Ok(());
             }
             let inst = self.code[self.ip].clone();
             self.ip += 1;
 
+            let operands = inst.value_operands();
+            if operands > 0 {
+                if let Err(e) = self.coerce_operands(operands) {
+                    if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                        self.context.set_err(e);
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+
             match inst {
                 // -- Constants --
                 Instruction::LoadConst(i) => {
@@ -110,36 +193,49 @@ impl<'a> Vm<'a> {
 
                 // -- Variables --
                 Instruction::LoadLocal(s) => {
-                    self.stack.push(self.locals[s as usize].clone());
+                    self.stack.push(self.locals[s ].clone());
                 }
                 Instruction::StoreLocal(s) => {
                     let val = self.stack.pop().unwrap();
-                    self.locals[s as usize] = val;
+                    self.locals[s] = val;
                 }
-                Instruction::LoadGlobal(i) => {
+                Instruction::LoadGlobal(g) => {
+                    let val = self.load_global(g)?;
+                    self.stack.push(val);
+                }
+                Instruction::StoreGlobal(g) => {
+                    let val = self.stack.pop().unwrap();
+                    *self.global_mut(g) = Some(val);
+                }
+                Instruction::CheckDeclared(i, line) => {
                     let name = self.constants[i as usize].to_string();
-                    if let Some(val) = self.context.get_variable(&name) {
-                        self.stack.push(val.clone());
-                    } else {
-                        let e = VBSError::new(0, format!("Variable '{}' is not defined", name), VBSErrorType::RuntimeError);
+                    if self.context.get_variable(&name).is_none()
+                        && self.context.get_function(&name).is_none()
+                        && !builtins::is_bare_builtin(&name)
+                    {
+                        let location = if self.context.script_path.is_empty() {
+                            format!("line {}", line)
+                        } else {
+                            format!("{}, line {}", self.context.script_path, line)
+                        };
+                        let e = VBSError::new(
+                            500,
+                            format!("Variable is undefined: '{}' ({})", name, location),
+                            VBSErrorType::RuntimeError,
+                        );
                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                             self.context.set_err(e);
-                            self.stack.push(VBValue::Empty);
                         } else {
                             return Err(e);
                         }
                     }
                 }
-                Instruction::StoreGlobal(i) => {
-                    let name = self.constants[i as usize].to_string();
-                    let val = self.stack.pop().unwrap();
-                    self.context.set_variable(&name, val);
-                }
 
                 // -- Unary --
                 Instruction::Neg => {
                     let val = self.stack.pop().unwrap();
-                    self.stack.push(Vm::negate(val));
+                    let result = Vm::negate(val);
+                    self.push_result(result)?;
                 }
                 Instruction::Not => {
                     let val = self.stack.pop().unwrap();
@@ -150,64 +246,52 @@ impl<'a> Vm<'a> {
                 Instruction::Add => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::add(l, r));
+                    let result = Vm::add(l, r);
+                    self.push_result(result)?;
                 }
                 Instruction::Sub => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::sub(l, r));
+                    let result = Vm::sub(l, r);
+                    self.push_result(result)?;
                 }
                 Instruction::Mul => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::mul(l, r));
+                    let result = Vm::mul(l, r);
+                    self.push_result(result)?;
                 }
                 Instruction::Div => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    match Vm::div(l, r) {
-                        Ok(v) => self.stack.push(v),
-                        Err(e) => {
-                            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                self.context.set_err(e);
-                                self.stack.push(VBValue::Empty);
-                            } else {
-                                return Err(e);
-                            }
-                        }
-                    }
+                    let result = Vm::div(l, r);
+                    self.push_result(result)?;
                 }
                 Instruction::IntDiv => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    match Vm::int_div(l, r) {
-                        Ok(v) => self.stack.push(v),
-                        Err(e) => {
-                            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                self.context.set_err(e);
-                                self.stack.push(VBValue::Empty);
-                            } else {
-                                return Err(e);
-                            }
-                        }
-                    }
+                    let result = Vm::int_div(l, r);
+                    self.push_result(result)?;
                 }
                 Instruction::Mod => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::mod_op(l, r));
+                    let result = Vm::mod_op(l, r);
+                    self.push_result(result)?;
                 }
                 Instruction::Pow => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::pow_op(l, r));
+                    let result = Vm::pow_op(l, r);
+                    self.push_result(result)?;
                 }
 
                 // -- String --
                 Instruction::Concat => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::concat_str(l, r));
+                    let result = Vm::concat_str(l, r);
+                    self.push_result(result)?;
                 }
 
                 // -- Comparison --
@@ -244,7 +328,18 @@ impl<'a> Vm<'a> {
                 Instruction::Is => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(VBValue::Boolean(Vm::values_equal(&l, &r)));
+                    match l.is_same_object(&r) {
+                        Some(same) => self.stack.push(VBValue::Boolean(same)),
+                        None => {
+                            let e = VBSError::runtime(codes::OBJECT_REQUIRED);
+                            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                                self.context.set_err(e);
+                                self.stack.push(VBValue::Boolean(false));
+                            } else {
+                                return Err(e);
+                            }
+                        }
+                    }
                 }
                 Instruction::Like => {
                     let r = self.stack.pop().unwrap();
@@ -256,73 +351,49 @@ impl<'a> Vm<'a> {
                 Instruction::And => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::bool_or_bitwise(l, r, |a, b| a & b));
+                    let result = Vm::bool_or_bitwise(l, r, |a, b| a & b);
+                    self.push_result(result)?;
                 }
                 Instruction::Or => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::bool_or_bitwise(l, r, |a, b| a | b));
+                    let result = Vm::bool_or_bitwise(l, r, |a, b| a | b);
+                    self.push_result(result)?;
                 }
                 Instruction::Xor => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::bool_or_bitwise(l, r, |a, b| a ^ b));
+                    let result = Vm::bool_or_bitwise(l, r, |a, b| a ^ b);
+                    self.push_result(result)?;
                 }
                 Instruction::Imp => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::imp_op(l, r));
+                    let result = Vm::imp_op(l, r);
+                    self.push_result(result)?;
                 }
                 Instruction::Eqv => {
                     let r = self.stack.pop().unwrap();
                     let l = self.stack.pop().unwrap();
-                    self.stack.push(Vm::eqv_op(l, r));
+                    let result = Vm::eqv_op(l, r);
+                    self.push_result(result)?;
                 }
 
                 // -- Objects --
-                Instruction::GetProp(i) => {
+                Instruction::GetProp(member) => {
                     let obj = self.stack.pop().unwrap();
-                    let prop = self.constants[i as usize].to_string();
-                    match obj {
-                        VBValue::Object(obj) => {
-                            let result = match obj.get_property(&prop, self.context)
-                                .map_err(|e| VBSError::new(
-                                    0, format!("Property '{}' not found: {}", prop, e),
-                                    VBSErrorType::RuntimeError
-                                )) {
-                                Ok(v) => v,
-                                Err(e) => {
-                                    if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                        self.context.set_err(e);
-                                        continue;
-                                    } else {
-                                        return Err(e);
-                                    }
-                                }
-                            };
-                            self.stack.push(result);
-                        }
-                        _ => {
-                            let e = VBSError::new(0, format!("Object required for property access: {}", prop), VBSErrorType::RuntimeError);
-                            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                self.context.set_err(e);
-                            } else {
-                                return Err(e);
-                            }
-                        }
-                    }
+                    let result = match &obj {
+                        VBValue::Object(obj) => self.get_object_property(obj, member),
+                        other => Err(Vm::object_required(other)),
+                    };
+                    self.push_result(result)?;
                 }
-                Instruction::SetProp(i) => {
+                Instruction::SetProp(member) => {
                     let val = self.stack.pop().unwrap();
-                    let mut obj = self.stack.pop().unwrap();
-                    let prop = self.constants[i as usize].to_string();
-                    match &mut obj {
+                    let obj = self.stack.pop().unwrap();
+                    match &obj {
                         VBValue::Object(obj) => {
-                            match obj.set_property(&prop, val, self.context)
-                                .map_err(|e| VBSError::new(
-                                    0, format!("Cannot set property '{}': {}", prop, e),
-                                    VBSErrorType::RuntimeError
-                                )) {
+                            match self.set_object_property(obj, member, val) {
                                 Ok(_) => {}
                                 Err(e) => {
                                     if *self.context.get_error_mode() == ErrorMode::ResumeNext {
@@ -335,7 +406,7 @@ impl<'a> Vm<'a> {
                             }
                         }
                         _ => {
-                            let e = VBSError::new(0, "Object required".to_string(), VBSErrorType::RuntimeError);
+                            let e = Vm::object_required(&obj);
                             if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                 self.context.set_err(e);
                             } else {
@@ -344,18 +415,15 @@ impl<'a> Vm<'a> {
                         }
                     }
                 }
-                Instruction::SetPropLocal(slot, i) => {
-                    let prop = self.constants[i as usize].to_string();
+                Instruction::SetPropLocal(slot, member) => {
                     let val = self.stack.pop().unwrap();
-                    let mut obj_val = VBValue::Empty;
-                    std::mem::swap(&mut self.locals[slot as usize], &mut obj_val);
-                    let result = match &mut obj_val {
+                    let result = match &self.locals[slot] {
                         VBValue::Object(obj) => {
-                            obj.set_property(&prop, val, self.context)
+                            let obj = obj.clone();
+                            self.set_object_property(&obj, member, val)
                         }
-                        _ => Err(VBSError::new(0, "Object required".to_string(), VBSErrorType::RuntimeError)),
+                        other => Err(Vm::object_required(other)),
                     };
-                    std::mem::swap(&mut self.locals[slot as usize], &mut obj_val);
                     if let Err(e) = result {
                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                             self.context.set_err(e);
@@ -364,21 +432,15 @@ impl<'a> Vm<'a> {
                         }
                     }
                 }
-                Instruction::SetPropGlobal(o, i) => {
-                    let prop = self.constants[i as usize].to_string();
+                Instruction::SetPropGlobal(g, member) => {
                     let val = self.stack.pop().unwrap();
-                    let obj_key = self.constants[o as usize].to_string();
-                    let mut obj_val = VBValue::Empty;
-                    if let Some(slot) = self.context.get_variable_mut(&obj_key) {
-                        std::mem::swap(slot, &mut obj_val);
-                    }
-                    let result = match &mut obj_val {
+                    let obj_val = self.global(g).cloned().unwrap_or(VBValue::Empty);
+                    let result = match &obj_val {
                         VBValue::Object(obj) => {
-                            obj.set_property(&prop, val, self.context)
+                            self.set_object_property(obj, member, val)
                         }
-                        _ => Err(VBSError::new(0, "Object required".to_string(), VBSErrorType::RuntimeError)),
+                        other => Err(Vm::object_required(other)),
                     };
-                    self.context.set_variable(&obj_key, obj_val);
                     if let Err(e) = result {
                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                             self.context.set_err(e);
@@ -387,8 +449,8 @@ impl<'a> Vm<'a> {
                         }
                     }
                 }
-                Instruction::CallMethod(i, n) => {
-                    let method = self.constants[i as usize].to_string();
+                Instruction::CallMethod(member, n) => {
+                    self.byref_results.clear();
                     let n_args = n as usize;
 
                     let args: Vec<VBValue> = if n_args > 0 {
@@ -398,48 +460,32 @@ impl<'a> Vm<'a> {
                         Vec::new()
                     };
 
-                    let mut obj = self.stack.pop().unwrap();
-                    match &mut obj {
+                    let obj = self.stack.pop().unwrap();
+                    match &obj {
                         VBValue::Object(obj) => {
                             // First try property + indexed access pattern
                             let found = if n_args == 1 && !args.is_empty() {
-                                if let Ok(sub) = obj.get_property(&method, self.context) {
-                                    if let VBValue::Object(sub_obj) = sub {
-                                        if let Ok(result) = sub_obj.indexed_get(&args[0], self.context) {
-                                            self.stack.push(result);
-                                            true
-                                        } else { false }
+                                if let Ok(VBValue::Object(sub_obj)) = self.get_object_property(obj, member) {
+                                    if let Ok(result) = sub_obj.indexed_get(&args[0], self.context) {
+                                        self.stack.push(result);
+                                        true
                                     } else { false }
                                 } else { false }
                             } else { false };
 
                             if !found {
-                                let result = match obj.call_method(&method, &args, self.context) {
-                                    Ok(v) => v,
-                                    Err(e) => {
-                                        if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                            self.context.set_err(e);
-                                            continue;
-                                        } else {
-                                            return Err(e);
-                                        }
-                                    }
-                                };
-                                self.stack.push(result);
+                                let result = self.call_object_method(obj, member, &args);
+                                self.push_result(result)?;
                             }
                         }
-                        _ => {
-                            let e = VBSError::new(0, format!("Object required for method call: {}", method), VBSErrorType::RuntimeError);
-                            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                self.context.set_err(e);
-                            } else {
-                                return Err(e);
-                            }
+                        other => {
+                            let e = Vm::object_required(other);
+                            self.push_result(Err(e))?;
                         }
                     }
                 }
-                Instruction::CallMethodLocal(slot, i, n) => {
-                    let method = self.constants[i as usize].to_string();
+                Instruction::CallMethodLocal(slot, member, n) => {
+                    self.byref_results.clear();
                     let n_args = n as usize;
 
                     let args: Vec<VBValue> = if n_args > 0 {
@@ -449,20 +495,13 @@ impl<'a> Vm<'a> {
                         Vec::new()
                     };
 
-                    let mut obj_val = VBValue::Empty;
-                    std::mem::swap(&mut self.locals[slot as usize], &mut obj_val);
-                    let result = match &mut obj_val {
+                    let result = match &self.locals[slot] {
                         VBValue::Object(obj) => {
-                            obj.call_method(&method, &args, self.context)
-                        }
-                        _ => {
-                            Err(VBSError::new(
-                                0, format!("Object required for method call: {}", method),
-                                VBSErrorType::RuntimeError
-                            ))
+                            let obj = obj.clone();
+                            self.call_object_method(&obj, member, &args)
                         }
+                        other => Err(Vm::object_required(other)),
                     };
-                    std::mem::swap(&mut self.locals[slot as usize], &mut obj_val);
                     match result {
                         Ok(v) => self.stack.push(v),
                         Err(e) => {
@@ -474,9 +513,8 @@ impl<'a> Vm<'a> {
                         }
                     }
                 }
-                Instruction::CallMethodGlobal(g, i, n) => {
-                    let method = self.constants[i as usize].to_string();
-                    let name = self.constants[g as usize].to_string();
+                Instruction::CallMethodGlobal(g, member, n) => {
+                    self.byref_results.clear();
                     let n_args = n as usize;
 
                     let args: Vec<VBValue> = if n_args > 0 {
@@ -486,22 +524,13 @@ impl<'a> Vm<'a> {
                         Vec::new()
                     };
 
-                    let mut obj_val = VBValue::Empty;
-                    if let Some(slot) = self.context.get_variable_mut(&name) {
-                        std::mem::swap(slot, &mut obj_val);
-                    }
-                    let result = match &mut obj_val {
+                    let obj_val = self.global(g).cloned().unwrap_or(VBValue::Empty);
+                    let result = match &obj_val {
                         VBValue::Object(obj) => {
-                            obj.call_method(&method, &args, self.context)
-                        }
-                        _ => {
-                            Err(VBSError::new(
-                                0, format!("Object required for method call: {}", method),
-                                VBSErrorType::RuntimeError
-                            ))
+                            self.call_object_method(obj, member, &args)
                         }
+                        other => Err(Vm::object_required(other)),
                     };
-                    self.context.set_variable(&name, obj_val);
                     match result {
                         Ok(v) => self.stack.push(v),
                         Err(e) => {
@@ -516,47 +545,15 @@ impl<'a> Vm<'a> {
                 Instruction::IndexGet => {
                     let key = self.stack.pop().unwrap();
                     let obj = self.stack.pop().unwrap();
-                    match obj {
-                        VBValue::Object(obj) => {
-                            let result = match obj.indexed_get(&key, self.context)
-                                .map_err(|e| VBSError::new(
-                                    0, format!("Indexed get failed: {}", e),
-                                    VBSErrorType::RuntimeError
-                                )) {
-                                Ok(v) => v,
-                                Err(e) => {
-                                    if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                        self.context.set_err(e);
-                                        continue;
-                                    } else {
-                                        return Err(e);
-                                    }
-                                }
-                            };
-                            self.stack.push(result);
-                        }
+                    let result = match obj {
+                        VBValue::Object(obj) => obj.indexed_get(&key, self.context),
                         VBValue::Array(arr, _dims) => {
                             let idx = value_utils::to_arg_f64(&key) as usize;
-                            if idx < arr.len() {
-                                self.stack.push(arr[idx].clone());
-                            } else {
-                                let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
-                                if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                    self.context.set_err(e);
-                                } else {
-                                    return Err(e);
-                                }
-                            }
+                            arr.get(idx).cloned().ok_or_else(|| VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE))
                         }
-                        _ => {
-                            let e = VBSError::new(0, "Object or array required".to_string(), VBSErrorType::RuntimeError);
-                            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                self.context.set_err(e);
-                            } else {
-                                return Err(e);
-                            }
-                        }
-                    }
+                        _ => Err(VBSError::runtime(codes::TYPE_MISMATCH)),
+                    };
+                    self.push_result(result)?;
                 }
                 Instruction::IndexSet => {
                     let val = self.stack.pop().unwrap();
@@ -564,11 +561,7 @@ impl<'a> Vm<'a> {
                     let mut obj = self.stack.pop().unwrap();
                     match &mut obj {
                         VBValue::Object(obj) => {
-                            match obj.indexed_set(&key, val, self.context)
-                                .map_err(|e| VBSError::new(
-                                    0, format!("Indexed set failed: {}", e),
-                                    VBSErrorType::RuntimeError
-                                )) {
+                            match obj.indexed_set(&key, val, self.context) {
                                 Ok(_) => {}
                                 Err(e) => {
                                     if *self.context.get_error_mode() == ErrorMode::ResumeNext {
@@ -586,7 +579,7 @@ impl<'a> Vm<'a> {
                                 if idx < arr.len() {
                                     arr[idx] = val;
                                 } else {
-                                    let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                    let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                     if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                         self.context.set_err(e);
                                     } else {
@@ -594,7 +587,7 @@ impl<'a> Vm<'a> {
                                     }
                                 }
                             } else {
-                                let e = VBSError::new(0, "Cannot mutate shared array".to_string(), VBSErrorType::RuntimeError);
+                                let e = VBSError::runtime(codes::ARRAY_FIXED_OR_LOCKED);
                                 if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                     self.context.set_err(e);
                                 } else {
@@ -603,7 +596,7 @@ impl<'a> Vm<'a> {
                             }
                         }
                         _ => {
-                            let e = VBSError::new(0, "Object or array required".to_string(), VBSErrorType::RuntimeError);
+                            let e = VBSError::runtime(codes::TYPE_MISMATCH);
                             if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                 self.context.set_err(e);
                             } else {
@@ -615,22 +608,22 @@ impl<'a> Vm<'a> {
                 Instruction::IndexStoreLocal(slot) => {
                     let val = self.stack.pop().unwrap();
                     let key = self.stack.pop().unwrap();
-                    let arr = &mut self.locals[slot as usize];
-                    if let VBValue::Array(arr_ref, _) = arr {
+                    let result = if let VBValue::Object(obj) = &self.locals[slot] {
+                        let obj = obj.clone();
+                        obj.indexed_set(&key, val, self.context)
+                    } else if let VBValue::Array(arr_ref, _) = &mut self.locals[slot] {
                         let idx = value_utils::to_arg_f64(&key) as usize;
                         let items = Arc::make_mut(arr_ref);
                         if idx < items.len() {
                             items[idx] = val;
+                            Ok(())
                         } else {
-                            let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
-                            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                                self.context.set_err(e);
-                            } else {
-                                return Err(e);
-                            }
+                            Err(VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE))
                         }
                     } else {
-                        let e = VBSError::new(0, "Object or array required".to_string(), VBSErrorType::RuntimeError);
+                        Err(VBSError::runtime(codes::TYPE_MISMATCH))
+                    };
+                    if let Err(e) = result {
                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                             self.context.set_err(e);
                         } else {
@@ -638,28 +631,20 @@ impl<'a> Vm<'a> {
                         }
                     }
                 }
-                Instruction::IndexStoreGlobal(idx) => {
+                Instruction::IndexStoreGlobal(g) => {
                     let val = self.stack.pop().unwrap();
                     let key = self.stack.pop().unwrap();
-                    let name = self.constants[idx as usize].to_string();
                     // Check type first to decide between array vs object path
-                    let is_object = matches!(
-                        self.context.get_variable(&name),
-                        Some(VBValue::Object(_))
-                    );
-                    let is_array = matches!(
-                        self.context.get_variable(&name),
-                        Some(VBValue::Array(..))
-                    );
+                    let is_object = matches!(self.global(g), Some(VBValue::Object(_)));
+                    let is_array = matches!(self.global(g), Some(VBValue::Array(..)));
                     if is_array {
-                        let var = self.context.get_variable_mut(&name).unwrap();
-                        if let VBValue::Array(arr_ref, _) = var {
+                        if let Some(VBValue::Array(arr_ref, _)) = self.global_mut(g) {
                             let idx_val = value_utils::to_arg_f64(&key) as usize;
                             let items = Arc::make_mut(arr_ref);
                             if idx_val < items.len() {
                                 items[idx_val] = val;
                             } else {
-                                let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                 if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                     self.context.set_err(e);
                                 } else {
@@ -668,17 +653,11 @@ impl<'a> Vm<'a> {
                             }
                         }
                     } else if is_object {
-                        let mut obj_val = {
-                            let slot = self.context.get_variable_mut(&name).unwrap();
-                            let mut replacement = VBValue::Empty;
-                            std::mem::swap(slot, &mut replacement);
-                            replacement
-                        };
-                        let result = match &mut obj_val {
-                            VBValue::Object(ref mut o) => o.indexed_set(&key, val, self.context),
+                        let obj_val = self.global(g).cloned().unwrap_or(VBValue::Empty);
+                        let result = match &obj_val {
+                            VBValue::Object(o) => o.indexed_set(&key, val, self.context),
                             _ => unreachable!(),
                         };
-                        self.context.set_variable(&name, obj_val);
                         if let Err(e) = result {
                             if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                 self.context.set_err(e);
@@ -687,7 +666,7 @@ impl<'a> Vm<'a> {
                             }
                         }
                     } else {
-                        let e = VBSError::new(0, "Object or array required".to_string(), VBSErrorType::RuntimeError);
+                        let e = VBSError::runtime(codes::TYPE_MISMATCH);
                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                             self.context.set_err(e);
                         } else {
@@ -700,12 +679,12 @@ impl<'a> Vm<'a> {
                     let val = self.stack.pop().unwrap();
                     let start = self.stack.len() - n_indices;
                     let indices: Vec<VBValue> = self.stack.drain(start..).collect();
-                    let arr = &mut self.locals[slot as usize];
+                    let arr = &mut self.locals[slot];
                     if let VBValue::Array(arr_ref, dims) = arr {
                         let flat_idx = if dims.is_empty() && n_indices == 1 {
                             let idx_val = value_utils::to_arg_f64(&indices[0]) as usize;
                             if idx_val >= arr_ref.len() {
-                                let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                 if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                     self.context.set_err(e);
                                 } else {
@@ -717,7 +696,7 @@ impl<'a> Vm<'a> {
                             let flat_idx = match value_utils::compute_flat_index(&indices, dims) {
                                 Some(v) => v,
                                 None => {
-                                    let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                    let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                     if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                         self.context.set_err(e);
                                         continue;
@@ -728,7 +707,7 @@ impl<'a> Vm<'a> {
                             };
                             flat_idx
                         } else {
-                            let e = VBSError::new(9, format!("Array has {} dimensions but {} indices provided", dims.len(), n_indices), VBSErrorType::RuntimeError);
+                            let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                             if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                 self.context.set_err(e);
                                 continue;
@@ -740,7 +719,7 @@ impl<'a> Vm<'a> {
                         if flat_idx < items.len() {
                             items[flat_idx] = val;
                         } else {
-                            let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                            let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                             if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                 self.context.set_err(e);
                             } else {
@@ -748,7 +727,7 @@ impl<'a> Vm<'a> {
                             }
                         }
                     } else {
-                        let e = VBSError::new(0, "Object or array required".to_string(), VBSErrorType::RuntimeError);
+                        let e = VBSError::runtime(codes::TYPE_MISMATCH);
                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                             self.context.set_err(e);
                             continue;
@@ -757,18 +736,17 @@ impl<'a> Vm<'a> {
                         }
                     }
                 }
-                Instruction::IndexStoreGlobalMulti(idx, n) => {
+                Instruction::IndexStoreGlobalMulti(g, n) => {
                     let n_indices = n as usize;
                     let val = self.stack.pop().unwrap();
                     let start = self.stack.len() - n_indices;
                     let indices: Vec<VBValue> = self.stack.drain(start..).collect();
-                    let name = self.constants[idx as usize].to_string();
-                    let var = self.context.get_variable_mut(&name);
-                    if let Some(VBValue::Array(arr_ref, dims)) = var {
+                    let slot = self.global_slots[g as usize];
+                    if let Some(VBValue::Array(arr_ref, dims)) = self.context.global_mut(slot) {
                         let flat_idx = if dims.is_empty() && n_indices == 1 {
                             let idx_val = value_utils::to_arg_f64(&indices[0]) as usize;
                             if idx_val >= arr_ref.len() {
-                                let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                 if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                     self.context.set_err(e);
                                     continue;
@@ -781,7 +759,7 @@ impl<'a> Vm<'a> {
                             let flat_idx = match value_utils::compute_flat_index(&indices, dims) {
                                 Some(v) => v,
                                 None => {
-                                    let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                    let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                     if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                         self.context.set_err(e);
                                         continue;
@@ -792,7 +770,7 @@ impl<'a> Vm<'a> {
                             };
                             flat_idx
                         } else {
-                            let e = VBSError::new(9, format!("Array has {} dimensions but {} indices provided", dims.len(), n_indices), VBSErrorType::RuntimeError);
+                            let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                             if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                 self.context.set_err(e);
                                 continue;
@@ -804,7 +782,7 @@ impl<'a> Vm<'a> {
                         if flat_idx < items.len() {
                             items[flat_idx] = val;
                         } else {
-                            let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                            let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                             if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                 self.context.set_err(e);
                             } else {
@@ -812,7 +790,7 @@ impl<'a> Vm<'a> {
                             }
                         }
                     } else {
-                        let e = VBSError::new(0, "Object or array required".to_string(), VBSErrorType::RuntimeError);
+                        let e = VBSError::runtime(codes::TYPE_MISMATCH);
                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                             self.context.set_err(e);
                         } else {
@@ -822,16 +800,20 @@ impl<'a> Vm<'a> {
                 }
                 Instruction::NewObject(i) => {
                     let name = self.constants[i as usize].to_string();
-                    let class = self.context.get_class(&name);
-                    if class.is_some() {
-                        let instance = crate::vbscript::vbobject::ClassInstance::new(&name);
-                        self.stack.push(VBValue::Object(Box::new(instance)));
+                    let created = if self.context.get_class(&name).is_some() {
+                        ClassInstance::create(&name, self.context)
                     } else {
-                        let e = VBSError::new(0, format!("Class '{}' not found", name), VBSErrorType::RuntimeError);
-                        if *self.context.get_error_mode() == ErrorMode::ResumeNext {
-                            self.context.set_err(e);
-                        } else {
-                            return Err(e);
+                        Err(VBSError::runtime_with(codes::CLASS_NOT_DEFINED, &name))
+                    };
+                    match created {
+                        Ok(obj) => self.stack.push(VBValue::Object(obj)),
+                        Err(e) => {
+                            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                                self.context.set_err(e);
+                                self.stack.push(VBValue::Empty);
+                            } else {
+                                return Err(e);
+                            }
                         }
                     }
                 }
@@ -840,20 +822,18 @@ impl<'a> Vm<'a> {
                 Instruction::NewArray(n) => {
                     let dim_count = n as usize;
                     let mut dims = Vec::with_capacity(dim_count);
-                    let mut total_size = 1;
                     for _ in 0..dim_count {
-                        let size = value_utils::to_arg_f64(&self.stack.pop().unwrap()) as usize;
-                        dims.push(size);
-                        total_size *= size + 1;
+                        dims.push(value_utils::to_arg_f64(&self.stack.pop().unwrap()) as usize);
                     }
                     dims.reverse();
-                    let arr = vec![VBValue::Empty; total_size];
-                    self.stack.push(VBValue::Array(Arc::new(arr), dims));
+                    let result = self.context.limits.array_size(&dims)
+                        .map(|total_size| VBValue::Array(Arc::new(vec![VBValue::Empty; total_size]), dims));
+                    self.push_result(result)?;
                 }
                 Instruction::ReDim(slot, n, preserve) => {
                     let dim_count = n as usize;
                     if preserve && dim_count > 1 {
-                        let e = VBSError::new(0, "ReDim Preserve can only change the last dimension of a multi-dimensional array".to_string(), VBSErrorType::RuntimeError);
+                        let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                             self.context.set_err(e);
                             continue;
@@ -861,36 +841,38 @@ impl<'a> Vm<'a> {
                             return Err(e);
                         }
                     }
-                    let mut dims = Vec::with_capacity(dim_count);
-                    let mut total_size = 1;
                     let mut new_dims = Vec::with_capacity(dim_count);
                     for _ in 0..dim_count {
-                        let bound = value_utils::to_arg_f64(&self.stack.pop().unwrap()) as usize;
-                        dims.push(bound);
-                        new_dims.push(bound);
-                        total_size *= bound + 1;
+                        new_dims.push(value_utils::to_arg_f64(&self.stack.pop().unwrap()) as usize);
                     }
-                    dims.reverse();
                     new_dims.reverse();
+                    let total_size = match self.context.limits.array_size(&new_dims) {
+                        Ok(total_size) => total_size,
+                        Err(e) => {
+                            self.trap_error(e)?;
+                            continue;
+                        }
+                    };
 
                     if preserve {
-                        if let VBValue::Array(old_arr, _old_dims) = &self.locals[slot as usize] {
+                        if let Some(VBValue::Array(old_arr, _old_dims)) = self.var_value(slot) {
                             let mut new_arr = vec![VBValue::Empty; total_size];
                             let copy_len = old_arr.len().min(total_size);
                             for i in 0..copy_len {
                                 new_arr[i] = old_arr[i].clone();
                             }
-                            self.locals[slot as usize] = VBValue::Array(Arc::new(new_arr), new_dims);
+                            self.store_var(slot, VBValue::Array(Arc::new(new_arr), new_dims));
                         } else {
-                            self.locals[slot as usize] = VBValue::Array(Arc::new(vec![VBValue::Empty; total_size]), new_dims);
+                            self.store_var(slot, VBValue::Array(Arc::new(vec![VBValue::Empty; total_size]), new_dims));
                         }
                     } else {
-                        self.locals[slot as usize] = VBValue::Array(Arc::new(vec![VBValue::Empty; total_size]), new_dims);
+                        self.store_var(slot, VBValue::Array(Arc::new(vec![VBValue::Empty; total_size]), new_dims));
                     }
                 }
 
                 // -- Functions --
                 Instruction::Call(i, n) => {
+                    self.byref_results.clear();
                     let name = self.constants[i as usize].to_string();
                     let n_args = n as usize;
 
@@ -901,13 +883,29 @@ impl<'a> Vm<'a> {
                         Vec::new()
                     };
 
+                    // Call through a `GetRef` function reference
+                    let target = match self.context.get_variable(&name) {
+                        Some(VBValue::Object(obj)) => obj.function_name().map(str::to_string),
+                        _ => None,
+                    };
+                    if let Some(target) = target {
+                        if let Err(e) = self.call_user_function(&target, &args) {
+                            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                                self.context.set_err(e);
+                            } else {
+                                return Err(e);
+                            }
+                        }
+                        continue;
+                    }
+
                     // Check array access first (matches old interpreter's evaluate order)
                     if n_args > 0 {
                         if let Some(VBValue::Array(items, dims)) = self.context.get_variable(&name) {
                             let flat_idx = if dims.is_empty() && args.len() == 1 {
                                 let idx = value_utils::to_arg_f64(&args[0]) as usize;
                                 if idx >= items.len() {
-                                    let e = VBSError::new(9, format!("Subscript out of range: index {} exceeds array size {}", idx, items.len()), VBSErrorType::RuntimeError);
+                                    let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                     if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                         self.context.set_err(e);
                                         continue;
@@ -920,7 +918,7 @@ impl<'a> Vm<'a> {
                                 let idx = match value_utils::compute_flat_index(&args, dims) {
                                     Some(v) => v,
                                     None => {
-                                        let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                        let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                             self.context.set_err(e);
                                             continue;
@@ -930,7 +928,7 @@ impl<'a> Vm<'a> {
                                     }
                                 };
                                 if idx >= items.len() {
-                                    let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                    let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                     if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                         self.context.set_err(e);
                                         continue;
@@ -940,7 +938,7 @@ impl<'a> Vm<'a> {
                                 }
                                 idx
                             } else {
-                                let e = VBSError::new(9, format!("Array has {} dimensions but {} indices provided", dims.len(), args.len()), VBSErrorType::RuntimeError);
+                                let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                 if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                     self.context.set_err(e);
                                     continue;
@@ -958,17 +956,13 @@ impl<'a> Vm<'a> {
                         let is_obj = matches!(self.context.get_variable(&name), Some(VBValue::Object(_)));
                         if is_obj {
                             let key = &args[0];
-                            let mut obj_val = VBValue::Empty;
-                            if let Some(slot) = self.context.get_variable_mut(&name) {
-                                std::mem::swap(slot, &mut obj_val);
-                            }
-                            let result = match &mut obj_val {
-                                VBValue::Object(ref mut obj) => {
+                            let obj_val = self.context.get_variable(&name).cloned().unwrap_or(VBValue::Empty);
+                            let result = match &obj_val {
+                                VBValue::Object(obj) => {
                                     obj.indexed_get(key, self.context)
                                 }
                                 _ => unreachable!(),
                             };
-                            self.context.set_variable(&name, obj_val);
                             match result {
                                 Ok(val) => {
                                     self.stack.push(val);
@@ -993,6 +987,31 @@ impl<'a> Vm<'a> {
                             Err(e) => {
                                 if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                     self.context.set_err(e);
+                                    self.stack.push(VBValue::Empty);
+                                } else {
+                                    return Err(e);
+                                }
+                            }
+                        }
+                    } else if name.eq_ignore_ascii_case("getref") {
+                        match self.get_ref(&args) {
+                            Ok(v) => self.stack.push(v),
+                            Err(e) => {
+                                if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                                    self.context.set_err(e);
+                                    self.stack.push(VBValue::Empty);
+                                } else {
+                                    return Err(e);
+                                }
+                            }
+                        }
+                    } else if let Some(kind) = DynamicCode::from_name(&name) {
+                        match self.run_dynamic_code(kind, &args) {
+                            Ok(v) => self.stack.push(v),
+                            Err(e) => {
+                                if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                                    self.context.set_err(e);
+                                    self.stack.push(VBValue::Empty);
                                 } else {
                                     return Err(e);
                                 }
@@ -1005,6 +1024,7 @@ impl<'a> Vm<'a> {
                             Err(e) => {
                                 if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                     self.context.set_err(e);
+                                    self.stack.push(VBValue::Empty);
                                 } else {
                                     return Err(e);
                                 }
@@ -1027,7 +1047,7 @@ impl<'a> Vm<'a> {
                             let flat_idx = if dims.is_empty() && args.len() == 1 {
                                 let idx = value_utils::to_arg_f64(&args[0]) as usize;
                                 if idx >= items.len() {
-                                    let e = VBSError::new(9, format!("Subscript out of range: index {} exceeds array size {}", idx, items.len()), VBSErrorType::RuntimeError);
+                                    let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                     if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                         self.context.set_err(e);
                                         continue;
@@ -1040,7 +1060,7 @@ impl<'a> Vm<'a> {
                                 let idx = match value_utils::compute_flat_index(&args, &dims) {
                                     Some(v) => v,
                                     None => {
-                                        let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                        let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                         if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                             self.context.set_err(e);
                                             continue;
@@ -1050,7 +1070,7 @@ impl<'a> Vm<'a> {
                                     }
                                 };
                                 if idx >= items.len() {
-                                    let e = VBSError::new(9, "Subscript out of range".to_string(), VBSErrorType::RuntimeError);
+                                    let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                     if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                         self.context.set_err(e);
                                         continue;
@@ -1060,7 +1080,7 @@ impl<'a> Vm<'a> {
                                 }
                                 idx
                             } else {
-                                let e = VBSError::new(9, format!("Array has {} dimensions but {} indices provided", dims.len(), args.len()), VBSErrorType::RuntimeError);
+                                let e = VBSError::runtime(codes::SUBSCRIPT_OUT_OF_RANGE);
                                 if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                     self.context.set_err(e);
                                     continue;
@@ -1070,6 +1090,16 @@ impl<'a> Vm<'a> {
                             };
                             self.stack.push(items[flat_idx].clone());
                         }
+                        VBValue::Object(obj) if obj.function_name().is_some() => {
+                            let target = obj.function_name().unwrap_or_default().to_string();
+                            if let Err(e) = self.call_user_function(&target, &args) {
+                                if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                                    self.context.set_err(e);
+                                } else {
+                                    return Err(e);
+                                }
+                            }
+                        }
                         VBValue::Object(obj) => {
                             if let Some(arg) = args.first() {
                                 match obj.indexed_get(arg, self.context) {
@@ -1087,7 +1117,7 @@ impl<'a> Vm<'a> {
                             }
                         }
                         _ => {
-                            let e = VBSError::new(424, "Object or array required".to_string(), VBSErrorType::RuntimeError);
+                            let e = VBSError::runtime(codes::TYPE_MISMATCH);
                             if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                 self.context.set_err(e);
                                 continue;
@@ -1097,6 +1127,22 @@ impl<'a> Vm<'a> {
                         }
                     }
                 }
+                Instruction::ByRefSkip(k, guard, offset) => {
+                    let bound = matches!(self.byref_results.get(k as usize), Some(Some(_)));
+                    let target_ok = match guard {
+                        ByRefGuard::None => true,
+                        ByRefGuard::Global(g) => self.global(g).is_some(),
+                        ByRefGuard::LocalArray(slot) => matches!(self.locals[slot], VBValue::Array(..)),
+                        ByRefGuard::GlobalArray(g) => matches!(self.global(g), Some(VBValue::Array(..))),
+                    };
+                    if !(bound && target_ok) {
+                        self.ip = (self.ip as isize + offset as isize) as usize;
+                    }
+                }
+                Instruction::LoadByRef(k) => {
+                    let val = self.byref_results.get(k as usize).cloned().flatten().unwrap_or(VBValue::Empty);
+                    self.stack.push(val);
+                }
                 Instruction::Return(n) => {
                     let frame = self.frames.pop().unwrap();
                     let result = if n > 0 {
@@ -1147,15 +1193,18 @@ impl<'a> Vm<'a> {
                     } else {
                         let step_val = self.stack.pop().unwrap();
                         let end_val = self.stack.pop().unwrap();
-                        let counter = self.locals[slot as usize].clone();
+                        let counter = self.var_value(slot).cloned().unwrap_or(VBValue::Empty);
                         let counter_num = value_utils::to_arg_f64(&counter);
                         let end_num = value_utils::to_arg_f64(&end_val);
                         let step_num = value_utils::to_arg_f64(&step_val);
+                        let kind = numeric::loop_kind(&[&counter, &end_val, &step_val]);
+                        self.store_var(slot, numeric::from_f64(kind, counter_num));
                         self.for_states.push(ForState {
                             counter_slot: slot,
                             end: end_num,
                             step: step_num,
                             current: counter_num,
+                            kind,
                         });
                         if (step_num >= 0.0 && counter_num > end_num) || (step_num < 0.0 && counter_num < end_num) {
                             self.for_states.pop();
@@ -1178,12 +1227,13 @@ impl<'a> Vm<'a> {
                     if let Some(fs) = self.for_states.last_mut() {
                         let step_val = fs.step;
                         fs.current += step_val;
-                        self.locals[slot as usize] = VBValue::Number(fs.current);
+                        let value = numeric::from_f64(fs.kind, fs.current);
                         let past_end = if step_val >= 0.0 {
                             fs.current > fs.end
                         } else {
                             fs.current < fs.end
                         };
+                        self.store_var(slot, value);
                         if !past_end {
                             self.ip = (self.ip as isize + back_offset as isize) as usize;
                         } else {
@@ -1202,7 +1252,7 @@ impl<'a> Vm<'a> {
                                 if arr.is_empty() {
                                     self.ip = (self.ip as isize + exit_offset as isize) as usize;
                                 } else {
-                                    self.locals[slot as usize] = arr[0].clone();
+                                    self.store_var(slot, arr[0].clone());
                                     self.for_each_states.push(ForEachState {
                                         element_slot: slot,
                                         array: arr,
@@ -1210,24 +1260,29 @@ impl<'a> Vm<'a> {
                                     });
                                 }
                             }
-                            VBValue::Object(obj) => {
-                                if let Ok(VBValue::Array(keys_arr, _)) = obj.get_property("keys", self.context) {
-                                    if keys_arr.is_empty() {
-                                        self.ip = (self.ip as isize + exit_offset as isize) as usize;
+                            VBValue::Object(obj) => match obj.for_each_items(self.context) {
+                                Ok(Some(items)) if !items.is_empty() => {
+                                    self.store_var(slot, items[0].clone());
+                                    self.for_each_states.push(ForEachState {
+                                        element_slot: slot,
+                                        array: Arc::new(items),
+                                        index: 0,
+                                    });
+                                }
+                                Ok(_) => {
+                                    self.ip = (self.ip as isize + exit_offset as isize) as usize;
+                                }
+                                Err(e) => {
+                                    self.ip = (self.ip as isize + exit_offset as isize) as usize;
+                                    if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                                        self.context.set_err(e);
                                     } else {
-                                        self.locals[slot as usize] = keys_arr[0].clone();
-                                        self.for_each_states.push(ForEachState {
-                                            element_slot: slot,
-                                            array: keys_arr,
-                                            index: 0,
-                                        });
+                                        return Err(e);
                                     }
-                                } else {
-                                    self.ip = (self.ip as isize + exit_offset as isize) as usize;
                                 }
-                            }
+                            },
                             _ => {
-                                let e = VBSError::new(0, "Object doesn't support this property or method".to_string(), VBSErrorType::RuntimeError);
+                                let e = VBSError::runtime(codes::OBJECT_NOT_A_COLLECTION);
                                 if *self.context.get_error_mode() == ErrorMode::ResumeNext {
                                     self.context.set_err(e);
                                 } else {
@@ -1242,7 +1297,8 @@ impl<'a> Vm<'a> {
                         if fes.element_slot == slot {
                             fes.index += 1;
                             if fes.index < fes.array.len() {
-                                self.locals[slot as usize] = fes.array[fes.index].clone();
+                                let value = fes.array[fes.index].clone();
+                                self.store_var(slot, value);
                                 self.ip = (self.ip as isize + back_offset as isize) as usize;
                             } else {
                                 self.for_each_states.pop();
@@ -1322,14 +1378,16 @@ impl<'a> Vm<'a> {
                 }
 
                 // -- Debug --
-                Instruction::DebugLine(_line) => {
-                    // No-op when debugger is not attached
+                Instruction::DebugLine(line) => {
+                    self.context.current_line = line;
                 }
 
                 // -- ASP-specific --
                 Instruction::ResponseWrite => {
                     let val = self.stack.pop().unwrap();
-                    self.context.write(&val.to_string());
+                    if let Err(e) = self.context.write(&val.to_string()) {
+                        self.trap_error(e)?;
+                    }
                 }
                 Instruction::ResponseEnd => {
                     self.context.response.ended = true;
@@ -1366,7 +1424,7 @@ impl<'a> Vm<'a> {
 
                 // -- Variable management --
                 Instruction::Erase(slot) => {
-                    match &mut self.locals[slot as usize] {
+                    match &mut self.locals[slot] {
                         VBValue::Array(ref mut items, _) => {
                             let items = Arc::make_mut(items);
                             for item in items.iter_mut() {
@@ -1378,9 +1436,8 @@ impl<'a> Vm<'a> {
                         }
                     }
                 }
-                Instruction::EraseGlobal(i) => {
-                    let name = self.constants[i as usize].to_string();
-                    if let Some(v) = self.context.get_variable_mut(&name) {
+                Instruction::EraseGlobal(g) => {
+                    if let Some(v) = self.global_mut(g) {
                         match v {
                             VBValue::Array(ref mut items, _) => {
                                 let items = std::sync::Arc::make_mut(items);
@@ -1394,29 +1451,323 @@ impl<'a> Vm<'a> {
                         }
                     }
                 }
+
+                // -- Superinstructions --
+                Instruction::AddLocalConst(slot, c) => {
+                    let l = self.locals[slot].clone();
+                    self.locals[slot] = self.apply_const(l, c, Vm::add)?;
+                }
+                Instruction::ConcatLocalConst(slot, c) => {
+                    let l = Vm::take_string(&mut self.locals[slot]);
+                    self.locals[slot] = self.apply_const(l, c, Vm::concat_str)?;
+                }
+                Instruction::StoreLocalConst(slot, c) => {
+                    self.locals[slot] = self.constants[c as usize].clone();
+                }
+                Instruction::AddGlobalConst(g, c) => {
+                    let l = self.load_global(g)?;
+                    let value = self.apply_const(l, c, Vm::add)?;
+                    *self.global_mut(g) = Some(value);
+                }
+                Instruction::ConcatGlobalConst(g, c) => {
+                    let l = match self.global_mut(g) {
+                        Some(v) => Vm::take_string(v),
+                        None => self.load_global(g)?,
+                    };
+                    let value = self.apply_const(l, c, Vm::concat_str)?;
+                    *self.global_mut(g) = Some(value);
+                }
+                Instruction::StoreGlobalConst(g, c) => {
+                    *self.global_mut(g) = Some(self.constants[c as usize].clone());
+                }
+                Instruction::WriteConst(c) => {
+                    let val = self.constants[c as usize].to_string();
+                    if let Err(e) = self.context.write(&val) {
+                        self.trap_error(e)?;
+                    }
+                }
+                Instruction::AppendLocal(slot, n) => {
+                    // Release the variable's reference first, so `s = s & x`
+                    // finds its string unshared and appends in place
+                    self.locals[slot] = VBValue::Empty;
+                    match self.concat_top(n) {
+                        Ok(value) => self.locals[slot] = value,
+                        Err(e) => self.trap_error(e)?,
+                    }
+                }
+                Instruction::AppendGlobal(g, n) => {
+                    *self.global_mut(g) = None;
+                    match self.concat_top(n) {
+                        Ok(value) => *self.global_mut(g) = Some(value),
+                        Err(e) => self.trap_error(e)?,
+                    }
+                }
             }
         }
     }
 
+    /// Bind the globals of code about to run to slots of the context.
+    /// Bindings are kept, since the slot of a name never changes.
+    fn bind_globals(&mut self, globals: &Arc<GlobalNames>) {
+        if Arc::ptr_eq(&self.globals, globals) {
+            return;
+        }
+        let slots = match self.bindings.iter().find(|(g, _)| Arc::ptr_eq(g, globals)) {
+            Some((_, slots)) => slots.clone(),
+            None => {
+                let slots: Arc<[usize]> = globals.names.iter().map(|name| self.context.global_slot(name)).collect();
+                self.bindings.push((globals.clone(), slots.clone()));
+                slots
+            }
+        };
+        self.globals = globals.clone();
+        self.global_slots = slots;
+    }
+
+    fn global(&self, g: u32) -> Option<&VBValue> {
+        self.context.global(self.global_slots[g as usize])
+    }
+
+    fn global_mut(&mut self, g: u32) -> &mut Option<VBValue> {
+        self.context.global_mut(self.global_slots[g as usize])
+    }
+
+    /// Value of global `g` as pushed by `LoadGlobal`.  An undefined name
+    /// may be a builtin called without parentheses (`Now`, `Timer`).
+    fn load_global(&mut self, g: u32) -> Result<VBValue, VBSError> {
+        if let Some(val) = self.global(g) {
+            return Ok(val.clone());
+        }
+        let name = &self.globals.names[g as usize];
+        let result = if builtins::is_bare_builtin(name) {
+            builtins::call_builtin(name, Vec::new())
+        } else {
+            Err(VBSError::runtime_with(codes::VARIABLE_UNDEFINED, name))
+        };
+        result.or_else(|e| {
+            if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                self.context.set_err(e);
+                Ok(VBValue::Empty)
+            } else {
+                Err(e)
+            }
+        })
+    }
+
+    fn var_value(&self, var: VarSlot) -> Option<&VBValue> {
+        match var {
+            VarSlot::Local(slot) => Some(&self.locals[slot]),
+            VarSlot::Global(g) => self.global(g),
+        }
+    }
+
+    fn store_var(&mut self, var: VarSlot, val: VBValue) {
+        match var {
+            VarSlot::Local(slot) => self.locals[slot] = val,
+            VarSlot::Global(g) => *self.global_mut(g) = Some(val),
+        }
+    }
+
+    /// `l <op> constant` for a fused read-modify-write instruction.  An
+    /// object goes through its default member, and errors are handled, as
+    /// in the unfused sequence.
+    fn apply_const(
+        &mut self,
+        mut l: VBValue,
+        c: u32,
+        op: fn(VBValue, VBValue) -> Result<VBValue, VBSError>,
+    ) -> Result<VBValue, VBSError> {
+        if let VBValue::Object(obj) = &l {
+            let obj = obj.clone();
+            match obj.default_value(self.context) {
+                Ok(Some(v)) => l = v,
+                Ok(None) => {}
+                Err(e) => {
+                    if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                        self.context.set_err(e);
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        let r = self.constants[c as usize].clone();
+        match op(l, r) {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                    self.context.set_err(e);
+                    Ok(VBValue::Empty)
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    /// Result of a unary operator, for constant folding; `None` for other
+    /// instructions.
+    pub(crate) fn eval_unary(op: &Instruction, val: VBValue) -> Option<Result<VBValue, VBSError>> {
+        Some(match op {
+            Instruction::Neg => Vm::negate(val),
+            Instruction::Not => Ok(Vm::logical_not(val)),
+            _ => return None,
+        })
+    }
+
+    /// Result of a binary operator that depends only on its operands, for
+    /// constant folding; `None` for other instructions.
+    pub(crate) fn eval_binary(op: &Instruction, l: VBValue, r: VBValue) -> Option<Result<VBValue, VBSError>> {
+        Some(match op {
+            Instruction::Add => Vm::add(l, r),
+            Instruction::Sub => Vm::sub(l, r),
+            Instruction::Mul => Vm::mul(l, r),
+            Instruction::Div => Vm::div(l, r),
+            Instruction::IntDiv => Vm::int_div(l, r),
+            Instruction::Mod => Vm::mod_op(l, r),
+            Instruction::Pow => Vm::pow_op(l, r),
+            Instruction::Concat => Vm::concat_str(l, r),
+            Instruction::Eq => Ok(VBValue::Boolean(Vm::values_equal(&l, &r))),
+            Instruction::Ne => Ok(VBValue::Boolean(!Vm::values_equal(&l, &r))),
+            Instruction::Lt => Ok(Vm::compare_lt(l, r)),
+            Instruction::Le => Ok(Vm::compare_le(l, r)),
+            Instruction::Gt => Ok(Vm::compare_gt(l, r)),
+            Instruction::Ge => Ok(Vm::compare_ge(l, r)),
+            Instruction::Like => Ok(Vm::like_match(l, r)),
+            Instruction::And => Vm::bool_or_bitwise(l, r, |a, b| a & b),
+            Instruction::Or => Vm::bool_or_bitwise(l, r, |a, b| a | b),
+            Instruction::Xor => Vm::bool_or_bitwise(l, r, |a, b| a ^ b),
+            Instruction::Imp => Vm::imp_op(l, r),
+            Instruction::Eqv => Vm::eqv_op(l, r),
+            _ => return None,
+        })
+    }
+
+    /// `GetRef(name)`: a reference to the user `Sub`/`Function` `name`,
+    /// callable later as `ref(args)`.
+    fn get_ref(&self, args: &[VBValue]) -> Result<VBValue, VBSError> {
+        let name = args.first().map(value_utils::to_arg_string).unwrap_or_default();
+        match self.context.get_function(&name) {
+            Some(func) => Ok(VBValue::Object(ObjectRef::new(FunctionRef::new(&func.name)))),
+            None => Err(VBSError::runtime_with(codes::INVALID_PROCEDURE_CALL, "GetRef")),
+        }
+    }
+
+    /// Run `Eval(expr)`, `Execute code` or `ExecuteGlobal code` through the
+    /// same tokenize / parse / compile pipeline as a page.
+    ///
+    /// `Eval` and `Execute` see the current scope: the caller's locals are
+    /// published as variables for the duration of the run and read back
+    /// afterwards.  `ExecuteGlobal` sees only global scope, so inside a
+    /// procedure the procedure's locals are left out.  Procedures and
+    /// classes defined by the code are always global.
+    fn run_dynamic_code(&mut self, kind: DynamicCode, args: &[VBValue]) -> Result<VBValue, VBSError> {
+        let code = args.first().map(value_utils::to_arg_string).unwrap_or_default();
+        let local_scope = kind != DynamicCode::ExecuteGlobal || self.call_depth == 0;
+        let shadowed = if local_scope { self.publish_locals() } else { Vec::new() };
+
+        let interp = crate::vbscript::VBScriptInterpreter;
+        let result = match kind {
+            DynamicCode::Eval => {
+                // Declared up front so the assignment passes `Option Explicit`.
+                self.context.set_variable(EVAL_RESULT, VBValue::Empty);
+                let result = interp.execute_vm(&format!("{} = {}", EVAL_RESULT, code), self.context);
+                let value = self.context.remove_variable(EVAL_RESULT);
+                result.map(|()| value.unwrap_or(VBValue::Empty))
+            }
+            DynamicCode::Execute | DynamicCode::ExecuteGlobal => {
+                interp.execute_vm(&code, self.context).map(|()| VBValue::Empty)
+            }
+        };
+
+        if local_scope {
+            self.unpublish_locals(shadowed);
+        }
+        result
+    }
+
+    /// Expose the named locals as context variables, returning the values
+    /// they shadow.
+    fn publish_locals(&mut self) -> Vec<(usize, Option<VBValue>)> {
+        let names = self.local_names.clone();
+        let mut shadowed = Vec::new();
+        for (slot, name) in names.iter().enumerate() {
+            if name.is_empty() || slot >= self.locals.len() {
+                continue;
+            }
+            let value = self.locals[slot].clone();
+            let global = self.context.global_slot(name);
+            let previous = self.context.global_mut(global).replace(value);
+            shadowed.push((slot, previous));
+        }
+        shadowed
+    }
+
+    /// Read published locals back into their slots and restore the
+    /// variables they shadowed.
+    fn unpublish_locals(&mut self, shadowed: Vec<(usize, Option<VBValue>)>) {
+        let names = self.local_names.clone();
+        for (slot, previous) in shadowed {
+            let global = self.context.global_slot(&names[slot]);
+            let current = std::mem::replace(self.context.global_mut(global), previous);
+            self.locals[slot] = current.unwrap_or(VBValue::Empty);
+        }
+    }
+
+    /// Resolve objects among the top `n` stack values through their default
+    /// member, as VBScript does for objects used as plain values.
+    fn coerce_operands(&mut self, n: usize) -> Result<(), VBSError> {
+        let start = self.stack.len().saturating_sub(n);
+        for i in start..self.stack.len() {
+            if let VBValue::Object(obj) = &self.stack[i] {
+                let obj = obj.clone();
+                if let Some(v) = obj.default_value(self.context)? {
+                    self.stack[i] = v;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Call a method on an object, collecting the `ByRef` results a class
+    /// method leaves in the context.
+    fn call_object_method(&mut self, obj: &ObjectRef, method: Symbol, args: &[VBValue]) -> Result<VBValue, VBSError> {
+        if let Some(instance) = obj.as_class_instance() {
+            self.byref_results.clear();
+            return instance.invoke_method(self, method.as_str(), args);
+        }
+        self.context.byref_results.clear();
+        let result = obj.call_method_sym(method, args, self.context);
+        self.byref_results = std::mem::take(&mut self.context.byref_results);
+        result
+    }
+
+    fn get_object_property(&mut self, obj: &ObjectRef, prop: Symbol) -> Result<VBValue, VBSError> {
+        match obj.as_class_instance() {
+            Some(instance) => instance.invoke_property_get(self, prop.as_str(), &[]),
+            None => obj.get_property_sym(prop, self.context),
+        }
+    }
+
+    fn set_object_property(&mut self, obj: &ObjectRef, prop: Symbol, val: VBValue) -> Result<(), VBSError> {
+        match obj.as_class_instance() {
+            Some(instance) => instance.invoke_property_let(self, prop.as_str(), &[], val),
+            None => obj.set_property_sym(prop, val, self.context),
+        }
+    }
+
     fn call_user_function(&mut self, name: &str, args: &[VBValue]) -> Result<(), VBSError> {
         let func = self.context.get_function(name)
-            .ok_or_else(|| VBSError::new(
-                0, format!("Function '{}' not found", name),
-                VBSErrorType::RuntimeError
-            ))?
+            .ok_or_else(|| VBSError::runtime_with(codes::SUB_OR_FUNCTION_NOT_DEFINED, name))?
             .clone();
 
         let func_name = func.name.clone();
         let is_func = func.is_function;
 
-        // Save/reset code_start_line
-        let saved_code_start_line = self.context.code_start_line;
-        self.context.code_start_line = 0;
-
         // Set up parameters in context for global access (e.g. Return statement stores function name as global)
         for (i, param) in func.params.iter().enumerate() {
             let val = args.get(i).cloned().unwrap_or(VBValue::Empty);
-            self.context.set_variable(param, val);
+            self.context.set_variable(&param.name, val);
         }
         if is_func {
             self.context.set_variable(&func_name, VBValue::Empty);
@@ -1444,12 +1795,63 @@ impl<'a> Vm<'a> {
             self.context.set_function_code(&func_name, compiled.clone());
             compiled
         };
+        let func_code = ProcedureCode::from(func_code);
+
+        // Set parameters in locals
+        let mut locals = vec![VBValue::Empty; func_code.local_count];
+        for (slot, arg) in locals.iter_mut().zip(args.iter().take(func.params.len())) {
+            *slot = arg.clone();
+        }
+
+        // Execute function body via VM
+        let (result, locals) = self.run_frame(&func_code, locals);
+
+        // Final values of ByRef parameters, for write-back by the caller
+        self.byref_results = func.params.iter().map(|param| {
+            if param.by_val {
+                return None;
+            }
+            match func_code.local_names.iter().position(|n| *n == param.name) {
+                Some(slot) => locals.get(slot).cloned(),
+                None => self.context.get_variable(&param.name).cloned(),
+            }
+        }).collect();
+
+        // Extract return value
+        if is_func {
+            let return_val = self.context.get_variable(&func_name).cloned().unwrap_or(VBValue::Empty);
+            self.stack.push(return_val);
+        }
+        result
+    }
+
+    /// Run `code` in a fresh frame starting with `locals`, then restore the
+    /// caller's frame.  Returns the outcome of the body (`Exit Function` /
+    /// `Exit Sub` count as success) and the final locals.
+    fn run_frame(&mut self, code: &ProcedureCode, locals: Vec<VBValue>) -> (Result<(), VBSError>, Vec<VBValue>) {
+        // Fail before unbounded recursion overflows the native stack
+        if ResourceLimits::exceeds(self.context.call_depth as u64 + 1, self.context.limits.max_call_depth as u64) {
+            return (Err(VBSError::runtime(codes::OUT_OF_STACK_SPACE)), locals);
+        }
+        self.context.call_depth += 1;
+
+        // Save/reset code_start_line
+        let saved_code_start_line = self.context.code_start_line;
+        self.context.code_start_line = 0;
+        let saved_line = self.context.current_line;
 
         // Save outer VM state
         let saved_ip = self.ip;
-        let saved_code = std::mem::replace(&mut self.code, Arc::new(func_code.instructions));
-        let saved_constants = std::mem::replace(&mut self.constants, Arc::new(func_code.constants));
-        let saved_locals = std::mem::replace(&mut self.locals, vec![VBValue::Empty; func_code.local_count]);
+        let saved_code = std::mem::replace(&mut self.code, code.instructions.clone());
+        let saved_constants = std::mem::replace(&mut self.constants, code.constants.clone());
+        let saved_locals = std::mem::replace(&mut self.locals, locals);
+        let saved_local_names = std::mem::replace(&mut self.local_names, code.local_names.clone());
+        let saved_globals = (!Arc::ptr_eq(&self.globals, &code.globals)).then(|| {
+            let saved = (self.globals.clone(), self.global_slots.clone());
+            self.bind_globals(&code.globals);
+            saved
+        });
+        self.call_depth += 1;
         let saved_stack = std::mem::take(&mut self.stack);
         let saved_for_states = std::mem::take(&mut self.for_states);
         let saved_for_each_states = std::mem::take(&mut self.for_each_states);
@@ -1461,28 +1863,20 @@ impl<'a> Vm<'a> {
         self.should_exit = false;
         self.ip = 0;
 
-        // Set parameters in locals (already initialized to Empty above)
-        for (i, _param) in func.params.iter().enumerate() {
-            if i < args.len() {
-                self.locals[i] = args[i].clone();
-            }
-        }
-
-        // Execute function body via VM
         let result = self.execute_loop();
 
-        // Extract return value
-        let return_val = if is_func {
-            self.context.get_variable(&func_name).cloned().unwrap_or(VBValue::Empty)
-        } else {
-            VBValue::Empty
-        };
-
         // Restore outer VM state entirely
+        let locals = std::mem::replace(&mut self.locals, saved_locals);
         self.should_exit = saved_should_exit;
         self.code = saved_code;
         self.constants = saved_constants;
-        self.locals = saved_locals;
+        self.local_names = saved_local_names;
+        if let Some((globals, slots)) = saved_globals {
+            self.globals = globals;
+            self.global_slots = slots;
+        }
+        self.call_depth -= 1;
+        self.context.call_depth -= 1;
         self.stack = saved_stack;
         self.for_states = saved_for_states;
         self.for_each_states = saved_for_each_states;
@@ -1493,116 +1887,198 @@ impl<'a> Vm<'a> {
 
         self.context.code_start_line = saved_code_start_line;
 
-        if is_func {
-            self.stack.push(return_val);
+        let result = match result {
+            Err(e) if e.is_exit_function() || e.is_exit_sub() => Ok(()),
+            other => other,
+        };
+        // On failure the line stays at the failing statement, for `Err.Line`
+        if result.is_ok() {
+            self.context.current_line = saved_line;
         }
+        (result, locals)
+    }
 
-        match result {
-            Ok(()) => Ok(()),
-            Err(e) if e.is_exit_function() || e.is_exit_sub() => Ok(()),
-            Err(e) => Err(e),
+    /// Run a compiled member of a class instance.  The instance fields are
+    /// loaded into the leading local slots and, after the call, the fields
+    /// the member can assign are stored back.
+    pub(crate) fn call_class_member(
+        &mut self,
+        instance: &ClassInstance,
+        member: &ClassMember,
+        args: &[VBValue],
+    ) -> Result<VBValue, VBSError> {
+        let mut locals = vec![VBValue::Empty; member.code.local_count];
+        let first_param = instance.load_fields(&mut locals);
+        for (slot, arg) in locals[first_param..].iter_mut().zip(args.iter().take(member.params.len())) {
+            *slot = arg.clone();
         }
+
+        let (result, locals) = self.run_frame(&member.code, locals);
+        instance.store_fields(&locals, &member.assigned_fields);
+
+        // Hand ByRef parameter values back for the caller to write back
+        self.byref_results = member
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, p)| if p.by_val { None } else { locals.get(first_param + i).cloned() })
+            .collect();
+
+        result?;
+        Ok(member
+            .result_slot
+            .and_then(|slot| locals.get(slot).cloned())
+            .unwrap_or(VBValue::Empty))
     }
 
     // -- Helper functions (ported from the existing interpreter) --
 
-    fn is_truthy(val: &VBValue) -> bool {
+    /// Record `e` in `Err` under `On Error Resume Next`, or fail with it.
+    fn trap_error(&mut self, e: VBSError) -> Result<(), VBSError> {
+        if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+            self.context.set_err(e);
+            Ok(())
+        } else {
+            Err(e)
+        }
+    }
+
+    /// Push the result of an operator, or record its error and push
+    /// `Empty` under `On Error Resume Next`.
+    fn push_result(&mut self, result: Result<VBValue, VBSError>) -> Result<(), VBSError> {
+        match result {
+            Ok(v) => self.stack.push(v),
+            Err(e) => {
+                if *self.context.get_error_mode() == ErrorMode::ResumeNext {
+                    self.context.set_err(e);
+                    self.stack.push(VBValue::Empty);
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_truthy(val: &VBValue) -> bool {
         match val {
             VBValue::Boolean(b) => *b,
-            VBValue::Empty | VBValue::Null => false,
-            VBValue::Number(n) => *n != 0.0,
+            VBValue::Empty | VBValue::Null | VBValue::Nothing => false,
             VBValue::String(s) => !s.is_empty(),
             VBValue::Array(_, _) => true,
             VBValue::Object(_) => true,
+            n => n.as_f64().is_some_and(|n| n != 0.0),
         }
     }
 
-    fn negate(val: VBValue) -> VBValue {
+    /// Error for member access on a value that is not an object: 91 for
+    /// `Nothing`, 424 otherwise.
+    fn object_required(val: &VBValue) -> VBSError {
         match val {
-            VBValue::Number(n) => VBValue::Number(-n),
-            VBValue::Empty => VBValue::Number(-0.0),
-            VBValue::String(s) => {
-                let n: f64 = s.parse().unwrap_or(0.0);
-                VBValue::Number(-n)
-            }
-            _ => VBValue::Number(-0.0),
+            VBValue::Nothing => VBSError::runtime(codes::OBJECT_VARIABLE_NOT_SET),
+            _ => VBSError::runtime(codes::OBJECT_REQUIRED),
         }
     }
 
+    fn negate(val: VBValue) -> Result<VBValue, VBSError> {
+        numeric::negate(&val)
+    }
+
     fn logical_not(val: VBValue) -> VBValue {
         VBValue::Boolean(!Vm::is_truthy(&val))
     }
 
-    fn add(l: VBValue, r: VBValue) -> VBValue {
-        if matches!(&l, VBValue::String(_)) || matches!(&r, VBValue::String(_)) {
-            Vm::concat_str(l, r)
-        } else {
-            let ln = value_utils::to_arg_f64(&l);
-            let rn = value_utils::to_arg_f64(&r);
-            VBValue::Number(ln + rn)
+    /// `l + r`: concatenation when both sides are strings (or one is Empty or
+    /// Null), otherwise a string operand must be numeric (error 13).
+    fn add(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
+        match (&l, &r) {
+            (VBValue::String(_), VBValue::String(_) | VBValue::Empty | VBValue::Null)
+            | (VBValue::Empty | VBValue::Null, VBValue::String(_)) => Vm::concat_str(l, r),
+            _ => numeric::add(&l, &r),
         }
     }
 
-    fn sub(l: VBValue, r: VBValue) -> VBValue {
-        Vm::number_binop(l, r, |a, b| a - b)
+    fn sub(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
+        numeric::sub(&l, &r)
     }
 
-    fn mul(l: VBValue, r: VBValue) -> VBValue {
-        Vm::number_binop(l, r, |a, b| a * b)
+    fn mul(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
+        numeric::mul(&l, &r)
     }
 
     fn div(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
-        let rn = value_utils::to_arg_f64(&r);
-        if rn == 0.0 {
-            Err(VBSError::new(0, "Division by zero".to_string(), VBSErrorType::RuntimeError))
-        } else {
-            Ok(Vm::number_binop(l, r, |a, b| a / b))
-        }
+        numeric::div(&l, &r)
     }
 
     fn int_div(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
-        let rn = value_utils::to_arg_f64(&r);
-        if rn == 0.0 {
-            Err(VBSError::new(0, "Division by zero".to_string(), VBSErrorType::RuntimeError))
-        } else {
-            Ok(Vm::number_binop(l, r, |a, b| (a / b).floor()))
-        }
+        numeric::int_div(&l, &r)
     }
 
-    fn mod_op(l: VBValue, r: VBValue) -> VBValue {
-        Vm::number_binop(l, r, |a, b| a % b)
+    fn mod_op(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
+        numeric::modulo(&l, &r)
     }
 
-    fn pow_op(l: VBValue, r: VBValue) -> VBValue {
-        Vm::number_binop(l, r, |a, b| a.powf(b))
+    fn pow_op(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
+        numeric::pow(&l, &r)
     }
 
-    fn number_binop(l: VBValue, r: VBValue, f: fn(f64, f64) -> f64) -> VBValue {
-        let ln = value_utils::to_arg_f64(&l);
-        let rn = value_utils::to_arg_f64(&r);
-        VBValue::Number(f(ln, rn))
+    /// `l & r`.  A left string that nothing else refers to is extended in
+    /// place, which keeps chains like `a & b & c` linear.
+    fn concat_str(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
+        let mut s = match l {
+            VBValue::String(s) => s,
+            l => VBString::from(value_utils::to_arg_string(&l)),
+        };
+        match &r {
+            VBValue::String(rs) => {
+                builtins::check_string_length(s.len() + rs.len())?;
+                s.push_str(rs);
+            }
+            r => {
+                let rs = value_utils::to_arg_string(r);
+                builtins::check_string_length(s.len() + rs.len())?;
+                s.push_str(&rs);
+            }
+        }
+        Ok(VBValue::String(s))
     }
 
-    fn concat_str(l: VBValue, r: VBValue) -> VBValue {
-        let ls = value_utils::to_arg_string(&l);
-        let rs = value_utils::to_arg_string(&r);
-        VBValue::String(format!("{}{}", ls, rs).into())
+    /// Concatenate the top `n + 1` stack values, left to right.
+    fn concat_top(&mut self, n: u8) -> Result<VBValue, VBSError> {
+        let mut parts = self.stack.drain(self.stack.len() - n as usize - 1..);
+        let first = parts.next().unwrap();
+        parts.try_fold(first, Vm::concat_str)
+    }
+
+    /// Move a string out of a variable about to be overwritten with its
+    /// concatenation, leaving the string unshared; other values are cloned.
+    fn take_string(var: &mut VBValue) -> VBValue {
+        match var {
+            VBValue::String(_) => std::mem::replace(var, VBValue::Empty),
+            other => other.clone(),
+        }
     }
 
     fn values_equal(a: &VBValue, b: &VBValue) -> bool {
         match (a, b) {
             (VBValue::String(a), VBValue::String(b)) => a == b,
-            (VBValue::Number(a), VBValue::Number(b)) => (a - b).abs() < f64::EPSILON,
             (VBValue::Boolean(a), VBValue::Boolean(b)) => a == b,
             (VBValue::Null, VBValue::Null) => true,
             (VBValue::Empty, VBValue::Empty) => true,
             (VBValue::Array(a, _), VBValue::Array(b, _)) => a == b,
             (VBValue::Object(_), VBValue::Object(_)) => false,
-            _ => {
-                let sa = value_utils::to_arg_string(a);
-                let sb = value_utils::to_arg_string(b);
-                sa == sb
+            (VBValue::Nothing, _) | (_, VBValue::Nothing) => false,
+            (VBValue::Date(_), VBValue::String(_)) | (VBValue::String(_), VBValue::Date(_)) => {
+                builtins::date_comparison_operands(a, b).is_some_and(|(x, y)| x == y)
             }
+            _ => match (a.as_f64(), b.as_f64()) {
+                (Some(x), Some(y)) => (x - y).abs() < f64::EPSILON,
+                _ => {
+                    let sa = value_utils::to_arg_string(a);
+                    let sb = value_utils::to_arg_string(b);
+                    sa == sb
+                }
+            },
         }
     }
 
@@ -1623,41 +2099,29 @@ impl<'a> Vm<'a> {
     }
 
     fn number_comparison(l: VBValue, r: VBValue, f: fn(f64, f64) -> bool) -> VBValue {
-        let ln = value_utils::to_arg_f64(&l);
-        let rn = value_utils::to_arg_f64(&r);
+        let (ln, rn) = builtins::date_comparison_operands(&l, &r)
+            .unwrap_or_else(|| (value_utils::to_arg_f64(&l), value_utils::to_arg_f64(&r)));
         VBValue::Boolean(f(ln, rn))
     }
 
-    fn bool_or_bitwise(l: VBValue, r: VBValue, f: fn(i64, i64) -> i64) -> VBValue {
+    fn bool_or_bitwise(l: VBValue, r: VBValue, f: fn(i64, i64) -> i64) -> Result<VBValue, VBSError> {
         match (&l, &r) {
-            (VBValue::Boolean(a), VBValue::Boolean(b)) => VBValue::Boolean(f(*a as i64, *b as i64) != 0),
-            _ => {
-                let ln = value_utils::to_arg_f64(&l) as i64;
-                let rn = value_utils::to_arg_f64(&r) as i64;
-                VBValue::Number(f(ln, rn) as f64)
-            }
+            (VBValue::Boolean(a), VBValue::Boolean(b)) => Ok(VBValue::Boolean(f(*a as i64, *b as i64) != 0)),
+            _ => numeric::bitwise(&l, &r, f),
         }
     }
 
-    fn imp_op(l: VBValue, r: VBValue) -> VBValue {
+    fn imp_op(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
         match (&l, &r) {
-            (VBValue::Boolean(a), VBValue::Boolean(b)) => VBValue::Boolean(!a || *b),
-            _ => {
-                let ln = value_utils::to_arg_f64(&l) as i64;
-                let rn = value_utils::to_arg_f64(&r) as i64;
-                VBValue::Number((!ln | rn) as f64)
-            }
+            (VBValue::Boolean(a), VBValue::Boolean(b)) => Ok(VBValue::Boolean(!a || *b)),
+            _ => numeric::bitwise(&l, &r, |a, b| !a | b),
         }
     }
 
-    fn eqv_op(l: VBValue, r: VBValue) -> VBValue {
+    fn eqv_op(l: VBValue, r: VBValue) -> Result<VBValue, VBSError> {
         match (&l, &r) {
-            (VBValue::Boolean(a), VBValue::Boolean(b)) => VBValue::Boolean(*a == *b),
-            _ => {
-                let ln = value_utils::to_arg_f64(&l) as i64;
-                let rn = value_utils::to_arg_f64(&r) as i64;
-                VBValue::Number(!(ln ^ rn) as f64)
-            }
+            (VBValue::Boolean(a), VBValue::Boolean(b)) => Ok(VBValue::Boolean(*a == *b)),
+            _ => numeric::bitwise(&l, &r, |a, b| !(a ^ b)),
         }
     }
 